@@ -0,0 +1,222 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::terminal::SandboxReport;
+
+/// How long the agent waits for the user to answer before treating the request as denied
+pub const APPROVAL_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// What the agent is asking permission to do
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ApprovalSubject {
+    /// Run a shell command in a granted folder
+    Command {
+        command: String,
+        cwd: String,
+        /// Why the command policy wants the user to decide
+        reason: String,
+        /// Whether the command asked to keep network access
+        network: bool,
+        /// Isolation the command will run with
+        sandbox: SandboxReport,
+    },
+    /// Fetch from a domain for the first time; approving it allows the domain from then on
+    Domain { domain: String, url: String },
+    /// Call a tool on an external MCP server
+    McpTool {
+        server: String,
+        tool: String,
+        arguments: Value,
+    },
+}
+
+/// A question for the user, as sent with `approval-requested` events
+#[derive(Debug, Clone, Serialize)]
+pub struct ApprovalRequest {
+    pub id: String,
+    pub tool_call_id: Option<String>,
+    pub conversation_id: Option<String>,
+    #[serde(flatten)]
+    pub subject: ApprovalSubject,
+}
+
+impl ApprovalRequest {
+    pub fn new(
+        subject: ApprovalSubject,
+        tool_call_id: Option<&str>,
+        conversation_id: Option<&str>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            tool_call_id: tool_call_id.map(|id| id.to_string()),
+            conversation_id: conversation_id.map(|id| id.to_string()),
+            subject,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApprovalDecision {
+    Approved,
+    Denied,
+    /// Nobody answered within the timeout, or the conversation ended first
+    Expired,
+}
+
+struct Pending {
+    conversation_id: Option<String>,
+    reply: Sender<ApprovalDecision>,
+}
+
+/// Parks a tool call until the user approves or denies it from the UI
+#[derive(Default)]
+pub struct ApprovalBroker {
+    pending: Mutex<HashMap<String, Pending>>,
+}
+
+impl ApprovalBroker {
+    /// Register the request, hand it to `notify` to show the user, and block until answered
+    pub fn request(
+        &self,
+        request: &ApprovalRequest,
+        notify: impl FnOnce(&ApprovalRequest),
+        timeout: Duration,
+    ) -> ApprovalDecision {
+        let (reply, answer) = mpsc::channel();
+        match self.pending.lock() {
+            Ok(mut pending) => {
+                pending.insert(
+                    request.id.clone(),
+                    Pending {
+                        conversation_id: request.conversation_id.clone(),
+                        reply,
+                    },
+                );
+            }
+            Err(_) => return ApprovalDecision::Denied,
+        }
+        notify(request);
+
+        let decision = match answer.recv_timeout(timeout) {
+            Ok(decision) => decision,
+            Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => {
+                ApprovalDecision::Expired
+            }
+        };
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.remove(&request.id);
+        }
+        decision
+    }
+
+    /// Answer a pending request from the UI
+    pub fn respond(&self, id: &str, approved: bool) -> Result<(), String> {
+        let pending = self
+            .pending
+            .lock()
+            .map_err(|e| e.to_string())?
+            .remove(id)
+            .ok_or_else(|| format!("No pending approval with id {}", id))?;
+        let decision = if approved {
+            ApprovalDecision::Approved
+        } else {
+            ApprovalDecision::Denied
+        };
+        // The waiting side may have just timed out; nothing left to tell it
+        let _ = pending.reply.send(decision);
+        Ok(())
+    }
+
+    /// Expire every request still waiting on behalf of a conversation that has ended
+    pub fn cancel_conversation(&self, conversation_id: &str) {
+        let Ok(mut pending) = self.pending.lock() else {
+            return;
+        };
+        pending.retain(|_, p| {
+            if p.conversation_id.as_deref() != Some(conversation_id) {
+                return true;
+            }
+            let _ = p.reply.send(ApprovalDecision::Expired);
+            false
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn command_request(conversation_id: &str) -> ApprovalRequest {
+        ApprovalRequest::new(
+            ApprovalSubject::Command {
+                command: "ls".to_string(),
+                cwd: "/tmp".to_string(),
+                reason: "No rule allows 'ls'".to_string(),
+                network: false,
+                sandbox: crate::terminal::sandbox::expected(&Default::default()),
+            },
+            Some("call_1"),
+            Some(conversation_id),
+        )
+    }
+
+    /// Ask on a background thread and answer with `answer` once the request shows up
+    fn ask(
+        broker: Arc<ApprovalBroker>,
+        answer: impl FnOnce(&ApprovalBroker, &str),
+    ) -> ApprovalDecision {
+        let (shown, seen) = mpsc::channel();
+        let asker = {
+            let broker = broker.clone();
+            std::thread::spawn(move || {
+                broker.request(
+                    &command_request("conv"),
+                    |r| shown.send(r.id.clone()).unwrap(),
+                    Duration::from_secs(10),
+                )
+            })
+        };
+        let id = seen.recv().unwrap();
+        answer(&broker, &id);
+        asker.join().unwrap()
+    }
+
+    #[test]
+    fn test_respond_unblocks_request() {
+        let broker = Arc::new(ApprovalBroker::default());
+        let decision = ask(broker.clone(), |b, id| b.respond(id, true).unwrap());
+        assert_eq!(decision, ApprovalDecision::Approved);
+
+        let decision = ask(broker.clone(), |b, id| b.respond(id, false).unwrap());
+        assert_eq!(decision, ApprovalDecision::Denied);
+
+        let decision = ask(broker.clone(), |b, _| b.cancel_conversation("conv"));
+        assert_eq!(decision, ApprovalDecision::Expired);
+
+        assert!(broker.respond("missing", true).is_err());
+    }
+
+    #[test]
+    fn test_unanswered_request_expires() {
+        let broker = ApprovalBroker::default();
+        let request = command_request("conv");
+        let decision = broker.request(&request, |_| {}, Duration::from_millis(10));
+        assert_eq!(decision, ApprovalDecision::Expired);
+        assert!(broker.respond(&request.id, true).is_err());
+    }
+
+    #[test]
+    fn test_request_serializes_flat() {
+        let value = serde_json::to_value(command_request("conv")).unwrap();
+        assert_eq!(value["kind"], "command");
+        assert_eq!(value["command"], "ls");
+        assert_eq!(value["tool_call_id"], "call_1");
+    }
+}
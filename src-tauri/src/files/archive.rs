@@ -0,0 +1,708 @@
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::result::ZipError;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use super::error::FileOpError;
+
+/// Most bytes an archive may expand to when extracted, guarding against archive bombs
+pub const MAX_EXTRACT_BYTES: u64 = 4 * 1024 * 1024 * 1024;
+
+/// Most entries an archive may hold when listed or extracted
+pub const MAX_ARCHIVE_ENTRIES: usize = 100_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+        if name.ends_with(".zip") {
+            Some(Self::Zip)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(Self::TarGz)
+        } else if name.ends_with(".tar") {
+            Some(Self::Tar)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveEntry {
+    /// Path of the entry inside the archive
+    pub path: String,
+    pub is_directory: bool,
+    /// Uncompressed size in bytes
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveListing {
+    pub format: ArchiveFormat,
+    pub entries: Vec<ArchiveEntry>,
+    pub total_size: u64,
+}
+
+/// An archive entry that was left out of an extraction, and why
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkippedEntry {
+    pub path: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExtractSummary {
+    pub files_extracted: usize,
+    pub bytes_written: u64,
+    pub skipped: Vec<SkippedEntry>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CreateSummary {
+    pub files_added: usize,
+    pub bytes_added: u64,
+}
+
+fn unsupported(path: &Path) -> FileOpError {
+    FileOpError::Invalid(format!(
+        "Unsupported archive type for {}; expected .zip, .tar, .tar.gz or .tgz",
+        path.display()
+    ))
+}
+
+/// Zip errors other than I/O mean the archive itself is corrupt or unsupported
+fn zip_error(err: ZipError) -> FileOpError {
+    match err {
+        ZipError::Io(e) => FileOpError::io("Failed to read archive")(e),
+        e => FileOpError::Invalid(format!("Failed to read archive: {}", e)),
+    }
+}
+
+fn open_tar(
+    path: &Path,
+    format: ArchiveFormat,
+) -> Result<tar::Archive<Box<dyn Read>>, FileOpError> {
+    let file = File::open(path).map_err(FileOpError::io("Failed to open archive"))?;
+    let reader: Box<dyn Read> = match format {
+        ArchiveFormat::TarGz => Box::new(GzDecoder::new(file)),
+        _ => Box::new(file),
+    };
+    Ok(tar::Archive::new(reader))
+}
+
+fn open_zip(path: &Path) -> Result<ZipArchive<File>, FileOpError> {
+    let file = File::open(path).map_err(FileOpError::io("Failed to open archive"))?;
+    ZipArchive::new(file).map_err(zip_error)
+}
+
+pub fn list_archive(path: &Path) -> Result<ArchiveListing, FileOpError> {
+    let format = ArchiveFormat::from_path(path).ok_or_else(|| unsupported(path))?;
+    let mut entries = Vec::new();
+
+    match format {
+        ArchiveFormat::Zip => {
+            let mut archive = open_zip(path)?;
+            for i in 0..archive.len().min(MAX_ARCHIVE_ENTRIES) {
+                let entry = archive.by_index_raw(i).map_err(zip_error)?;
+                entries.push(ArchiveEntry {
+                    path: entry.name().to_string(),
+                    is_directory: entry.is_dir(),
+                    size: entry.size(),
+                });
+            }
+        }
+        ArchiveFormat::Tar | ArchiveFormat::TarGz => {
+            let mut archive = open_tar(path, format)?;
+            let iter = archive
+                .entries()
+                .map_err(FileOpError::io("Failed to read archive"))?;
+            for entry in iter.take(MAX_ARCHIVE_ENTRIES) {
+                let entry = entry.map_err(FileOpError::io("Failed to read archive"))?;
+                let entry_path = entry
+                    .path()
+                    .map_err(FileOpError::io("Failed to read archive"))?;
+                entries.push(ArchiveEntry {
+                    path: entry_path.to_string_lossy().into_owned(),
+                    is_directory: entry.header().entry_type().is_dir(),
+                    size: entry.size(),
+                });
+            }
+        }
+    }
+
+    let total_size = entries.iter().map(|e| e.size).sum();
+    Ok(ArchiveListing {
+        format,
+        entries,
+        total_size,
+    })
+}
+
+/// The path an entry should be written to below the extraction root, or `None` if the
+/// entry name is absolute or climbs out with `..` (a "zip slip")
+fn safe_relative_path(name: &Path) -> Option<PathBuf> {
+    let mut out = PathBuf::new();
+    for component in name.components() {
+        match component {
+            Component::Normal(part) => out.push(part),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    (!out.as_os_str().is_empty()).then_some(out)
+}
+
+/// Extracts entries into `dest`, consulting `allowed` for each target path
+struct Extractor<'a> {
+    dest: &'a Path,
+    overwrite: bool,
+    allowed: &'a dyn Fn(&Path) -> bool,
+    summary: ExtractSummary,
+}
+
+impl Extractor<'_> {
+    fn skip(&mut self, name: &str, reason: &str) {
+        self.summary.skipped.push(SkippedEntry {
+            path: name.to_string(),
+            reason: reason.to_string(),
+        });
+    }
+
+    /// Resolve where an entry goes, recording a skip if it can't be written
+    fn target(&mut self, name: &str) -> Option<PathBuf> {
+        let Some(relative) = safe_relative_path(Path::new(name)) else {
+            self.skip(name, "path escapes the destination folder");
+            return None;
+        };
+        let target = self.dest.join(&relative);
+        if !(self.allowed)(&target) {
+            self.skip(name, "blocked by policy or outside granted folders");
+            return None;
+        }
+        if self.through_link(&relative) {
+            self.skip(name, "path goes through a link");
+            return None;
+        }
+        Some(target)
+    }
+
+    /// Whether any existing component of `relative` below `dest` is a symlink, which
+    /// writing through could redirect outside the destination
+    fn through_link(&self, relative: &Path) -> bool {
+        let mut path = self.dest.to_path_buf();
+        for component in relative.components() {
+            path.push(component);
+            match fs::symlink_metadata(&path) {
+                Ok(metadata) if metadata.file_type().is_symlink() => return true,
+                Ok(_) => {}
+                Err(_) => return false,
+            }
+        }
+        false
+    }
+
+    fn directory(&mut self, name: &str) -> Result<(), FileOpError> {
+        if let Some(target) = self.target(name) {
+            fs::create_dir_all(&target).map_err(FileOpError::io("Failed to create directory"))?;
+        }
+        Ok(())
+    }
+
+    fn file(&mut self, name: &str, mut contents: impl Read) -> Result<(), FileOpError> {
+        let Some(target) = self.target(name) else {
+            return Ok(());
+        };
+        if let Ok(existing) = fs::symlink_metadata(&target) {
+            if existing.is_dir() || !self.overwrite {
+                self.skip(name, "already exists");
+                return Ok(());
+            }
+            // Replace rather than truncate, so the new file is never opened through a link
+            fs::remove_file(&target).map_err(FileOpError::io("Failed to replace file"))?;
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(FileOpError::io("Failed to create directory"))?;
+        }
+
+        // Allow one byte past the budget so an oversized archive is detected, not truncated
+        let budget = MAX_EXTRACT_BYTES - self.summary.bytes_written;
+        let mut out = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&target)
+            .map_err(FileOpError::io("Failed to write file"))?;
+        let written = io::copy(&mut (&mut contents).take(budget + 1), &mut out)
+            .map_err(|e| FileOpError::io(&format!("Failed to extract {}", name))(e))?;
+        if written > budget {
+            drop(out);
+            let _ = fs::remove_file(&target);
+            return Err(FileOpError::TooLarge(format!(
+                "Archive expands to more than {} bytes; extraction stopped",
+                MAX_EXTRACT_BYTES
+            )));
+        }
+        self.summary.files_extracted += 1;
+        self.summary.bytes_written += written;
+        Ok(())
+    }
+}
+
+/// Extract an archive into `dest`. Entries whose target fails `allowed`, that would land
+/// outside `dest`, or that are links or special files are skipped rather than written.
+pub fn extract_archive(
+    path: &Path,
+    dest: &Path,
+    overwrite: bool,
+    allowed: &dyn Fn(&Path) -> bool,
+) -> Result<ExtractSummary, FileOpError> {
+    let format = ArchiveFormat::from_path(path).ok_or_else(|| unsupported(path))?;
+    fs::create_dir_all(dest).map_err(FileOpError::io("Failed to create directory"))?;
+    let mut extractor = Extractor {
+        dest,
+        overwrite,
+        allowed,
+        summary: ExtractSummary::default(),
+    };
+
+    match format {
+        ArchiveFormat::Zip => {
+            let mut archive = open_zip(path)?;
+            if archive.len() > MAX_ARCHIVE_ENTRIES {
+                return Err(FileOpError::TooLarge(format!(
+                    "Archive has more than {} entries",
+                    MAX_ARCHIVE_ENTRIES
+                )));
+            }
+            for i in 0..archive.len() {
+                let entry = archive.by_index(i).map_err(zip_error)?;
+                let name = entry.name().to_string();
+                if entry.is_dir() {
+                    extractor.directory(&name)?;
+                } else if entry.is_symlink() {
+                    extractor.skip(&name, "links are not extracted");
+                } else {
+                    extractor.file(&name, entry)?;
+                }
+            }
+        }
+        ArchiveFormat::Tar | ArchiveFormat::TarGz => {
+            let mut archive = open_tar(path, format)?;
+            let entries = archive
+                .entries()
+                .map_err(FileOpError::io("Failed to read archive"))?;
+            for (i, entry) in entries.enumerate() {
+                if i >= MAX_ARCHIVE_ENTRIES {
+                    return Err(FileOpError::TooLarge(format!(
+                        "Archive has more than {} entries",
+                        MAX_ARCHIVE_ENTRIES
+                    )));
+                }
+                let entry = entry.map_err(FileOpError::io("Failed to read archive"))?;
+                let name = entry
+                    .path()
+                    .map_err(FileOpError::io("Failed to read archive"))?
+                    .to_string_lossy()
+                    .into_owned();
+                let kind = entry.header().entry_type();
+                if kind.is_dir() {
+                    extractor.directory(&name)?;
+                } else if kind.is_file() {
+                    extractor.file(&name, entry)?;
+                } else if !kind.is_pax_global_extensions() && !kind.is_pax_local_extensions() {
+                    extractor.skip(&name, "links and special files are not extracted");
+                }
+            }
+        }
+    }
+
+    Ok(extractor.summary)
+}
+
+/// Create a new archive at `dest` holding each of `sources`, named by its final path
+/// component. Directories are added recursively, skipping paths that fail `allowed`
+/// and symlinks. The archive is removed again if anything fails.
+pub fn create_archive(
+    sources: &[PathBuf],
+    dest: &Path,
+    allowed: &dyn Fn(&Path) -> bool,
+) -> Result<CreateSummary, FileOpError> {
+    let format = ArchiveFormat::from_path(dest).ok_or_else(|| unsupported(dest))?;
+    if sources.is_empty() {
+        return Err(FileOpError::Invalid(
+            "No paths given to archive".to_string(),
+        ));
+    }
+
+    let mut files = Vec::new();
+    for source in sources {
+        let name = source
+            .file_name()
+            .ok_or_else(|| FileOpError::Invalid(format!("Cannot archive {}", source.display())))?;
+        collect_entries(source, PathBuf::from(name), dest, allowed, &mut files)?;
+    }
+
+    let file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(dest)
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::AlreadyExists => {
+                FileOpError::AlreadyExists("Destination already exists".to_string())
+            }
+            _ => FileOpError::io("Failed to create archive")(e),
+        })?;
+
+    let result = match format {
+        ArchiveFormat::Zip => write_zip(file, &files),
+        ArchiveFormat::Tar => write_tar(file, &files).map(|(summary, _)| summary),
+        ArchiveFormat::TarGz => {
+            let encoder = GzEncoder::new(file, Compression::default());
+            write_tar(encoder, &files).and_then(|(summary, encoder)| {
+                encoder.finish().map(|_| summary).map_err(archive_error)
+            })
+        }
+    };
+    if result.is_err() {
+        let _ = fs::remove_file(dest);
+    }
+    result
+}
+
+/// A file or directory to add to an archive, with its name inside the archive
+struct SourceEntry {
+    path: PathBuf,
+    name: PathBuf,
+    is_directory: bool,
+}
+
+fn collect_entries(
+    path: &Path,
+    name: PathBuf,
+    dest: &Path,
+    allowed: &dyn Fn(&Path) -> bool,
+    out: &mut Vec<SourceEntry>,
+) -> Result<(), FileOpError> {
+    // Never follow symlinks or add the archive being written
+    let metadata =
+        fs::symlink_metadata(path).map_err(FileOpError::io("Failed to read metadata"))?;
+    if metadata.file_type().is_symlink() || path == dest || !allowed(path) {
+        return Ok(());
+    }
+
+    if metadata.is_dir() {
+        let mut children: Vec<_> = fs::read_dir(path)
+            .map_err(FileOpError::io("Failed to read directory"))?
+            .collect::<Result<_, _>>()
+            .map_err(FileOpError::io("Failed to read entry"))?;
+        children.sort_by_key(|c| c.file_name());
+        out.push(SourceEntry {
+            path: path.to_path_buf(),
+            name: name.clone(),
+            is_directory: true,
+        });
+        for child in children {
+            collect_entries(
+                &child.path(),
+                name.join(child.file_name()),
+                dest,
+                allowed,
+                out,
+            )?;
+        }
+    } else if metadata.is_file() {
+        out.push(SourceEntry {
+            path: path.to_path_buf(),
+            name,
+            is_directory: false,
+        });
+    }
+    Ok(())
+}
+
+fn archive_error(e: impl std::fmt::Display) -> FileOpError {
+    FileOpError::Io {
+        kind: io::ErrorKind::Other,
+        message: format!("Failed to write archive: {}", e),
+    }
+}
+
+/// Archive entry names always use forward slashes
+fn entry_name(name: &Path) -> String {
+    name.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn write_zip(file: File, entries: &[SourceEntry]) -> Result<CreateSummary, FileOpError> {
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut summary = CreateSummary::default();
+
+    for entry in entries {
+        let name = entry_name(&entry.name);
+        if entry.is_directory {
+            zip.add_directory(name, options).map_err(archive_error)?;
+            continue;
+        }
+        zip.start_file(name, options).map_err(archive_error)?;
+        let mut source = File::open(&entry.path).map_err(archive_error)?;
+        summary.bytes_added += io::copy(&mut source, &mut zip).map_err(archive_error)?;
+        summary.files_added += 1;
+    }
+
+    zip.finish()
+        .and_then(|file| file.sync_all().map_err(Into::into))
+        .map_err(archive_error)?;
+    Ok(summary)
+}
+
+fn write_tar<W: Write>(
+    writer: W,
+    entries: &[SourceEntry],
+) -> Result<(CreateSummary, W), FileOpError> {
+    let mut builder = tar::Builder::new(writer);
+    builder.follow_symlinks(false);
+    let mut summary = CreateSummary::default();
+
+    for entry in entries {
+        let name = entry_name(&entry.name);
+        if entry.is_directory {
+            builder
+                .append_dir(&name, &entry.path)
+                .map_err(archive_error)?;
+            continue;
+        }
+        let mut source = File::open(&entry.path).map_err(archive_error)?;
+        builder
+            .append_file(&name, &mut source)
+            .map_err(archive_error)?;
+        summary.files_added += 1;
+        summary.bytes_added += source.metadata().map(|m| m.len()).unwrap_or(0);
+    }
+
+    let writer = builder.into_inner().map_err(archive_error)?;
+    Ok((summary, writer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn allow_all(_: &Path) -> bool {
+        true
+    }
+
+    fn sample_tree(root: &Path) -> PathBuf {
+        let src = root.join("project");
+        fs::create_dir_all(src.join("docs/empty")).unwrap();
+        fs::write(src.join("README.md"), "hello").unwrap();
+        fs::write(src.join("docs/guide.md"), "guide").unwrap();
+        src
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(
+            ArchiveFormat::from_path(Path::new("/a/b.ZIP")),
+            Some(ArchiveFormat::Zip)
+        );
+        assert_eq!(
+            ArchiveFormat::from_path(Path::new("b.tar.gz")),
+            Some(ArchiveFormat::TarGz)
+        );
+        assert_eq!(
+            ArchiveFormat::from_path(Path::new("b.tgz")),
+            Some(ArchiveFormat::TarGz)
+        );
+        assert_eq!(
+            ArchiveFormat::from_path(Path::new("b.tar")),
+            Some(ArchiveFormat::Tar)
+        );
+        assert_eq!(ArchiveFormat::from_path(Path::new("b.rar")), None);
+    }
+
+    #[test]
+    fn test_safe_relative_path() {
+        assert_eq!(
+            safe_relative_path(Path::new("./a/b.txt")),
+            Some(PathBuf::from("a/b.txt"))
+        );
+        assert_eq!(safe_relative_path(Path::new("../evil.txt")), None);
+        assert_eq!(safe_relative_path(Path::new("a/../../evil.txt")), None);
+        assert_eq!(safe_relative_path(Path::new("/etc/passwd")), None);
+        assert_eq!(safe_relative_path(Path::new(".")), None);
+    }
+
+    #[test]
+    fn test_round_trip_each_format() {
+        for archive_name in ["out.zip", "out.tar", "out.tar.gz"] {
+            let dir = tempdir().unwrap();
+            let src = sample_tree(dir.path());
+            let archive = dir.path().join(archive_name);
+
+            let created = create_archive(&[src], &archive, &allow_all).unwrap();
+            assert_eq!(created.files_added, 2, "{}", archive_name);
+            assert_eq!(created.bytes_added, 10);
+
+            let listing = list_archive(&archive).unwrap();
+            let mut names: Vec<_> = listing
+                .entries
+                .iter()
+                .map(|e| e.path.trim_end_matches('/').to_string())
+                .collect();
+            names.sort();
+            assert_eq!(
+                names,
+                vec![
+                    "project",
+                    "project/README.md",
+                    "project/docs",
+                    "project/docs/empty",
+                    "project/docs/guide.md"
+                ],
+                "{}",
+                archive_name
+            );
+            assert_eq!(listing.total_size, 10);
+
+            let out = dir.path().join("extracted");
+            let summary = extract_archive(&archive, &out, false, &allow_all).unwrap();
+            assert_eq!(summary.files_extracted, 2);
+            assert!(summary.skipped.is_empty());
+            assert_eq!(
+                fs::read_to_string(out.join("project/docs/guide.md")).unwrap(),
+                "guide"
+            );
+            assert!(out.join("project/docs/empty").is_dir());
+
+            // A second extraction leaves existing files alone unless asked to overwrite
+            let again = extract_archive(&archive, &out, false, &allow_all).unwrap();
+            assert_eq!(again.files_extracted, 0);
+            assert_eq!(again.skipped.len(), 2);
+            let forced = extract_archive(&archive, &out, true, &allow_all).unwrap();
+            assert_eq!(forced.files_extracted, 2);
+        }
+    }
+
+    #[test]
+    fn test_extract_zip_slip_is_skipped() {
+        let dir = tempdir().unwrap();
+        let archive = dir.path().join("evil.zip");
+        let mut zip = ZipWriter::new(File::create(&archive).unwrap());
+        for name in ["../escaped.txt", "/abs.txt", "ok.txt"] {
+            zip.start_file(name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(b"x").unwrap();
+        }
+        zip.finish().unwrap();
+
+        let out = dir.path().join("out");
+        let summary = extract_archive(&archive, &out, false, &allow_all).unwrap();
+        assert_eq!(summary.files_extracted, 1);
+        assert_eq!(summary.skipped.len(), 2);
+        assert!(summary
+            .skipped
+            .iter()
+            .all(|s| s.reason == "path escapes the destination folder"));
+        assert!(!dir.path().join("escaped.txt").exists());
+        assert!(out.join("ok.txt").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_extract_never_writes_through_existing_links() {
+        let dir = tempdir().unwrap();
+        let outside = dir.path().join("outside");
+        fs::create_dir(&outside).unwrap();
+        fs::write(outside.join("secret.txt"), "keep").unwrap();
+        let out = dir.path().join("out");
+        fs::create_dir(&out).unwrap();
+        std::os::unix::fs::symlink(&outside, out.join("linked")).unwrap();
+        std::os::unix::fs::symlink(outside.join("secret.txt"), out.join("file.txt")).unwrap();
+        fs::write(out.join("plain.txt"), "old").unwrap();
+
+        let archive = dir.path().join("over.zip");
+        let mut zip = ZipWriter::new(File::create(&archive).unwrap());
+        for name in ["linked/new.txt", "file.txt", "plain.txt"] {
+            zip.start_file(name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(b"new").unwrap();
+        }
+        zip.finish().unwrap();
+
+        let summary = extract_archive(&archive, &out, true, &allow_all).unwrap();
+        assert_eq!(summary.files_extracted, 1);
+        assert!(summary
+            .skipped
+            .iter()
+            .all(|s| s.reason == "path goes through a link"));
+        assert!(!outside.join("new.txt").exists());
+        assert_eq!(
+            fs::read_to_string(outside.join("secret.txt")).unwrap(),
+            "keep"
+        );
+        assert_eq!(fs::read_to_string(out.join("plain.txt")).unwrap(), "new");
+    }
+
+    #[test]
+    fn test_extract_tar_skips_symlinks_and_disallowed() {
+        let dir = tempdir().unwrap();
+        let archive = dir.path().join("links.tar");
+        let mut builder = tar::Builder::new(File::create(&archive).unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        builder
+            .append_link(&mut header, "link", "/etc/passwd")
+            .unwrap();
+        for name in ["secret.env", "notes.txt"] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(2);
+            header.set_mode(0o644);
+            builder.append_data(&mut header, name, &b"hi"[..]).unwrap();
+        }
+        builder.finish().unwrap();
+
+        let out = dir.path().join("out");
+        let not_env = |p: &Path| p.extension().is_none_or(|e| e != "env");
+        let summary = extract_archive(&archive, &out, false, &not_env).unwrap();
+        assert_eq!(summary.files_extracted, 1);
+        let reasons: Vec<_> = summary.skipped.iter().map(|s| s.reason.as_str()).collect();
+        assert_eq!(
+            reasons,
+            vec![
+                "links and special files are not extracted",
+                "blocked by policy or outside granted folders"
+            ]
+        );
+        assert!(!out.join("link").exists());
+        assert!(out.join("notes.txt").exists());
+    }
+
+    #[test]
+    fn test_create_archive_skips_disallowed_and_refuses_existing() {
+        let dir = tempdir().unwrap();
+        let src = sample_tree(dir.path());
+        let archive = dir.path().join("out.zip");
+        let no_docs = |p: &Path| !p.ends_with("docs");
+
+        let created = create_archive(std::slice::from_ref(&src), &archive, &no_docs).unwrap();
+        assert_eq!(created.files_added, 1);
+
+        let err = create_archive(&[src], &archive, &allow_all).unwrap_err();
+        assert!(matches!(err, FileOpError::AlreadyExists(_)));
+    }
+}
@@ -0,0 +1,327 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use super::permissions::now_secs;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditOutcome {
+    Success,
+    Error,
+}
+
+/// A single file access recorded in the audit log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub timestamp: u64,
+    pub tool_call_id: Option<String>,
+    pub conversation_id: Option<String>,
+    pub operation: String,
+    pub path: String,
+    /// Second path for operations like move that touch two locations
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// Command line, for operations that run one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    pub outcome: AuditOutcome,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bytes: Option<u64>,
+}
+
+impl AuditEntry {
+    /// Build an entry from the result of a file operation
+    pub fn from_result<T, E: std::fmt::Display>(
+        operation: &str,
+        path: &str,
+        result: &Result<T, E>,
+        bytes: Option<u64>,
+    ) -> Self {
+        let (outcome, error) = match result {
+            Ok(_) => (AuditOutcome::Success, None),
+            Err(e) => (AuditOutcome::Error, Some(e.to_string())),
+        };
+        Self {
+            timestamp: now_secs(),
+            tool_call_id: None,
+            conversation_id: None,
+            operation: operation.to_string(),
+            path: path.to_string(),
+            target: None,
+            command: None,
+            outcome,
+            error,
+            bytes: if result.is_ok() { bytes } else { None },
+        }
+    }
+
+    pub fn with_target(mut self, target: &str) -> Self {
+        self.target = Some(target.to_string());
+        self
+    }
+
+    pub fn with_command(mut self, command: &str) -> Self {
+        self.command = Some(command.to_string());
+        self
+    }
+
+    pub fn with_tool_call(mut self, tool_call_id: &str, conversation_id: Option<&str>) -> Self {
+        self.tool_call_id = Some(tool_call_id.to_string());
+        self.conversation_id = conversation_id.map(|c| c.to_string());
+        self
+    }
+}
+
+/// Filters for querying the audit log; unset fields match everything
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AuditFilter {
+    pub conversation_id: Option<String>,
+    pub tool_call_id: Option<String>,
+    pub operation: Option<String>,
+    pub path_contains: Option<String>,
+    pub outcome: Option<AuditOutcome>,
+    pub since: Option<u64>,
+    pub until: Option<u64>,
+    /// Return only the most recent N matching entries
+    pub limit: Option<usize>,
+}
+
+impl AuditFilter {
+    fn matches(&self, entry: &AuditEntry) -> bool {
+        if let Some(ref conv) = self.conversation_id {
+            if entry.conversation_id.as_ref() != Some(conv) {
+                return false;
+            }
+        }
+        if let Some(ref id) = self.tool_call_id {
+            if entry.tool_call_id.as_ref() != Some(id) {
+                return false;
+            }
+        }
+        if let Some(ref op) = self.operation {
+            if &entry.operation != op {
+                return false;
+            }
+        }
+        if let Some(ref needle) = self.path_contains {
+            let in_target = entry.target.as_ref().is_some_and(|t| t.contains(needle));
+            if !entry.path.contains(needle) && !in_target {
+                return false;
+            }
+        }
+        if self.outcome.is_some_and(|o| o != entry.outcome) {
+            return false;
+        }
+        if self.since.is_some_and(|since| entry.timestamp < since) {
+            return false;
+        }
+        if self.until.is_some_and(|until| entry.timestamp > until) {
+            return false;
+        }
+        true
+    }
+}
+
+/// Append-only JSONL log of every file access
+pub struct AuditLog {
+    path: PathBuf,
+    write_lock: Mutex<()>,
+}
+
+impl AuditLog {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            write_lock: Mutex::new(()),
+        }
+    }
+
+    pub fn record(&self, entry: &AuditEntry) -> Result<(), String> {
+        let _guard = self.write_lock.lock().map_err(|e| e.to_string())?;
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create audit log directory: {}", e))?;
+        }
+
+        let mut line = serde_json::to_string(entry)
+            .map_err(|e| format!("Failed to serialize audit entry: {}", e))?;
+        line.push('\n');
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| format!("Failed to open audit log: {}", e))?;
+        file.write_all(line.as_bytes())
+            .map_err(|e| format!("Failed to write audit log: {}", e))
+    }
+
+    pub fn query(&self, filter: &AuditFilter) -> Result<Vec<AuditEntry>, String> {
+        let file = match fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("Failed to open audit log: {}", e)),
+        };
+
+        let mut entries = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(|e| format!("Failed to read audit log: {}", e))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            // Skip lines that fail to parse (e.g. a torn final write) rather than
+            // making the whole log unreadable
+            if let Ok(entry) = serde_json::from_str::<AuditEntry>(&line) {
+                if filter.matches(&entry) {
+                    entries.push(entry);
+                }
+            }
+        }
+
+        if let Some(limit) = filter.limit {
+            let skip = entries.len().saturating_sub(limit);
+            entries.drain(..skip);
+        }
+
+        Ok(entries)
+    }
+
+    /// Write the entries matching `filter` as JSONL to `dest`, returning how many were written
+    pub fn export(&self, dest: &Path, filter: &AuditFilter) -> Result<usize, String> {
+        let entries = self.query(filter)?;
+        let mut out = String::new();
+        for entry in &entries {
+            let line = serde_json::to_string(entry)
+                .map_err(|e| format!("Failed to serialize audit entry: {}", e))?;
+            out.push_str(&line);
+            out.push('\n');
+        }
+        fs::write(dest, out).map_err(|e| format!("Failed to export audit log: {}", e))?;
+        Ok(entries.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn entry(operation: &str, path: &str, conversation_id: &str) -> AuditEntry {
+        let result: Result<(), String> = Ok(());
+        AuditEntry::from_result(operation, path, &result, None)
+            .with_tool_call("call_0", Some(conversation_id))
+    }
+
+    #[test]
+    fn test_record_and_query_all() {
+        let dir = tempdir().unwrap();
+        let log = AuditLog::new(dir.path().join("logs").join("audit.jsonl"));
+
+        log.record(&entry("read_file", "/tmp/a.txt", "conv-1"))
+            .unwrap();
+        log.record(&entry("write_file", "/tmp/b.txt", "conv-2"))
+            .unwrap();
+
+        let entries = log.query(&AuditFilter::default()).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].operation, "read_file");
+        assert_eq!(entries[1].path, "/tmp/b.txt");
+    }
+
+    #[test]
+    fn test_query_missing_log_is_empty() {
+        let dir = tempdir().unwrap();
+        let log = AuditLog::new(dir.path().join("audit.jsonl"));
+        assert!(log.query(&AuditFilter::default()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_query_filters() {
+        let dir = tempdir().unwrap();
+        let log = AuditLog::new(dir.path().join("audit.jsonl"));
+
+        log.record(&entry("read_file", "/tmp/a.txt", "conv-1"))
+            .unwrap();
+        log.record(&entry("read_file", "/tmp/b.txt", "conv-2"))
+            .unwrap();
+        log.record(&entry("delete_file", "/tmp/a.txt", "conv-1"))
+            .unwrap();
+        let failed: Result<(), String> = Err("Access denied".to_string());
+        log.record(&AuditEntry::from_result(
+            "read_file",
+            "/etc/passwd",
+            &failed,
+            None,
+        ))
+        .unwrap();
+
+        let by_conv = log
+            .query(&AuditFilter {
+                conversation_id: Some("conv-1".to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(by_conv.len(), 2);
+
+        let by_op_and_path = log
+            .query(&AuditFilter {
+                operation: Some("read_file".to_string()),
+                path_contains: Some("a.txt".to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(by_op_and_path.len(), 1);
+
+        let errors = log
+            .query(&AuditFilter {
+                outcome: Some(AuditOutcome::Error),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].error.as_deref(), Some("Access denied"));
+        assert!(errors[0].tool_call_id.is_none());
+
+        let latest = log
+            .query(&AuditFilter {
+                limit: Some(1),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(latest.len(), 1);
+        assert_eq!(latest[0].path, "/etc/passwd");
+    }
+
+    #[test]
+    fn test_export() {
+        let dir = tempdir().unwrap();
+        let log = AuditLog::new(dir.path().join("audit.jsonl"));
+        log.record(&entry("read_file", "/tmp/a.txt", "conv-1"))
+            .unwrap();
+        log.record(&entry("read_file", "/tmp/b.txt", "conv-2"))
+            .unwrap();
+
+        let dest = dir.path().join("export.jsonl");
+        let count = log
+            .export(
+                &dest,
+                &AuditFilter {
+                    conversation_id: Some("conv-2".to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        assert_eq!(count, 1);
+        let exported = fs::read_to_string(&dest).unwrap();
+        assert_eq!(exported.lines().count(), 1);
+        assert!(exported.contains("/tmp/b.txt"));
+    }
+}
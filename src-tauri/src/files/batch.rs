@@ -0,0 +1,630 @@
+use globset::Glob;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+use uuid::Uuid;
+
+use super::encoding::guess_mime_type;
+use super::error::FileOpError;
+use super::permissions::{civil_date, now_secs};
+
+/// Most moves a single batch may contain
+pub const MAX_BATCH_MOVES: usize = 10_000;
+
+/// Most applied batches kept around for undo
+const MAX_UNDO_HISTORY: usize = 20;
+
+/// What a batch should do, before it is expanded into individual moves
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BatchRequest {
+    /// Explicit moves; each `to` is the full destination path
+    Moves { moves: Vec<BatchMove> },
+    /// Rename the files directly inside `folder` from a template. Supports `{name}` (the name
+    /// without extension), `{ext}`, `{date}` (modified date, YYYY-MM-DD) and `{n}` (position
+    /// when sorted by modified time). The original extension is kept unless `{ext}` is used.
+    Rename {
+        folder: String,
+        template: String,
+        /// Glob on file names, e.g. `*.jpg`; every non-hidden file when unset
+        #[serde(default)]
+        include: Option<String>,
+    },
+    /// Move the files directly inside `folder` into subfolders such as Images and Documents
+    OrganizeByType { folder: String },
+}
+
+impl BatchRequest {
+    /// Folder the batch works in, or the first source for explicit moves
+    pub fn primary_path(&self) -> &str {
+        match self {
+            BatchRequest::Moves { moves } => moves.first().map(|m| m.from.as_str()).unwrap_or(""),
+            BatchRequest::Rename { folder, .. } | BatchRequest::OrganizeByType { folder } => folder,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchMove {
+    pub from: String,
+    pub to: String,
+}
+
+/// A move that can't be carried out, and why
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchConflict {
+    pub from: String,
+    pub to: String,
+    pub reason: String,
+}
+
+/// Dry-run result: every move that would happen, plus anything blocking the batch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchPlan {
+    pub moves: Vec<BatchMove>,
+    /// Folders that will be created, parents first
+    pub create_directories: Vec<String>,
+    pub conflicts: Vec<BatchConflict>,
+}
+
+impl BatchPlan {
+    pub fn is_ready(&self) -> bool {
+        self.conflicts.is_empty() && !self.moves.is_empty()
+    }
+}
+
+/// A batch that has been carried out, with enough detail to reverse it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppliedBatch {
+    pub id: String,
+    pub applied_at: u64,
+    pub moves: Vec<BatchMove>,
+    pub created_directories: Vec<String>,
+}
+
+/// Applied batches that can still be undone, most recent last
+#[derive(Default)]
+pub struct BatchHistory {
+    applied: Mutex<Vec<AppliedBatch>>,
+}
+
+impl BatchHistory {
+    pub fn push(&self, batch: AppliedBatch) {
+        if let Ok(mut applied) = self.applied.lock() {
+            if applied.len() >= MAX_UNDO_HISTORY {
+                applied.remove(0);
+            }
+            applied.push(batch);
+        }
+    }
+
+    pub fn last(&self) -> Option<AppliedBatch> {
+        self.applied.lock().ok()?.last().cloned()
+    }
+
+    /// Drop the most recent batch once it has been undone
+    pub fn pop(&self) -> Option<AppliedBatch> {
+        self.applied.lock().ok()?.pop()
+    }
+
+    pub fn list(&self) -> Vec<AppliedBatch> {
+        self.applied
+            .lock()
+            .map(|applied| applied.clone())
+            .unwrap_or_default()
+    }
+}
+
+/// Expand a request into moves and validate them without touching the filesystem
+pub fn plan_batch(
+    request: &BatchRequest,
+    allowed: &dyn Fn(&Path) -> bool,
+) -> Result<BatchPlan, FileOpError> {
+    let moves = match request {
+        BatchRequest::Moves { moves } => moves.clone(),
+        BatchRequest::Rename {
+            folder,
+            template,
+            include,
+        } => rename_moves(Path::new(folder), template, include.as_deref())?,
+        BatchRequest::OrganizeByType { folder } => organize_moves(Path::new(folder))?,
+    };
+    if moves.len() > MAX_BATCH_MOVES {
+        return Err(FileOpError::TooLarge(format!(
+            "Batch has {} moves; the limit is {}",
+            moves.len(),
+            MAX_BATCH_MOVES
+        )));
+    }
+    Ok(validate(moves, allowed))
+}
+
+fn validate(moves: Vec<BatchMove>, allowed: &dyn Fn(&Path) -> bool) -> BatchPlan {
+    let sources: HashSet<PathBuf> = moves.iter().map(|m| PathBuf::from(&m.from)).collect();
+    let mut targets = HashSet::new();
+    let mut plan = BatchPlan {
+        moves: Vec::new(),
+        create_directories: Vec::new(),
+        conflicts: Vec::new(),
+    };
+
+    for m in moves {
+        let from = Path::new(&m.from);
+        let to = Path::new(&m.to);
+        if from == to {
+            continue;
+        }
+        let reason = if !allowed(from) || !allowed(to) {
+            Some("outside granted folders or blocked by policy")
+        } else if from.symlink_metadata().is_err() {
+            Some("source does not exist")
+        } else if !targets.insert(to.to_path_buf()) {
+            Some("another move has the same destination")
+        } else if to.starts_with(from) {
+            Some("cannot move a folder into itself")
+        } else if to.symlink_metadata().is_ok() && !sources.contains(to) && !same_file(from, to) {
+            Some("destination already exists")
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            plan.conflicts.push(BatchConflict {
+                from: m.from,
+                to: m.to,
+                reason: reason.to_string(),
+            });
+            continue;
+        }
+
+        let mut missing: Vec<String> = to
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| !dir.as_os_str().is_empty() && !dir.exists())
+            .map(|dir| dir.to_string_lossy().to_string())
+            .collect();
+        missing.reverse();
+        for dir in missing {
+            if !plan.create_directories.contains(&dir) {
+                plan.create_directories.push(dir);
+            }
+        }
+        plan.moves.push(m);
+    }
+    plan
+}
+
+/// Whether two paths name the same file, as with a case-only rename on a case-insensitive disk
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Non-hidden regular files directly inside `folder`, sorted by name
+fn folder_files(folder: &Path) -> Result<Vec<PathBuf>, FileOpError> {
+    let entries = fs::read_dir(folder).map_err(FileOpError::io("Failed to read directory"))?;
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_file()))
+        .filter(|e| !e.file_name().to_string_lossy().starts_with('.'))
+        .map(|e| e.path())
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+fn rename_moves(
+    folder: &Path,
+    template: &str,
+    include: Option<&str>,
+) -> Result<Vec<BatchMove>, FileOpError> {
+    if template.contains('/') || template.contains('\\') {
+        return Err(FileOpError::Invalid(
+            "Rename template must produce a file name, not a path".to_string(),
+        ));
+    }
+    let matcher = include
+        .map(|pattern| {
+            Glob::new(pattern)
+                .map(|g| g.compile_matcher())
+                .map_err(|e| {
+                    FileOpError::Invalid(format!("Invalid include pattern '{}': {}", pattern, e))
+                })
+        })
+        .transpose()?;
+
+    let mut files: Vec<(PathBuf, u64)> = folder_files(folder)?
+        .into_iter()
+        .filter(|path| {
+            matcher
+                .as_ref()
+                .is_none_or(|m| path.file_name().is_some_and(|name| m.is_match(name)))
+        })
+        .map(|path| {
+            let modified = fs::metadata(&path)
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            (path, modified)
+        })
+        .collect();
+    files.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+
+    let width = files.len().to_string().len();
+    let mut moves = Vec::with_capacity(files.len());
+    for (n, (path, modified)) in files.iter().enumerate() {
+        let stem = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let ext = path
+            .extension()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let (year, month, day) = civil_date(*modified);
+
+        let mut name = template
+            .replace("{name}", &stem)
+            .replace("{date}", &format!("{:04}-{:02}-{:02}", year, month, day))
+            .replace("{n}", &format!("{:0width$}", n + 1, width = width))
+            .replace("{ext}", &ext);
+        if !template.contains("{ext}") && !ext.is_empty() {
+            name = format!("{}.{}", name, ext);
+        }
+        if name.trim_matches('.').is_empty() {
+            return Err(FileOpError::Invalid(format!(
+                "Rename template produces an empty name for {}",
+                path.display()
+            )));
+        }
+        moves.push(BatchMove {
+            from: path.to_string_lossy().to_string(),
+            to: folder.join(name).to_string_lossy().to_string(),
+        });
+    }
+    Ok(moves)
+}
+
+/// Subfolder a file belongs in when sorting a folder by type
+fn type_folder(path: &Path) -> &'static str {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "zip" | "tar" | "gz" | "tgz" | "bz2" | "xz" | "7z" | "rar" => return "Archives",
+        "pdf" | "doc" | "docx" | "odt" | "rtf" | "txt" | "md" | "epub" | "pages" => {
+            return "Documents"
+        }
+        "xls" | "xlsx" | "ods" | "csv" | "tsv" | "numbers" => return "Spreadsheets",
+        "ppt" | "pptx" | "odp" | "key" => return "Presentations",
+        _ => {}
+    }
+    let mime_type = guess_mime_type(path);
+    match mime_type.split('/').next() {
+        Some("image") => "Images",
+        Some("video") => "Videos",
+        Some("audio") => "Audio",
+        Some("text") => "Documents",
+        _ => "Other",
+    }
+}
+
+fn organize_moves(folder: &Path) -> Result<Vec<BatchMove>, FileOpError> {
+    Ok(folder_files(folder)?
+        .into_iter()
+        .filter_map(|path| {
+            let name = path.file_name()?.to_os_string();
+            let to = folder.join(type_folder(&path)).join(name);
+            Some(BatchMove {
+                from: path.to_string_lossy().to_string(),
+                to: to.to_string_lossy().to_string(),
+            })
+        })
+        .collect())
+}
+
+/// Carry out a validated plan. Every source is first renamed to a temporary name beside it and
+/// then to its destination, so swaps and chains work; any failure rolls back what was done.
+pub fn apply_plan(plan: &BatchPlan) -> Result<AppliedBatch, FileOpError> {
+    if !plan.conflicts.is_empty() {
+        return Err(FileOpError::Invalid(format!(
+            "Batch has {} conflicts; resolve them before applying",
+            plan.conflicts.len()
+        )));
+    }
+    if plan.moves.is_empty() {
+        return Err(FileOpError::Invalid(
+            "Batch has nothing to move".to_string(),
+        ));
+    }
+
+    let mut created = Vec::new();
+    for dir in &plan.create_directories {
+        if Path::new(dir).exists() {
+            continue;
+        }
+        if let Err(e) = fs::create_dir(dir) {
+            remove_directories(&created);
+            return Err(FileOpError::io(&format!(
+                "Failed to create directory {}",
+                dir
+            ))(e));
+        }
+        created.push(dir.clone());
+    }
+
+    if let Err(e) = move_all(&plan.moves) {
+        remove_directories(&created);
+        return Err(e);
+    }
+
+    Ok(AppliedBatch {
+        id: Uuid::new_v4().to_string(),
+        applied_at: now_secs(),
+        moves: plan.moves.clone(),
+        created_directories: created,
+    })
+}
+
+/// Reverse an applied batch, moving everything back and removing folders it created
+pub fn undo_batch(batch: &AppliedBatch) -> Result<(), FileOpError> {
+    let reversed: Vec<BatchMove> = batch
+        .moves
+        .iter()
+        .map(|m| BatchMove {
+            from: m.to.clone(),
+            to: m.from.clone(),
+        })
+        .collect();
+    for m in &reversed {
+        if Path::new(&m.from).symlink_metadata().is_err() {
+            return Err(FileOpError::NotFound(format!(
+                "Cannot undo: {} no longer exists",
+                m.from
+            )));
+        }
+    }
+    move_all(&reversed)?;
+    remove_directories(&batch.created_directories);
+    Ok(())
+}
+
+fn move_all(moves: &[BatchMove]) -> Result<(), FileOpError> {
+    let token = Uuid::new_v4().simple().to_string();
+    let staged: Vec<PathBuf> = moves
+        .iter()
+        .enumerate()
+        .map(|(i, m)| staging_path(Path::new(&m.from), &token, i))
+        .collect();
+
+    let steps = moves
+        .iter()
+        .zip(&staged)
+        .map(|(m, stage)| (PathBuf::from(&m.from), stage.clone()))
+        .chain(
+            staged
+                .iter()
+                .zip(moves)
+                .map(|(stage, m)| (stage.clone(), PathBuf::from(&m.to))),
+        );
+
+    let mut done: Vec<(PathBuf, PathBuf)> = Vec::new();
+    for (from, to) in steps {
+        let result = if to.symlink_metadata().is_ok() && !same_file(&from, &to) {
+            Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists", to.display()),
+            ))
+        } else {
+            fs::rename(&from, &to)
+        };
+        if let Err(e) = result {
+            for (from, to) in done.iter().rev() {
+                if let Err(undo_err) = fs::rename(to, from) {
+                    log::warn!(
+                        "Failed to roll back {} -> {}: {}",
+                        to.display(),
+                        from.display(),
+                        undo_err
+                    );
+                }
+            }
+            return Err(FileOpError::io(&format!(
+                "Failed to move {}",
+                from.display()
+            ))(e));
+        }
+        done.push((from, to));
+    }
+    Ok(())
+}
+
+/// Temporary name in the same folder as `path`, so the first rename never crosses filesystems
+fn staging_path(path: &Path, token: &str, index: usize) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}.{}-{}.batch", name, token, index))
+}
+
+/// Remove folders created by a batch, deepest first, leaving any that are no longer empty
+fn remove_directories(dirs: &[String]) {
+    for dir in dirs.iter().rev() {
+        let _ = fs::remove_dir(dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn allow_all(_: &Path) -> bool {
+        true
+    }
+
+    fn mv(from: &Path, to: &Path) -> BatchMove {
+        BatchMove {
+            from: from.to_string_lossy().to_string(),
+            to: to.to_string_lossy().to_string(),
+        }
+    }
+
+    #[test]
+    fn test_plan_reports_conflicts() {
+        let dir = tempdir().unwrap();
+        let a = dir.path().join("a.txt");
+        let b = dir.path().join("b.txt");
+        fs::write(&a, "a").unwrap();
+        fs::write(&b, "b").unwrap();
+        let c = dir.path().join("c.txt");
+        fs::write(&c, "c").unwrap();
+        let missing = dir.path().join("missing.txt");
+        let blocked = dir.path().join("secret.txt");
+
+        let request = BatchRequest::Moves {
+            moves: vec![
+                mv(&a, &c),
+                mv(&missing, &dir.path().join("x.txt")),
+                mv(&b, &blocked),
+            ],
+        };
+        let plan = plan_batch(&request, &|p: &Path| p != blocked.as_path()).unwrap();
+        let reasons: Vec<&str> = plan.conflicts.iter().map(|c| c.reason.as_str()).collect();
+        assert_eq!(
+            reasons,
+            vec![
+                "destination already exists",
+                "source does not exist",
+                "outside granted folders or blocked by policy"
+            ]
+        );
+        assert!(!plan.is_ready());
+        assert!(apply_plan(&plan).is_err());
+        assert!(a.exists() && b.exists());
+    }
+
+    #[test]
+    fn test_swap_apply_and_undo() {
+        let dir = tempdir().unwrap();
+        let a = dir.path().join("a.txt");
+        let b = dir.path().join("b.txt");
+        fs::write(&a, "first").unwrap();
+        fs::write(&b, "second").unwrap();
+
+        let request = BatchRequest::Moves {
+            moves: vec![mv(&a, &b), mv(&b, &a)],
+        };
+        let plan = plan_batch(&request, &allow_all).unwrap();
+        assert!(plan.is_ready());
+        let applied = apply_plan(&plan).unwrap();
+        assert_eq!(fs::read_to_string(&a).unwrap(), "second");
+        assert_eq!(fs::read_to_string(&b).unwrap(), "first");
+
+        undo_batch(&applied).unwrap();
+        assert_eq!(fs::read_to_string(&a).unwrap(), "first");
+        assert_eq!(fs::read_to_string(&b).unwrap(), "second");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_rename_template() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("IMG_1.jpg"), "").unwrap();
+        fs::write(dir.path().join("IMG_2.jpg"), "").unwrap();
+        fs::write(dir.path().join("notes.txt"), "").unwrap();
+
+        let request = BatchRequest::Rename {
+            folder: dir.path().to_string_lossy().to_string(),
+            template: "holiday-{n}".to_string(),
+            include: Some("*.jpg".to_string()),
+        };
+        let plan = plan_batch(&request, &allow_all).unwrap();
+        let mut names: Vec<String> = plan
+            .moves
+            .iter()
+            .map(|m| {
+                Path::new(&m.to)
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string()
+            })
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["holiday-1.jpg", "holiday-2.jpg"]);
+
+        let request = BatchRequest::Rename {
+            folder: dir.path().to_string_lossy().to_string(),
+            template: "../{name}".to_string(),
+            include: None,
+        };
+        assert!(plan_batch(&request, &allow_all).is_err());
+    }
+
+    #[test]
+    fn test_organize_by_type_and_undo() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("photo.JPG"), "").unwrap();
+        fs::write(dir.path().join("report.pdf"), "").unwrap();
+        fs::write(dir.path().join("data.xlsx"), "").unwrap();
+        fs::write(dir.path().join("mystery.bin"), "").unwrap();
+
+        let request = BatchRequest::OrganizeByType {
+            folder: dir.path().to_string_lossy().to_string(),
+        };
+        let plan = plan_batch(&request, &allow_all).unwrap();
+        assert_eq!(plan.moves.len(), 4);
+        assert_eq!(plan.create_directories.len(), 4);
+
+        let applied = apply_plan(&plan).unwrap();
+        assert!(dir.path().join("Images/photo.JPG").exists());
+        assert!(dir.path().join("Documents/report.pdf").exists());
+        assert!(dir.path().join("Spreadsheets/data.xlsx").exists());
+        assert!(dir.path().join("Other/mystery.bin").exists());
+
+        undo_batch(&applied).unwrap();
+        assert!(dir.path().join("photo.JPG").exists());
+        assert!(!dir.path().join("Images").exists());
+    }
+
+    #[test]
+    fn test_apply_rolls_back_on_failure() {
+        let dir = tempdir().unwrap();
+        let a = dir.path().join("a.txt");
+        let b = dir.path().join("b.txt");
+        fs::write(&a, "a").unwrap();
+        fs::write(&b, "b").unwrap();
+
+        let plan = plan_batch(
+            &BatchRequest::Moves {
+                moves: vec![
+                    mv(&a, &dir.path().join("new/a.txt")),
+                    mv(&b, &dir.path().join("c.txt")),
+                ],
+            },
+            &allow_all,
+        )
+        .unwrap();
+        // Something appears at a destination between the dry run and applying it
+        fs::write(dir.path().join("c.txt"), "late").unwrap();
+
+        assert!(apply_plan(&plan).is_err());
+        assert_eq!(fs::read_to_string(&a).unwrap(), "a");
+        assert_eq!(fs::read_to_string(&b).unwrap(), "b");
+        assert_eq!(
+            fs::read_to_string(dir.path().join("c.txt")).unwrap(),
+            "late"
+        );
+        assert!(!dir.path().join("new").exists());
+    }
+}
@@ -0,0 +1,203 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+/// Most changes remembered between agent turns; anything older is only counted
+const MAX_JOURNAL_CHANGES: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Created,
+    Modified,
+    Removed,
+    Renamed,
+}
+
+impl ChangeKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            ChangeKind::Created => "created",
+            ChangeKind::Modified => "modified",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Renamed => "renamed",
+        }
+    }
+}
+
+/// A change seen in a granted folder, as sent with `fs-changed` events
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FsChange {
+    pub path: String,
+    pub kind: ChangeKind,
+    /// Previous path, for renames
+    pub from: Option<String>,
+}
+
+/// What changed since a conversation last looked, one entry per path
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChangesSince {
+    pub changes: Vec<FsChange>,
+    /// Changes that were dropped from the journal before they could be reported
+    pub missed: u64,
+}
+
+/// Recent filesystem changes, with a cursor per conversation so each agent turn hears only
+/// about what happened since its previous turn
+#[derive(Default)]
+pub struct ChangeJournal {
+    inner: Mutex<JournalState>,
+}
+
+#[derive(Default)]
+struct JournalState {
+    changes: VecDeque<(u64, FsChange)>,
+    next_seq: u64,
+    cursors: HashMap<String, u64>,
+}
+
+impl ChangeJournal {
+    pub fn record(&self, changes: &[FsChange]) {
+        let Ok(mut state) = self.inner.lock() else {
+            return;
+        };
+        for change in changes {
+            let seq = state.next_seq;
+            state.next_seq += 1;
+            state.changes.push_back((seq, change.clone()));
+            if state.changes.len() > MAX_JOURNAL_CHANGES {
+                state.changes.pop_front();
+            }
+        }
+    }
+
+    /// Changes since the conversation's previous call. The first call for a conversation only
+    /// starts tracking it and reports nothing.
+    pub fn take_since(&self, conversation_id: &str) -> ChangesSince {
+        let Ok(mut state) = self.inner.lock() else {
+            return ChangesSince::default();
+        };
+        let now = state.next_seq;
+        let Some(cursor) = state.cursors.insert(conversation_id.to_string(), now) else {
+            return ChangesSince::default();
+        };
+
+        let oldest = state.changes.front().map(|(seq, _)| *seq).unwrap_or(now);
+        let recent = state
+            .changes
+            .iter()
+            .filter(|(seq, _)| *seq >= cursor)
+            .map(|(_, change)| change);
+        ChangesSince {
+            changes: collapse(recent),
+            missed: oldest.saturating_sub(cursor),
+        }
+    }
+
+    /// Stop tracking a conversation that has ended
+    pub fn forget(&self, conversation_id: &str) {
+        if let Ok(mut state) = self.inner.lock() {
+            state.cursors.remove(conversation_id);
+        }
+    }
+}
+
+/// Reduce a run of changes to one per path, in order of first appearance. A file created and
+/// then edited is still new, and one created and removed again never happened.
+fn collapse<'a>(changes: impl Iterator<Item = &'a FsChange>) -> Vec<FsChange> {
+    let mut collapsed: Vec<Option<FsChange>> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for change in changes {
+        let Some(&i) = index.get(&change.path) else {
+            index.insert(change.path.clone(), collapsed.len());
+            collapsed.push(Some(change.clone()));
+            continue;
+        };
+        let merged = match (collapsed[i].as_ref().map(|c| c.kind), change.kind) {
+            (Some(ChangeKind::Created), ChangeKind::Removed) => None,
+            (Some(ChangeKind::Created), ChangeKind::Modified) => collapsed[i].clone(),
+            (None, ChangeKind::Modified) => Some(FsChange {
+                kind: ChangeKind::Created,
+                ..change.clone()
+            }),
+            _ => Some(change.clone()),
+        };
+        collapsed[i] = merged;
+    }
+    collapsed.into_iter().flatten().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(path: &str, kind: ChangeKind) -> FsChange {
+        FsChange {
+            path: path.to_string(),
+            kind,
+            from: None,
+        }
+    }
+
+    #[test]
+    fn test_take_since_tracks_each_conversation() {
+        let journal = ChangeJournal::default();
+        journal.record(&[change("/a", ChangeKind::Modified)]);
+
+        // Nothing is reported before a conversation's first turn
+        assert!(journal.take_since("one").changes.is_empty());
+
+        journal.record(&[change("/b", ChangeKind::Created)]);
+        assert!(journal.take_since("two").changes.is_empty());
+        assert_eq!(
+            journal.take_since("one").changes,
+            vec![change("/b", ChangeKind::Created)]
+        );
+        assert!(journal.take_since("one").changes.is_empty());
+
+        journal.record(&[change("/c", ChangeKind::Removed)]);
+        assert_eq!(journal.take_since("two").changes.len(), 1);
+
+        journal.forget("two");
+        journal.record(&[change("/d", ChangeKind::Modified)]);
+        assert!(journal.take_since("two").changes.is_empty());
+    }
+
+    #[test]
+    fn test_collapse_per_path() {
+        let journal = ChangeJournal::default();
+        journal.take_since("c");
+        journal.record(&[
+            change("/new", ChangeKind::Created),
+            change("/edited", ChangeKind::Modified),
+            change("/new", ChangeKind::Modified),
+            change("/temp", ChangeKind::Created),
+            change("/edited", ChangeKind::Modified),
+            change("/temp", ChangeKind::Removed),
+            change("/gone", ChangeKind::Modified),
+            change("/gone", ChangeKind::Removed),
+        ]);
+        assert_eq!(
+            journal.take_since("c").changes,
+            vec![
+                change("/new", ChangeKind::Created),
+                change("/edited", ChangeKind::Modified),
+                change("/gone", ChangeKind::Removed),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_missed_changes_are_counted() {
+        let journal = ChangeJournal::default();
+        journal.take_since("c");
+        let flood: Vec<FsChange> = (0..MAX_JOURNAL_CHANGES + 5)
+            .map(|i| change(&format!("/f{}", i), ChangeKind::Created))
+            .collect();
+        journal.record(&flood);
+
+        let since = journal.take_since("c");
+        assert_eq!(since.missed, 5);
+        assert_eq!(since.changes.len(), MAX_JOURNAL_CHANGES);
+    }
+}
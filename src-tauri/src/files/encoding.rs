@@ -0,0 +1,355 @@
+use encoding_rs::{Decoder, Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use super::error::FileOpError;
+
+/// How many leading bytes are inspected to classify a file
+pub const SNIFF_BYTES: usize = 8192;
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+/// Text encodings a file can be read in and written back as
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TextEncoding {
+    #[serde(rename = "utf-8")]
+    Utf8,
+    #[serde(rename = "utf-8-bom")]
+    Utf8Bom,
+    #[serde(rename = "utf-16le")]
+    Utf16Le,
+    #[serde(rename = "utf-16be")]
+    Utf16Be,
+    /// Also covers Latin-1, which it is a superset of for printable text
+    #[serde(rename = "windows-1252")]
+    Windows1252,
+}
+
+impl TextEncoding {
+    pub fn label(self) -> &'static str {
+        match self {
+            TextEncoding::Utf8 => "utf-8",
+            TextEncoding::Utf8Bom => "utf-8-bom",
+            TextEncoding::Utf16Le => "utf-16le",
+            TextEncoding::Utf16Be => "utf-16be",
+            TextEncoding::Windows1252 => "windows-1252",
+        }
+    }
+
+    /// Whether content must be transcoded as a whole rather than split on raw bytes
+    pub fn is_utf16(self) -> bool {
+        matches!(self, TextEncoding::Utf16Le | TextEncoding::Utf16Be)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+    Mixed,
+    /// No line breaks seen
+    None,
+}
+
+impl LineEnding {
+    pub fn label(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "lf",
+            LineEnding::Crlf => "crlf",
+            LineEnding::Mixed => "mixed",
+            LineEnding::None => "no line breaks",
+        }
+    }
+}
+
+/// Encoding and line endings of a text file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextFormat {
+    pub encoding: TextEncoding,
+    pub line_ending: LineEnding,
+}
+
+/// Guess the encoding of a file from its leading bytes, or `None` if it looks binary
+pub fn detect_encoding(sample: &[u8]) -> Option<TextEncoding> {
+    if sample.starts_with(UTF8_BOM) {
+        return Some(TextEncoding::Utf8Bom);
+    }
+    if sample.starts_with(UTF16LE_BOM) {
+        return Some(TextEncoding::Utf16Le);
+    }
+    if sample.starts_with(UTF16BE_BOM) {
+        return Some(TextEncoding::Utf16Be);
+    }
+    if looks_binary(sample) {
+        return None;
+    }
+    match std::str::from_utf8(sample) {
+        Ok(_) => Some(TextEncoding::Utf8),
+        // The sample may end partway through a multi-byte character
+        Err(e) if e.error_len().is_none() => Some(TextEncoding::Utf8),
+        Err(_) => Some(TextEncoding::Windows1252),
+    }
+}
+
+/// Treat content with a NUL byte or mostly control characters as binary
+pub fn looks_binary(sample: &[u8]) -> bool {
+    let sample = &sample[..sample.len().min(SNIFF_BYTES)];
+    if sample.contains(&0) {
+        return true;
+    }
+    let control = sample
+        .iter()
+        .filter(|&&b| b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0C | 0x1B))
+        .count();
+    control * 10 > sample.len()
+}
+
+pub fn detect_line_ending(text: &str) -> LineEnding {
+    let crlf = text.matches("\r\n").count();
+    let lf = text.matches('\n').count() - crlf;
+    match (lf, crlf) {
+        (0, 0) => LineEnding::None,
+        (_, 0) => LineEnding::Lf,
+        (0, _) => LineEnding::Crlf,
+        _ => LineEnding::Mixed,
+    }
+}
+
+fn codec(encoding: TextEncoding) -> &'static Encoding {
+    match encoding {
+        TextEncoding::Utf8 | TextEncoding::Utf8Bom => UTF_8,
+        TextEncoding::Utf16Le => UTF_16LE,
+        TextEncoding::Utf16Be => UTF_16BE,
+        TextEncoding::Windows1252 => WINDOWS_1252,
+    }
+}
+
+/// Decode bytes in `encoding`, dropping a leading byte order mark
+pub fn decode(bytes: &[u8], encoding: TextEncoding) -> String {
+    let (text, _) = codec(encoding).decode_with_bom_removal(bytes);
+    text.into_owned()
+}
+
+/// Transcodes a stream to UTF-8 as it is read, so large files can be split into lines
+pub struct Utf8Reader<R> {
+    inner: R,
+    decoder: Decoder,
+    decoded: Vec<u8>,
+    pos: usize,
+    done: bool,
+}
+
+impl<R: Read> Utf8Reader<R> {
+    pub fn new(inner: R, encoding: TextEncoding) -> Self {
+        Self {
+            inner,
+            decoder: codec(encoding).new_decoder_with_bom_removal(),
+            decoded: Vec::new(),
+            pos: 0,
+            done: false,
+        }
+    }
+}
+
+impl<R: Read> Read for Utf8Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.decoded.len() && !self.done {
+            let mut input = [0u8; SNIFF_BYTES];
+            let n = self.inner.read(&mut input)?;
+            self.done = n == 0;
+            // Sized for the worst case, so the decoder always consumes all of the input
+            let capacity = self.decoder.max_utf8_buffer_length(n).unwrap_or(n * 3 + 16);
+            let mut text = String::with_capacity(capacity);
+            let _ = self
+                .decoder
+                .decode_to_string(&input[..n], &mut text, self.done);
+            self.decoded = text.into_bytes();
+            self.pos = 0;
+        }
+        let n = buf.len().min(self.decoded.len() - self.pos);
+        buf[..n].copy_from_slice(&self.decoded[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Encode text back into `encoding`; fails rather than mixing in bytes from another encoding
+pub fn encode(text: &str, encoding: TextEncoding) -> Result<Vec<u8>, FileOpError> {
+    let bytes = match encoding {
+        TextEncoding::Utf8 => text.as_bytes().to_vec(),
+        TextEncoding::Utf8Bom => [UTF8_BOM, text.as_bytes()].concat(),
+        TextEncoding::Utf16Le => UTF16LE_BOM
+            .iter()
+            .copied()
+            .chain(text.encode_utf16().flat_map(u16::to_le_bytes))
+            .collect(),
+        TextEncoding::Utf16Be => UTF16BE_BOM
+            .iter()
+            .copied()
+            .chain(text.encode_utf16().flat_map(u16::to_be_bytes))
+            .collect(),
+        TextEncoding::Windows1252 => {
+            let (bytes, _, had_errors) = WINDOWS_1252.encode(text);
+            if had_errors {
+                return Err(FileOpError::Invalid(
+                    "text contains characters not representable in Windows-1252".to_string(),
+                ));
+            }
+            bytes.into_owned()
+        }
+    };
+    Ok(bytes)
+}
+
+/// Encode text that follows existing content in `encoding`, so without a byte order mark
+pub fn encode_continuation(text: &str, encoding: TextEncoding) -> Result<Vec<u8>, FileOpError> {
+    let bom = match encoding {
+        TextEncoding::Utf8Bom => UTF8_BOM.len(),
+        TextEncoding::Utf16Le | TextEncoding::Utf16Be => UTF16LE_BOM.len(),
+        TextEncoding::Utf8 | TextEncoding::Windows1252 => 0,
+    };
+    Ok(encode(text, encoding)?.split_off(bom))
+}
+
+/// Convert every line break in `text` to `line_ending`; mixed or unknown endings are left alone
+pub fn apply_line_ending(text: &str, line_ending: LineEnding) -> String {
+    match line_ending {
+        LineEnding::Crlf => text.replace("\r\n", "\n").replace('\n', "\r\n"),
+        LineEnding::Lf => text.replace("\r\n", "\n"),
+        LineEnding::Mixed | LineEnding::None => text.to_string(),
+    }
+}
+
+/// Read up to `SNIFF_BYTES` from the start of a file
+pub fn read_sample(path: &Path) -> io::Result<Vec<u8>> {
+    let mut sample = Vec::with_capacity(SNIFF_BYTES);
+    File::open(path)?
+        .take(SNIFF_BYTES as u64)
+        .read_to_end(&mut sample)?;
+    Ok(sample)
+}
+
+/// Classify a file from its leading bytes, or `None` if it looks binary
+pub fn sniff_file(path: &Path) -> io::Result<Option<TextFormat>> {
+    let sample = read_sample(path)?;
+    Ok(detect_encoding(&sample).map(|encoding| TextFormat {
+        encoding,
+        line_ending: detect_line_ending(&decode(&sample, encoding)),
+    }))
+}
+
+/// Hex-encoded SHA-256 of a file's contents
+pub fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Best guess at a file's MIME type from its extension
+pub fn guess_mime_type(path: &Path) -> String {
+    mime_guess::from_path(path)
+        .first_or_octet_stream()
+        .essence_str()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utf8_reader() {
+        let text = "caf\u{e9} \u{1F600}\n".repeat(2000);
+        for encoding in [TextEncoding::Utf16Le, TextEncoding::Utf16Be] {
+            let mut decoded = String::new();
+            Utf8Reader::new(encode(&text, encoding).unwrap().as_slice(), encoding)
+                .read_to_string(&mut decoded)
+                .unwrap();
+            assert_eq!(decoded, text);
+        }
+    }
+
+    #[test]
+    fn test_detect_encoding() {
+        assert_eq!(detect_encoding(b"plain text\n"), Some(TextEncoding::Utf8));
+        assert_eq!(
+            detect_encoding("caf\u{e9}".as_bytes()),
+            Some(TextEncoding::Utf8)
+        );
+        assert_eq!(
+            detect_encoding(b"\xEF\xBB\xBFhi"),
+            Some(TextEncoding::Utf8Bom)
+        );
+        assert_eq!(
+            detect_encoding(b"\xFF\xFEh\0i\0"),
+            Some(TextEncoding::Utf16Le)
+        );
+        assert_eq!(
+            detect_encoding(b"\xFE\xFF\0h\0i"),
+            Some(TextEncoding::Utf16Be)
+        );
+        assert_eq!(
+            detect_encoding(b"caf\xE9 \x93quoted\x94"),
+            Some(TextEncoding::Windows1252)
+        );
+        // Cut off in the middle of a multi-byte character
+        assert_eq!(detect_encoding(b"abc\xE2\x82"), Some(TextEncoding::Utf8));
+        assert_eq!(detect_encoding(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), None);
+        assert_eq!(detect_encoding(&[1, 2, 3, 4, 5, 6, b'a']), None);
+    }
+
+    #[test]
+    fn test_detect_line_ending() {
+        assert_eq!(detect_line_ending("a\nb\n"), LineEnding::Lf);
+        assert_eq!(detect_line_ending("a\r\nb\r\n"), LineEnding::Crlf);
+        assert_eq!(detect_line_ending("a\r\nb\n"), LineEnding::Mixed);
+        assert_eq!(detect_line_ending("a"), LineEnding::None);
+    }
+
+    #[test]
+    fn test_decode_encode_round_trip() {
+        for encoding in [
+            TextEncoding::Utf8,
+            TextEncoding::Utf8Bom,
+            TextEncoding::Utf16Le,
+            TextEncoding::Utf16Be,
+            TextEncoding::Windows1252,
+        ] {
+            let text = "caf\u{e9} \u{201c}quoted\u{201d}\n";
+            let bytes = encode(text, encoding).unwrap();
+            assert_eq!(detect_encoding(&bytes), Some(encoding));
+            assert_eq!(decode(&bytes, encoding), text, "{}", encoding.label());
+        }
+        assert_eq!(
+            encode("caf\u{e9}", TextEncoding::Windows1252).unwrap(),
+            b"caf\xE9".to_vec()
+        );
+    }
+
+    #[test]
+    fn test_encode_unrepresentable_is_an_error() {
+        let text = "snowman \u{2603}";
+        let err = encode(text, TextEncoding::Windows1252).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("not representable in Windows-1252"));
+        assert!(encode_continuation(text, TextEncoding::Windows1252).is_err());
+    }
+
+    #[test]
+    fn test_apply_line_ending() {
+        assert_eq!(
+            apply_line_ending("a\nb\r\n", LineEnding::Crlf),
+            "a\r\nb\r\n"
+        );
+        assert_eq!(apply_line_ending("a\r\nb\n", LineEnding::Lf), "a\nb\n");
+        assert_eq!(apply_line_ending("a\r\nb\n", LineEnding::Mixed), "a\r\nb\n");
+    }
+}
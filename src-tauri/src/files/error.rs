@@ -0,0 +1,118 @@
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::io;
+use thiserror::Error;
+
+/// Why a file operation failed. Each variant carries the full human-readable message; across
+/// IPC it is sent as `{ code, message }` so callers can branch on `code`.
+#[derive(Debug, Error)]
+pub enum FileOpError {
+    /// Outside every granted folder
+    #[error("{0}")]
+    AccessDenied(String),
+    /// Matching a protected file pattern on the deny-list
+    #[error("{0}")]
+    Blocked(String),
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{0}")]
+    AlreadyExists(String),
+    /// Binary content where text was expected
+    #[error("{0}")]
+    NotUtf8(String),
+    #[error("{0}")]
+    TooLarge(String),
+    #[error("{message}")]
+    Io {
+        kind: io::ErrorKind,
+        message: String,
+    },
+    /// A bad argument: an invalid pattern, query, edit or path
+    #[error("{0}")]
+    Invalid(String),
+    /// A failure outside the file operation itself, such as a poisoned lock
+    #[error("{0}")]
+    Other(String),
+}
+
+impl FileOpError {
+    /// Adapter for `map_err` that prefixes an I/O error with what was being attempted,
+    /// keeping "not found" and "already exists" distinguishable
+    pub fn io(context: &str) -> impl Fn(io::Error) -> FileOpError + '_ {
+        move |err| {
+            let message = format!("{}: {}", context, err);
+            match err.kind() {
+                io::ErrorKind::NotFound => FileOpError::NotFound(message),
+                io::ErrorKind::AlreadyExists => FileOpError::AlreadyExists(message),
+                kind => FileOpError::Io { kind, message },
+            }
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            FileOpError::AccessDenied(_) => "access_denied",
+            FileOpError::Blocked(_) => "blocked",
+            FileOpError::NotFound(_) => "not_found",
+            FileOpError::AlreadyExists(_) => "already_exists",
+            FileOpError::NotUtf8(_) => "not_utf8",
+            FileOpError::TooLarge(_) => "too_large",
+            FileOpError::Io { .. } => "io",
+            FileOpError::Invalid(_) => "invalid",
+            FileOpError::Other(_) => "other",
+        }
+    }
+}
+
+impl Serialize for FileOpError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("FileOpError", 2)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
+}
+
+/// Errors that don't come from a file operation, such as a poisoned lock
+impl From<String> for FileOpError {
+    fn from(message: String) -> Self {
+        FileOpError::Other(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_io_errors_keep_their_kind() {
+        let err = FileOpError::io("Failed to read file")(io::Error::from(io::ErrorKind::NotFound));
+        assert_eq!(err.code(), "not_found");
+        assert!(err.to_string().starts_with("Failed to read file: "));
+
+        let err = FileOpError::io("Failed to write file")(io::Error::from(
+            io::ErrorKind::PermissionDenied,
+        ));
+        assert!(matches!(
+            err,
+            FileOpError::Io {
+                kind: io::ErrorKind::PermissionDenied,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_serializes_as_code_and_message() {
+        let err = FileOpError::AccessDenied("Access denied: file not in granted folder".into());
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            serde_json::json!({
+                "code": "access_denied",
+                "message": "Access denied: file not in granted folder"
+            })
+        );
+        let err = FileOpError::from("lock poisoned".to_string());
+        assert_eq!(err.code(), "other");
+    }
+}
@@ -0,0 +1,840 @@
+use calamine::Reader as _;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use scraper::{ElementRef, Html, Node};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use zip::ZipArchive;
+
+/// Largest XML part read out of an office document, guarding against zip bombs
+const MAX_XML_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Document formats text can be extracted from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DocumentFormat {
+    Pdf,
+    Docx,
+    Odt,
+    Pptx,
+    /// XLSX, XLS, XLSB and ODS workbooks
+    Spreadsheet,
+    Csv,
+    Tsv,
+    Html,
+}
+
+impl DocumentFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "pdf" => Some(Self::Pdf),
+            "docx" => Some(Self::Docx),
+            "odt" => Some(Self::Odt),
+            "pptx" => Some(Self::Pptx),
+            "xlsx" | "xlsm" | "xlsb" | "xls" | "ods" => Some(Self::Spreadsheet),
+            "csv" => Some(Self::Csv),
+            "tsv" => Some(Self::Tsv),
+            "html" | "htm" | "xhtml" => Some(Self::Html),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Pdf => "PDF",
+            Self::Docx => "Word document",
+            Self::Odt => "OpenDocument text",
+            Self::Pptx => "PowerPoint presentation",
+            Self::Spreadsheet => "spreadsheet",
+            Self::Csv => "CSV",
+            Self::Tsv => "TSV",
+            Self::Html => "HTML",
+        }
+    }
+}
+
+/// One page, slide or sheet of a document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentSection {
+    /// e.g. "Page 3", "Slide 2" or "Sheet: Sales"; `None` for single-part documents
+    pub title: Option<String>,
+    pub content: String,
+}
+
+/// Text extracted from a document, as markdown
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtractedDocument {
+    pub format: DocumentFormat,
+    pub sections: Vec<DocumentSection>,
+}
+
+impl ExtractedDocument {
+    /// The whole document as markdown, with a heading marking each page, slide or sheet
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        for section in &self.sections {
+            if let Some(ref title) = section.title {
+                out.push_str(&format!("## {}\n\n", title));
+            }
+            out.push_str(&section.content);
+            if !out.ends_with("\n\n") {
+                out.push_str(if out.ends_with('\n') { "\n" } else { "\n\n" });
+            }
+        }
+        out.truncate(out.trim_end().len());
+        out.push('\n');
+        out
+    }
+}
+
+/// Extract the text of a document, picking the format from its extension
+pub fn extract_document(path: &Path) -> Result<ExtractedDocument, String> {
+    let format = DocumentFormat::from_path(path).ok_or_else(|| {
+        "Unsupported document type; expected PDF, DOCX, ODT, PPTX, XLSX/XLS/ODS, CSV/TSV or HTML"
+            .to_string()
+    })?;
+
+    let sections = match format {
+        DocumentFormat::Pdf => extract_pdf(path)?,
+        DocumentFormat::Docx => {
+            let xml = read_zip_entry(&mut open_zip(path)?, "word/document.xml")?;
+            single(docx_to_markdown(&xml)?)
+        }
+        DocumentFormat::Odt => {
+            let xml = read_zip_entry(&mut open_zip(path)?, "content.xml")?;
+            single(odt_to_markdown(&xml)?)
+        }
+        DocumentFormat::Pptx => extract_pptx(path)?,
+        DocumentFormat::Spreadsheet => extract_spreadsheet(path)?,
+        DocumentFormat::Csv => single(delimited_to_markdown(path, b',')?),
+        DocumentFormat::Tsv => single(delimited_to_markdown(path, b'\t')?),
+        DocumentFormat::Html => {
+            let bytes = std::fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?;
+            single(html_to_markdown(&String::from_utf8_lossy(&bytes)))
+        }
+    };
+
+    Ok(ExtractedDocument { format, sections })
+}
+
+fn single(content: String) -> Vec<DocumentSection> {
+    vec![DocumentSection {
+        title: None,
+        content,
+    }]
+}
+
+fn extract_pdf(path: &Path) -> Result<Vec<DocumentSection>, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    // pdf-extract panics on some malformed files rather than returning an error
+    let pages = std::panic::catch_unwind(|| pdf_extract::extract_text_from_mem_by_pages(&bytes))
+        .map_err(|_| "Failed to extract PDF text: the file appears to be malformed".to_string())?
+        .map_err(|e| format!("Failed to extract PDF text: {}", e))?;
+
+    Ok(pages
+        .iter()
+        .enumerate()
+        .map(|(i, text)| DocumentSection {
+            title: Some(format!("Page {}", i + 1)),
+            content: tidy_lines(text),
+        })
+        .collect())
+}
+
+/// Trim trailing spaces and collapse runs of blank lines
+fn tidy_lines(text: &str) -> String {
+    let mut out = String::new();
+    let mut blank = true;
+    for line in text.lines().map(str::trim_end) {
+        if line.is_empty() {
+            if !blank {
+                out.push('\n');
+            }
+            blank = true;
+            continue;
+        }
+        out.push_str(line);
+        out.push('\n');
+        blank = false;
+    }
+    out.trim_end().to_string()
+}
+
+fn open_zip(path: &Path) -> Result<ZipArchive<File>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    ZipArchive::new(file).map_err(|e| format!("Failed to open document: {}", e))
+}
+
+fn read_zip_entry(archive: &mut ZipArchive<File>, name: &str) -> Result<String, String> {
+    let entry = archive
+        .by_name(name)
+        .map_err(|_| format!("Failed to open document: missing {}", name))?;
+    let mut xml = String::new();
+    entry
+        .take(MAX_XML_BYTES)
+        .read_to_string(&mut xml)
+        .map_err(|e| format!("Failed to read document: {}", e))?;
+    Ok(xml)
+}
+
+fn extract_pptx(path: &Path) -> Result<Vec<DocumentSection>, String> {
+    let mut archive = open_zip(path)?;
+    let mut slides: Vec<(usize, String)> = archive
+        .file_names()
+        .filter_map(|name| {
+            let number = name
+                .strip_prefix("ppt/slides/slide")?
+                .strip_suffix(".xml")?
+                .parse()
+                .ok()?;
+            Some((number, name.to_string()))
+        })
+        .collect();
+    slides.sort();
+
+    slides
+        .iter()
+        .map(|(number, name)| {
+            let xml = read_zip_entry(&mut archive, name)?;
+            Ok(DocumentSection {
+                title: Some(format!("Slide {}", number)),
+                content: pptx_slide_to_markdown(&xml)?,
+            })
+        })
+        .collect()
+}
+
+fn extract_spreadsheet(path: &Path) -> Result<Vec<DocumentSection>, String> {
+    let mut workbook = calamine::open_workbook_auto(path)
+        .map_err(|e| format!("Failed to open spreadsheet: {}", e))?;
+
+    let mut sections = Vec::new();
+    for name in workbook.sheet_names() {
+        let range = workbook
+            .worksheet_range(&name)
+            .map_err(|e| format!("Failed to read sheet '{}': {}", name, e))?;
+        let rows: Vec<Vec<String>> = range
+            .rows()
+            .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+            .collect();
+        sections.push(DocumentSection {
+            title: Some(format!("Sheet: {}", name)),
+            content: markdown_table(&rows),
+        });
+    }
+    Ok(sections)
+}
+
+fn delimited_to_markdown(path: &Path, delimiter: u8) -> Result<String, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .delimiter(delimiter)
+        .from_path(path)
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+
+    let mut rows = Vec::new();
+    for record in reader.byte_records() {
+        let record = record.map_err(|e| format!("Failed to parse file: {}", e))?;
+        rows.push(
+            record
+                .iter()
+                .map(|field| String::from_utf8_lossy(field).into_owned())
+                .collect(),
+        );
+    }
+    Ok(markdown_table(&rows))
+}
+
+/// Render rows as a markdown table, treating the first row as the header
+pub fn markdown_table(rows: &[Vec<String>]) -> String {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    if columns == 0 {
+        return String::new();
+    }
+
+    let mut out = String::new();
+    for (i, row) in rows.iter().enumerate() {
+        out.push('|');
+        for col in 0..columns {
+            let cell = row.get(col).map(String::as_str).unwrap_or("");
+            let cell = cell.replace('|', "\\|").replace(['\r', '\n'], " ");
+            out.push_str(&format!(" {} |", cell.trim()));
+        }
+        out.push('\n');
+        if i == 0 {
+            out.push('|');
+            out.push_str(&" --- |".repeat(columns));
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Accumulates paragraphs, headings, lists and tables into markdown
+#[derive(Default)]
+struct MarkdownBuilder {
+    out: String,
+    paragraph: String,
+    /// Heading or list marker for the current paragraph
+    prefix: String,
+    table_depth: usize,
+    rows: Vec<Vec<String>>,
+    row: Vec<String>,
+    cell: Option<String>,
+}
+
+impl MarkdownBuilder {
+    fn text(&mut self, text: &str) {
+        self.paragraph.push_str(text);
+    }
+
+    /// Add HTML-style text, collapsing runs of whitespace
+    fn collapsed_text(&mut self, text: &str) {
+        let mut collapsed = String::with_capacity(text.len());
+        let mut last_space = self.paragraph.is_empty() || self.paragraph.ends_with([' ', '\n']);
+        for c in text.chars() {
+            if c.is_whitespace() {
+                if !last_space {
+                    collapsed.push(' ');
+                }
+                last_space = true;
+            } else {
+                collapsed.push(c);
+                last_space = false;
+            }
+        }
+        self.paragraph.push_str(&collapsed);
+    }
+
+    fn heading(&mut self, level: usize) {
+        self.prefix = format!("{} ", "#".repeat(level.clamp(1, 6)));
+    }
+
+    fn list_item(&mut self, depth: usize, marker: &str) {
+        self.prefix = format!("{}{} ", "  ".repeat(depth), marker);
+    }
+
+    fn end_paragraph(&mut self) {
+        let text = self
+            .paragraph
+            .lines()
+            .map(str::trim)
+            .collect::<Vec<_>>()
+            .join("\n");
+        let text = text.trim();
+        if let Some(ref mut cell) = self.cell {
+            if !text.is_empty() {
+                if !cell.is_empty() {
+                    cell.push(' ');
+                }
+                cell.push_str(text);
+            }
+        } else if !text.is_empty() {
+            self.out.push_str(&self.prefix);
+            self.out.push_str(text);
+            self.out.push_str("\n\n");
+        }
+        self.paragraph.clear();
+        self.prefix.clear();
+    }
+
+    fn block(&mut self, text: &str) {
+        self.end_paragraph();
+        if let Some(ref mut cell) = self.cell {
+            cell.push_str(text.trim());
+        } else {
+            self.out.push_str(text);
+            self.out.push_str("\n\n");
+        }
+    }
+
+    fn start_table(&mut self) {
+        self.end_paragraph();
+        self.table_depth += 1;
+        if self.table_depth == 1 {
+            self.rows.clear();
+        }
+    }
+
+    // Nested tables are flattened into the cell of the outermost one
+    fn start_row(&mut self) {
+        if self.table_depth == 1 {
+            self.row.clear();
+        }
+    }
+
+    fn start_cell(&mut self) {
+        if self.table_depth == 1 {
+            self.cell = Some(String::new());
+        }
+    }
+
+    fn end_cell(&mut self) {
+        self.end_paragraph();
+        if self.table_depth == 1 {
+            if let Some(cell) = self.cell.take() {
+                self.row.push(cell);
+            }
+        }
+    }
+
+    fn end_row(&mut self) {
+        if self.table_depth == 1 && !self.row.is_empty() {
+            self.rows.push(std::mem::take(&mut self.row));
+        }
+    }
+
+    fn end_table(&mut self) {
+        if self.table_depth == 1 {
+            self.cell = None;
+            let table = markdown_table(&self.rows);
+            if !table.is_empty() {
+                self.out.push_str(&table);
+                self.out.push('\n');
+            }
+        }
+        self.table_depth = self.table_depth.saturating_sub(1);
+    }
+
+    fn finish(mut self) -> String {
+        self.end_paragraph();
+        self.out.trim_end().to_string()
+    }
+}
+
+fn xml_error(e: quick_xml::Error) -> String {
+    format!("Failed to parse document: {}", e)
+}
+
+fn attr(e: &BytesStart, name: &[u8]) -> Option<String> {
+    e.attributes()
+        .flatten()
+        .find(|a| a.key.local_name().as_ref() == name)
+        .and_then(|a| a.unescape_value().ok().map(|v| v.into_owned()))
+}
+
+/// Heading level for Word's built-in "Title" and "HeadingN" paragraph styles
+fn docx_heading_level(style: &str) -> Option<usize> {
+    if style == "Title" {
+        return Some(1);
+    }
+    style.strip_prefix("Heading")?.parse().ok()
+}
+
+fn docx_to_markdown(xml: &str) -> Result<String, String> {
+    let mut reader = Reader::from_str(xml);
+    let mut md = MarkdownBuilder::default();
+    let mut in_text = false;
+    let mut in_props = false;
+
+    loop {
+        match reader.read_event().map_err(xml_error)? {
+            Event::Start(e) => match e.local_name().as_ref() {
+                b"pPr" => in_props = true,
+                b"t" => in_text = true,
+                b"tbl" => md.start_table(),
+                b"tr" => md.start_row(),
+                b"tc" => md.start_cell(),
+                _ => {}
+            },
+            Event::Empty(e) => match e.local_name().as_ref() {
+                b"pStyle" => {
+                    if let Some(level) = attr(&e, b"val").and_then(|v| docx_heading_level(&v)) {
+                        md.heading(level);
+                    }
+                }
+                b"ilvl" => {
+                    let depth = attr(&e, b"val").and_then(|v| v.parse().ok()).unwrap_or(0);
+                    md.list_item(depth, "-");
+                }
+                // Tab stops in paragraph properties aren't content
+                b"tab" if !in_props => md.text("\t"),
+                b"br" | b"cr" => md.text("\n"),
+                _ => {}
+            },
+            Event::End(e) => match e.local_name().as_ref() {
+                b"p" => md.end_paragraph(),
+                b"pPr" => in_props = false,
+                b"t" => in_text = false,
+                b"tc" => md.end_cell(),
+                b"tr" => md.end_row(),
+                b"tbl" => md.end_table(),
+                _ => {}
+            },
+            Event::Text(t) if in_text => md.text(&t.unescape().map_err(xml_error)?),
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(md.finish())
+}
+
+fn odt_to_markdown(xml: &str) -> Result<String, String> {
+    let mut reader = Reader::from_str(xml);
+    let mut md = MarkdownBuilder::default();
+    let mut in_paragraph = 0usize;
+    let mut list_depth = 0usize;
+
+    loop {
+        match reader.read_event().map_err(xml_error)? {
+            Event::Start(e) => match e.local_name().as_ref() {
+                b"h" => {
+                    in_paragraph += 1;
+                    let level = attr(&e, b"outline-level")
+                        .and_then(|v| v.parse().ok())
+                        .unwrap_or(1);
+                    md.heading(level);
+                }
+                b"p" => in_paragraph += 1,
+                b"list" => list_depth += 1,
+                b"list-item" => md.list_item(list_depth.saturating_sub(1), "-"),
+                b"table" => md.start_table(),
+                b"table-row" => md.start_row(),
+                b"table-cell" => md.start_cell(),
+                _ => {}
+            },
+            Event::Empty(e) if in_paragraph > 0 => match e.local_name().as_ref() {
+                b"s" => {
+                    let count = attr(&e, b"c").and_then(|v| v.parse().ok()).unwrap_or(1);
+                    md.text(&" ".repeat(count));
+                }
+                b"tab" => md.text("\t"),
+                b"line-break" => md.text("\n"),
+                _ => {}
+            },
+            Event::End(e) => match e.local_name().as_ref() {
+                b"h" | b"p" => {
+                    in_paragraph = in_paragraph.saturating_sub(1);
+                    md.end_paragraph();
+                }
+                b"list" => list_depth = list_depth.saturating_sub(1),
+                b"table-cell" => md.end_cell(),
+                b"table-row" => md.end_row(),
+                b"table" => md.end_table(),
+                _ => {}
+            },
+            Event::Text(t) if in_paragraph > 0 => md.text(&t.unescape().map_err(xml_error)?),
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(md.finish())
+}
+
+fn pptx_slide_to_markdown(xml: &str) -> Result<String, String> {
+    let mut reader = Reader::from_str(xml);
+    let mut md = MarkdownBuilder::default();
+    let mut in_text = false;
+
+    loop {
+        match reader.read_event().map_err(xml_error)? {
+            Event::Start(e) => match e.local_name().as_ref() {
+                b"t" => in_text = true,
+                b"tbl" => md.start_table(),
+                b"tr" => md.start_row(),
+                b"tc" => md.start_cell(),
+                _ => {}
+            },
+            Event::Empty(e) if e.local_name().as_ref() == b"br" => md.text("\n"),
+            Event::End(e) => match e.local_name().as_ref() {
+                b"p" => md.end_paragraph(),
+                b"t" => in_text = false,
+                b"tc" => md.end_cell(),
+                b"tr" => md.end_row(),
+                b"tbl" => md.end_table(),
+                _ => {}
+            },
+            Event::Text(t) if in_text => md.text(&t.unescape().map_err(xml_error)?),
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(md.finish())
+}
+
+/// Convert an HTML page to markdown, dropping scripts, styles and other non-content markup
+pub fn html_to_markdown(html: &str) -> String {
+    let document = Html::parse_document(html);
+    let mut md = MarkdownBuilder::default();
+    let mut lists = Vec::new();
+    walk_html(document.root_element(), &mut md, &mut lists);
+    md.finish()
+}
+
+/// Open lists while walking HTML: `Some(next number)` for ordered lists, `None` for bullets
+type ListStack = Vec<Option<usize>>;
+
+fn walk_html(element: ElementRef, md: &mut MarkdownBuilder, lists: &mut ListStack) {
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => md.collapsed_text(text),
+            Node::Element(_) => {
+                if let Some(child) = ElementRef::wrap(child) {
+                    html_element(child, md, lists);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn html_element(element: ElementRef, md: &mut MarkdownBuilder, lists: &mut ListStack) {
+    let name = element.value().name();
+    match name {
+        "script" | "style" | "noscript" | "template" | "svg" | "head" | "iframe" | "button"
+        | "select" => {}
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            md.end_paragraph();
+            md.heading(name[1..].parse().unwrap_or(1));
+            walk_html(element, md, lists);
+            md.end_paragraph();
+        }
+        "br" => md.text("\n"),
+        "hr" => md.block("---"),
+        "pre" => {
+            let code: String = element.text().collect();
+            md.block(&format!("```\n{}\n```", code.trim_end_matches('\n')));
+        }
+        "code" => {
+            let code: String = element.text().collect();
+            md.collapsed_text(&format!("`{}`", code.trim()));
+        }
+        "a" => {
+            let text: String = element.text().collect();
+            let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            match element.value().attr("href") {
+                Some(href) if !text.is_empty() && !href.starts_with("javascript:") => {
+                    md.collapsed_text(&format!("[{}]({})", text, href));
+                }
+                _ => md.collapsed_text(&text),
+            }
+        }
+        "img" => {
+            if let Some(alt) = element.value().attr("alt").filter(|a| !a.trim().is_empty()) {
+                md.collapsed_text(&format!("[image: {}]", alt.trim()));
+            }
+        }
+        "strong" | "b" => inline(element, md, lists, "**"),
+        "em" | "i" => inline(element, md, lists, "*"),
+        "ul" | "ol" => {
+            md.end_paragraph();
+            lists.push((name == "ol").then_some(1));
+            walk_html(element, md, lists);
+            lists.pop();
+        }
+        "li" => {
+            md.end_paragraph();
+            let depth = lists.len().saturating_sub(1);
+            let marker = match lists.last_mut() {
+                Some(Some(n)) => {
+                    *n += 1;
+                    format!("{}.", *n - 1)
+                }
+                _ => "-".to_string(),
+            };
+            md.list_item(depth, &marker);
+            walk_html(element, md, lists);
+            md.end_paragraph();
+        }
+        "table" => {
+            md.start_table();
+            walk_html(element, md, lists);
+            md.end_table();
+        }
+        "tr" => {
+            md.start_row();
+            walk_html(element, md, lists);
+            md.end_row();
+        }
+        "td" | "th" => {
+            md.start_cell();
+            walk_html(element, md, lists);
+            md.end_cell();
+        }
+        "p" | "div" | "section" | "article" | "main" | "header" | "footer" | "nav" | "aside"
+        | "blockquote" | "figure" | "figcaption" | "dl" | "dt" | "dd" | "form" | "address"
+        | "details" | "summary" | "body" | "html" => {
+            md.end_paragraph();
+            walk_html(element, md, lists);
+            md.end_paragraph();
+        }
+        _ => walk_html(element, md, lists),
+    }
+}
+
+fn inline(element: ElementRef, md: &mut MarkdownBuilder, lists: &mut ListStack, marker: &str) {
+    let text: String = element.text().collect();
+    if text.trim().is_empty() {
+        return;
+    }
+    md.collapsed_text(marker);
+    walk_html(element, md, lists);
+    md.paragraph.truncate(md.paragraph.trim_end().len());
+    md.text(marker);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    fn write_zip(path: &Path, entries: &[(&str, &str)]) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        for (name, contents) in entries {
+            zip.start_file(*name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn test_markdown_table() {
+        let rows = vec![
+            vec!["name".to_string(), "total".to_string()],
+            vec!["a|b".to_string()],
+        ];
+        assert_eq!(
+            markdown_table(&rows),
+            "| name | total |\n| --- | --- |\n| a\\|b |  |\n"
+        );
+        assert_eq!(markdown_table(&[]), "");
+    }
+
+    #[test]
+    fn test_extract_docx() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("report.docx");
+        write_zip(
+            &path,
+            &[(
+                "word/document.xml",
+                r#"<w:document xmlns:w="w"><w:body>
+                <w:p><w:pPr><w:pStyle w:val="Heading1"/><w:tabs><w:tab w:val="left"/></w:tabs></w:pPr><w:r><w:t>Summary</w:t></w:r></w:p>
+                <w:p><w:r><w:t xml:space="preserve">Revenue &amp; costs </w:t></w:r><w:r><w:t>grew.</w:t></w:r></w:p>
+                <w:p><w:pPr><w:numPr><w:ilvl w:val="0"/></w:numPr></w:pPr><w:r><w:t>First point</w:t></w:r></w:p>
+                <w:tbl><w:tr><w:tc><w:p><w:r><w:t>Region</w:t></w:r></w:p></w:tc><w:tc><w:p><w:r><w:t>Total</w:t></w:r></w:p></w:tc></w:tr>
+                <w:tr><w:tc><w:p><w:r><w:t>EU</w:t></w:r></w:p></w:tc><w:tc><w:p><w:r><w:t>42</w:t></w:r></w:p></w:tc></w:tr></w:tbl>
+                </w:body></w:document>"#,
+            )],
+        );
+
+        let doc = extract_document(&path).unwrap();
+        assert_eq!(doc.format, DocumentFormat::Docx);
+        assert_eq!(
+            doc.to_markdown(),
+            "# Summary\n\nRevenue & costs grew.\n\n- First point\n\n| Region | Total |\n| --- | --- |\n| EU | 42 |\n"
+        );
+    }
+
+    #[test]
+    fn test_extract_odt() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("notes.odt");
+        write_zip(
+            &path,
+            &[(
+                "content.xml",
+                r#"<office:document-content xmlns:office="o" xmlns:text="t"><office:body><office:text>
+                <text:h text:outline-level="2">Agenda</text:h>
+                <text:p>One<text:s text:c="2"/>two<text:line-break/>three</text:p>
+                <text:list><text:list-item><text:p>Item</text:p></text:list-item></text:list>
+                </office:text></office:body></office:document-content>"#,
+            )],
+        );
+
+        let doc = extract_document(&path).unwrap();
+        assert_eq!(
+            doc.to_markdown(),
+            "## Agenda\n\nOne  two\nthree\n\n- Item\n"
+        );
+    }
+
+    #[test]
+    fn test_extract_pptx_orders_slides() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("deck.pptx");
+        let slide = |text: &str| {
+            format!(
+                r#"<p:sld xmlns:p="p" xmlns:a="a"><p:txBody><a:p><a:r><a:t>{}</a:t></a:r></a:p></p:txBody></p:sld>"#,
+                text
+            )
+        };
+        let (s1, s2, s10) = (slide("Intro"), slide("Plan"), slide("End"));
+        write_zip(
+            &path,
+            &[
+                ("ppt/slides/slide10.xml", s10.as_str()),
+                ("ppt/slides/slide2.xml", s2.as_str()),
+                ("ppt/slides/slide1.xml", s1.as_str()),
+                ("ppt/slides/_rels/slide1.xml.rels", "<Relationships/>"),
+            ],
+        );
+
+        let doc = extract_document(&path).unwrap();
+        let titles: Vec<_> = doc
+            .sections
+            .iter()
+            .filter_map(|s| s.title.clone())
+            .collect();
+        assert_eq!(titles, vec!["Slide 1", "Slide 2", "Slide 10"]);
+        assert!(doc
+            .to_markdown()
+            .starts_with("## Slide 1\n\nIntro\n\n## Slide 2\n\nPlan"));
+    }
+
+    #[test]
+    fn test_extract_csv_and_tsv() {
+        let dir = tempdir().unwrap();
+        let csv_path = dir.path().join("data.csv");
+        std::fs::write(&csv_path, "region,total\nEU,\"1,200\"\n").unwrap();
+        let doc = extract_document(&csv_path).unwrap();
+        assert_eq!(
+            doc.to_markdown(),
+            "| region | total |\n| --- | --- |\n| EU | 1,200 |\n"
+        );
+
+        let tsv_path = dir.path().join("data.tsv");
+        std::fs::write(&tsv_path, "a\tb\n1\t2\n").unwrap();
+        assert!(extract_document(&tsv_path)
+            .unwrap()
+            .to_markdown()
+            .contains("| 1 | 2 |"));
+    }
+
+    #[test]
+    fn test_html_to_markdown() {
+        let html = r#"<html><head><title>T</title><style>p{}</style></head><body>
+            <h1>Title</h1>
+            <p>Some   <b>bold</b> text with a <a href="https://example.com">link</a>.</p>
+            <script>alert(1)</script>
+            <ol><li>one</li><li>two</li></ol>
+            <pre>let x = 1;
+let y = 2;</pre>
+            <table><tr><th>k</th><th>v</th></tr><tr><td>a</td><td>1</td></tr></table>
+            </body></html>"#;
+
+        assert_eq!(
+            html_to_markdown(html),
+            "# Title\n\nSome **bold** text with a [link](https://example.com).\n\n1. one\n\n2. two\n\n```\nlet x = 1;\nlet y = 2;\n```\n\n| k | v |\n| --- | --- |\n| a | 1 |"
+        );
+    }
+
+    #[test]
+    fn test_extract_malformed_pdf_errors() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("broken.pdf");
+        std::fs::write(&path, b"%PDF-1.4 not really a pdf").unwrap();
+        assert!(extract_document(&path).is_err());
+    }
+
+    #[test]
+    fn test_extract_unsupported() {
+        assert!(extract_document(Path::new("/tmp/archive.tar")).is_err());
+    }
+}
@@ -1,6 +1,26 @@
 pub mod types;
 pub mod permissions;
 pub mod operations;
+pub mod audit;
+pub mod encoding;
+pub mod error;
+pub mod extract;
+pub mod query;
+pub mod archive;
+pub mod batch;
+pub mod changes;
 
-pub use types::{FileInfo, FolderPermission};
-pub use permissions::PermissionStore;
+pub use types::{
+    BinaryFileInfo, DirectoryTree, DuplicateGroup, DuplicateReport, EditResult, FileChunk,
+    FileEdit, FileInfo, FileRead, FileStat, FolderPermission, ListOptions, ReadRange,
+    SearchMatch, SearchOptions, SearchResults, TreeEntry,
+};
+pub use permissions::{PathAccess, PermissionStore};
+pub use audit::{AuditEntry, AuditFilter, AuditLog};
+pub use encoding::{LineEnding, TextEncoding};
+pub use error::FileOpError;
+pub use extract::{DocumentFormat, DocumentSection, ExtractedDocument};
+pub use query::QueryResult;
+pub use archive::{ArchiveListing, CreateSummary, ExtractSummary};
+pub use batch::{AppliedBatch, BatchHistory, BatchPlan, BatchRequest};
+pub use changes::{ChangeJournal, ChangeKind, ChangesSince, FsChange};
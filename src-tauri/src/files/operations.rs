@@ -1,80 +1,1190 @@
-use std::fs;
-use std::path::Path;
+use ignore::overrides::OverrideBuilder;
+use ignore::WalkBuilder;
+use regex::RegexBuilder;
+use similar::TextDiff;
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
 
-use super::permissions::PermissionStore;
-use super::types::FileInfo;
+use super::error::FileOpError;
+use super::encoding::{
+    apply_line_ending, decode, detect_encoding, detect_line_ending, encode, encode_continuation,
+    guess_mime_type, looks_binary, read_sample, sha256_file, sniff_file, LineEnding, TextFormat,
+    Utf8Reader,
+};
+use super::archive::{self, ArchiveListing, CreateSummary, ExtractSummary};
+use super::batch::{self, AppliedBatch, BatchPlan, BatchRequest};
+use super::extract::{extract_document, ExtractedDocument};
+use super::permissions::{PathAccess, PermissionStore};
+use super::query::{load_table, run_query, QueryResult};
+use super::types::{
+    BinaryFileInfo, DirectoryTree, DuplicateGroup, DuplicateReport, EditResult, FileChunk,
+    FileEdit, FileInfo, FileRead, FileStat, ListOptions, ReadRange, SearchMatch, SearchOptions,
+    SearchResults, TreeEntry,
+};
 
-pub fn list_directory(store: &PermissionStore, path: &str) -> Result<Vec<FileInfo>, String> {
-    if !store.is_path_allowed(path) {
-        return Err("Access denied: folder not in granted permissions".to_string());
+/// Largest file `read_file` will load whole; bigger files must be read in ranges
+pub const MAX_READ_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Longest line (in characters) returned from a content search
+const MAX_SEARCH_LINE_CHARS: usize = 240;
+
+/// Check a path against the store, producing the error reported to the caller
+fn ensure_allowed(store: &PermissionStore, path: &str, denied: &str) -> Result<(), FileOpError> {
+    match store.check_access(path) {
+        PathAccess::Allowed => Ok(()),
+        PathAccess::Blocked => Err(FileOpError::Blocked(format!(
+            "Blocked by policy: {} matches a protected file pattern",
+            path
+        ))),
+        PathAccess::NotGranted => Err(FileOpError::AccessDenied(format!(
+            "Access denied: {}",
+            denied
+        ))),
+    }
+}
+
+/// Errors from the directory walker, which wraps I/O errors with path context
+fn walk_error(context: &str) -> impl Fn(ignore::Error) -> FileOpError + '_ {
+    move |err| {
+        let kind = err
+            .io_error()
+            .map_or(std::io::ErrorKind::Other, |e| e.kind());
+        FileOpError::io(context)(std::io::Error::new(kind, err.to_string()))
     }
+}
 
-    let entries =
-        fs::read_dir(path).map_err(|e| format!("Failed to read directory: {}", e))?;
+fn file_info(name: String, path: String, metadata: &fs::Metadata) -> FileInfo {
+    FileInfo {
+        name,
+        path,
+        is_directory: metadata.is_dir(),
+        size: metadata.len(),
+        modified: metadata
+            .modified()
+            .map(|t| t.duration_since(std::time::UNIX_EPOCH).unwrap().as_secs())
+            .unwrap_or(0),
+    }
+}
+
+pub fn list_directory(store: &PermissionStore, path: &str) -> Result<Vec<FileInfo>, FileOpError> {
+    ensure_allowed(store, path, "folder not in granted permissions")?;
+
+    let entries = fs::read_dir(path).map_err(FileOpError::io("Failed to read directory"))?;
 
     let mut files = Vec::new();
     for entry in entries {
-        let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
+        let entry = entry.map_err(FileOpError::io("Failed to read entry"))?;
+        let entry_path = entry.path().to_string_lossy().to_string();
+        if store.is_path_blocked(&entry_path) {
+            continue;
+        }
         let metadata = entry
             .metadata()
-            .map_err(|e| format!("Failed to read metadata: {}", e))?;
-
-        files.push(FileInfo {
-            name: entry.file_name().to_string_lossy().to_string(),
-            path: entry.path().to_string_lossy().to_string(),
-            is_directory: metadata.is_dir(),
-            size: metadata.len(),
-            modified: metadata
-                .modified()
-                .map(|t| t.duration_since(std::time::UNIX_EPOCH).unwrap().as_secs())
-                .unwrap_or(0),
-        });
+            .map_err(FileOpError::io("Failed to read metadata"))?;
+
+        files.push(file_info(
+            entry.file_name().to_string_lossy().to_string(),
+            entry_path,
+            &metadata,
+        ));
     }
 
     Ok(files)
 }
 
-pub fn read_file(store: &PermissionStore, path: &str) -> Result<String, String> {
-    if !store.is_path_allowed(path) {
-        return Err("Access denied: file not in granted folder".to_string());
+/// Walker over `root` honoring include/exclude globs, hidden files and gitignore rules
+fn build_walker(
+    root: &str,
+    include: &[String],
+    exclude: &[String],
+    show_hidden: bool,
+    respect_gitignore: bool,
+) -> Result<WalkBuilder, FileOpError> {
+    let mut overrides = OverrideBuilder::new(root);
+    for pattern in include {
+        overrides.add(pattern).map_err(|e| {
+            FileOpError::Invalid(format!("Invalid include pattern '{}': {}", pattern, e))
+        })?;
+    }
+    for pattern in exclude {
+        overrides.add(&format!("!{}", pattern)).map_err(|e| {
+            FileOpError::Invalid(format!("Invalid exclude pattern '{}': {}", pattern, e))
+        })?;
+    }
+    let overrides = overrides
+        .build()
+        .map_err(|e| FileOpError::Invalid(format!("Invalid filter patterns: {}", e)))?;
+
+    let mut builder = WalkBuilder::new(root);
+    builder
+        .hidden(!show_hidden)
+        .git_ignore(respect_gitignore)
+        .git_global(respect_gitignore)
+        .git_exclude(respect_gitignore)
+        .ignore(respect_gitignore)
+        .parents(respect_gitignore)
+        .require_git(false)
+        .overrides(overrides)
+        .sort_by_file_name(|a, b| a.cmp(b));
+    Ok(builder)
+}
+
+pub fn list_directory_recursive(
+    store: &PermissionStore,
+    path: &str,
+    options: &ListOptions,
+) -> Result<DirectoryTree, FileOpError> {
+    ensure_allowed(store, path, "folder not in granted permissions")?;
+
+    let walker = build_walker(
+        path,
+        &options.include,
+        &options.exclude,
+        options.show_hidden,
+        options.respect_gitignore,
+    )?
+    .max_depth(options.max_depth)
+    .build();
+
+    let mut entries = Vec::new();
+    let mut truncated = false;
+    for result in walker {
+        let entry = result.map_err(walk_error("Failed to read entry"))?;
+        if entry.depth() == 0 {
+            continue;
+        }
+        let entry_path = entry.path().to_string_lossy().to_string();
+        if store.is_path_blocked(&entry_path) {
+            continue;
+        }
+        if entries.len() >= options.max_entries {
+            truncated = true;
+            break;
+        }
+        let metadata = entry
+            .metadata()
+            .map_err(walk_error("Failed to read metadata"))?;
+
+        entries.push(TreeEntry {
+            info: file_info(
+                entry.file_name().to_string_lossy().to_string(),
+                entry_path,
+                &metadata,
+            ),
+            depth: entry.depth(),
+        });
+    }
+
+    // With include filters, directories holding no matching files are just noise
+    if !options.include.is_empty() {
+        let file_paths: Vec<String> = entries
+            .iter()
+            .filter(|e| !e.info.is_directory)
+            .map(|e| e.info.path.clone())
+            .collect();
+        entries.retain(|e| {
+            !e.info.is_directory
+                || file_paths
+                    .iter()
+                    .any(|f| Path::new(f).starts_with(&e.info.path))
+        });
+    }
+
+    Ok(DirectoryTree {
+        root: path.to_string(),
+        entries,
+        truncated,
+    })
+}
+
+/// Search file contents under one granted path, or all granted folders when none is given
+pub fn search_files(
+    store: &PermissionStore,
+    options: &SearchOptions,
+) -> Result<SearchResults, FileOpError> {
+    if options.pattern.is_empty() {
+        return Err(FileOpError::Invalid("Search pattern is empty".to_string()));
+    }
+
+    let roots: Vec<String> = match options.path {
+        Some(ref path) => {
+            ensure_allowed(store, path, "folder not in granted permissions")?;
+            vec![path.clone()]
+        }
+        None => store.list().into_iter().map(|f| f.path).collect(),
+    };
+    if roots.is_empty() {
+        return Err(FileOpError::AccessDenied(
+            "No folders have been granted to search".to_string(),
+        ));
+    }
+
+    let pattern = if options.literal {
+        regex::escape(&options.pattern)
+    } else {
+        options.pattern.clone()
+    };
+    let regex = RegexBuilder::new(&pattern)
+        .case_insensitive(!options.case_sensitive)
+        .build()
+        .map_err(|e| FileOpError::Invalid(format!("Invalid search pattern: {}", e)))?;
+
+    let mut results = SearchResults {
+        matches: Vec::new(),
+        files_searched: 0,
+        truncated: false,
+    };
+
+    for root in &roots {
+        let walker = build_walker(root, &options.include, &options.exclude, false, true)?.build();
+        for entry in walker.flatten() {
+            if !entry.file_type().is_some_and(|t| t.is_file()) {
+                continue;
+            }
+            let file_path = entry.path().to_string_lossy().to_string();
+            if store.is_path_blocked(&file_path) {
+                continue;
+            }
+            let too_large = entry
+                .metadata()
+                .map(|m| m.len() > options.max_file_size)
+                .unwrap_or(true);
+            if too_large {
+                continue;
+            }
+            let bytes = match fs::read(entry.path()) {
+                Ok(bytes) => bytes,
+                Err(_) => continue,
+            };
+            if looks_binary(&bytes) {
+                continue;
+            }
+            results.files_searched += 1;
+
+            let text = String::from_utf8_lossy(&bytes);
+            let lines: Vec<&str> = text.lines().collect();
+            for (idx, line) in lines.iter().enumerate() {
+                if !regex.is_match(line) {
+                    continue;
+                }
+                if results.matches.len() >= options.max_results {
+                    results.truncated = true;
+                    return Ok(results);
+                }
+                let start = idx.saturating_sub(options.context_lines);
+                let end = (idx + 1 + options.context_lines).min(lines.len());
+                results.matches.push(SearchMatch {
+                    path: file_path.clone(),
+                    line_number: idx + 1,
+                    line: truncate_line(line),
+                    before: lines[start..idx].iter().map(|l| truncate_line(l)).collect(),
+                    after: lines[idx + 1..end]
+                        .iter()
+                        .map(|l| truncate_line(l))
+                        .collect(),
+                });
+            }
+        }
+    }
+
+    Ok(results)
+}
+
+fn truncate_line(line: &str) -> String {
+    match line.char_indices().nth(MAX_SEARCH_LINE_CHARS) {
+        Some((idx, _)) => format!("{}...", &line[..idx]),
+        None => line.to_string(),
+    }
+}
+
+/// Most files a duplicate scan considers before stopping
+const MAX_DUPLICATE_SCAN_FILES: usize = 50_000;
+
+fn unix_secs(time: std::io::Result<SystemTime>) -> Option<u64> {
+    time.ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_secs())
+}
+
+#[cfg(unix)]
+fn unix_mode(metadata: &fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    Some(metadata.permissions().mode() & 0o7777)
+}
+
+#[cfg(not(unix))]
+fn unix_mode(_metadata: &fs::Metadata) -> Option<u32> {
+    None
+}
+
+/// Detailed metadata for a path; a symlink is described itself rather than followed
+pub fn stat_file(store: &PermissionStore, path: &str, hash: bool) -> Result<FileStat, FileOpError> {
+    ensure_allowed(store, path, "file not in granted folder")?;
+
+    let target = Path::new(path);
+    let metadata =
+        fs::symlink_metadata(target).map_err(FileOpError::io("Failed to read metadata"))?;
+    let is_symlink = metadata.file_type().is_symlink();
+    let symlink_target = if is_symlink {
+        fs::read_link(target)
+            .ok()
+            .map(|t| t.to_string_lossy().to_string())
+    } else {
+        None
+    };
+    let sha256 = if hash && metadata.is_file() {
+        Some(sha256_file(target).map_err(FileOpError::io("Failed to hash file"))?)
+    } else {
+        None
+    };
+    let name = target
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string());
+
+    Ok(FileStat {
+        info: file_info(name, path.to_string(), &metadata),
+        created: unix_secs(metadata.created()),
+        accessed: unix_secs(metadata.accessed()),
+        mode: unix_mode(&metadata),
+        readonly: metadata.permissions().readonly(),
+        is_symlink,
+        symlink_target,
+        mime_type: (!metadata.is_dir()).then(|| guess_mime_type(target)),
+        sha256,
+    })
+}
+
+/// Group files under a granted folder whose contents are identical. Files are bucketed by
+/// size first so only same-sized candidates get hashed.
+pub fn find_duplicates(
+    store: &PermissionStore,
+    path: &str,
+    min_size: u64,
+) -> Result<DuplicateReport, FileOpError> {
+    ensure_allowed(store, path, "folder not in granted permissions")?;
+
+    let walker = build_walker(path, &[], &[], false, true)?.build();
+    let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+    let mut files_scanned = 0;
+    let mut truncated = false;
+    for entry in walker.flatten() {
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        if store.is_path_blocked(&entry.path().to_string_lossy()) {
+            continue;
+        }
+        let size = match entry.metadata() {
+            Ok(metadata) => metadata.len(),
+            Err(_) => continue,
+        };
+        // Empty files are trivially identical and not worth reporting
+        if size == 0 || size < min_size {
+            continue;
+        }
+        if files_scanned >= MAX_DUPLICATE_SCAN_FILES {
+            truncated = true;
+            break;
+        }
+        files_scanned += 1;
+        by_size.entry(size).or_default().push(entry.into_path());
+    }
+
+    let mut groups = Vec::new();
+    for (size, candidates) in by_size {
+        if candidates.len() < 2 {
+            continue;
+        }
+        let mut by_hash: HashMap<String, Vec<String>> = HashMap::new();
+        for candidate in candidates {
+            if let Ok(hash) = sha256_file(&candidate) {
+                by_hash
+                    .entry(hash)
+                    .or_default()
+                    .push(candidate.to_string_lossy().to_string());
+            }
+        }
+        for (sha256, mut paths) in by_hash {
+            if paths.len() > 1 {
+                paths.sort();
+                groups.push(DuplicateGroup {
+                    sha256,
+                    size,
+                    paths,
+                });
+            }
+        }
+    }
+    groups.sort_by(|a, b| {
+        b.wasted_bytes()
+            .cmp(&a.wasted_bytes())
+            .then_with(|| a.paths[0].cmp(&b.paths[0]))
+    });
+
+    Ok(DuplicateReport {
+        root: path.to_string(),
+        wasted_bytes: groups.iter().map(DuplicateGroup::wasted_bytes).sum(),
+        groups,
+        files_scanned,
+        truncated,
+    })
+}
+
+/// Read a whole text file, decoding it from whatever encoding it was saved in
+pub fn read_file(store: &PermissionStore, path: &str) -> Result<String, FileOpError> {
+    ensure_allowed(store, path, "file not in granted folder")?;
+    read_text(Path::new(path)).map(|(text, _)| text)
+}
+
+fn read_text(path: &Path) -> Result<(String, TextFormat), FileOpError> {
+    let size = fs::metadata(path)
+        .map_err(FileOpError::io("Failed to read file"))?
+        .len();
+    if size > MAX_READ_BYTES {
+        return Err(FileOpError::TooLarge(format!(
+            "File too large to read at once ({} bytes, limit {}); read it in ranges",
+            size, MAX_READ_BYTES
+        )));
+    }
+    let bytes = fs::read(path).map_err(FileOpError::io("Failed to read file"))?;
+    let encoding = detect_encoding(&bytes).ok_or_else(|| {
+        FileOpError::NotUtf8(format!(
+            "Cannot read binary file ({}, {} bytes)",
+            guess_mime_type(path),
+            size
+        ))
+    })?;
+    let text = decode(&bytes, encoding);
+    let line_ending = detect_line_ending(&text);
+    Ok((
+        text,
+        TextFormat {
+            encoding,
+            line_ending,
+        },
+    ))
+}
+
+/// Read part of a file without loading the rest of it, stopping at `max_bytes` of content.
+/// Binary files are described by type, size and hash instead of being read.
+pub fn read_file_range(
+    store: &PermissionStore,
+    path: &str,
+    range: &ReadRange,
+    max_bytes: u64,
+) -> Result<FileRead, FileOpError> {
+    ensure_allowed(store, path, "file not in granted folder")?;
+    let path = Path::new(path);
+    let metadata = fs::metadata(path).map_err(FileOpError::io("Failed to read file"))?;
+    if metadata.is_dir() {
+        return Err(FileOpError::Invalid(
+            "Failed to read file: path is a directory".to_string(),
+        ));
+    }
+    let total_bytes = metadata.len();
+
+    let sample = read_sample(path).map_err(FileOpError::io("Failed to read file"))?;
+    let Some(encoding) = detect_encoding(&sample) else {
+        let sha256 = sha256_file(path).map_err(FileOpError::io("Failed to read file"))?;
+        return Ok(FileRead::Binary(BinaryFileInfo {
+            mime_type: guess_mime_type(path),
+            size: total_bytes,
+            sha256,
+        }));
+    };
+
+    let format = TextFormat {
+        encoding,
+        line_ending: detect_line_ending(&decode(&sample, encoding)),
+    };
+    let file = File::open(path).map_err(FileOpError::io("Failed to read file"))?;
+    read_range(file, total_bytes, range, max_bytes, format).map(FileRead::Text)
+}
+
+fn read_range(
+    file: File,
+    total_bytes: u64,
+    range: &ReadRange,
+    max_bytes: u64,
+    format: TextFormat,
+) -> Result<FileChunk, FileOpError> {
+    let (start, limit) = match *range {
+        ReadRange::Bytes { offset, limit } => {
+            return read_byte_range(file, total_bytes, offset, limit, max_bytes, format)
+        }
+        ReadRange::Tail { lines } => {
+            return read_tail(
+                line_reader(file, format),
+                total_bytes,
+                lines,
+                max_bytes,
+                format,
+            )
+        }
+        ReadRange::Head { lines } => (1, Some(lines)),
+        ReadRange::Lines { offset, limit } => (offset.max(1), limit),
+    };
+    read_line_range(
+        line_reader(file, format),
+        total_bytes,
+        start,
+        limit,
+        max_bytes,
+        format,
+    )
+}
+
+/// UTF-16 can't be split on raw newline bytes, so its lines are read from a UTF-8 transcoding
+fn line_reader(file: File, format: TextFormat) -> Box<dyn Read> {
+    if format.encoding.is_utf16() {
+        Box::new(Utf8Reader::new(file, format.encoding))
+    } else {
+        Box::new(file)
     }
-    fs::read_to_string(path).map_err(|e| format!("Failed to read file: {}", e))
 }
 
-pub fn write_file(store: &PermissionStore, path: &str, content: &str) -> Result<(), String> {
-    if !store.is_path_allowed(path) {
-        return Err("Access denied: file not in granted folder".to_string());
+/// Decode lines that came from `line_reader`
+fn decode_lines(bytes: &[u8], format: TextFormat) -> String {
+    if format.encoding.is_utf16() {
+        String::from_utf8_lossy(bytes).into_owned()
+    } else {
+        decode(bytes, format.encoding)
     }
-    fs::write(path, content).map_err(|e| format!("Failed to write file: {}", e))
 }
 
-pub fn create_file(store: &PermissionStore, path: &str, content: &str) -> Result<(), String> {
-    if !store.is_path_allowed(path) {
-        return Err("Access denied: location not in granted folder".to_string());
+/// Most bytes of a single line kept in memory; the rest of a longer line is skipped
+const MAX_LINE_BYTES: usize = 64 * 1024;
+
+/// Call `f` with each line (including its terminator) and its 1-based number.
+/// Lines longer than `MAX_LINE_BYTES` are cut short but keep their terminator.
+fn for_each_line(reader: impl Read, mut f: impl FnMut(usize, &[u8])) -> Result<usize, FileOpError> {
+    let mut reader = BufReader::new(reader);
+    let mut buf = Vec::new();
+    let mut line_no = 0;
+    loop {
+        buf.clear();
+        let mut read_any = false;
+        let mut ended = false;
+        let mut cut = false;
+        while !ended {
+            let available = reader
+                .fill_buf()
+                .map_err(FileOpError::io("Failed to read file"))?;
+            if available.is_empty() {
+                break;
+            }
+            read_any = true;
+            let used = match available.iter().position(|&b| b == b'\n') {
+                Some(idx) => {
+                    ended = true;
+                    idx + 1
+                }
+                None => available.len(),
+            };
+            let room = MAX_LINE_BYTES - buf.len();
+            if used > room {
+                cut = true;
+            }
+            buf.extend_from_slice(&available[..used.min(room)]);
+            reader.consume(used);
+        }
+        if !read_any {
+            return Ok(line_no);
+        }
+        if cut && ended && buf.last() != Some(&b'\n') {
+            buf.push(b'\n');
+        }
+        line_no += 1;
+        f(line_no, &buf);
     }
-    if Path::new(path).exists() {
-        return Err("File already exists".to_string());
+}
+
+fn read_line_range(
+    reader: impl Read,
+    total_bytes: u64,
+    start: usize,
+    limit: Option<usize>,
+    max_bytes: u64,
+    format: TextFormat,
+) -> Result<FileChunk, FileOpError> {
+    let mut content = Vec::new();
+    let mut end_line = start - 1;
+    let mut truncated = false;
+    let total_lines = for_each_line(reader, |line_no, line| {
+        let in_range = line_no >= start && limit.is_none_or(|l| line_no < start + l);
+        if !in_range || truncated {
+            return;
+        }
+        if (content.len() + line.len()) as u64 > max_bytes {
+            truncated = true;
+            return;
+        }
+        content.extend_from_slice(line);
+        end_line = line_no;
+    })?;
+
+    Ok(FileChunk {
+        content: decode_lines(&content, format),
+        total_bytes,
+        total_lines: Some(total_lines),
+        start_line: Some(start),
+        end_line: Some(end_line),
+        start_byte: None,
+        end_byte: None,
+        truncated,
+        encoding: format.encoding,
+        line_ending: format.line_ending,
+    })
+}
+
+fn read_tail(
+    reader: impl Read,
+    total_bytes: u64,
+    lines: usize,
+    max_bytes: u64,
+    format: TextFormat,
+) -> Result<FileChunk, FileOpError> {
+    // Keep only the newest lines that fit under the cap, so memory stays bounded
+    let mut window: VecDeque<Vec<u8>> = VecDeque::new();
+    let mut size: u64 = 0;
+    let total_lines = for_each_line(reader, |_, line| {
+        if lines == 0 {
+            return;
+        }
+        window.push_back(line.to_vec());
+        size += line.len() as u64;
+        while window.len() > lines || size > max_bytes {
+            match window.pop_front() {
+                Some(line) => size -= line.len() as u64,
+                None => break,
+            }
+        }
+    })?;
+    let truncated = window.len() < lines.min(total_lines);
+    let content: Vec<u8> = window.iter().flatten().copied().collect();
+
+    Ok(FileChunk {
+        content: decode_lines(&content, format),
+        total_bytes,
+        total_lines: Some(total_lines),
+        start_line: Some(total_lines - window.len() + 1),
+        end_line: Some(total_lines),
+        start_byte: None,
+        end_byte: None,
+        truncated,
+        encoding: format.encoding,
+        line_ending: format.line_ending,
+    })
+}
+
+fn read_byte_range<R: Read + Seek>(
+    mut reader: R,
+    total_bytes: u64,
+    offset: u64,
+    limit: Option<u64>,
+    max_bytes: u64,
+    format: TextFormat,
+) -> Result<FileChunk, FileOpError> {
+    let mut start = offset.min(total_bytes);
+    let mut requested = limit
+        .unwrap_or(total_bytes - start)
+        .min(total_bytes - start);
+    let mut len = requested.min(max_bytes);
+    // Keep UTF-16 reads on code unit boundaries
+    if format.encoding.is_utf16() {
+        start -= start % 2;
+        requested -= requested % 2;
+        len -= len % 2;
     }
-    fs::write(path, content).map_err(|e| format!("Failed to create file: {}", e))
+
+    reader
+        .seek(SeekFrom::Start(start))
+        .map_err(FileOpError::io("Failed to read file"))?;
+    let mut buf = Vec::with_capacity(len as usize);
+    (&mut reader)
+        .take(len)
+        .read_to_end(&mut buf)
+        .map_err(FileOpError::io("Failed to read file"))?;
+
+    Ok(FileChunk {
+        content: decode(&buf, format.encoding),
+        total_bytes,
+        // Counting lines would mean reading the whole file
+        total_lines: None,
+        start_line: None,
+        end_line: None,
+        start_byte: Some(start),
+        end_byte: Some(start + buf.len() as u64),
+        truncated: len < requested,
+        encoding: format.encoding,
+        line_ending: format.line_ending,
+    })
 }
 
-pub fn delete_file(store: &PermissionStore, path: &str) -> Result<(), String> {
-    if !store.is_path_allowed(path) {
-        return Err("Access denied: file not in granted folder".to_string());
+/// Largest document `read_document` will extract text from
+pub const MAX_DOCUMENT_BYTES: u64 = 50 * 1024 * 1024;
+
+/// Extract the text of a PDF, office document, spreadsheet, CSV or HTML file as markdown
+pub fn read_document(
+    store: &PermissionStore,
+    path: &str,
+) -> Result<ExtractedDocument, FileOpError> {
+    ensure_allowed(store, path, "file not in granted folder")?;
+    let metadata = fs::metadata(path).map_err(FileOpError::io("Failed to read file"))?;
+    if metadata.is_dir() {
+        return Err(FileOpError::Invalid(
+            "Failed to read file: path is a directory".to_string(),
+        ));
     }
-    fs::remove_file(path).map_err(|e| format!("Failed to delete file: {}", e))
+    if metadata.len() > MAX_DOCUMENT_BYTES {
+        return Err(FileOpError::TooLarge(format!(
+            "Document too large to extract ({} bytes, limit {})",
+            metadata.len(),
+            MAX_DOCUMENT_BYTES
+        )));
+    }
+    extract_document(Path::new(path)).map_err(FileOpError::Invalid)
 }
 
-pub fn move_file(store: &PermissionStore, src: &str, dest: &str) -> Result<(), String> {
-    if !store.is_path_allowed(src) || !store.is_path_allowed(dest) {
-        return Err("Access denied: source or destination not in granted folder".to_string());
+/// Largest CSV/JSON file `query_data` will load
+pub const MAX_DATA_BYTES: u64 = 50 * 1024 * 1024;
+
+/// Run a SQL-like query over a CSV, TSV, JSON or JSONL file
+pub fn query_data(
+    store: &PermissionStore,
+    path: &str,
+    query: &str,
+) -> Result<QueryResult, FileOpError> {
+    ensure_allowed(store, path, "file not in granted folder")?;
+    let size = fs::metadata(path)
+        .map_err(FileOpError::io("Failed to read file"))?
+        .len();
+    if size > MAX_DATA_BYTES {
+        return Err(FileOpError::TooLarge(format!(
+            "Data file too large to query ({} bytes, limit {})",
+            size, MAX_DATA_BYTES
+        )));
+    }
+    let table = load_table(Path::new(path)).map_err(FileOpError::Invalid)?;
+    run_query(&table, query).map_err(|e| FileOpError::Invalid(format!("Invalid query: {}", e)))
+}
+
+/// Overwrite a file, keeping the encoding and line endings of the text already there
+pub fn write_file(store: &PermissionStore, path: &str, content: &str) -> Result<(), FileOpError> {
+    ensure_allowed(store, path, "file not in granted folder")?;
+    let path = write_target(store, path)?;
+    let bytes = match sniff_file(&path) {
+        Ok(Some(format)) => encode_text(content, format)?,
+        _ => content.as_bytes().to_vec(),
+    };
+    write_atomic(&path, &bytes).map_err(FileOpError::io("Failed to write file"))
+}
+
+/// The file a write to `path` should replace; a symlink is followed to its target, which
+/// must be allowed too, so the rename doesn't swap out the link itself
+fn write_target(store: &PermissionStore, path: &str) -> Result<PathBuf, FileOpError> {
+    let is_symlink = fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink());
+    if !is_symlink {
+        return Ok(PathBuf::from(path));
+    }
+    let target = fs::canonicalize(path).map_err(FileOpError::io("Failed to resolve symlink"))?;
+    ensure_allowed(
+        store,
+        &target.to_string_lossy(),
+        "symlink target not in granted folder",
+    )?;
+    Ok(target)
+}
+
+fn encode_text(text: &str, format: TextFormat) -> Result<Vec<u8>, FileOpError> {
+    encode(
+        &apply_line_ending(text, format.line_ending),
+        format.encoding,
+    )
+}
+
+/// Replace a file's contents via a synced sibling temp file and a rename, so a crash
+/// leaves either the old or the new contents but never a truncated file
+fn write_atomic(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let file_name = path.file_name().ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, "path has no file name")
+    })?;
+    let tmp_path = dir.join(format!(
+        ".{}.{}.tmp",
+        file_name.to_string_lossy(),
+        Uuid::new_v4().simple()
+    ));
+
+    let result = (|| {
+        let mut tmp = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&tmp_path)?;
+        tmp.write_all(contents)?;
+        if let Ok(metadata) = fs::metadata(path) {
+            tmp.set_permissions(metadata.permissions())?;
+        }
+        tmp.sync_all()?;
+        fs::rename(&tmp_path, path)?;
+        sync_dir(dir);
+        Ok(())
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+/// Persist a rename by syncing the containing directory (best effort)
+#[cfg(unix)]
+fn sync_dir(dir: &Path) {
+    if let Ok(dir) = File::open(dir) {
+        let _ = dir.sync_all();
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) {}
+
+/// Apply targeted edits to a file, writing nothing unless every edit applies cleanly
+pub fn edit_file(
+    store: &PermissionStore,
+    path: &str,
+    edits: &[FileEdit],
+) -> Result<EditResult, FileOpError> {
+    ensure_allowed(store, path, "file not in granted folder")?;
+    if edits.is_empty() {
+        return Err(FileOpError::Invalid("No edits given".to_string()));
+    }
+    let target = write_target(store, path)?;
+    let (text, format) = read_text(&target)?;
+    // Edit CRLF files as LF so search text from the model matches, then restore on write
+    let original = match format.line_ending {
+        LineEnding::Crlf => text.replace("\r\n", "\n"),
+        _ => text,
+    };
+
+    let mut updated = original.clone();
+    for (i, edit) in edits.iter().enumerate() {
+        updated = apply_edit(&updated, edit)
+            .map_err(|e| FileOpError::Invalid(format!("Edit {} failed: {}", i + 1, e)))?;
+    }
+
+    let diff = TextDiff::from_lines(&original, &updated)
+        .unified_diff()
+        .context_radius(3)
+        .header(path, path)
+        .to_string();
+
+    if updated != original {
+        write_atomic(&target, &encode_text(&updated, format)?)
+            .map_err(FileOpError::io("Failed to write file"))?;
+    }
+
+    Ok(EditResult {
+        edits_applied: edits.len(),
+        diff,
+    })
+}
+
+fn apply_edit(content: &str, edit: &FileEdit) -> Result<String, String> {
+    match edit {
+        FileEdit::Replace { search, replace } => {
+            if search.is_empty() {
+                return Err("search text is empty".to_string());
+            }
+            match content.matches(search.as_str()).count() {
+                0 => Err("search text not found in file".to_string()),
+                1 => Ok(content.replacen(search.as_str(), replace, 1)),
+                n => Err(format!(
+                    "search text found {} times; include more surrounding text to make it unique",
+                    n
+                )),
+            }
+        }
+        FileEdit::ReplaceLines {
+            start,
+            end,
+            content: replacement,
+        } => {
+            let lines: Vec<&str> = content.split_inclusive('\n').collect();
+            if *start == 0 || start > end || *end > lines.len() {
+                return Err(format!(
+                    "line range {}-{} is outside the file's {} lines",
+                    start,
+                    end,
+                    lines.len()
+                ));
+            }
+
+            let mut replacement = replacement.clone();
+            let replaced_ends_with_newline = lines[*end - 1].ends_with('\n');
+            if replaced_ends_with_newline && !replacement.is_empty() && !replacement.ends_with('\n')
+            {
+                replacement.push('\n');
+            }
+
+            let mut out = String::with_capacity(content.len() + replacement.len());
+            out.extend(lines[..*start - 1].iter().copied());
+            out.push_str(&replacement);
+            out.extend(lines[*end..].iter().copied());
+            Ok(out)
+        }
     }
-    fs::rename(src, dest).map_err(|e| format!("Failed to move file: {}", e))
+}
+
+pub fn create_file(store: &PermissionStore, path: &str, content: &str) -> Result<(), FileOpError> {
+    ensure_allowed(store, path, "location not in granted folder")?;
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::AlreadyExists => {
+                FileOpError::AlreadyExists("File already exists".to_string())
+            }
+            _ => FileOpError::io("Failed to create file")(e),
+        })?;
+
+    let result = file
+        .write_all(content.as_bytes())
+        .and_then(|()| file.sync_all());
+    if let Err(e) = result {
+        // Don't leave a partially written new file behind
+        let _ = fs::remove_file(path);
+        return Err(FileOpError::io("Failed to create file")(e));
+    }
+    Ok(())
+}
+
+pub fn delete_file(store: &PermissionStore, path: &str) -> Result<(), FileOpError> {
+    ensure_allowed(store, path, "file not in granted folder")?;
+    fs::remove_file(path).map_err(FileOpError::io("Failed to delete file"))
+}
+
+pub fn move_file(store: &PermissionStore, src: &str, dest: &str) -> Result<(), FileOpError> {
+    ensure_allowed(store, src, "source or destination not in granted folder")?;
+    ensure_allowed(store, dest, "source or destination not in granted folder")?;
+    fs::rename(src, dest).map_err(FileOpError::io("Failed to move file"))
+}
+
+/// Create a directory along with any missing parents
+pub fn create_directory(store: &PermissionStore, path: &str) -> Result<(), FileOpError> {
+    ensure_allowed(store, path, "location not in granted folder")?;
+    fs::create_dir_all(path).map_err(FileOpError::io("Failed to create directory"))
+}
+
+/// Copy a single file, refusing to overwrite an existing destination; returns bytes copied
+pub fn copy_file(store: &PermissionStore, src: &str, dest: &str) -> Result<u64, FileOpError> {
+    ensure_allowed(store, src, "source or destination not in granted folder")?;
+    ensure_allowed(store, dest, "source or destination not in granted folder")?;
+    if Path::new(src).is_dir() {
+        return Err(FileOpError::Invalid(
+            "Source is a directory; use copy_directory".to_string(),
+        ));
+    }
+    let mut source = File::open(src).map_err(FileOpError::io("Failed to open source file"))?;
+    // create_new refuses an existing destination, including a symlink, without a race
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(dest)
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::AlreadyExists => {
+                FileOpError::AlreadyExists("Destination already exists".to_string())
+            }
+            _ => FileOpError::io("Failed to create destination file")(e),
+        })?;
+
+    let result = std::io::copy(&mut source, &mut file).and_then(|bytes| {
+        if let Ok(metadata) = source.metadata() {
+            file.set_permissions(metadata.permissions())?;
+        }
+        file.sync_all().map(|()| bytes)
+    });
+    result.map_err(|e| {
+        // Don't leave a partial copy behind
+        let _ = fs::remove_file(dest);
+        FileOpError::io("Failed to copy file")(e)
+    })
+}
+
+/// Copy a directory tree into a new destination, skipping files blocked by policy;
+/// returns the number of files copied
+pub fn copy_directory(
+    store: &PermissionStore,
+    src: &str,
+    dest: &str,
+) -> Result<usize, FileOpError> {
+    ensure_allowed(store, src, "source or destination not in granted folder")?;
+    ensure_allowed(store, dest, "source or destination not in granted folder")?;
+    let src_path = Path::new(src);
+    let dest_path = Path::new(dest);
+    if !src_path.is_dir() {
+        return Err(FileOpError::Invalid(
+            "Source is not a directory".to_string(),
+        ));
+    }
+    if dest_path.exists() {
+        return Err(FileOpError::AlreadyExists(
+            "Destination already exists".to_string(),
+        ));
+    }
+    if dest_path.starts_with(src_path) {
+        return Err(FileOpError::Invalid(
+            "Cannot copy a directory into itself".to_string(),
+        ));
+    }
+    copy_dir_contents(store, src_path, dest_path)
+}
+
+fn copy_dir_contents(
+    store: &PermissionStore,
+    src: &Path,
+    dest: &Path,
+) -> Result<usize, FileOpError> {
+    fs::create_dir(dest).map_err(FileOpError::io("Failed to create directory"))?;
+    let entries = fs::read_dir(src).map_err(FileOpError::io("Failed to read directory"))?;
+
+    let mut copied = 0;
+    for entry in entries {
+        let entry = entry.map_err(FileOpError::io("Failed to read entry"))?;
+        let entry_path = entry.path();
+        if store.is_path_blocked(&entry_path.to_string_lossy()) {
+            continue;
+        }
+        let file_type = entry
+            .file_type()
+            .map_err(FileOpError::io("Failed to read metadata"))?;
+        let target = dest.join(entry.file_name());
+        if file_type.is_dir() {
+            copied += copy_dir_contents(store, &entry_path, &target)?;
+        } else if file_type.is_file() {
+            fs::copy(&entry_path, &target).map_err(FileOpError::io("Failed to copy file"))?;
+            copied += 1;
+        }
+    }
+    Ok(copied)
+}
+
+/// Whether a path may be written or read, used for per-entry checks inside bulk operations
+fn is_accessible(store: &PermissionStore, path: &Path) -> bool {
+    store.check_access(&path.to_string_lossy()) == PathAccess::Allowed
+}
+
+pub fn list_archive(store: &PermissionStore, path: &str) -> Result<ArchiveListing, FileOpError> {
+    ensure_allowed(store, path, "file not in granted folder")?;
+    archive::list_archive(Path::new(path))
+}
+
+/// Extract an archive into a granted folder; entries that would escape it, land on
+/// protected paths or are links are skipped and reported
+pub fn extract_archive(
+    store: &PermissionStore,
+    path: &str,
+    dest: &str,
+    overwrite: bool,
+) -> Result<ExtractSummary, FileOpError> {
+    ensure_allowed(store, path, "archive or destination not in granted folder")?;
+    ensure_allowed(store, dest, "archive or destination not in granted folder")?;
+    archive::extract_archive(Path::new(path), Path::new(dest), overwrite, &|target| {
+        is_accessible(store, target)
+    })
+}
+
+/// Create a zip or tar(.gz) archive of granted files and folders, skipping protected files
+pub fn create_archive(
+    store: &PermissionStore,
+    sources: &[String],
+    dest: &str,
+) -> Result<CreateSummary, FileOpError> {
+    for source in sources {
+        ensure_allowed(store, source, "source or destination not in granted folder")?;
+    }
+    ensure_allowed(store, dest, "source or destination not in granted folder")?;
+    let sources: Vec<PathBuf> = sources.iter().map(PathBuf::from).collect();
+    archive::create_archive(&sources, Path::new(dest), &|path| {
+        is_accessible(store, path)
+    })
+}
+
+/// Expand a batch of moves and check it against the grants and the filesystem, changing nothing
+pub fn plan_batch(
+    store: &PermissionStore,
+    request: &BatchRequest,
+) -> Result<BatchPlan, FileOpError> {
+    if let BatchRequest::Rename { folder, .. } | BatchRequest::OrganizeByType { folder } = request {
+        ensure_allowed(store, folder, "folder not in granted permissions")?;
+    }
+    batch::plan_batch(request, &|path| is_accessible(store, path))
+}
+
+/// Plan a batch and carry it out all at once; nothing moves if any part of it conflicts
+pub fn apply_batch(
+    store: &PermissionStore,
+    request: &BatchRequest,
+) -> Result<AppliedBatch, FileOpError> {
+    let plan = plan_batch(store, request)?;
+    batch::apply_plan(&plan)
+}
+
+/// Move everything in an applied batch back where it came from
+pub fn undo_batch(store: &PermissionStore, applied: &AppliedBatch) -> Result<(), FileOpError> {
+    for m in &applied.moves {
+        ensure_allowed(store, &m.from, "batch touches a folder no longer granted")?;
+        ensure_allowed(store, &m.to, "batch touches a folder no longer granted")?;
+    }
+    batch::undo_batch(applied)
+}
+
+/// Remove a directory only if it is empty
+pub fn remove_empty_directory(store: &PermissionStore, path: &str) -> Result<(), FileOpError> {
+    ensure_allowed(store, path, "folder not in granted permissions")?;
+    let mut entries = fs::read_dir(path).map_err(FileOpError::io("Failed to read directory"))?;
+    if entries.next().is_some() {
+        return Err(FileOpError::Invalid("Directory is not empty".to_string()));
+    }
+    fs::remove_dir(path).map_err(FileOpError::io("Failed to remove directory"))
+}
+
+/// Append content to the end of an existing file, in the file's encoding and line endings
+pub fn append_to_file(
+    store: &PermissionStore,
+    path: &str,
+    content: &str,
+) -> Result<(), FileOpError> {
+    ensure_allowed(store, path, "file not in granted folder")?;
+    let target = write_target(store, path)?;
+    let bytes = match sniff_file(&target) {
+        Ok(Some(format)) => encode_continuation(
+            &apply_line_ending(content, format.line_ending),
+            format.encoding,
+        )?,
+        _ => content.as_bytes().to_vec(),
+    };
+    let mut file = OpenOptions::new()
+        .append(true)
+        .open(&target)
+        .map_err(FileOpError::io("Failed to open file for append"))?;
+    file.write_all(&bytes)
+        .map_err(FileOpError::io("Failed to append to file"))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::files::TextEncoding;
     use std::fs;
     use tempfile::tempdir;
 
@@ -104,7 +1214,206 @@ mod tests {
         let store = PermissionStore::new();
         let result = list_directory(&store, "/tmp/some-path");
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("Access denied"));
+        assert!(result.unwrap_err().to_string().contains("Access denied"));
+    }
+
+    fn setup_tree() -> tempfile::TempDir {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src/nested")).unwrap();
+        fs::create_dir_all(dir.path().join("target")).unwrap();
+        fs::create_dir_all(dir.path().join("docs")).unwrap();
+        fs::write(dir.path().join("README.md"), "readme").unwrap();
+        fs::write(dir.path().join(".hidden"), "hidden").unwrap();
+        fs::write(dir.path().join(".gitignore"), "target/\n").unwrap();
+        fs::write(dir.path().join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(dir.path().join("src/nested/deep.rs"), "").unwrap();
+        fs::write(dir.path().join("target/out.bin"), "bin").unwrap();
+        fs::write(dir.path().join("docs/guide.md"), "guide").unwrap();
+        dir
+    }
+
+    fn tree_names(tree: &DirectoryTree) -> Vec<String> {
+        tree.entries.iter().map(|e| e.info.name.clone()).collect()
+    }
+
+    #[test]
+    fn test_list_recursive_respects_gitignore_and_hidden() {
+        let dir = setup_tree();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+
+        let tree = list_directory_recursive(
+            &store,
+            dir.path().to_str().unwrap(),
+            &ListOptions::default(),
+        )
+        .unwrap();
+        let names = tree_names(&tree);
+
+        assert!(names.contains(&"deep.rs".to_string()));
+        assert!(names.contains(&"guide.md".to_string()));
+        assert!(!names.contains(&"target".to_string()));
+        assert!(!names.contains(&".hidden".to_string()));
+        assert!(!tree.truncated);
+
+        let options = ListOptions {
+            show_hidden: true,
+            respect_gitignore: false,
+            ..Default::default()
+        };
+        let tree =
+            list_directory_recursive(&store, dir.path().to_str().unwrap(), &options).unwrap();
+        let names = tree_names(&tree);
+        assert!(names.contains(&"out.bin".to_string()));
+        assert!(names.contains(&".hidden".to_string()));
+    }
+
+    #[test]
+    fn test_list_recursive_max_depth() {
+        let dir = setup_tree();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+
+        let options = ListOptions {
+            max_depth: Some(1),
+            ..Default::default()
+        };
+        let tree =
+            list_directory_recursive(&store, dir.path().to_str().unwrap(), &options).unwrap();
+
+        assert!(tree.entries.iter().all(|e| e.depth == 1));
+        assert!(tree_names(&tree).contains(&"src".to_string()));
+        assert!(!tree_names(&tree).contains(&"main.rs".to_string()));
+    }
+
+    #[test]
+    fn test_list_recursive_include_exclude() {
+        let dir = setup_tree();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+
+        let options = ListOptions {
+            include: vec!["*.rs".to_string()],
+            exclude: vec!["nested".to_string()],
+            ..Default::default()
+        };
+        let tree =
+            list_directory_recursive(&store, dir.path().to_str().unwrap(), &options).unwrap();
+
+        assert_eq!(
+            tree_names(&tree),
+            vec!["src".to_string(), "main.rs".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_list_recursive_truncates() {
+        let dir = setup_tree();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+
+        let options = ListOptions {
+            max_entries: 2,
+            ..Default::default()
+        };
+        let tree =
+            list_directory_recursive(&store, dir.path().to_str().unwrap(), &options).unwrap();
+
+        assert_eq!(tree.entries.len(), 2);
+        assert!(tree.truncated);
+    }
+
+    #[test]
+    fn test_list_recursive_denied() {
+        let store = PermissionStore::new();
+        let result = list_directory_recursive(&store, "/tmp/some-path", &ListOptions::default());
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("Access denied"));
+    }
+
+    #[test]
+    fn test_search_files_with_context() {
+        let dir = setup_tree();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+        fs::write(
+            dir.path().join("docs/notes.txt"),
+            "alpha\nbeta TODO item\ngamma\n",
+        )
+        .unwrap();
+
+        let options = SearchOptions {
+            pattern: "todo".to_string(),
+            ..Default::default()
+        };
+        let results = search_files(&store, &options).unwrap();
+
+        assert_eq!(results.matches.len(), 1);
+        let m = &results.matches[0];
+        assert!(m.path.ends_with("notes.txt"));
+        assert_eq!(m.line_number, 2);
+        assert_eq!(m.line, "beta TODO item");
+        assert_eq!(m.before, vec!["alpha".to_string()]);
+        assert_eq!(m.after, vec!["gamma".to_string()]);
+    }
+
+    #[test]
+    fn test_search_files_literal_and_filters() {
+        let dir = setup_tree();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+        fs::write(dir.path().join("src/lib.rs"), "fn a() {}\nlet x = a.b();\n").unwrap();
+        fs::write(dir.path().join("docs/a.md"), "a.b()\n").unwrap();
+
+        let options = SearchOptions {
+            pattern: "a.b()".to_string(),
+            literal: true,
+            include: vec!["*.rs".to_string()],
+            path: Some(dir.path().to_str().unwrap().to_string()),
+            ..Default::default()
+        };
+        let results = search_files(&store, &options).unwrap();
+
+        assert_eq!(results.matches.len(), 1);
+        assert!(results.matches[0].path.ends_with("lib.rs"));
+        assert_eq!(results.matches[0].line_number, 2);
+    }
+
+    #[test]
+    fn test_search_files_skips_blocked_and_binary() {
+        let dir = setup_tree();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+        fs::write(dir.path().join(".env"), "needle=1").unwrap();
+        fs::write(dir.path().join("blob.bin"), b"needle\0\0").unwrap();
+
+        let options = SearchOptions {
+            pattern: "needle".to_string(),
+            ..Default::default()
+        };
+        let results = search_files(&store, &options).unwrap();
+        assert!(results.matches.is_empty());
+    }
+
+    #[test]
+    fn test_search_files_caps_results() {
+        let dir = tempdir().unwrap();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+        fs::write(dir.path().join("many.txt"), "hit\n".repeat(10)).unwrap();
+
+        let options = SearchOptions {
+            pattern: "hit".to_string(),
+            max_results: 3,
+            ..Default::default()
+        };
+        let results = search_files(&store, &options).unwrap();
+        assert_eq!(results.matches.len(), 3);
+        assert!(results.truncated);
+    }
+
+    #[test]
+    fn test_search_files_denied() {
+        let store = PermissionStore::new();
+        let options = SearchOptions {
+            pattern: "x".to_string(),
+            path: Some("/etc".to_string()),
+            ..Default::default()
+        };
+        let result = search_files(&store, &options);
+        assert!(result.unwrap_err().to_string().contains("Access denied"));
     }
 
     #[test]
@@ -120,12 +1429,270 @@ mod tests {
         assert_eq!(result.unwrap(), "hello world");
     }
 
+    fn numbered_file(dir: &tempfile::TempDir, lines: usize) -> String {
+        let file_path = dir.path().join("numbered.txt");
+        let content: String = (1..=lines).map(|i| format!("line {}\n", i)).collect();
+        fs::write(&file_path, content).unwrap();
+        file_path.to_str().unwrap().to_string()
+    }
+
+    fn read_chunk(store: &PermissionStore, path: &str, range: &ReadRange, max: u64) -> FileChunk {
+        match read_file_range(store, path, range, max).unwrap() {
+            FileRead::Text(chunk) => chunk,
+            FileRead::Binary(info) => panic!("unexpected binary file: {:?}", info),
+        }
+    }
+
+    #[test]
+    fn test_read_file_range_lines() {
+        let dir = tempdir().unwrap();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+        let path = numbered_file(&dir, 10);
+
+        let range = ReadRange::Lines {
+            offset: 3,
+            limit: Some(2),
+        };
+        let chunk = read_chunk(&store, &path, &range, 1024);
+
+        assert_eq!(chunk.content, "line 3\nline 4\n");
+        assert_eq!(chunk.total_lines, Some(10));
+        assert_eq!(chunk.total_bytes, 71);
+        assert_eq!((chunk.start_line, chunk.end_line), (Some(3), Some(4)));
+        assert!(!chunk.truncated);
+        assert!(!chunk.is_complete());
+    }
+
+    #[test]
+    fn test_read_file_range_whole_file_is_complete() {
+        let dir = tempdir().unwrap();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+        let path = numbered_file(&dir, 3);
+
+        let range = ReadRange::Lines {
+            offset: 1,
+            limit: None,
+        };
+        let chunk = read_chunk(&store, &path, &range, 1024);
+        assert_eq!(chunk.content, "line 1\nline 2\nline 3\n");
+        assert!(chunk.is_complete());
+    }
+
+    #[test]
+    fn test_read_file_range_head_and_tail() {
+        let dir = tempdir().unwrap();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+        let path = numbered_file(&dir, 10);
+
+        let head = read_chunk(&store, &path, &ReadRange::Head { lines: 2 }, 1024);
+        assert_eq!(head.content, "line 1\nline 2\n");
+
+        let tail = read_chunk(&store, &path, &ReadRange::Tail { lines: 2 }, 1024);
+        assert_eq!(tail.content, "line 9\nline 10\n");
+        assert_eq!((tail.start_line, tail.end_line), (Some(9), Some(10)));
+    }
+
+    #[test]
+    fn test_read_file_range_cuts_overlong_lines() {
+        let dir = tempdir().unwrap();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+        let path = dir.path().join("long.txt");
+        let long = "x".repeat(MAX_LINE_BYTES + 100);
+        fs::write(&path, format!("{long}\nshort\n")).unwrap();
+        let path = path.to_str().unwrap().to_string();
+
+        let tail = read_chunk(&store, &path, &ReadRange::Tail { lines: 2 }, u64::MAX);
+        assert_eq!(tail.total_lines, Some(2));
+        assert_eq!(tail.content.len(), MAX_LINE_BYTES + "\nshort\n".len());
+        assert!(tail.content.ends_with("x\nshort\n"));
+    }
+
+    #[test]
+    fn test_read_file_range_bytes() {
+        let dir = tempdir().unwrap();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+        let path = numbered_file(&dir, 10);
+
+        let range = ReadRange::Bytes {
+            offset: 7,
+            limit: Some(6),
+        };
+        let chunk = read_chunk(&store, &path, &range, 1024);
+        assert_eq!(chunk.content, "line 2");
+        assert_eq!((chunk.start_byte, chunk.end_byte), (Some(7), Some(13)));
+        assert_eq!(chunk.total_lines, None);
+    }
+
+    #[test]
+    fn test_read_file_range_size_cap() {
+        let dir = tempdir().unwrap();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+        let path = numbered_file(&dir, 10);
+
+        let range = ReadRange::Lines {
+            offset: 1,
+            limit: None,
+        };
+        let chunk = read_chunk(&store, &path, &range, 21);
+        assert_eq!(chunk.content, "line 1\nline 2\nline 3\n");
+        assert_eq!(chunk.end_line, Some(3));
+        assert!(chunk.truncated);
+
+        let tail = read_chunk(&store, &path, &ReadRange::Tail { lines: 5 }, 10);
+        assert_eq!(tail.content, "line 10\n");
+        assert!(tail.truncated);
+
+        let bytes = ReadRange::Bytes {
+            offset: 0,
+            limit: None,
+        };
+        let chunk = read_chunk(&store, &path, &bytes, 5);
+        assert_eq!(chunk.content, "line ");
+        assert!(chunk.truncated);
+    }
+
+    #[test]
+    fn test_read_file_range_binary() {
+        let dir = tempdir().unwrap();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+        let file_path = dir.path().join("logo.png");
+        fs::write(&file_path, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+        let path = file_path.to_str().unwrap();
+
+        let read = read_file_range(&store, path, &ReadRange::Head { lines: 10 }, 1024).unwrap();
+        match read {
+            FileRead::Binary(info) => {
+                assert_eq!(info.mime_type, "image/png");
+                assert_eq!(info.size, 16);
+                assert_eq!(info.sha256.len(), 64);
+            }
+            FileRead::Text(_) => panic!("binary file read as text"),
+        }
+
+        let err = read_file(&store, path).unwrap_err();
+        assert!(matches!(err, FileOpError::NotUtf8(_)));
+        assert!(err
+            .to_string()
+            .contains("Cannot read binary file (image/png, 16 bytes)"));
+    }
+
+    #[test]
+    fn test_read_file_transcodes_encodings() {
+        let dir = tempdir().unwrap();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+
+        let latin1 = dir.path().join("latin1.txt");
+        fs::write(&latin1, b"caf\xE9\r\nna\xEFve\r\n").unwrap();
+        let latin1 = latin1.to_str().unwrap();
+        assert_eq!(
+            read_file(&store, latin1).unwrap(),
+            "caf\u{e9}\r\nna\u{ef}ve\r\n"
+        );
+        let chunk = read_chunk(&store, latin1, &ReadRange::Tail { lines: 1 }, 1024);
+        assert_eq!(chunk.content, "na\u{ef}ve\r\n");
+        assert_eq!(chunk.encoding, TextEncoding::Windows1252);
+        assert_eq!(chunk.line_ending, LineEnding::Crlf);
+
+        let utf16 = dir.path().join("utf16.txt");
+        fs::write(
+            &utf16,
+            encode("one\ntwo\nthree\n", TextEncoding::Utf16Le).unwrap(),
+        )
+        .unwrap();
+        let utf16 = utf16.to_str().unwrap();
+        let chunk = read_chunk(
+            &store,
+            utf16,
+            &ReadRange::Lines {
+                offset: 2,
+                limit: Some(1),
+            },
+            1024,
+        );
+        assert_eq!(chunk.content, "two\n");
+        assert_eq!(chunk.total_lines, Some(3));
+        assert_eq!(chunk.total_bytes, 30);
+        assert_eq!(chunk.encoding, TextEncoding::Utf16Le);
+        assert_eq!(chunk.line_ending, LineEnding::Lf);
+
+        let tail = read_chunk(&store, utf16, &ReadRange::Tail { lines: 1 }, 1024);
+        assert_eq!(tail.content, "three\n");
+        // Byte offsets are into the stored file, rounded down to a whole code unit
+        let range = ReadRange::Bytes {
+            offset: 3,
+            limit: Some(6),
+        };
+        let chunk = read_chunk(&store, utf16, &range, 1024);
+        assert_eq!(chunk.content, "one");
+        assert_eq!((chunk.start_byte, chunk.end_byte), (Some(2), Some(8)));
+    }
+
+    #[test]
+    fn test_write_and_edit_preserve_format() {
+        let dir = tempdir().unwrap();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+        let file_path = dir.path().join("notes.txt");
+        fs::write(&file_path, b"caf\xE9\r\nold\r\n").unwrap();
+        let path = file_path.to_str().unwrap();
+
+        write_file(&store, path, "caf\u{e9}\nnew\n").unwrap();
+        assert_eq!(fs::read(&file_path).unwrap(), b"caf\xE9\r\nnew\r\n");
+
+        let edits = vec![FileEdit::Replace {
+            search: "caf\u{e9}\nnew".to_string(),
+            replace: "caf\u{e9}\nnewer".to_string(),
+        }];
+        let result = edit_file(&store, path, &edits).unwrap();
+        assert!(result.diff.contains("+newer"));
+        assert_eq!(fs::read(&file_path).unwrap(), b"caf\xE9\r\nnewer\r\n");
+    }
+
+    #[test]
+    fn test_read_document() {
+        let dir = tempdir().unwrap();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+        let file_path = dir.path().join("table.csv");
+        fs::write(&file_path, "a,b\n1,2\n").unwrap();
+
+        let doc = read_document(&store, file_path.to_str().unwrap()).unwrap();
+        assert!(doc.to_markdown().contains("| 1 | 2 |"));
+
+        let result = read_document(&store, "/etc/report.pdf");
+        assert!(result.unwrap_err().to_string().contains("Access denied"));
+    }
+
+    #[test]
+    fn test_query_data() {
+        let dir = tempdir().unwrap();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+        let file_path = dir.path().join("orders.csv");
+        fs::write(&file_path, "region,total\nEU,10\nUS,5\nEU,2.5\n").unwrap();
+        let path = file_path.to_str().unwrap();
+
+        let result = query_data(&store, path, "SELECT SUM(total) WHERE region = 'EU'").unwrap();
+        assert_eq!(result.rows, vec![vec![serde_json::json!(12.5)]]);
+
+        let err = query_data(&store, path, "SELECT FROM").unwrap_err();
+        assert!(matches!(err, FileOpError::Invalid(_)));
+        assert!(err.to_string().starts_with("Invalid query:"));
+
+        let result = query_data(&store, "/etc/data.csv", "SELECT *");
+        assert!(result.unwrap_err().to_string().contains("Access denied"));
+    }
+
+    #[test]
+    fn test_read_file_range_denied() {
+        let store = PermissionStore::new();
+        let result = read_file_range(&store, "/etc/passwd", &ReadRange::Head { lines: 1 }, 1024);
+        assert!(result.unwrap_err().to_string().contains("Access denied"));
+    }
+
     #[test]
     fn test_read_file_denied() {
         let store = PermissionStore::new();
         let result = read_file(&store, "/etc/passwd");
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("Access denied"));
+        assert!(result.unwrap_err().to_string().contains("Access denied"));
     }
 
     #[test]
@@ -141,12 +1708,213 @@ mod tests {
         assert_eq!(fs::read_to_string(&file_path).unwrap(), "updated content");
     }
 
+    #[test]
+    fn test_edit_file_replace() {
+        let dir = tempdir().unwrap();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+        let file_path = dir.path().join("config.txt");
+        fs::write(&file_path, "name = old\nport = 80\n").unwrap();
+        let path = file_path.to_str().unwrap();
+
+        let edits = vec![FileEdit::Replace {
+            search: "port = 80".to_string(),
+            replace: "port = 8080".to_string(),
+        }];
+        let result = edit_file(&store, path, &edits).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&file_path).unwrap(),
+            "name = old\nport = 8080\n"
+        );
+        assert_eq!(result.edits_applied, 1);
+        assert!(result.diff.contains("-port = 80\n"));
+        assert!(result.diff.contains("+port = 8080\n"));
+    }
+
+    #[test]
+    fn test_edit_file_replace_lines() {
+        let dir = tempdir().unwrap();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+        let path = numbered_file(&dir, 4);
+
+        let edits = vec![FileEdit::ReplaceLines {
+            start: 2,
+            end: 3,
+            content: "middle".to_string(),
+        }];
+        edit_file(&store, &path, &edits).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "line 1\nmiddle\nline 4\n"
+        );
+    }
+
+    #[test]
+    fn test_edit_file_missing_or_ambiguous_anchor() {
+        let dir = tempdir().unwrap();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+        let file_path = dir.path().join("dup.txt");
+        fs::write(&file_path, "x = 1\nx = 1\n").unwrap();
+        let path = file_path.to_str().unwrap();
+
+        let missing = vec![FileEdit::Replace {
+            search: "y = 2".to_string(),
+            replace: "y = 3".to_string(),
+        }];
+        assert!(edit_file(&store, path, &missing)
+            .unwrap_err()
+            .to_string()
+            .contains("not found"));
+
+        let ambiguous = vec![FileEdit::Replace {
+            search: "x = 1".to_string(),
+            replace: "x = 2".to_string(),
+        }];
+        assert!(edit_file(&store, path, &ambiguous)
+            .unwrap_err()
+            .to_string()
+            .contains("found 2 times"));
+    }
+
+    #[test]
+    fn test_edit_file_is_all_or_nothing() {
+        let dir = tempdir().unwrap();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+        let path = numbered_file(&dir, 3);
+
+        let edits = vec![
+            FileEdit::Replace {
+                search: "line 1".to_string(),
+                replace: "first".to_string(),
+            },
+            FileEdit::ReplaceLines {
+                start: 5,
+                end: 6,
+                content: "nope".to_string(),
+            },
+        ];
+        let err = edit_file(&store, &path, &edits).unwrap_err();
+
+        assert!(err.to_string().starts_with("Edit 2 failed"));
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "line 1\nline 2\nline 3\n"
+        );
+    }
+
+    #[test]
+    fn test_edit_file_denied() {
+        let store = PermissionStore::new();
+        let edits = vec![FileEdit::Replace {
+            search: "a".to_string(),
+            replace: "b".to_string(),
+        }];
+        let result = edit_file(&store, "/tmp/unauthorized.txt", &edits);
+        assert!(result.unwrap_err().to_string().contains("Access denied"));
+    }
+
+    #[test]
+    fn test_write_file_rejects_text_outside_windows_1252() {
+        let dir = tempdir().unwrap();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+        let file_path = dir.path().join("latin1.txt");
+        fs::write(&file_path, b"caf\xE9\n").unwrap();
+        let path = file_path.to_str().unwrap();
+
+        let err = write_file(&store, path, "snowman \u{2603}\n").unwrap_err();
+        assert!(err.to_string().contains("Windows-1252"));
+        assert!(append_to_file(&store, path, "\u{2603}").is_err());
+        let edits = vec![FileEdit::Replace {
+            search: "caf".to_string(),
+            replace: "\u{2603}".to_string(),
+        }];
+        assert!(edit_file(&store, path, &edits).is_err());
+        assert_eq!(fs::read(&file_path).unwrap(), b"caf\xE9\n");
+
+        write_file(&store, path, "na\u{ef}ve\n").unwrap();
+        assert_eq!(fs::read(&file_path).unwrap(), b"na\xEFve\n");
+    }
+
+    #[test]
+    fn test_write_file_leaves_no_temp_files() {
+        let dir = tempdir().unwrap();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+
+        let file_path = dir.path().join("atomic.txt");
+        write_file(&store, file_path.to_str().unwrap(), "first").unwrap();
+        write_file(&store, file_path.to_str().unwrap(), "second").unwrap();
+
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "second");
+        let names: Vec<String> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names, vec!["atomic.txt".to_string()]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_file_preserves_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempdir().unwrap();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+
+        let file_path = dir.path().join("script.sh");
+        fs::write(&file_path, "echo old").unwrap();
+        fs::set_permissions(&file_path, fs::Permissions::from_mode(0o750)).unwrap();
+
+        write_file(&store, file_path.to_str().unwrap(), "echo new").unwrap();
+
+        let mode = fs::metadata(&file_path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o750);
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "echo new");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_file_follows_symlinks() {
+        let dir = tempdir().unwrap();
+        let outside = tempdir().unwrap();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+
+        let target = dir.path().join("target.txt");
+        fs::write(&target, "old\n").unwrap();
+        let link = dir.path().join("link.txt");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        let link = link.to_str().unwrap();
+
+        write_file(&store, link, "new\n").unwrap();
+        let edits = vec![FileEdit::Replace {
+            search: "new".to_string(),
+            replace: "newer".to_string(),
+        }];
+        edit_file(&store, link, &edits).unwrap();
+        assert!(fs::symlink_metadata(link).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "newer\n");
+
+        let secret = outside.path().join("secret.txt");
+        fs::write(&secret, "keep").unwrap();
+        let escape = dir.path().join("escape.txt");
+        std::os::unix::fs::symlink(&secret, &escape).unwrap();
+        let err = write_file(&store, escape.to_str().unwrap(), "gone").unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("symlink target not in granted folder"));
+        assert!(append_to_file(&store, escape.to_str().unwrap(), "gone").is_err());
+        assert_eq!(fs::read_to_string(&secret).unwrap(), "keep");
+
+        append_to_file(&store, link, "last\n").unwrap();
+        assert_eq!(fs::read_to_string(&target).unwrap(), "newer\nlast\n");
+    }
+
     #[test]
     fn test_write_file_denied() {
         let store = PermissionStore::new();
         let result = write_file(&store, "/tmp/unauthorized.txt", "content");
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("Access denied"));
+        assert!(result.unwrap_err().to_string().contains("Access denied"));
     }
 
     #[test]
@@ -172,7 +1940,7 @@ mod tests {
 
         let result = create_file(&store, file_path.to_str().unwrap(), "new content");
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("already exists"));
+        assert!(result.unwrap_err().to_string().contains("already exists"));
     }
 
     #[test]
@@ -193,7 +1961,7 @@ mod tests {
         let store = PermissionStore::new();
         let result = delete_file(&store, "/tmp/unauthorized.txt");
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("Access denied"));
+        assert!(result.unwrap_err().to_string().contains("Access denied"));
     }
 
     #[test]
@@ -220,7 +1988,7 @@ mod tests {
         let dest_path = dir.path().join("dest.txt");
         let result = move_file(&store, "/tmp/unauthorized.txt", dest_path.to_str().unwrap());
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("Access denied"));
+        assert!(result.unwrap_err().to_string().contains("Access denied"));
     }
 
     #[test]
@@ -233,7 +2001,187 @@ mod tests {
 
         let result = move_file(&store, src_path.to_str().unwrap(), "/tmp/unauthorized.txt");
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("Access denied"));
+        assert!(result.unwrap_err().to_string().contains("Access denied"));
+    }
+
+    #[test]
+    fn test_read_blocked_file() {
+        let dir = tempdir().unwrap();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+
+        let file_path = dir.path().join(".env");
+        fs::write(&file_path, "SECRET=1").unwrap();
+
+        let result = read_file(&store, file_path.to_str().unwrap());
+        assert!(result.is_err());
+        let err = result.unwrap_err();
+        assert!(matches!(err, FileOpError::Blocked(_)));
+        assert!(err.to_string().contains("Blocked by policy"));
+    }
+
+    #[test]
+    fn test_list_directory_hides_blocked() {
+        let dir = tempdir().unwrap();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+
+        fs::write(dir.path().join("visible.txt"), "ok").unwrap();
+        fs::write(dir.path().join(".env"), "SECRET=1").unwrap();
+        fs::write(dir.path().join("server.pem"), "key").unwrap();
+
+        let files = list_directory(&store, dir.path().to_str().unwrap()).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].name, "visible.txt");
+    }
+
+    #[test]
+    fn test_create_directory_recursive() {
+        let dir = tempdir().unwrap();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+        let nested = dir.path().join("a/b/c");
+
+        create_directory(&store, nested.to_str().unwrap()).unwrap();
+        assert!(nested.is_dir());
+
+        let result = create_directory(&store, "/tmp/unauthorized-dir");
+        assert!(result.unwrap_err().to_string().contains("Access denied"));
+    }
+
+    #[test]
+    fn test_copy_file() {
+        let dir = tempdir().unwrap();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+        let src = dir.path().join("src.txt");
+        let dest = dir.path().join("dest.txt");
+        fs::write(&src, "copy me").unwrap();
+
+        let bytes = copy_file(&store, src.to_str().unwrap(), dest.to_str().unwrap()).unwrap();
+        assert_eq!(bytes, 7);
+        assert_eq!(fs::read_to_string(&dest).unwrap(), "copy me");
+        assert!(src.exists());
+
+        let again = copy_file(&store, src.to_str().unwrap(), dest.to_str().unwrap());
+        assert!(again.unwrap_err().to_string().contains("already exists"));
+        assert_eq!(fs::read_to_string(&dest).unwrap(), "copy me");
+
+        #[cfg(unix)]
+        {
+            let outside = tempdir().unwrap();
+            let secret = outside.path().join("secret.txt");
+            fs::write(&secret, "keep").unwrap();
+            let link = dir.path().join("link.txt");
+            std::os::unix::fs::symlink(&secret, &link).unwrap();
+            let err = copy_file(&store, src.to_str().unwrap(), link.to_str().unwrap());
+            assert!(err.unwrap_err().to_string().contains("already exists"));
+            assert_eq!(fs::read_to_string(&secret).unwrap(), "keep");
+        }
+    }
+
+    #[test]
+    fn test_copy_directory() {
+        let dir = setup_tree();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+        fs::write(dir.path().join("src/.env"), "SECRET=1").unwrap();
+        let src = dir.path().join("src");
+        let dest = dir.path().join("backup");
+
+        let copied = copy_directory(&store, src.to_str().unwrap(), dest.to_str().unwrap()).unwrap();
+
+        assert_eq!(copied, 2);
+        assert!(dest.join("main.rs").exists());
+        assert!(dest.join("nested/deep.rs").exists());
+        assert!(!dest.join(".env").exists());
+    }
+
+    #[test]
+    fn test_copy_directory_into_itself() {
+        let dir = setup_tree();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+        let src = dir.path().join("src");
+        let dest = src.join("inner");
+
+        let result = copy_directory(&store, src.to_str().unwrap(), dest.to_str().unwrap());
+        assert!(result.unwrap_err().to_string().contains("into itself"));
+    }
+
+    #[test]
+    fn test_archive_round_trip_within_grants() {
+        let dir = tempdir().unwrap();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+        let src = dir.path().join("docs");
+        fs::create_dir(&src).unwrap();
+        fs::write(src.join("a.txt"), "a").unwrap();
+        fs::write(src.join(".env"), "SECRET=1").unwrap();
+
+        let archive_path = dir.path().join("docs.tar.gz");
+        let archive = archive_path.to_str().unwrap();
+        let created =
+            create_archive(&store, &[src.to_str().unwrap().to_string()], archive).unwrap();
+        assert_eq!(created.files_added, 1);
+
+        let listing = list_archive(&store, archive).unwrap();
+        assert!(listing.entries.iter().all(|e| !e.path.contains(".env")));
+
+        let out = dir.path().join("restored");
+        let summary = extract_archive(&store, archive, out.to_str().unwrap(), false).unwrap();
+        assert_eq!(summary.files_extracted, 1);
+        assert!(out.join("docs/a.txt").exists());
+    }
+
+    #[test]
+    fn test_archive_operations_denied() {
+        let dir = tempdir().unwrap();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+        let archive = dir.path().join("a.zip");
+        let archive = archive.to_str().unwrap();
+
+        assert!(matches!(
+            list_archive(&store, "/tmp/other.zip"),
+            Err(FileOpError::AccessDenied(_))
+        ));
+        assert!(matches!(
+            extract_archive(&store, archive, "/etc/out", false),
+            Err(FileOpError::AccessDenied(_))
+        ));
+        assert!(matches!(
+            create_archive(&store, &["/etc".to_string()], archive),
+            Err(FileOpError::AccessDenied(_))
+        ));
+    }
+
+    #[test]
+    fn test_remove_empty_directory() {
+        let dir = setup_tree();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+        let empty = dir.path().join("empty");
+        fs::create_dir(&empty).unwrap();
+
+        remove_empty_directory(&store, empty.to_str().unwrap()).unwrap();
+        assert!(!empty.exists());
+
+        let result = remove_empty_directory(&store, dir.path().join("src").to_str().unwrap());
+        assert!(result.unwrap_err().to_string().contains("not empty"));
+    }
+
+    #[test]
+    fn test_append_to_file() {
+        let dir = tempdir().unwrap();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+        let file_path = dir.path().join("log.txt");
+        fs::write(&file_path, "first\n").unwrap();
+
+        append_to_file(&store, file_path.to_str().unwrap(), "second\n").unwrap();
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "first\nsecond\n");
+
+        let utf16 = dir.path().join("utf16.txt");
+        fs::write(&utf16, encode("first\r\n", TextEncoding::Utf16Le).unwrap()).unwrap();
+        append_to_file(&store, utf16.to_str().unwrap(), "second\n").unwrap();
+        assert_eq!(
+            fs::read(&utf16).unwrap(),
+            encode("first\r\nsecond\r\n", TextEncoding::Utf16Le).unwrap()
+        );
+
+        let missing = dir.path().join("missing.txt");
+        assert!(append_to_file(&store, missing.to_str().unwrap(), "x").is_err());
     }
 
     #[test]
@@ -253,4 +2201,82 @@ mod tests {
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), "nested content");
     }
+
+    #[test]
+    fn test_stat_file() {
+        let dir = tempdir().unwrap();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+        let file = dir.path().join("report.pdf");
+        fs::write(&file, "hello").unwrap();
+
+        let stat = stat_file(&store, file.to_str().unwrap(), true).unwrap();
+        assert_eq!(stat.info.size, 5);
+        assert_eq!(stat.mime_type.as_deref(), Some("application/pdf"));
+        assert_eq!(
+            stat.sha256.as_deref(),
+            Some("2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824")
+        );
+        assert!(!stat.is_symlink);
+        assert!(stat.accessed.is_some());
+
+        let stat = stat_file(&store, dir.path().to_str().unwrap(), true).unwrap();
+        assert!(stat.info.is_directory);
+        assert!(stat.mime_type.is_none());
+        assert!(stat.sha256.is_none());
+
+        assert!(stat_file(&PermissionStore::new(), file.to_str().unwrap(), false).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_stat_file_describes_symlink_and_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempdir().unwrap();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+        let file = dir.path().join("script.sh");
+        fs::write(&file, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o750)).unwrap();
+        let link = dir.path().join("link");
+        std::os::unix::fs::symlink("/etc/passwd", &link).unwrap();
+
+        let stat = stat_file(&store, file.to_str().unwrap(), false).unwrap();
+        assert_eq!(stat.mode, Some(0o750));
+        assert!(stat.sha256.is_none());
+
+        // The link is described, never followed out of the granted folder
+        let stat = stat_file(&store, link.to_str().unwrap(), true).unwrap();
+        assert!(stat.is_symlink);
+        assert_eq!(stat.symlink_target.as_deref(), Some("/etc/passwd"));
+        assert!(stat.sha256.is_none());
+    }
+
+    #[test]
+    fn test_find_duplicates() {
+        let dir = tempdir().unwrap();
+        let store = setup_store_with_path(dir.path().to_str().unwrap());
+        fs::create_dir_all(dir.path().join("a/b")).unwrap();
+        fs::write(dir.path().join("one.txt"), "same content").unwrap();
+        fs::write(dir.path().join("a/two.txt"), "same content").unwrap();
+        fs::write(dir.path().join("a/b/three.txt"), "same content").unwrap();
+        // Same size, different bytes
+        fs::write(dir.path().join("a/other.txt"), "diff content").unwrap();
+        fs::write(dir.path().join("big1.bin"), vec![7u8; 100]).unwrap();
+        fs::write(dir.path().join("a/big2.bin"), vec![7u8; 100]).unwrap();
+        fs::write(dir.path().join("empty1"), "").unwrap();
+        fs::write(dir.path().join("empty2"), "").unwrap();
+
+        let report = find_duplicates(&store, dir.path().to_str().unwrap(), 0).unwrap();
+        assert_eq!(report.files_scanned, 6);
+        assert_eq!(report.groups.len(), 2);
+        assert_eq!(report.groups[0].size, 100);
+        assert_eq!(report.groups[1].paths.len(), 3);
+        assert!(report.groups[1].paths[0].ends_with("a/b/three.txt"));
+        assert_eq!(report.wasted_bytes, 100 + 2 * 12);
+
+        let report = find_duplicates(&store, dir.path().to_str().unwrap(), 50).unwrap();
+        assert_eq!(report.groups.len(), 1);
+
+        assert!(find_duplicates(&PermissionStore::new(), "/tmp", 0).is_err());
+    }
 }
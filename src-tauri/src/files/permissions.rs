@@ -1,4 +1,7 @@
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
 use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 use tauri::AppHandle;
 use tauri_plugin_fs::FsExt;
@@ -6,27 +9,77 @@ use uuid::Uuid;
 
 use super::types::FolderPermission;
 
+/// Sensitive files that stay off-limits even inside granted folders
+pub const DEFAULT_DENY_PATTERNS: &[&str] = &[
+    "**/.env",
+    "**/.env.*",
+    "**/.git/config",
+    "**/.git-credentials",
+    "**/.ssh/**",
+    "**/id_rsa*",
+    "**/id_dsa*",
+    "**/id_ecdsa*",
+    "**/id_ed25519*",
+    "**/*.pem",
+    "**/*.key",
+    "**/*.p12",
+    "**/*.pfx",
+    "**/.aws/credentials",
+    "**/.netrc",
+    "**/.npmrc",
+    "**/.pypirc",
+    "**/.gnupg/**",
+    "**/*.keychain",
+    "**/*.keychain-db",
+    "**/Library/Keychains/**",
+];
+
+/// Outcome of checking a path against granted folders and the deny-list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathAccess {
+    Allowed,
+    NotGranted,
+    Blocked,
+}
+
+#[derive(Clone)]
 pub struct PermissionStore {
     folders: HashMap<String, FolderPermission>,
+    deny_patterns: Vec<String>,
+    deny_set: GlobSet,
 }
 
 impl PermissionStore {
     pub fn new() -> Self {
+        let deny_patterns: Vec<String> =
+            DEFAULT_DENY_PATTERNS.iter().map(|p| p.to_string()).collect();
+        let deny_set = build_deny_set(&deny_patterns).expect("default deny patterns are valid");
         Self {
             folders: HashMap::new(),
+            deny_patterns,
+            deny_set,
         }
     }
 
     pub fn add(&mut self, path: String) -> FolderPermission {
+        self.add_scoped(path, None, None)
+    }
+
+    /// Grant a folder that lapses at `expires_at` (unix seconds) and/or when
+    /// the conversation identified by `session_id` ends.
+    pub fn add_scoped(
+        &mut self,
+        path: String,
+        expires_at: Option<u64>,
+        session_id: Option<String>,
+    ) -> FolderPermission {
         let id = Uuid::new_v4().to_string();
-        let granted_at = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
         let perm = FolderPermission {
             id: id.clone(),
             path,
-            granted_at,
+            granted_at: now_secs(),
+            expires_at,
+            session_id,
         };
         self.folders.insert(id.clone(), perm.clone());
         perm
@@ -36,13 +89,134 @@ impl PermissionStore {
         self.folders.remove(id)
     }
 
+    /// Drop every grant whose expiry has passed, returning the removed grants
+    pub fn remove_expired(&mut self) -> Vec<FolderPermission> {
+        let now = now_secs();
+        self.remove_where(|f| f.is_expired(now))
+    }
+
+    /// Drop every grant scoped to the given session, returning the removed grants
+    pub fn remove_session(&mut self, session_id: &str) -> Vec<FolderPermission> {
+        self.remove_where(|f| f.session_id.as_deref() == Some(session_id))
+    }
+
+    fn remove_where(&mut self, pred: impl Fn(&FolderPermission) -> bool) -> Vec<FolderPermission> {
+        let ids: Vec<String> = self
+            .folders
+            .values()
+            .filter(|f| pred(f))
+            .map(|f| f.id.clone())
+            .collect();
+        ids.iter().filter_map(|id| self.folders.remove(id)).collect()
+    }
+
     pub fn list(&self) -> Vec<FolderPermission> {
-        self.folders.values().cloned().collect()
+        let now = now_secs();
+        self.folders
+            .values()
+            .filter(|f| !f.is_expired(now))
+            .cloned()
+            .collect()
     }
 
     pub fn is_path_allowed(&self, path: &str) -> bool {
-        self.folders.values().any(|f| path.starts_with(&f.path))
+        let now = now_secs();
+        self.folders
+            .values()
+            .any(|f| !f.is_expired(now) && path.starts_with(&f.path))
+    }
+
+    /// Whether the path matches a deny-list pattern, as given or with symlinks and `..` resolved
+    pub fn is_path_blocked(&self, path: &str) -> bool {
+        self.deny_set.is_match(path) || resolve(path).is_some_and(|p| self.deny_set.is_match(p))
+    }
+
+    /// Check a path against the deny-list first, then the granted folders
+    pub fn check_access(&self, path: &str) -> PathAccess {
+        if self.is_path_blocked(path) {
+            PathAccess::Blocked
+        } else if self.is_path_allowed(path) {
+            PathAccess::Allowed
+        } else {
+            PathAccess::NotGranted
+        }
+    }
+
+    pub fn deny_patterns(&self) -> Vec<String> {
+        self.deny_patterns.clone()
+    }
+
+    /// Replace the deny-list; leaves the current list untouched if any pattern is invalid
+    pub fn set_deny_patterns(&mut self, patterns: Vec<String>) -> Result<(), String> {
+        self.deny_set = build_deny_set(&patterns)?;
+        self.deny_patterns = patterns;
+        Ok(())
+    }
+
+    /// Use the deny-list saved at `path`, keeping the defaults when none was saved
+    pub fn load_deny_patterns(&mut self, path: &Path) {
+        let patterns = match fs::read_to_string(path) {
+            Ok(text) => match serde_json::from_str::<Vec<String>>(&text) {
+                Ok(patterns) => patterns,
+                Err(e) => {
+                    log::warn!("Failed to parse deny patterns: {}", e);
+                    return;
+                }
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return,
+            Err(e) => {
+                log::warn!("Failed to read deny patterns: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = self.set_deny_patterns(patterns) {
+            log::warn!("{}", e);
+        }
+    }
+
+    /// Replace the deny-list and save it to `path`, so it survives a restart
+    pub fn save_deny_patterns(&mut self, path: &Path, patterns: Vec<String>) -> Result<(), String> {
+        let deny_set = build_deny_set(&patterns)?;
+        let json = serde_json::to_string_pretty(&patterns)
+            .map_err(|e| format!("Failed to serialize deny patterns: {}", e))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create config directory: {}", e))?;
+        }
+        fs::write(path, json).map_err(|e| format!("Failed to save deny patterns: {}", e))?;
+        self.deny_set = deny_set;
+        self.deny_patterns = patterns;
+        Ok(())
+    }
+}
+
+/// The path with symlinks and `..` resolved; for a path not created yet, its parent resolved
+fn resolve(path: &str) -> Option<PathBuf> {
+    let path = Path::new(path);
+    if let Ok(resolved) = fs::canonicalize(path) {
+        return Some(resolved);
+    }
+    let parent = fs::canonicalize(path.parent()?).ok()?;
+    Some(parent.join(path.file_name()?))
+}
+
+fn build_deny_set(patterns: &[String]) -> Result<GlobSet, String> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        // A pattern for everything inside a folder protects the folder itself too,
+        // so it can't be moved or renamed out from under the deny-list
+        let folder = pattern.strip_suffix("/**").filter(|f| !f.is_empty());
+        for pattern in std::iter::once(pattern.as_str()).chain(folder) {
+            let glob = GlobBuilder::new(pattern)
+                .literal_separator(true)
+                .build()
+                .map_err(|e| format!("Invalid deny pattern '{}': {}", pattern, e))?;
+            builder.add(glob);
+        }
     }
+    builder
+        .build()
+        .map_err(|e| format!("Failed to build deny-list: {}", e))
 }
 
 impl Default for PermissionStore {
@@ -51,6 +225,28 @@ impl Default for PermissionStore {
     }
 }
 
+/// Current unix time in seconds
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// UTC calendar date `(year, month, day)` of a unix timestamp in seconds
+pub fn civil_date(secs: u64) -> (i64, u32, u32) {
+    // Civil-from-days conversion (Howard Hinnant's algorithm)
+    let z = (secs / 86_400) as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    (yoe + era * 400 + i64::from(month <= 2), month, day)
+}
+
 pub fn grant_folder_to_scope(app: &AppHandle, path: &str) -> Result<(), String> {
     let scope = app.fs_scope();
     scope
@@ -151,6 +347,137 @@ mod tests {
         assert!(!store.is_path_allowed("/home/user/other/file.txt"));
     }
 
+    #[test]
+    fn test_expired_grant_is_ignored() {
+        let mut store = PermissionStore::new();
+        store.add_scoped("/home/user/docs".to_string(), Some(now_secs() - 1), None);
+
+        assert!(!store.is_path_allowed("/home/user/docs/file.txt"));
+        assert!(store.list().is_empty());
+    }
+
+    #[test]
+    fn test_unexpired_grant_is_allowed() {
+        let mut store = PermissionStore::new();
+        let expires_at = now_secs() + 3600;
+        let perm = store.add_scoped("/home/user/docs".to_string(), Some(expires_at), None);
+
+        assert!(store.is_path_allowed("/home/user/docs/file.txt"));
+        assert_eq!(perm.expires_at, Some(expires_at));
+    }
+
+    #[test]
+    fn test_remove_expired() {
+        let mut store = PermissionStore::new();
+        store.add_scoped("/home/user/old".to_string(), Some(now_secs() - 1), None);
+        store.add("/home/user/docs".to_string());
+
+        let expired = store.remove_expired();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].path, "/home/user/old");
+        assert!(store.remove_expired().is_empty());
+        assert!(store.is_path_allowed("/home/user/docs/file.txt"));
+    }
+
+    #[test]
+    fn test_remove_session() {
+        let mut store = PermissionStore::new();
+        store.add_scoped("/home/user/task".to_string(), None, Some("conv-1".to_string()));
+        store.add_scoped("/home/user/other".to_string(), None, Some("conv-2".to_string()));
+
+        let removed = store.remove_session("conv-1");
+        assert_eq!(removed.len(), 1);
+        assert!(!store.is_path_allowed("/home/user/task/file.txt"));
+        assert!(store.is_path_allowed("/home/user/other/file.txt"));
+    }
+
+    #[test]
+    fn test_default_deny_patterns_block_secrets() {
+        let mut store = PermissionStore::new();
+        store.add("/home/user/project".to_string());
+
+        assert!(store.is_path_blocked("/home/user/project/.env"));
+        assert!(store.is_path_blocked("/home/user/project/.env.local"));
+        assert!(store.is_path_blocked("/home/user/project/.git/config"));
+        assert!(store.is_path_blocked("/home/user/project/.ssh/id_rsa"));
+        assert!(store.is_path_blocked("/home/user/.ssh"));
+        assert!(!store.is_path_blocked("/home/user/.sshrc"));
+        assert!(store.is_path_blocked("/home/user/project/keys/id_ed25519.pub"));
+        assert!(store.is_path_blocked("/home/user/project/certs/server.pem"));
+        assert!(!store.is_path_blocked("/home/user/project/src/main.rs"));
+        assert!(!store.is_path_blocked("/home/user/project/.gitignore"));
+    }
+
+    #[test]
+    fn test_check_access() {
+        let mut store = PermissionStore::new();
+        store.add("/home/user/project".to_string());
+
+        assert_eq!(store.check_access("/home/user/project/README.md"), PathAccess::Allowed);
+        assert_eq!(store.check_access("/home/user/project/.env"), PathAccess::Blocked);
+        assert_eq!(store.check_access("/home/user/other/README.md"), PathAccess::NotGranted);
+    }
+
+    #[test]
+    fn test_set_deny_patterns() {
+        let mut store = PermissionStore::new();
+        store
+            .set_deny_patterns(vec!["**/*.sqlite".to_string()])
+            .unwrap();
+
+        assert!(store.is_path_blocked("/data/app.sqlite"));
+        assert!(!store.is_path_blocked("/data/.env"));
+        assert_eq!(store.deny_patterns(), vec!["**/*.sqlite".to_string()]);
+    }
+
+    #[test]
+    fn test_set_invalid_deny_pattern_keeps_previous() {
+        let mut store = PermissionStore::new();
+        let result = store.set_deny_patterns(vec!["**/[.env".to_string()]);
+
+        assert!(result.is_err());
+        assert!(store.is_path_blocked("/data/.env"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_deny_list_follows_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let ssh = dir.path().join(".ssh");
+        fs::create_dir(&ssh).unwrap();
+        fs::write(ssh.join("config"), "Host *").unwrap();
+        let granted = dir.path().join("granted");
+        fs::create_dir(&granted).unwrap();
+        std::os::unix::fs::symlink(&ssh, granted.join("keys")).unwrap();
+        let store = PermissionStore::new();
+
+        let linked = granted.join("keys").join("config");
+        assert!(store.is_path_blocked(linked.to_str().unwrap()));
+        let dotted = granted.join("..").join(".ssh").join("new_key");
+        assert!(store.is_path_blocked(dotted.to_str().unwrap()));
+        assert!(!store.is_path_blocked(granted.join("notes.txt").to_str().unwrap()));
+    }
+
+    #[test]
+    fn test_deny_patterns_are_saved() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("deny-patterns.json");
+        let mut store = PermissionStore::new();
+        store.load_deny_patterns(&path);
+        assert!(store.is_path_blocked("/data/.env"));
+
+        store
+            .save_deny_patterns(&path, vec!["**/*.sqlite".to_string()])
+            .unwrap();
+        assert!(store
+            .save_deny_patterns(&path, vec!["**/[.env".to_string()])
+            .is_err());
+        let mut restarted = PermissionStore::new();
+        restarted.load_deny_patterns(&path);
+        assert_eq!(restarted.deny_patterns(), vec!["**/*.sqlite".to_string()]);
+        assert!(restarted.is_path_blocked("/data/app.sqlite"));
+    }
+
     #[test]
     fn test_default_impl() {
         let store = PermissionStore::default();
@@ -0,0 +1,1433 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Rows loaded from a data file, with every row as wide as `columns`
+#[derive(Debug, Clone)]
+pub struct Table {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+    /// Rows in the file before any filtering
+    pub source_rows: usize,
+    /// Rows the query produced before LIMIT/OFFSET was applied
+    pub matched_rows: usize,
+}
+
+/// Load a CSV, TSV, JSON or JSONL file, picking the format from its extension
+pub fn load_table(path: &Path) -> Result<Table, String> {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "csv" => load_delimited(path, b','),
+        "tsv" | "tab" => load_delimited(path, b'\t'),
+        "json" => {
+            let text =
+                std::fs::read_to_string(path).map_err(|e| format!("Failed to read file: {}", e))?;
+            let value: Value =
+                serde_json::from_str(&text).map_err(|e| format!("Invalid JSON: {}", e))?;
+            Ok(table_from_records(json_records(value)))
+        }
+        "jsonl" | "ndjson" => {
+            let text =
+                std::fs::read_to_string(path).map_err(|e| format!("Failed to read file: {}", e))?;
+            let mut records = Vec::new();
+            for (i, line) in text.lines().enumerate() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let value = serde_json::from_str(line)
+                    .map_err(|e| format!("Invalid JSON on line {}: {}", i + 1, e))?;
+                records.push(value);
+            }
+            Ok(table_from_records(records))
+        }
+        _ => Err("Unsupported data file; expected .csv, .tsv, .json or .jsonl".to_string()),
+    }
+}
+
+fn load_delimited(path: &Path, delimiter: u8) -> Result<Table, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .delimiter(delimiter)
+        .from_path(path)
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+
+    let headers = reader
+        .byte_headers()
+        .map_err(|e| format!("Failed to parse file: {}", e))?
+        .iter()
+        .map(|h| String::from_utf8_lossy(h).trim().to_string())
+        .collect();
+    let mut columns = unique_columns(headers);
+
+    let mut rows = Vec::new();
+    for record in reader.byte_records() {
+        let record = record.map_err(|e| format!("Failed to parse file: {}", e))?;
+        let row: Vec<Value> = record
+            .iter()
+            .map(|field| match String::from_utf8_lossy(field) {
+                f if f.is_empty() => Value::Null,
+                f => Value::String(f.into_owned()),
+            })
+            .collect();
+        while columns.len() < row.len() {
+            columns.push(format!("column_{}", columns.len() + 1));
+        }
+        rows.push(row);
+    }
+    for row in &mut rows {
+        row.resize(columns.len(), Value::Null);
+    }
+    Ok(Table { columns, rows })
+}
+
+/// Give blank headers a positional name and disambiguate repeated ones
+fn unique_columns(headers: Vec<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    headers
+        .into_iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let base = if name.is_empty() {
+                format!("column_{}", i + 1)
+            } else {
+                name
+            };
+            let mut name = base.clone();
+            let mut n = 2;
+            while !seen.insert(name.clone()) {
+                name = format!("{}_{}", base, n);
+                n += 1;
+            }
+            name
+        })
+        .collect()
+}
+
+/// The records in a JSON document: a top-level array, or the first array of objects
+/// inside a top-level object (as API responses often wrap results)
+fn json_records(value: Value) -> Vec<Value> {
+    match value {
+        Value::Array(items) => items,
+        Value::Object(map) => {
+            let nested = map.values().find_map(|v| match v {
+                Value::Array(items) if items.iter().any(Value::is_object) => Some(items.clone()),
+                _ => None,
+            });
+            nested.unwrap_or_else(|| vec![Value::Object(map)])
+        }
+        other => vec![other],
+    }
+}
+
+fn table_from_records(records: Vec<Value>) -> Table {
+    let mut columns: Vec<String> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    let mut flat_rows = Vec::with_capacity(records.len());
+
+    for record in records {
+        let mut flat = Vec::new();
+        match record {
+            Value::Object(map) => flatten_object("", map, &mut flat),
+            other => flat.push(("value".to_string(), other)),
+        }
+        for (key, _) in &flat {
+            if !index.contains_key(key) {
+                index.insert(key.clone(), columns.len());
+                columns.push(key.clone());
+            }
+        }
+        flat_rows.push(flat);
+    }
+
+    let rows = flat_rows
+        .into_iter()
+        .map(|flat| {
+            let mut row = vec![Value::Null; columns.len()];
+            for (key, value) in flat {
+                row[index[&key]] = value;
+            }
+            row
+        })
+        .collect();
+    Table { columns, rows }
+}
+
+/// Flatten nested objects into dotted keys; arrays are kept as JSON text
+fn flatten_object(prefix: &str, map: Map<String, Value>, out: &mut Vec<(String, Value)>) {
+    for (key, value) in map {
+        let key = if prefix.is_empty() {
+            key
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        match value {
+            Value::Object(inner) => flatten_object(&key, inner, out),
+            Value::Array(_) => out.push((key, Value::String(value.to_string()))),
+            other => out.push((key, other)),
+        }
+    }
+}
+
+/// Parse and run a SQL-like query against a table, e.g.
+/// `SELECT region, SUM(total) AS total WHERE year >= 2023 GROUP BY region ORDER BY total DESC`.
+/// The FROM clause is optional. Text that looks numeric is treated as a number wherever
+/// arithmetic, comparison or aggregation needs one.
+pub fn run_query(table: &Table, query: &str) -> Result<QueryResult, String> {
+    let query = Parser::new(query)?.parse_query()?;
+    execute(table, &query)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    QuotedIdent(String),
+    Str(String),
+    Num(f64),
+    Symbol(&'static str),
+}
+
+/// A token and the byte span of the query text it came from
+type Spanned = (Token, usize, usize);
+
+fn tokenize(input: &str) -> Result<Vec<Spanned>, String> {
+    const SYMBOLS: [&str; 14] = [
+        "<=", ">=", "!=", "<>", "(", ")", ",", "*", "+", "-", "/", "=", "<", ">",
+    ];
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        if c == '\'' || c == '"' || c == '`' {
+            chars.next();
+            let mut text = String::new();
+            let mut closed = false;
+            while let Some((_, ch)) = chars.next() {
+                if ch == c {
+                    // A doubled quote is an escaped quote
+                    if chars.peek().map(|&(_, n)| n) == Some(c) {
+                        chars.next();
+                        text.push(c);
+                        continue;
+                    }
+                    closed = true;
+                    break;
+                }
+                text.push(ch);
+            }
+            if !closed {
+                return Err(format!("Unterminated quote starting at position {}", start));
+            }
+            let end = chars.peek().map(|&(i, _)| i).unwrap_or(input.len());
+            let token = if c == '\'' {
+                Token::Str(text)
+            } else {
+                Token::QuotedIdent(text)
+            };
+            tokens.push((token, start, end));
+            continue;
+        }
+        if c.is_ascii_digit()
+            || (c == '.' && input[start + 1..].starts_with(|n: char| n.is_ascii_digit()))
+        {
+            let mut end = start;
+            while let Some(&(i, ch)) = chars.peek() {
+                if ch.is_ascii_digit() || ch == '.' {
+                    end = i + ch.len_utf8();
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            let n = input[start..end]
+                .parse()
+                .map_err(|_| format!("Invalid number '{}'", &input[start..end]))?;
+            tokens.push((Token::Num(n), start, end));
+            continue;
+        }
+        if c.is_alphanumeric() || c == '_' {
+            let mut end = start;
+            while let Some(&(i, ch)) = chars.peek() {
+                if ch.is_alphanumeric() || ch == '_' || ch == '.' {
+                    end = i + ch.len_utf8();
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            tokens.push((Token::Ident(input[start..end].to_string()), start, end));
+            continue;
+        }
+        match SYMBOLS.iter().find(|s| input[start..].starts_with(**s)) {
+            Some(symbol) => {
+                for _ in 0..symbol.len() {
+                    chars.next();
+                }
+                tokens.push((Token::Symbol(symbol), start, start + symbol.len()));
+            }
+            None => return Err(format!("Unexpected character '{}'", c)),
+        }
+    }
+    Ok(tokens)
+}
+
+const KEYWORDS: &[&str] = &[
+    "SELECT", "DISTINCT", "FROM", "WHERE", "GROUP", "BY", "HAVING", "ORDER", "ASC", "DESC",
+    "LIMIT", "OFFSET", "AND", "OR", "NOT", "LIKE", "IN", "IS", "NULL", "AS", "TRUE", "FALSE",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BinaryOp {
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    And,
+    Or,
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Aggregate {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Column(String),
+    Literal(Value),
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Like {
+        expr: Box<Expr>,
+        pattern: Box<Expr>,
+        negated: bool,
+    },
+    In {
+        expr: Box<Expr>,
+        list: Vec<Expr>,
+        negated: bool,
+    },
+    IsNull {
+        expr: Box<Expr>,
+        negated: bool,
+    },
+    /// `arg` is `None` for `COUNT(*)`
+    Aggregate {
+        func: Aggregate,
+        arg: Option<Box<Expr>>,
+        distinct: bool,
+    },
+    Function(String, Vec<Expr>),
+}
+
+impl Expr {
+    fn has_aggregate(&self) -> bool {
+        match self {
+            Expr::Aggregate { .. } => true,
+            Expr::Column(_) | Expr::Literal(_) => false,
+            Expr::Neg(e) | Expr::Not(e) => e.has_aggregate(),
+            Expr::Binary(_, l, r) => l.has_aggregate() || r.has_aggregate(),
+            Expr::Like { expr, pattern, .. } => expr.has_aggregate() || pattern.has_aggregate(),
+            Expr::In { expr, list, .. } => {
+                expr.has_aggregate() || list.iter().any(Expr::has_aggregate)
+            }
+            Expr::IsNull { expr, .. } => expr.has_aggregate(),
+            Expr::Function(_, args) => args.iter().any(Expr::has_aggregate),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum SelectItem {
+    Wildcard,
+    Expr { expr: Expr, name: String },
+}
+
+#[derive(Debug)]
+struct Query {
+    distinct: bool,
+    select: Vec<SelectItem>,
+    filter: Option<Expr>,
+    group_by: Vec<Expr>,
+    having: Option<Expr>,
+    order_by: Vec<(Expr, bool)>,
+    limit: Option<usize>,
+    offset: usize,
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    tokens: Vec<Spanned>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Result<Self, String> {
+        Ok(Self {
+            input,
+            tokens: tokenize(input)?,
+            pos: 0,
+        })
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(t, _, _)| t)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|(t, _, _)| t.clone());
+        self.pos += 1;
+        token
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(w)) if w.eq_ignore_ascii_case(keyword))
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if self.peek_keyword(keyword) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), String> {
+        if self.eat_keyword(keyword) {
+            Ok(())
+        } else {
+            Err(format!("Expected {} {}", keyword, self.describe_position()))
+        }
+    }
+
+    fn eat_symbol(&mut self, symbol: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Symbol(s)) if *s == symbol) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), String> {
+        if self.eat_symbol(symbol) {
+            Ok(())
+        } else {
+            Err(format!(
+                "Expected '{}' {}",
+                symbol,
+                self.describe_position()
+            ))
+        }
+    }
+
+    fn describe_position(&self) -> String {
+        match self.tokens.get(self.pos) {
+            Some((_, start, end)) => format!("at '{}'", &self.input[*start..*end]),
+            None => "at end of query".to_string(),
+        }
+    }
+
+    fn parse_query(&mut self) -> Result<Query, String> {
+        self.expect_keyword("SELECT")?;
+        let distinct = self.eat_keyword("DISTINCT");
+
+        let mut select = Vec::new();
+        loop {
+            select.push(self.parse_select_item()?);
+            if !self.eat_symbol(",") {
+                break;
+            }
+        }
+
+        // The file is given separately, so any FROM clause is just skipped
+        if self.eat_keyword("FROM") {
+            self.next();
+        }
+
+        let filter = if self.eat_keyword("WHERE") {
+            Some(self.parse_expr()?)
+        } else {
+            None
+        };
+
+        let mut group_by = Vec::new();
+        if self.eat_keyword("GROUP") {
+            self.expect_keyword("BY")?;
+            loop {
+                group_by.push(self.parse_expr()?);
+                if !self.eat_symbol(",") {
+                    break;
+                }
+            }
+        }
+
+        let having = if self.eat_keyword("HAVING") {
+            Some(self.parse_expr()?)
+        } else {
+            None
+        };
+
+        let mut order_by = Vec::new();
+        if self.eat_keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            loop {
+                let expr = self.parse_expr()?;
+                let descending = if self.eat_keyword("DESC") {
+                    true
+                } else {
+                    self.eat_keyword("ASC");
+                    false
+                };
+                order_by.push((expr, descending));
+                if !self.eat_symbol(",") {
+                    break;
+                }
+            }
+        }
+
+        let limit = if self.eat_keyword("LIMIT") {
+            Some(self.parse_count("LIMIT")?)
+        } else {
+            None
+        };
+        let offset = if self.eat_keyword("OFFSET") {
+            self.parse_count("OFFSET")?
+        } else {
+            0
+        };
+
+        if self.pos < self.tokens.len() {
+            return Err(format!("Unexpected input {}", self.describe_position()));
+        }
+
+        Ok(Query {
+            distinct,
+            select,
+            filter,
+            group_by,
+            having,
+            order_by,
+            limit,
+            offset,
+        })
+    }
+
+    fn parse_count(&mut self, clause: &str) -> Result<usize, String> {
+        match self.next() {
+            Some(Token::Num(n)) if n >= 0.0 && n.fract() == 0.0 => Ok(n as usize),
+            _ => Err(format!("{} needs a whole number", clause)),
+        }
+    }
+
+    fn parse_select_item(&mut self) -> Result<SelectItem, String> {
+        if self.eat_symbol("*") {
+            return Ok(SelectItem::Wildcard);
+        }
+        let start = self.tokens.get(self.pos).map(|(_, s, _)| *s).unwrap_or(0);
+        let expr = self.parse_expr()?;
+        let end = self.tokens[..self.pos]
+            .last()
+            .map(|(_, _, e)| *e)
+            .unwrap_or(start);
+
+        let alias = if self.eat_keyword("AS") {
+            Some(self.parse_name()?)
+        } else {
+            match self.peek() {
+                Some(Token::QuotedIdent(_)) => Some(self.parse_name()?),
+                Some(Token::Ident(w)) if !is_keyword(w) => Some(self.parse_name()?),
+                _ => None,
+            }
+        };
+        let name = match (alias, &expr) {
+            (Some(alias), _) => alias,
+            (None, Expr::Column(column)) => column.clone(),
+            (None, _) => self.input[start..end].to_string(),
+        };
+        Ok(SelectItem::Expr { expr, name })
+    }
+
+    fn parse_name(&mut self) -> Result<String, String> {
+        match self.next() {
+            Some(Token::Ident(name)) | Some(Token::QuotedIdent(name)) => Ok(name),
+            _ => {
+                self.pos -= 1;
+                Err(format!("Expected a name {}", self.describe_position()))
+            }
+        }
+    }
+
+    fn parse_expr(&mut self) -> Result<Expr, String> {
+        let mut left = self.parse_and()?;
+        while self.eat_keyword("OR") {
+            let right = self.parse_and()?;
+            left = Expr::Binary(BinaryOp::Or, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, String> {
+        let mut left = self.parse_not()?;
+        while self.eat_keyword("AND") {
+            let right = self.parse_not()?;
+            left = Expr::Binary(BinaryOp::And, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr, String> {
+        if self.eat_keyword("NOT") {
+            return Ok(Expr::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr, String> {
+        let left = self.parse_additive()?;
+
+        if self.eat_keyword("IS") {
+            let negated = self.eat_keyword("NOT");
+            self.expect_keyword("NULL")?;
+            return Ok(Expr::IsNull {
+                expr: Box::new(left),
+                negated,
+            });
+        }
+
+        let negated = self.peek_keyword("NOT")
+            && matches!(
+                self.tokens.get(self.pos + 1),
+                Some((Token::Ident(w), _, _)) if w.eq_ignore_ascii_case("LIKE") || w.eq_ignore_ascii_case("IN")
+            );
+        if negated {
+            self.pos += 1;
+        }
+        if self.eat_keyword("LIKE") {
+            let pattern = self.parse_additive()?;
+            return Ok(Expr::Like {
+                expr: Box::new(left),
+                pattern: Box::new(pattern),
+                negated,
+            });
+        }
+        if self.eat_keyword("IN") {
+            self.expect_symbol("(")?;
+            let mut list = Vec::new();
+            loop {
+                list.push(self.parse_additive()?);
+                if !self.eat_symbol(",") {
+                    break;
+                }
+            }
+            self.expect_symbol(")")?;
+            return Ok(Expr::In {
+                expr: Box::new(left),
+                list,
+                negated,
+            });
+        }
+
+        let op = match self.peek() {
+            Some(Token::Symbol("=")) => BinaryOp::Eq,
+            Some(Token::Symbol("!=")) | Some(Token::Symbol("<>")) => BinaryOp::NotEq,
+            Some(Token::Symbol("<")) => BinaryOp::Lt,
+            Some(Token::Symbol("<=")) => BinaryOp::LtEq,
+            Some(Token::Symbol(">")) => BinaryOp::Gt,
+            Some(Token::Symbol(">=")) => BinaryOp::GtEq,
+            _ => return Ok(left),
+        };
+        self.pos += 1;
+        let right = self.parse_additive()?;
+        Ok(Expr::Binary(op, Box::new(left), Box::new(right)))
+    }
+
+    fn parse_additive(&mut self) -> Result<Expr, String> {
+        let mut left = self.parse_multiplicative()?;
+        loop {
+            let op = if self.eat_symbol("+") {
+                BinaryOp::Add
+            } else if self.eat_symbol("-") {
+                BinaryOp::Sub
+            } else {
+                return Ok(left);
+            };
+            let right = self.parse_multiplicative()?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+    }
+
+    fn parse_multiplicative(&mut self) -> Result<Expr, String> {
+        let mut left = self.parse_unary()?;
+        loop {
+            let op = if self.eat_symbol("*") {
+                BinaryOp::Mul
+            } else if self.eat_symbol("/") {
+                BinaryOp::Div
+            } else {
+                return Ok(left);
+            };
+            let right = self.parse_unary()?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, String> {
+        if self.eat_symbol("-") {
+            return Ok(Expr::Neg(Box::new(self.parse_unary()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, String> {
+        let position = self.describe_position();
+        match self.next() {
+            Some(Token::Num(n)) => Ok(Expr::Literal(number(n))),
+            Some(Token::Str(s)) => Ok(Expr::Literal(Value::String(s))),
+            Some(Token::QuotedIdent(name)) => Ok(Expr::Column(name)),
+            Some(Token::Symbol("(")) => {
+                let expr = self.parse_expr()?;
+                self.expect_symbol(")")?;
+                Ok(expr)
+            }
+            Some(Token::Ident(word)) => {
+                if self.eat_symbol("(") {
+                    return self.parse_call(&word);
+                }
+                match word.to_ascii_uppercase().as_str() {
+                    "NULL" => Ok(Expr::Literal(Value::Null)),
+                    "TRUE" => Ok(Expr::Literal(Value::Bool(true))),
+                    "FALSE" => Ok(Expr::Literal(Value::Bool(false))),
+                    upper if KEYWORDS.contains(&upper) => Err(format!(
+                        "Unexpected keyword {}; quote column names that are keywords",
+                        position
+                    )),
+                    _ => Ok(Expr::Column(word)),
+                }
+            }
+            _ => Err(format!("Expected a value or column {}", position)),
+        }
+    }
+
+    fn parse_call(&mut self, name: &str) -> Result<Expr, String> {
+        let upper = name.to_ascii_uppercase();
+        let aggregate = match upper.as_str() {
+            "COUNT" => Some(Aggregate::Count),
+            "SUM" => Some(Aggregate::Sum),
+            "AVG" => Some(Aggregate::Avg),
+            "MIN" => Some(Aggregate::Min),
+            "MAX" => Some(Aggregate::Max),
+            _ => None,
+        };
+
+        if let Some(func) = aggregate {
+            if func == Aggregate::Count && self.eat_symbol("*") {
+                self.expect_symbol(")")?;
+                return Ok(Expr::Aggregate {
+                    func,
+                    arg: None,
+                    distinct: false,
+                });
+            }
+            let distinct = self.eat_keyword("DISTINCT");
+            let arg = self.parse_expr()?;
+            self.expect_symbol(")")?;
+            return Ok(Expr::Aggregate {
+                func,
+                arg: Some(Box::new(arg)),
+                distinct,
+            });
+        }
+
+        const FUNCTIONS: &[&str] = &["LOWER", "UPPER", "LENGTH", "ROUND", "ABS", "COALESCE"];
+        if !FUNCTIONS.contains(&upper.as_str()) {
+            return Err(format!(
+                "Unknown function {}; available: COUNT, SUM, AVG, MIN, MAX, {}",
+                name,
+                FUNCTIONS.join(", ")
+            ));
+        }
+        let mut args = Vec::new();
+        if !self.eat_symbol(")") {
+            loop {
+                args.push(self.parse_expr()?);
+                if !self.eat_symbol(",") {
+                    break;
+                }
+            }
+            self.expect_symbol(")")?;
+        }
+        Ok(Expr::Function(upper, args))
+    }
+}
+
+fn is_keyword(word: &str) -> bool {
+    KEYWORDS.contains(&word.to_ascii_uppercase().as_str())
+}
+
+/// Column positions by exact name, with a case-insensitive fallback
+struct Columns<'a> {
+    names: &'a [String],
+}
+
+impl Columns<'_> {
+    fn index(&self, name: &str) -> Result<usize, String> {
+        self.names
+            .iter()
+            .position(|c| c == name)
+            .or_else(|| self.names.iter().position(|c| c.eq_ignore_ascii_case(name)))
+            .ok_or_else(|| {
+                format!(
+                    "Unknown column '{}'; columns are: {}",
+                    name,
+                    self.names.join(", ")
+                )
+            })
+    }
+}
+
+/// What an expression is evaluated against: one row, or a group of rows for aggregates
+enum Scope<'a> {
+    Row(&'a [Value]),
+    Group(&'a [&'a Vec<Value>]),
+}
+
+fn eval(expr: &Expr, columns: &Columns, scope: &Scope) -> Result<Value, String> {
+    Ok(match expr {
+        Expr::Literal(v) => v.clone(),
+        Expr::Column(name) => {
+            let idx = columns.index(name)?;
+            match scope {
+                Scope::Row(row) => row[idx].clone(),
+                // Outside an aggregate, a group takes the value of its first row
+                Scope::Group(rows) => rows.first().map(|r| r[idx].clone()).unwrap_or(Value::Null),
+            }
+        }
+        Expr::Neg(e) => match as_number(&eval(e, columns, scope)?) {
+            Some(n) => number(-n),
+            None => Value::Null,
+        },
+        Expr::Not(e) => match eval(e, columns, scope)? {
+            Value::Null => Value::Null,
+            v => Value::Bool(!truthy(&v)),
+        },
+        Expr::Binary(op, l, r) => {
+            let left = eval(l, columns, scope)?;
+            let right = eval(r, columns, scope)?;
+            binary(*op, &left, &right)
+        }
+        Expr::Like {
+            expr,
+            pattern,
+            negated,
+        } => {
+            let value = eval(expr, columns, scope)?;
+            let pattern = eval(pattern, columns, scope)?;
+            match (value, pattern) {
+                (Value::Null, _) | (_, Value::Null) => Value::Null,
+                (v, p) => Value::Bool(like(&display(&v), &display(&p)) != *negated),
+            }
+        }
+        Expr::In {
+            expr,
+            list,
+            negated,
+        } => {
+            let value = eval(expr, columns, scope)?;
+            if value.is_null() {
+                return Ok(Value::Null);
+            }
+            let mut found = false;
+            for item in list {
+                if compare(&value, &eval(item, columns, scope)?) == Some(Ordering::Equal) {
+                    found = true;
+                    break;
+                }
+            }
+            Value::Bool(found != *negated)
+        }
+        Expr::IsNull { expr, negated } => {
+            Value::Bool(eval(expr, columns, scope)?.is_null() != *negated)
+        }
+        Expr::Aggregate {
+            func,
+            arg,
+            distinct,
+        } => {
+            let Scope::Group(rows) = scope else {
+                return Err(
+                    "Aggregates like SUM and COUNT can't be used in WHERE; use HAVING".to_string(),
+                );
+            };
+            aggregate(*func, arg.as_deref(), *distinct, columns, rows)?
+        }
+        Expr::Function(name, args) => {
+            let values = args
+                .iter()
+                .map(|a| eval(a, columns, scope))
+                .collect::<Result<Vec<_>, _>>()?;
+            function(name, &values)?
+        }
+    })
+}
+
+fn aggregate(
+    func: Aggregate,
+    arg: Option<&Expr>,
+    distinct: bool,
+    columns: &Columns,
+    rows: &[&Vec<Value>],
+) -> Result<Value, String> {
+    let Some(arg) = arg else {
+        return Ok(Value::from(rows.len()));
+    };
+
+    let mut values = Vec::with_capacity(rows.len());
+    let mut seen = HashSet::new();
+    for row in rows {
+        let value = eval(arg, columns, &Scope::Row(row))?;
+        if value.is_null() || (distinct && !seen.insert(value.to_string())) {
+            continue;
+        }
+        values.push(value);
+    }
+
+    Ok(match func {
+        Aggregate::Count => Value::from(values.len()),
+        Aggregate::Sum | Aggregate::Avg => {
+            let numbers: Vec<f64> = values.iter().filter_map(as_number).collect();
+            if numbers.is_empty() {
+                Value::Null
+            } else if func == Aggregate::Sum {
+                number(numbers.iter().sum())
+            } else {
+                number(numbers.iter().sum::<f64>() / numbers.len() as f64)
+            }
+        }
+        Aggregate::Min => values
+            .into_iter()
+            .min_by(compare_for_sort)
+            .unwrap_or(Value::Null),
+        Aggregate::Max => values
+            .into_iter()
+            .max_by(compare_for_sort)
+            .unwrap_or(Value::Null),
+    })
+}
+
+fn function(name: &str, args: &[Value]) -> Result<Value, String> {
+    let arg = |i: usize| args.get(i).cloned().unwrap_or(Value::Null);
+    let expect = |n: usize| {
+        if args.len() == n {
+            Ok(())
+        } else {
+            Err(format!("{} takes {} argument(s)", name, n))
+        }
+    };
+    Ok(match name {
+        "LOWER" | "UPPER" | "LENGTH" => {
+            expect(1)?;
+            match arg(0) {
+                Value::Null => Value::Null,
+                v if name == "LOWER" => Value::String(display(&v).to_lowercase()),
+                v if name == "UPPER" => Value::String(display(&v).to_uppercase()),
+                v => Value::from(display(&v).chars().count()),
+            }
+        }
+        "ABS" => {
+            expect(1)?;
+            as_number(&arg(0))
+                .map(|n| number(n.abs()))
+                .unwrap_or(Value::Null)
+        }
+        "ROUND" => {
+            if args.is_empty() || args.len() > 2 {
+                return Err("ROUND takes 1 or 2 arguments".to_string());
+            }
+            let places = as_number(&arg(1)).unwrap_or(0.0) as i32;
+            let scale = 10f64.powi(places);
+            as_number(&arg(0))
+                .map(|n| number((n * scale).round() / scale))
+                .unwrap_or(Value::Null)
+        }
+        "COALESCE" => args
+            .iter()
+            .find(|v| !v.is_null())
+            .cloned()
+            .unwrap_or(Value::Null),
+        _ => return Err(format!("Unknown function {}", name)),
+    })
+}
+
+fn binary(op: BinaryOp, left: &Value, right: &Value) -> Value {
+    match op {
+        BinaryOp::And => match (left, right) {
+            (l, r) if (!l.is_null() && !truthy(l)) || (!r.is_null() && !truthy(r)) => {
+                Value::Bool(false)
+            }
+            (Value::Null, _) | (_, Value::Null) => Value::Null,
+            _ => Value::Bool(true),
+        },
+        BinaryOp::Or => match (left, right) {
+            (l, r) if truthy(l) || truthy(r) => Value::Bool(true),
+            (Value::Null, _) | (_, Value::Null) => Value::Null,
+            _ => Value::Bool(false),
+        },
+        BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div => {
+            let (Some(l), Some(r)) = (as_number(left), as_number(right)) else {
+                return Value::Null;
+            };
+            match op {
+                BinaryOp::Add => number(l + r),
+                BinaryOp::Sub => number(l - r),
+                BinaryOp::Mul => number(l * r),
+                _ if r == 0.0 => Value::Null,
+                _ => number(l / r),
+            }
+        }
+        _ => {
+            let Some(ordering) = compare(left, right) else {
+                return Value::Null;
+            };
+            Value::Bool(match op {
+                BinaryOp::Eq => ordering == Ordering::Equal,
+                BinaryOp::NotEq => ordering != Ordering::Equal,
+                BinaryOp::Lt => ordering == Ordering::Less,
+                BinaryOp::LtEq => ordering != Ordering::Greater,
+                BinaryOp::Gt => ordering == Ordering::Greater,
+                _ => ordering != Ordering::Less,
+            })
+        }
+    }
+}
+
+/// Compare two non-null values, numerically when both look like numbers
+fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    if a.is_null() || b.is_null() {
+        return None;
+    }
+    match (as_number(a), as_number(b)) {
+        (Some(x), Some(y)) => x.partial_cmp(&y),
+        _ => Some(display(a).cmp(&display(b))),
+    }
+}
+
+/// Total order for sorting: nulls first, then numbers, then text
+fn compare_for_sort(a: &Value, b: &Value) -> Ordering {
+    let rank = |v: &Value| match v {
+        Value::Null => 0,
+        v if as_number(v).is_some() => 1,
+        _ => 2,
+    };
+    rank(a)
+        .cmp(&rank(b))
+        .then_with(|| compare(a, b).unwrap_or(Ordering::Equal))
+}
+
+fn as_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok().filter(|n: &f64| n.is_finite()),
+        _ => None,
+    }
+}
+
+fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Bool(b) => *b,
+        Value::Null => false,
+        v => as_number(v).is_some_and(|n| n != 0.0),
+    }
+}
+
+/// A JSON number, kept integral when it has no fractional part
+fn number(n: f64) -> Value {
+    if n.fract() == 0.0 && n.abs() < 1e15 {
+        Value::from(n as i64)
+    } else {
+        serde_json::Number::from_f64(n)
+            .map(Value::Number)
+            .unwrap_or(Value::Null)
+    }
+}
+
+/// Render a value for display or text comparison
+pub fn display(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Number(n) => match n.as_f64() {
+            Some(f) if !n.is_i64() && !n.is_u64() => {
+                let s = format!("{:.6}", f);
+                s.trim_end_matches('0').trim_end_matches('.').to_string()
+            }
+            _ => n.to_string(),
+        },
+        other => other.to_string(),
+    }
+}
+
+/// Case-insensitive SQL LIKE, where `%` matches any run of characters and `_` any one
+fn like(text: &str, pattern: &str) -> bool {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let (mut t, mut p) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '_' || pattern[p] == text[t]) {
+            t += 1;
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == '%' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((bp, bt)) = backtrack {
+            p = bp + 1;
+            t = bt + 1;
+            backtrack = Some((bp, bt + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '%')
+}
+
+fn execute(table: &Table, query: &Query) -> Result<QueryResult, String> {
+    let columns = Columns {
+        names: &table.columns,
+    };
+
+    let mut filtered: Vec<&Vec<Value>> = Vec::new();
+    for row in &table.rows {
+        let keep = match query.filter {
+            Some(ref filter) => truthy(&eval(filter, &columns, &Scope::Row(row))?),
+            None => true,
+        };
+        if keep {
+            filtered.push(row);
+        }
+    }
+
+    // Expand `*` into the table's columns
+    let mut items: Vec<(Expr, String)> = Vec::new();
+    for item in &query.select {
+        match item {
+            SelectItem::Wildcard => items.extend(
+                table
+                    .columns
+                    .iter()
+                    .map(|c| (Expr::Column(c.clone()), c.clone())),
+            ),
+            SelectItem::Expr { expr, name } => items.push((expr.clone(), name.clone())),
+        }
+    }
+    let output_columns: Vec<String> = items.iter().map(|(_, name)| name.clone()).collect();
+
+    let grouped = !query.group_by.is_empty()
+        || items.iter().any(|(e, _)| e.has_aggregate())
+        || query.having.is_some();
+
+    let groups: Vec<Vec<&Vec<Value>>> = if grouped {
+        group_rows(&filtered, &query.group_by, &columns)?
+    } else {
+        filtered.iter().map(|row| vec![*row]).collect()
+    };
+
+    // Each output row keeps the rows it came from so ORDER BY can see unselected columns
+    let mut output: Vec<(Vec<Value>, Vec<&Vec<Value>>)> = Vec::new();
+    for group in groups {
+        let scope = if grouped {
+            Scope::Group(&group)
+        } else {
+            Scope::Row(group[0])
+        };
+        if let Some(ref having) = query.having {
+            if !truthy(&eval(having, &columns, &scope)?) {
+                continue;
+            }
+        }
+        let values = items
+            .iter()
+            .map(|(expr, _)| eval(expr, &columns, &scope))
+            .collect::<Result<Vec<_>, _>>()?;
+        output.push((values, group));
+    }
+
+    if query.distinct {
+        let mut seen = HashSet::new();
+        output.retain(|(values, _)| seen.insert(Value::Array(values.clone()).to_string()));
+    }
+
+    if !query.order_by.is_empty() {
+        let mut keyed = Vec::with_capacity(output.len());
+        for (values, group) in output {
+            let scope = if grouped {
+                Scope::Group(&group)
+            } else {
+                Scope::Row(group[0])
+            };
+            let mut keys = Vec::with_capacity(query.order_by.len());
+            for (expr, _) in &query.order_by {
+                // Output names (including aliases) win over source columns
+                let key = match expr {
+                    Expr::Column(name) => match output_columns.iter().position(|c| c == name) {
+                        Some(i) => values[i].clone(),
+                        None => eval(expr, &columns, &scope)?,
+                    },
+                    _ => eval(expr, &columns, &scope)?,
+                };
+                keys.push(key);
+            }
+            keyed.push((keys, values));
+        }
+        keyed.sort_by(|(a, _), (b, _)| {
+            query
+                .order_by
+                .iter()
+                .zip(a.iter().zip(b))
+                .map(|((_, descending), (x, y))| {
+                    let ordering = compare_for_sort(x, y);
+                    if *descending {
+                        ordering.reverse()
+                    } else {
+                        ordering
+                    }
+                })
+                .find(|o| *o != Ordering::Equal)
+                .unwrap_or(Ordering::Equal)
+        });
+        output = keyed
+            .into_iter()
+            .map(|(_, values)| (values, Vec::new()))
+            .collect();
+    }
+
+    let matched_rows = output.len();
+    let rows = output
+        .into_iter()
+        .map(|(values, _)| values)
+        .skip(query.offset)
+        .take(query.limit.unwrap_or(usize::MAX))
+        .collect();
+
+    Ok(QueryResult {
+        columns: output_columns,
+        rows,
+        source_rows: table.rows.len(),
+        matched_rows,
+    })
+}
+
+/// Split rows into groups by key, in order of each key's first appearance
+fn group_rows<'a>(
+    rows: &[&'a Vec<Value>],
+    keys: &[Expr],
+    columns: &Columns,
+) -> Result<Vec<Vec<&'a Vec<Value>>>, String> {
+    if keys.is_empty() {
+        return Ok(vec![rows.to_vec()]);
+    }
+    let mut groups: Vec<Vec<&Vec<Value>>> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for row in rows {
+        let key = keys
+            .iter()
+            .map(|k| eval(k, columns, &Scope::Row(row)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let key = Value::Array(key).to_string();
+        match index.get(&key) {
+            Some(&i) => groups[i].push(*row),
+            None => {
+                index.insert(key, groups.len());
+                groups.push(vec![*row]);
+            }
+        }
+    }
+    Ok(groups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    fn sales() -> Table {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("sales.csv");
+        std::fs::write(
+            &path,
+            "region,product,amount,year\nEU,widget,100,2023\nUS,widget,250,2023\nEU,gadget,50.5,2024\nAPAC,gadget,,2024\nEU,widget,30,2024\n",
+        )
+        .unwrap();
+        load_table(&path).unwrap()
+    }
+
+    fn rows(result: &QueryResult) -> Vec<Vec<String>> {
+        result
+            .rows
+            .iter()
+            .map(|r| r.iter().map(display).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_load_csv() {
+        let table = sales();
+        assert_eq!(table.columns, vec!["region", "product", "amount", "year"]);
+        assert_eq!(table.rows.len(), 5);
+        assert_eq!(table.rows[3][2], Value::Null);
+    }
+
+    #[test]
+    fn test_filter_and_aggregate() {
+        let result = run_query(
+            &sales(),
+            "SELECT SUM(amount) AS total, COUNT(*) WHERE region = 'EU'",
+        )
+        .unwrap();
+        assert_eq!(result.columns, vec!["total", "COUNT(*)"]);
+        assert_eq!(rows(&result), vec![vec!["180.5", "3"]]);
+    }
+
+    #[test]
+    fn test_group_order_limit() {
+        let result = run_query(
+            &sales(),
+            "select region, sum(amount) as total, count(amount) n from sales group by region order by total desc limit 2",
+        )
+        .unwrap();
+        assert_eq!(result.columns, vec!["region", "total", "n"]);
+        assert_eq!(
+            rows(&result),
+            vec![vec!["US", "250", "1"], vec!["EU", "180.5", "3"]]
+        );
+        assert_eq!(result.matched_rows, 3);
+        assert_eq!(result.source_rows, 5);
+    }
+
+    #[test]
+    fn test_having_and_distinct() {
+        let result = run_query(
+            &sales(),
+            "SELECT product, AVG(amount) GROUP BY product HAVING COUNT(*) > 2",
+        )
+        .unwrap();
+        assert_eq!(
+            rows(&result),
+            vec![vec!["widget".to_string(), "126.666667".to_string()]]
+        );
+
+        let result = run_query(&sales(), "SELECT DISTINCT year ORDER BY year").unwrap();
+        assert_eq!(rows(&result), vec![vec!["2023"], vec!["2024"]]);
+    }
+
+    #[test]
+    fn test_where_operators() {
+        let table = sales();
+        let count = |q: &str| run_query(&table, q).unwrap().rows.len();
+        assert_eq!(count("SELECT * WHERE amount > 60 AND year = 2023"), 2);
+        assert_eq!(count("SELECT * WHERE product LIKE 'WID%'"), 3);
+        assert_eq!(count("SELECT * WHERE region NOT IN ('EU', 'US')"), 1);
+        assert_eq!(count("SELECT * WHERE amount IS NULL"), 1);
+        assert_eq!(
+            count("SELECT * WHERE NOT (region = 'EU' OR region = 'US')"),
+            1
+        );
+        // Comparisons with null are unknown, so the APAC row doesn't match either way
+        assert_eq!(
+            count("SELECT * WHERE NOT (region = 'EU' OR amount >= 100)"),
+            0
+        );
+        assert_eq!(count("SELECT * WHERE amount * 2 + 1 > 200"), 2);
+        assert_eq!(count("SELECT * WHERE LOWER(region) = 'apac'"), 1);
+    }
+
+    #[test]
+    fn test_order_by_unselected_column() {
+        let result = run_query(&sales(), "SELECT product ORDER BY amount LIMIT 2").unwrap();
+        // The null amount sorts first
+        assert_eq!(rows(&result), vec![vec!["gadget"], vec!["widget"]]);
+    }
+
+    #[test]
+    fn test_json_records_are_flattened() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("people.json");
+        let data = json!({"results": [
+            {"name": "Ann", "address": {"city": "Oslo"}, "tags": ["a"]},
+            {"name": "Bo", "address": {"city": "Rome"}, "age": 40}
+        ]});
+        std::fs::write(&path, data.to_string()).unwrap();
+
+        let table = load_table(&path).unwrap();
+        assert!(table.columns.contains(&"address.city".to_string()));
+        let result = run_query(&table, "SELECT name WHERE \"address.city\" = 'Rome'").unwrap();
+        assert_eq!(rows(&result), vec![vec!["Bo"]]);
+        let result = run_query(&table, "SELECT name, age WHERE age IS NULL").unwrap();
+        assert_eq!(rows(&result), vec![vec!["Ann", ""]]);
+    }
+
+    #[test]
+    fn test_jsonl() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("events.jsonl");
+        std::fs::write(
+            &path,
+            "{\"kind\": \"a\", \"ms\": 5}\n\n{\"kind\": \"b\", \"ms\": 7.5}\n",
+        )
+        .unwrap();
+        let result = run_query(&load_table(&path).unwrap(), "SELECT MAX(ms), MIN(kind)").unwrap();
+        assert_eq!(rows(&result), vec![vec!["7.5", "a"]]);
+    }
+
+    #[test]
+    fn test_query_errors() {
+        let table = sales();
+        assert!(run_query(&table, "SELECT nope")
+            .unwrap_err()
+            .contains("Unknown column 'nope'"));
+        assert!(run_query(&table, "SELECT * WHERE SUM(amount) > 1")
+            .unwrap_err()
+            .contains("HAVING"));
+        assert!(run_query(&table, "SELECT * LIMIT x").is_err());
+        assert!(run_query(&table, "SELECT region region2 extra").is_err());
+        assert!(run_query(&table, "DELETE FROM t").is_err());
+    }
+
+    #[test]
+    fn test_like() {
+        assert!(like("Hello World", "hello%"));
+        assert!(like("abc", "a_c"));
+        assert!(like("abcbc", "%bc"));
+        assert!(!like("abc", "a_"));
+        assert!(like("", "%"));
+    }
+}
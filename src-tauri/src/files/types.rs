@@ -1,10 +1,24 @@
 use serde::{Deserialize, Serialize};
 
+use super::encoding::{LineEnding, TextEncoding};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FolderPermission {
     pub id: String,
     pub path: String,
     pub granted_at: u64,
+    /// Unix timestamp (seconds) after which the grant no longer applies
+    #[serde(default)]
+    pub expires_at: Option<u64>,
+    /// Conversation the grant is scoped to; revoked when that session ends
+    #[serde(default)]
+    pub session_id: Option<String>,
+}
+
+impl FolderPermission {
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expires_at.is_some_and(|expires_at| now >= expires_at)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,3 +29,245 @@ pub struct FileInfo {
     pub size: u64,
     pub modified: u64,
 }
+
+/// Full metadata for one file, folder or link, beyond what a listing carries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileStat {
+    #[serde(flatten)]
+    pub info: FileInfo,
+    /// Unix timestamps in seconds; `None` where the filesystem doesn't record them
+    pub created: Option<u64>,
+    pub accessed: Option<u64>,
+    /// Permission bits such as `0o644`; `None` on platforms without Unix modes
+    pub mode: Option<u32>,
+    pub readonly: bool,
+    pub is_symlink: bool,
+    pub symlink_target: Option<String>,
+    /// Guessed from the extension; `None` for folders
+    pub mime_type: Option<String>,
+    /// Only computed on request, and only for regular files
+    pub sha256: Option<String>,
+}
+
+/// Files with identical contents
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    pub sha256: String,
+    /// Size of each copy in bytes
+    pub size: u64,
+    pub paths: Vec<String>,
+}
+
+impl DuplicateGroup {
+    /// Space freed by keeping a single copy
+    pub fn wasted_bytes(&self) -> u64 {
+        self.size * (self.paths.len() as u64).saturating_sub(1)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateReport {
+    pub root: String,
+    /// Largest reclaimable space first
+    pub groups: Vec<DuplicateGroup>,
+    pub files_scanned: usize,
+    /// Bytes taken up by every copy beyond the first in each group
+    pub wasted_bytes: u64,
+    /// The scan stopped at the file limit, so some duplicates may be missing
+    pub truncated: bool,
+}
+
+/// Options for a recursive directory listing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ListOptions {
+    /// Maximum depth below the root (1 = direct children); `None` walks the whole tree
+    pub max_depth: Option<usize>,
+    /// Glob patterns a file must match to be listed
+    pub include: Vec<String>,
+    /// Glob patterns for files and directories to skip
+    pub exclude: Vec<String>,
+    pub show_hidden: bool,
+    pub respect_gitignore: bool,
+    /// Stop after this many entries and mark the listing as truncated
+    pub max_entries: usize,
+}
+
+impl Default for ListOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: None,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            show_hidden: false,
+            respect_gitignore: true,
+            max_entries: 1000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TreeEntry {
+    #[serde(flatten)]
+    pub info: FileInfo,
+    /// Depth below the listing root (1 = direct child)
+    pub depth: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectoryTree {
+    pub root: String,
+    pub entries: Vec<TreeEntry>,
+    pub truncated: bool,
+}
+
+/// Options for searching file contents across granted folders
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchOptions {
+    pub pattern: String,
+    /// Folder or file to search; `None` searches every granted folder
+    pub path: Option<String>,
+    /// Treat the pattern as plain text instead of a regex
+    pub literal: bool,
+    pub case_sensitive: bool,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    /// Lines of context to return before and after each match
+    pub context_lines: usize,
+    pub max_results: usize,
+    /// Files larger than this many bytes are skipped
+    pub max_file_size: u64,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            pattern: String::new(),
+            path: None,
+            literal: false,
+            case_sensitive: false,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            context_lines: 1,
+            max_results: 100,
+            max_file_size: 5 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchMatch {
+    pub path: String,
+    pub line_number: usize,
+    pub line: String,
+    pub before: Vec<String>,
+    pub after: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResults {
+    pub matches: Vec<SearchMatch>,
+    pub files_searched: usize,
+    pub truncated: bool,
+}
+
+/// Which part of a file to read
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum ReadRange {
+    /// `limit` lines starting at 1-based line `offset`
+    Lines {
+        offset: usize,
+        limit: Option<usize>,
+    },
+    /// `limit` bytes starting at byte `offset`
+    Bytes {
+        offset: u64,
+        limit: Option<u64>,
+    },
+    Head {
+        lines: usize,
+    },
+    Tail {
+        lines: usize,
+    },
+}
+
+/// A slice of a file along with the size of the whole file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileChunk {
+    pub content: String,
+    pub total_bytes: u64,
+    /// Not counted for byte-based reads, which would otherwise scan the whole file
+    pub total_lines: Option<usize>,
+    /// 1-based inclusive line span of `content`, for line-based reads
+    pub start_line: Option<usize>,
+    pub end_line: Option<usize>,
+    /// Byte span `[start_byte, end_byte)` of `content`, for byte-based reads
+    pub start_byte: Option<u64>,
+    pub end_byte: Option<u64>,
+    /// The read stopped early because it hit the size cap
+    pub truncated: bool,
+    /// Encoding the file was decoded from; sizes and byte offsets refer to the file as stored
+    pub encoding: TextEncoding,
+    pub line_ending: LineEnding,
+}
+
+impl FileChunk {
+    /// Whether `content` holds the entire file
+    pub fn is_complete(&self) -> bool {
+        if self.truncated {
+            return false;
+        }
+        match (
+            self.start_line,
+            self.end_line,
+            self.start_byte,
+            self.end_byte,
+        ) {
+            (Some(start), Some(end), _, _) => {
+                start <= 1 && self.total_lines.is_some_and(|total| end >= total)
+            }
+            (_, _, Some(start), Some(end)) => start == 0 && end >= self.total_bytes,
+            _ => self.total_bytes == 0,
+        }
+    }
+}
+
+/// What a binary file is, in place of its contents
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BinaryFileInfo {
+    pub mime_type: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// Result of reading a file: decoded text, or a description of a binary file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FileRead {
+    Text(FileChunk),
+    Binary(BinaryFileInfo),
+}
+
+/// A single targeted change applied by `edit_file`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FileEdit {
+    /// Replace the one exact occurrence of `search` with `replace`
+    Replace { search: String, replace: String },
+    /// Replace 1-based inclusive lines `start..=end` with `content`
+    ReplaceLines {
+        start: usize,
+        end: usize,
+        content: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EditResult {
+    pub edits_applied: usize,
+    /// Unified diff of the change
+    pub diff: String,
+}
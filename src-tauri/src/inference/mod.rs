@@ -2,4 +2,7 @@ pub mod llama;
 pub mod tools;
 
 pub use llama::{LlamaInference, Message};
-pub use tools::{execute_tool, parse_tool_calls, format_tools_for_prompt, extract_text_content, ToolCall};
+pub use tools::{
+    execute_tool, extract_text_content, format_changes_note, format_tools_for_prompt,
+    parse_tool_calls, process_audit_entry, ToolCall, ToolContext,
+};
@@ -1,8 +1,53 @@
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 
+use crate::approval::{ApprovalDecision, ApprovalRequest, ApprovalSubject};
+use crate::files::audit::{AuditEntry, AuditLog};
+use crate::files::extract::markdown_table;
 use crate::files::operations;
-use crate::files::PermissionStore;
+use crate::files::permissions::civil_date;
+use crate::files::query;
+use crate::files::{
+    ArchiveListing, BatchHistory, BatchPlan, BatchRequest, BinaryFileInfo, ChangesSince,
+    DirectoryTree, DocumentFormat, DuplicateReport, ExtractSummary, ExtractedDocument, FileChunk,
+    FileEdit, FileOpError, FileRead, FileStat, LineEnding, ListOptions, PermissionStore,
+    QueryResult, ReadRange, SearchOptions, SearchResults, TextEncoding,
+};
+use crate::mcp::{self, McpManager, McpTool};
+use crate::terminal::{
+    self, CommandOutput, CommandOutputEvent, CommandPolicy, CommandSpec, OutputStream,
+    PolicyAction, ProcessInfo, ProcessManager, ProcessOutput, ProcessScope, ProcessStatus,
+    SandboxMode, SandboxSpec,
+};
+use crate::web::{self, DomainDecision, DomainPolicy, FetchedPage, SearchResponse, WebSearch};
+
+/// Most file content returned to the model from a single read_file call
+const MAX_TOOL_READ_BYTES: u64 = 8 * 1024;
+/// Most lines the model may ask read_file for with head, tail or limit
+const MAX_TOOL_READ_LINES: usize = 10_000;
+
+/// Most extracted text returned to the model from a single read_document call
+const MAX_TOOL_DOCUMENT_CHARS: usize = 16 * 1024;
+
+/// Most result rows shown to the model from a single query_data call
+const MAX_TOOL_QUERY_ROWS: usize = 50;
+/// Longest diff echoed back to the model after an edit
+const MAX_TOOL_DIFF_CHARS: usize = 4000;
+/// Default depth for recursive listings requested by the model
+const DEFAULT_TREE_DEPTH: usize = 3;
+/// Cap on tree entries returned to the model so the listing fits in context
+const MAX_TREE_ENTRIES: usize = 200;
+/// Default and maximum number of search matches returned to the model
+const DEFAULT_SEARCH_RESULTS: usize = 30;
+const MAX_SEARCH_RESULTS: usize = 100;
+/// Most context lines the model may request around each search match
+const MAX_SEARCH_CONTEXT: usize = 5;
+/// Most of each output stream shown to the model from a single run_command call
+const MAX_TOOL_COMMAND_OUTPUT_BYTES: usize = 8 * 1024;
+/// Most page content returned to the model from a single fetch_url call
+const MAX_TOOL_FETCH_BYTES: usize = 16 * 1024;
+/// Most of an MCP tool's output returned to the model
+const MAX_TOOL_MCP_OUTPUT_CHARS: usize = 16 * 1024;
 
 /// A tool call parsed from LLM output
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,13 +72,40 @@ pub fn get_file_tools() -> Vec<ToolDefinition> {
     vec![
         ToolDefinition {
             name: "list_files",
-            description: "List files and directories in a given path",
+            description:
+                "List files and directories in a given path, optionally as a recursive tree",
             parameters: json!({
                 "type": "object",
                 "properties": {
                     "path": {
                         "type": "string",
                         "description": "Absolute path to the directory to list"
+                    },
+                    "recursive": {
+                        "type": "boolean",
+                        "description": "List subdirectories too (default false)"
+                    },
+                    "max_depth": {
+                        "type": "integer",
+                        "description": "How many levels to descend when recursive (default 3)"
+                    },
+                    "include": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Only list files matching these globs, e.g. [\"*.md\"]"
+                    },
+                    "exclude": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Skip files and folders matching these globs"
+                    },
+                    "hidden": {
+                        "type": "boolean",
+                        "description": "Include hidden files (default false)"
+                    },
+                    "gitignore": {
+                        "type": "boolean",
+                        "description": "Skip files ignored by .gitignore (default true)"
                     }
                 },
                 "required": ["path"]
@@ -41,13 +113,73 @@ pub fn get_file_tools() -> Vec<ToolDefinition> {
         },
         ToolDefinition {
             name: "read_file",
-            description: "Read the contents of a text file",
+            description: "Read a text file, or part of it for large files. The reply starts with a header giving the file's total size and line count. Binary files are described by type, size and SHA-256 instead",
             parameters: json!({
                 "type": "object",
                 "properties": {
                     "path": {
                         "type": "string",
                         "description": "Absolute path to the file to read"
+                    },
+                    "offset": {
+                        "type": "integer",
+                        "description": "First line to read, starting at 1"
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "description": "Number of lines to read from offset (at most 10000)"
+                    },
+                    "head": {
+                        "type": "integer",
+                        "description": "Read only the first N lines (at most 10000)"
+                    },
+                    "tail": {
+                        "type": "integer",
+                        "description": "Read only the last N lines (at most 10000)"
+                    },
+                    "byte_offset": {
+                        "type": "integer",
+                        "description": "Read by bytes instead of lines, starting at this byte"
+                    },
+                    "byte_limit": {
+                        "type": "integer",
+                        "description": "Number of bytes to read from byte_offset"
+                    }
+                },
+                "required": ["path"]
+            }),
+        },
+        ToolDefinition {
+            name: "read_document",
+            description: "Extract the text of a PDF, Word (DOCX), OpenDocument (ODT), PowerPoint (PPTX), spreadsheet (XLSX/XLS/ODS), CSV/TSV or HTML file as markdown, with a heading marking each page, slide or sheet",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Absolute path to the document"
+                    },
+                    "section": {
+                        "type": "integer",
+                        "description": "Read only this page, slide or sheet, starting at 1"
+                    }
+                },
+                "required": ["path"]
+            }),
+        },
+        ToolDefinition {
+            name: "query_data",
+            description: "Run a SQL-like query over a CSV, TSV, JSON or JSONL file and get the result as a table. Use this instead of reading the file when you need to filter, count, sum or sort rows. Syntax: SELECT cols | * | COUNT(*) | SUM(col) | AVG(col) | MIN(col) | MAX(col) [AS name] [WHERE cond] [GROUP BY cols] [HAVING cond] [ORDER BY col [DESC]] [LIMIT n]. Conditions support = != < > <= >= AND OR NOT, LIKE '%text%', IN (...), IS NULL. Quote column names containing spaces with double quotes and text values with single quotes",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Absolute path to the data file"
+                    },
+                    "query": {
+                        "type": "string",
+                        "description": "Query to run, e.g. SELECT region, SUM(total) AS total WHERE year = 2024 GROUP BY region ORDER BY total DESC (default: SELECT * LIMIT 5, to see the columns)"
                     }
                 },
                 "required": ["path"]
@@ -71,6 +203,49 @@ pub fn get_file_tools() -> Vec<ToolDefinition> {
                 "required": ["path", "content"]
             }),
         },
+        ToolDefinition {
+            name: "edit_file",
+            description: "Make targeted changes to an existing file without rewriting it. Each edit either replaces an exact snippet (search must occur exactly once) or replaces a range of lines. Returns the resulting diff",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Absolute path to the file to edit"
+                    },
+                    "edits": {
+                        "type": "array",
+                        "description": "Edits applied in order; nothing is written if any edit fails",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "search": {
+                                    "type": "string",
+                                    "description": "Exact text to find, copied from the file"
+                                },
+                                "replace": {
+                                    "type": "string",
+                                    "description": "Text to put in place of search"
+                                },
+                                "start_line": {
+                                    "type": "integer",
+                                    "description": "First line to replace (1-based), instead of search"
+                                },
+                                "end_line": {
+                                    "type": "integer",
+                                    "description": "Last line to replace (inclusive)"
+                                },
+                                "content": {
+                                    "type": "string",
+                                    "description": "New text for the line range"
+                                }
+                            }
+                        }
+                    }
+                },
+                "required": ["path", "edits"]
+            }),
+        },
         ToolDefinition {
             name: "create_file",
             description: "Create a new file with content (fails if file already exists)",
@@ -121,182 +296,2168 @@ pub fn get_file_tools() -> Vec<ToolDefinition> {
                 "required": ["src", "dest"]
             }),
         },
-    ]
-}
-
-/// Format tool definitions as a string for the system prompt
-pub fn format_tools_for_prompt() -> String {
-    let tools = get_file_tools();
-    let tools_json: Vec<Value> = tools
-        .iter()
-        .map(|t| {
-            json!({
-                "name": t.name,
-                "description": t.description,
-                "parameters": t.parameters
-            })
-        })
-        .collect();
-
-    format!(
-        r#"You have access to the following tools to help users with file operations:
-
-{}
-
-To use a tool, respond with a tool call in this exact format:
-<tool_call>{{"name": "tool_name", "arguments": {{"arg1": "value1"}}}}</tool_call>
-
-You can use multiple tool calls in a single response. After each tool call, you will receive the result.
-Only use tools when the user asks for file operations. Always provide a natural language response along with your tool calls."#,
-        serde_json::to_string_pretty(&tools_json).unwrap_or_default()
-    )
-}
-
-/// Execute a tool call and return the result
-pub fn execute_tool(store: &PermissionStore, tool_call: &ToolCall) -> String {
-    match tool_call.name.as_str() {
-        "list_files" => {
-            let path = tool_call.arguments.get("path").and_then(|v| v.as_str());
-            match path {
-                Some(p) => match operations::list_directory(store, p) {
-                    Ok(files) => {
-                        let file_list: Vec<String> = files
-                            .iter()
-                            .map(|f| {
-                                let type_indicator = if f.is_directory { "[DIR]" } else { "[FILE]" };
-                                format!("{} {} ({})", type_indicator, f.name, f.path)
-                            })
-                            .collect();
-                        if file_list.is_empty() {
-                            "Directory is empty".to_string()
-                        } else {
-                            file_list.join("\n")
-                        }
+        ToolDefinition {
+            name: "create_directory",
+            description: "Create a directory, including any missing parent directories",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Absolute path of the directory to create"
                     }
-                    Err(e) => format!("Error: {}", e),
                 },
-                None => "Error: Missing 'path' argument".to_string(),
-            }
-        }
-        "read_file" => {
-            let path = tool_call.arguments.get("path").and_then(|v| v.as_str());
-            match path {
-                Some(p) => match operations::read_file(store, p) {
-                    Ok(content) => content,
-                    Err(e) => format!("Error: {}", e),
+                "required": ["path"]
+            }),
+        },
+        ToolDefinition {
+            name: "copy_file",
+            description: "Copy a file (fails if the destination already exists)",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "src": {
+                        "type": "string",
+                        "description": "Absolute path to the file to copy"
+                    },
+                    "dest": {
+                        "type": "string",
+                        "description": "Absolute path for the copy"
+                    }
                 },
-                None => "Error: Missing 'path' argument".to_string(),
-            }
-        }
-        "write_file" => {
-            let path = tool_call.arguments.get("path").and_then(|v| v.as_str());
-            let content = tool_call.arguments.get("content").and_then(|v| v.as_str());
-            match (path, content) {
-                (Some(p), Some(c)) => match operations::write_file(store, p, c) {
-                    Ok(()) => format!("Successfully wrote to {}", p),
-                    Err(e) => format!("Error: {}", e),
+                "required": ["src", "dest"]
+            }),
+        },
+        ToolDefinition {
+            name: "copy_directory",
+            description: "Copy a directory and everything in it to a new location",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "src": {
+                        "type": "string",
+                        "description": "Absolute path to the directory to copy"
+                    },
+                    "dest": {
+                        "type": "string",
+                        "description": "Absolute path for the copy (must not exist yet)"
+                    }
                 },
-                _ => "Error: Missing 'path' or 'content' argument".to_string(),
-            }
-        }
-        "create_file" => {
-            let path = tool_call.arguments.get("path").and_then(|v| v.as_str());
-            let content = tool_call.arguments.get("content").and_then(|v| v.as_str());
-            match (path, content) {
-                (Some(p), Some(c)) => match operations::create_file(store, p, c) {
-                    Ok(()) => format!("Successfully created {}", p),
-                    Err(e) => format!("Error: {}", e),
+                "required": ["src", "dest"]
+            }),
+        },
+        ToolDefinition {
+            name: "remove_empty_directory",
+            description: "Remove a directory that contains no files",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Absolute path to the empty directory"
+                    }
                 },
-                _ => "Error: Missing 'path' or 'content' argument".to_string(),
-            }
-        }
-        "delete_file" => {
-            let path = tool_call.arguments.get("path").and_then(|v| v.as_str());
-            match path {
-                Some(p) => match operations::delete_file(store, p) {
-                    Ok(()) => format!("Successfully deleted {}", p),
-                    Err(e) => format!("Error: {}", e),
+                "required": ["path"]
+            }),
+        },
+        ToolDefinition {
+            name: "append_to_file",
+            description: "Append content to the end of an existing file",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Absolute path to the file"
+                    },
+                    "content": {
+                        "type": "string",
+                        "description": "Content to append"
+                    }
                 },
-                None => "Error: Missing 'path' argument".to_string(),
-            }
-        }
-        "move_file" => {
-            let src = tool_call.arguments.get("src").and_then(|v| v.as_str());
-            let dest = tool_call.arguments.get("dest").and_then(|v| v.as_str());
-            match (src, dest) {
-                (Some(s), Some(d)) => match operations::move_file(store, s, d) {
-                    Ok(()) => format!("Successfully moved {} to {}", s, d),
-                    Err(e) => format!("Error: {}", e),
+                "required": ["path", "content"]
+            }),
+        },
+        ToolDefinition {
+            name: "batch_move",
+            description: "Move or rename many files in one step. Without apply=true it only returns the plan; call it again with the same arguments and apply=true to carry it out all at once. kind 'moves' takes an explicit list, 'rename' renames files in a folder from a template using {name}, {ext}, {date} (modified date) and {n} (order by date), 'organize_by_type' sorts a folder's files into Images, Documents, etc.",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "kind": {
+                        "type": "string",
+                        "enum": ["moves", "rename", "organize_by_type"],
+                        "description": "What kind of batch to run"
+                    },
+                    "moves": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "from": { "type": "string" },
+                                "to": { "type": "string" }
+                            },
+                            "required": ["from", "to"]
+                        },
+                        "description": "For 'moves': absolute source and full destination paths"
+                    },
+                    "folder": {
+                        "type": "string",
+                        "description": "For 'rename' and 'organize_by_type': absolute path of the folder whose files are affected"
+                    },
+                    "template": {
+                        "type": "string",
+                        "description": "For 'rename': new file name, e.g. \"{date}_{n}\". The extension is kept unless {ext} is used"
+                    },
+                    "include": {
+                        "type": "string",
+                        "description": "For 'rename': only files whose name matches this glob, e.g. \"*.jpg\""
+                    },
+                    "apply": {
+                        "type": "boolean",
+                        "description": "Carry out the plan instead of only previewing it (default false)"
+                    }
                 },
-                _ => "Error: Missing 'src' or 'dest' argument".to_string(),
-            }
-        }
-        _ => format!("Error: Unknown tool '{}'", tool_call.name),
-    }
-}
-
-/// Parse tool calls from LLM output
-pub fn parse_tool_calls(text: &str) -> Vec<ToolCall> {
-    let mut tool_calls = Vec::new();
-    let mut id_counter = 0;
-
-    // Find all <tool_call>...</tool_call> blocks
-    let mut remaining = text;
-    while let Some(start) = remaining.find("<tool_call>") {
-        let after_start = &remaining[start + 11..];
-        if let Some(end) = after_start.find("</tool_call>") {
-            let json_str = &after_start[..end].trim();
-
-            // Parse the JSON inside the tags
-            if let Ok(parsed) = serde_json::from_str::<Value>(json_str) {
-                if let (Some(name), Some(arguments)) = (
-                    parsed.get("name").and_then(|v| v.as_str()),
-                    parsed.get("arguments"),
-                ) {
-                    tool_calls.push(ToolCall {
-                        id: format!("call_{}", id_counter),
-                        name: name.to_string(),
-                        arguments: arguments.clone(),
-                        result: None,
-                    });
-                    id_counter += 1;
-                }
-            }
-            remaining = &after_start[end + 12..];
-        } else {
-            break;
-        }
-    }
-
-    tool_calls
-}
-
-/// Extract the text content from LLM output, excluding tool calls
-pub fn extract_text_content(text: &str) -> String {
-    let mut result = text.to_string();
-
-    // Remove all <tool_call>...</tool_call> blocks
-    while let Some(start) = result.find("<tool_call>") {
-        if let Some(end) = result[start..].find("</tool_call>") {
-            result = format!("{}{}", &result[..start], &result[start + end + 12..]);
-        } else {
-            break;
-        }
-    }
-
-    result.trim().to_string()
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_parse_single_tool_call() {
-        let text = r#"Let me list the files for you.
-<tool_call>{"name": "list_files", "arguments": {"path": "/tmp/test"}}</tool_call>"#;
-
-        let calls = parse_tool_calls(text);
+                "required": ["kind"]
+            }),
+        },
+        ToolDefinition {
+            name: "stat_file",
+            description: "Show detailed metadata for a file or folder: timestamps, permissions, symlink target, MIME type and optionally a SHA-256 hash",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Absolute path to the file or folder"
+                    },
+                    "hash": {
+                        "type": "boolean",
+                        "description": "Also compute the SHA-256 of a file's contents (default false)"
+                    }
+                },
+                "required": ["path"]
+            }),
+        },
+        ToolDefinition {
+            name: "find_duplicates",
+            description: "Find files with identical contents under a folder, grouped by hash, largest reclaimable space first",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Absolute path to the folder to scan"
+                    },
+                    "min_size": {
+                        "type": "integer",
+                        "description": "Ignore files smaller than this many bytes (default 1)"
+                    }
+                },
+                "required": ["path"]
+            }),
+        },
+        ToolDefinition {
+            name: "list_archive",
+            description: "List the contents of a .zip, .tar, .tar.gz or .tgz archive",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Absolute path to the archive"
+                    }
+                },
+                "required": ["path"]
+            }),
+        },
+        ToolDefinition {
+            name: "extract_archive",
+            description: "Extract a .zip, .tar, .tar.gz or .tgz archive into a folder. Existing files are kept unless overwrite is true; unsafe entries and links are skipped",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Absolute path to the archive"
+                    },
+                    "destination": {
+                        "type": "string",
+                        "description": "Absolute path of the folder to extract into (created if missing)"
+                    },
+                    "overwrite": {
+                        "type": "boolean",
+                        "description": "Replace files that already exist (default false)"
+                    }
+                },
+                "required": ["path", "destination"]
+            }),
+        },
+        ToolDefinition {
+            name: "create_archive",
+            description: "Create a new archive from files and folders. The format follows the destination extension: .zip, .tar, .tar.gz or .tgz",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "paths": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Absolute paths of files and folders to include"
+                    },
+                    "destination": {
+                        "type": "string",
+                        "description": "Absolute path of the archive to create"
+                    }
+                },
+                "required": ["paths", "destination"]
+            }),
+        },
+        ToolDefinition {
+            name: "search_files",
+            description: "Search file contents for text or a regex across granted folders",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "pattern": {
+                        "type": "string",
+                        "description": "Regex (or plain text when literal is true) to search for"
+                    },
+                    "path": {
+                        "type": "string",
+                        "description": "Folder or file to search (default: all granted folders)"
+                    },
+                    "literal": {
+                        "type": "boolean",
+                        "description": "Match the pattern as plain text (default false)"
+                    },
+                    "case_sensitive": {
+                        "type": "boolean",
+                        "description": "Match case exactly (default false)"
+                    },
+                    "include": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Only search files matching these globs, e.g. [\"*.txt\"]"
+                    },
+                    "exclude": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Skip files and folders matching these globs"
+                    },
+                    "context_lines": {
+                        "type": "integer",
+                        "description": "Lines of context around each match (default 1, max 5)"
+                    },
+                    "max_results": {
+                        "type": "integer",
+                        "description": "Maximum matches to return (default 30, max 100)"
+                    }
+                },
+                "required": ["pattern"]
+            }),
+        },
+        ToolDefinition {
+            name: "run_command",
+            description: "Run a shell command in a granted folder and return its exit code and output. Unless the user's command rules allow it, the user must approve the command before it runs, so explain why it is needed; some commands, such as sudo, are always refused. Commands run without a terminal or prompts and are killed when they time out. Where the system supports it, commands can only write inside granted folders and have no network access unless network is set, which always needs the user's approval",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "command": {
+                        "type": "string",
+                        "description": "The command line, run with sh -c (cmd /C on Windows)"
+                    },
+                    "cwd": {
+                        "type": "string",
+                        "description": "Absolute path of the folder to run in; must be inside a granted folder"
+                    },
+                    "timeout_secs": {
+                        "type": "integer",
+                        "description": "Seconds before the command is killed (default 60, max 600)"
+                    },
+                    "network": {
+                        "type": "boolean",
+                        "description": "Whether the command needs network access, e.g. to install packages (default false)"
+                    }
+                },
+                "required": ["command", "cwd"]
+            }),
+        },
+        ToolDefinition {
+            name: "fetch_url",
+            description: "Fetch a web page and return its title and main content as markdown, with absolute links. Plain text and JSON are returned as-is. The user approves each new domain the first time it is fetched, and some domains may be blocked. Long pages are returned in parts; use offset to read further",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "url": {
+                        "type": "string",
+                        "description": "The http or https URL to fetch"
+                    },
+                    "offset": {
+                        "type": "integer",
+                        "description": "Byte offset into the page content to start from, for reading past the first part (default 0)"
+                    }
+                },
+                "required": ["url"]
+            }),
+        },
+        ToolDefinition {
+            name: "web_search",
+            description: "Search the web and return result titles, URLs and snippets. Use fetch_url to read a result. Only available when the user has configured a search endpoint",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "query": {
+                        "type": "string",
+                        "description": "What to search for"
+                    },
+                    "max_results": {
+                        "type": "integer",
+                        "description": "How many results to return (default 8, at most 20)"
+                    }
+                },
+                "required": ["query"]
+            }),
+        },
+        ToolDefinition {
+            name: "start_process",
+            description: "Start a long-running command, such as a dev server or a watcher, in the background and return a process id. It is approved and sandboxed like run_command. Use read_process_output to see what it prints and stop_process when done; processes are stopped when the conversation ends",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "command": {
+                        "type": "string",
+                        "description": "The command line, run with sh -c (cmd /C on Windows)"
+                    },
+                    "cwd": {
+                        "type": "string",
+                        "description": "Absolute path of the folder to run in; must be inside a granted folder"
+                    },
+                    "network": {
+                        "type": "boolean",
+                        "description": "Whether the process needs network access, e.g. to serve or fetch over the network (default false)"
+                    }
+                },
+                "required": ["command", "cwd"]
+            }),
+        },
+        ToolDefinition {
+            name: "read_process_output",
+            description: "Read the combined stdout and stderr of a background process and whether it is still running. Only the most recent 64 KB is kept. Once an exited process's last output has been read, its id is released",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "process_id": {
+                        "type": "string",
+                        "description": "Id returned by start_process"
+                    },
+                    "since": {
+                        "type": "integer",
+                        "description": "Offset from a previous read to get only newer output (default: all kept output)"
+                    },
+                    "wait_secs": {
+                        "type": "integer",
+                        "description": "Seconds to wait for new output if there is none yet (default 0, max 30)"
+                    }
+                },
+                "required": ["process_id"]
+            }),
+        },
+        ToolDefinition {
+            name: "send_process_input",
+            description: "Write text to the standard input of a background process",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "process_id": {
+                        "type": "string",
+                        "description": "Id returned by start_process"
+                    },
+                    "input": {
+                        "type": "string",
+                        "description": "Text to send; include a trailing newline to submit a line"
+                    },
+                    "close": {
+                        "type": "boolean",
+                        "description": "Close the input afterwards, signalling end of input (default false)"
+                    }
+                },
+                "required": ["process_id", "input"]
+            }),
+        },
+        ToolDefinition {
+            name: "stop_process",
+            description: "Stop a background process and everything it started",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "process_id": {
+                        "type": "string",
+                        "description": "Id returned by start_process"
+                    }
+                },
+                "required": ["process_id"]
+            }),
+        },
+    ]
+}
+
+/// Format tool definitions, followed by those of connected MCP servers, for the system prompt
+pub fn format_tools_for_prompt(mcp_tools: &[McpTool]) -> String {
+    let tools = get_file_tools();
+    let mut tools_json: Vec<Value> = tools
+        .iter()
+        .map(|t| {
+            json!({
+                "name": t.name,
+                "description": t.description,
+                "parameters": t.parameters
+            })
+        })
+        .collect();
+    tools_json.extend(mcp_tools.iter().map(|t| {
+        json!({
+            "name": t.qualified_name(),
+            "description": format!("{} (from the '{}' MCP server)", t.description.trim(), t.server),
+            "parameters": t.input_schema
+        })
+    }));
+
+    format!(
+        r#"You have access to the following tools to help users with file operations:
+
+{}
+
+To use a tool, respond with a tool call in this exact format:
+<tool_call>{{"name": "tool_name", "arguments": {{"arg1": "value1"}}}}</tool_call>
+
+You can use multiple tool calls in a single response. After each tool call, you will receive the result.
+Only use tools when the user asks for file operations. Always provide a natural language response along with your tool calls."#,
+        serde_json::to_string_pretty(&tools_json).unwrap_or_default()
+    )
+}
+
+/// Everything a tool call needs from the running app
+pub struct ToolContext<'a> {
+    pub store: &'a PermissionStore,
+    pub audit: &'a AuditLog,
+    /// Applied batch moves, so the user can undo what the agent reorganized
+    pub batches: &'a BatchHistory,
+    pub conversation_id: Option<&'a str>,
+    /// Decides which commands run freely, need approval or are refused
+    pub policy: &'a CommandPolicy,
+    /// Asks the user to allow an action and waits for the answer
+    pub approve: &'a dyn Fn(&ApprovalRequest) -> ApprovalDecision,
+    /// Receives run_command output as it is produced
+    pub on_command_output: &'a (dyn Fn(CommandOutputEvent) + Sync),
+    /// Background processes started with start_process
+    pub processes: &'a ProcessManager,
+    /// Which domains fetch_url may reach
+    pub domains: &'a DomainPolicy,
+    /// The search endpoint behind web_search
+    pub search: &'a WebSearch,
+    /// External MCP servers whose tools are offered alongside ours
+    pub mcp: &'a McpManager,
+}
+
+impl<'a> ToolContext<'a> {
+    /// The background processes this conversation may see and control
+    fn process_scope(&self) -> ProcessScope<'a> {
+        ProcessScope::Conversation(self.conversation_id)
+    }
+
+    /// Record a file access made on behalf of the given tool call
+    fn audit(&self, tool_call: &ToolCall, entry: AuditEntry) {
+        let entry = entry.with_tool_call(&tool_call.id, self.conversation_id);
+        if let Err(e) = self.audit.record(&entry) {
+            log::warn!("{}", e);
+        }
+    }
+}
+
+/// Execute a tool call and return the result
+pub fn execute_tool(ctx: &ToolContext, tool_call: &ToolCall) -> String {
+    let store = ctx.store;
+    match tool_call.name.as_str() {
+        "list_files" => {
+            let path = tool_call.arguments.get("path").and_then(|v| v.as_str());
+            match path {
+                Some(p) if wants_recursive_listing(&tool_call.arguments) => {
+                    list_files_recursive(ctx, tool_call, p)
+                }
+                Some(p) => {
+                    let result = operations::list_directory(store, p);
+                    ctx.audit(
+                        tool_call,
+                        AuditEntry::from_result("list_directory", p, &result, None),
+                    );
+                    match result {
+                        Ok(files) => {
+                            let file_list: Vec<String> = files
+                                .iter()
+                                .map(|f| {
+                                    let type_indicator =
+                                        if f.is_directory { "[DIR]" } else { "[FILE]" };
+                                    format!("{} {} ({})", type_indicator, f.name, f.path)
+                                })
+                                .collect();
+                            if file_list.is_empty() {
+                                "Directory is empty".to_string()
+                            } else {
+                                file_list.join("\n")
+                            }
+                        }
+                        Err(e) => tool_error(&e),
+                    }
+                }
+                None => "Error: Missing 'path' argument".to_string(),
+            }
+        }
+        "read_file" => {
+            let path = tool_call.arguments.get("path").and_then(|v| v.as_str());
+            match path {
+                Some(p) => read_file(ctx, tool_call, p),
+                None => "Error: Missing 'path' argument".to_string(),
+            }
+        }
+        "read_document" => {
+            let path = tool_call.arguments.get("path").and_then(|v| v.as_str());
+            match path {
+                Some(p) => read_document(ctx, tool_call, p),
+                None => "Error: Missing 'path' argument".to_string(),
+            }
+        }
+        "query_data" => {
+            let path = tool_call.arguments.get("path").and_then(|v| v.as_str());
+            let query = tool_call
+                .arguments
+                .get("query")
+                .and_then(|v| v.as_str())
+                .unwrap_or("SELECT * LIMIT 5");
+            match path {
+                Some(p) => {
+                    let result = operations::query_data(store, p, query);
+                    ctx.audit(
+                        tool_call,
+                        AuditEntry::from_result("query_data", p, &result, None),
+                    );
+                    match result {
+                        Ok(result) => format_query_result(p, &result),
+                        Err(e) => tool_error(&e),
+                    }
+                }
+                None => "Error: Missing 'path' argument".to_string(),
+            }
+        }
+        "write_file" => {
+            let path = tool_call.arguments.get("path").and_then(|v| v.as_str());
+            let content = tool_call.arguments.get("content").and_then(|v| v.as_str());
+            match (path, content) {
+                (Some(p), Some(c)) => {
+                    let result = operations::write_file(store, p, c);
+                    let bytes = Some(c.len() as u64);
+                    ctx.audit(
+                        tool_call,
+                        AuditEntry::from_result("write_file", p, &result, bytes),
+                    );
+                    match result {
+                        Ok(()) => format!("Successfully wrote to {}", p),
+                        Err(e) => tool_error(&e),
+                    }
+                }
+                _ => "Error: Missing 'path' or 'content' argument".to_string(),
+            }
+        }
+        "edit_file" => {
+            let path = tool_call.arguments.get("path").and_then(|v| v.as_str());
+            match (path, parse_edits(&tool_call.arguments)) {
+                (Some(p), Ok(edits)) => {
+                    let result = operations::edit_file(store, p, &edits);
+                    ctx.audit(
+                        tool_call,
+                        AuditEntry::from_result("edit_file", p, &result, None),
+                    );
+                    match result {
+                        Ok(edit) => format!(
+                            "Successfully applied {} edit(s) to {}\n{}",
+                            edit.edits_applied,
+                            p,
+                            truncate_diff(&edit.diff)
+                        ),
+                        Err(e) => tool_error(&e),
+                    }
+                }
+                (None, _) => "Error: Missing 'path' argument".to_string(),
+                (_, Err(e)) => format!("Error: {}", e),
+            }
+        }
+        "create_file" => {
+            let path = tool_call.arguments.get("path").and_then(|v| v.as_str());
+            let content = tool_call.arguments.get("content").and_then(|v| v.as_str());
+            match (path, content) {
+                (Some(p), Some(c)) => {
+                    let result = operations::create_file(store, p, c);
+                    let bytes = Some(c.len() as u64);
+                    ctx.audit(
+                        tool_call,
+                        AuditEntry::from_result("create_file", p, &result, bytes),
+                    );
+                    match result {
+                        Ok(()) => format!("Successfully created {}", p),
+                        Err(e) => tool_error(&e),
+                    }
+                }
+                _ => "Error: Missing 'path' or 'content' argument".to_string(),
+            }
+        }
+        "delete_file" => {
+            let path = tool_call.arguments.get("path").and_then(|v| v.as_str());
+            match path {
+                Some(p) => {
+                    let result = operations::delete_file(store, p);
+                    ctx.audit(
+                        tool_call,
+                        AuditEntry::from_result("delete_file", p, &result, None),
+                    );
+                    match result {
+                        Ok(()) => format!("Successfully deleted {}", p),
+                        Err(e) => tool_error(&e),
+                    }
+                }
+                None => "Error: Missing 'path' argument".to_string(),
+            }
+        }
+        "move_file" => {
+            let src = tool_call.arguments.get("src").and_then(|v| v.as_str());
+            let dest = tool_call.arguments.get("dest").and_then(|v| v.as_str());
+            match (src, dest) {
+                (Some(s), Some(d)) => {
+                    let result = operations::move_file(store, s, d);
+                    ctx.audit(
+                        tool_call,
+                        AuditEntry::from_result("move_file", s, &result, None).with_target(d),
+                    );
+                    match result {
+                        Ok(()) => format!("Successfully moved {} to {}", s, d),
+                        Err(e) => tool_error(&e),
+                    }
+                }
+                _ => "Error: Missing 'src' or 'dest' argument".to_string(),
+            }
+        }
+        "create_directory" => {
+            let path = tool_call.arguments.get("path").and_then(|v| v.as_str());
+            match path {
+                Some(p) => {
+                    let result = operations::create_directory(store, p);
+                    ctx.audit(
+                        tool_call,
+                        AuditEntry::from_result("create_directory", p, &result, None),
+                    );
+                    match result {
+                        Ok(()) => format!("Successfully created directory {}", p),
+                        Err(e) => tool_error(&e),
+                    }
+                }
+                None => "Error: Missing 'path' argument".to_string(),
+            }
+        }
+        "copy_file" => {
+            let src = tool_call.arguments.get("src").and_then(|v| v.as_str());
+            let dest = tool_call.arguments.get("dest").and_then(|v| v.as_str());
+            match (src, dest) {
+                (Some(s), Some(d)) => {
+                    let result = operations::copy_file(store, s, d);
+                    let bytes = result.as_ref().ok().copied();
+                    ctx.audit(
+                        tool_call,
+                        AuditEntry::from_result("copy_file", s, &result, bytes).with_target(d),
+                    );
+                    match result {
+                        Ok(_) => format!("Successfully copied {} to {}", s, d),
+                        Err(e) => tool_error(&e),
+                    }
+                }
+                _ => "Error: Missing 'src' or 'dest' argument".to_string(),
+            }
+        }
+        "copy_directory" => {
+            let src = tool_call.arguments.get("src").and_then(|v| v.as_str());
+            let dest = tool_call.arguments.get("dest").and_then(|v| v.as_str());
+            match (src, dest) {
+                (Some(s), Some(d)) => {
+                    let result = operations::copy_directory(store, s, d);
+                    ctx.audit(
+                        tool_call,
+                        AuditEntry::from_result("copy_directory", s, &result, None).with_target(d),
+                    );
+                    match result {
+                        Ok(count) => {
+                            format!("Successfully copied {} files from {} to {}", count, s, d)
+                        }
+                        Err(e) => tool_error(&e),
+                    }
+                }
+                _ => "Error: Missing 'src' or 'dest' argument".to_string(),
+            }
+        }
+        "remove_empty_directory" => {
+            let path = tool_call.arguments.get("path").and_then(|v| v.as_str());
+            match path {
+                Some(p) => {
+                    let result = operations::remove_empty_directory(store, p);
+                    ctx.audit(
+                        tool_call,
+                        AuditEntry::from_result("remove_empty_directory", p, &result, None),
+                    );
+                    match result {
+                        Ok(()) => format!("Successfully removed directory {}", p),
+                        Err(e) => tool_error(&e),
+                    }
+                }
+                None => "Error: Missing 'path' argument".to_string(),
+            }
+        }
+        "append_to_file" => {
+            let path = tool_call.arguments.get("path").and_then(|v| v.as_str());
+            let content = tool_call.arguments.get("content").and_then(|v| v.as_str());
+            match (path, content) {
+                (Some(p), Some(c)) => {
+                    let result = operations::append_to_file(store, p, c);
+                    let bytes = Some(c.len() as u64);
+                    ctx.audit(
+                        tool_call,
+                        AuditEntry::from_result("append_to_file", p, &result, bytes),
+                    );
+                    match result {
+                        Ok(()) => format!("Successfully appended to {}", p),
+                        Err(e) => tool_error(&e),
+                    }
+                }
+                _ => "Error: Missing 'path' or 'content' argument".to_string(),
+            }
+        }
+        "batch_move" => batch_move(ctx, tool_call),
+        "run_command" => run_command(ctx, tool_call),
+        "fetch_url" => fetch_url(ctx, tool_call),
+        "web_search" => web_search(ctx, tool_call),
+        "start_process" => start_process(ctx, tool_call),
+        "read_process_output" => read_process_output(ctx, tool_call),
+        "send_process_input" => send_process_input(ctx, tool_call),
+        "stop_process" => stop_process(ctx, tool_call),
+        "stat_file" => {
+            let path = tool_call.arguments.get("path").and_then(|v| v.as_str());
+            let hash = tool_call
+                .arguments
+                .get("hash")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            match path {
+                Some(p) => {
+                    let result = operations::stat_file(store, p, hash);
+                    ctx.audit(
+                        tool_call,
+                        AuditEntry::from_result("stat_file", p, &result, None),
+                    );
+                    match result {
+                        Ok(stat) => format_file_stat(&stat),
+                        Err(e) => tool_error(&e),
+                    }
+                }
+                None => "Error: Missing 'path' argument".to_string(),
+            }
+        }
+        "find_duplicates" => {
+            let path = tool_call.arguments.get("path").and_then(|v| v.as_str());
+            let min_size = tool_call
+                .arguments
+                .get("min_size")
+                .and_then(|v| v.as_u64())
+                .unwrap_or(0);
+            match path {
+                Some(p) => {
+                    let result = operations::find_duplicates(store, p, min_size);
+                    ctx.audit(
+                        tool_call,
+                        AuditEntry::from_result("find_duplicates", p, &result, None),
+                    );
+                    match result {
+                        Ok(report) => format_duplicates(&report),
+                        Err(e) => tool_error(&e),
+                    }
+                }
+                None => "Error: Missing 'path' argument".to_string(),
+            }
+        }
+        "list_archive" => {
+            let path = tool_call.arguments.get("path").and_then(|v| v.as_str());
+            match path {
+                Some(p) => {
+                    let result = operations::list_archive(store, p);
+                    ctx.audit(
+                        tool_call,
+                        AuditEntry::from_result("list_archive", p, &result, None),
+                    );
+                    match result {
+                        Ok(listing) => format_archive_listing(p, &listing),
+                        Err(e) => tool_error(&e),
+                    }
+                }
+                None => "Error: Missing 'path' argument".to_string(),
+            }
+        }
+        "extract_archive" => {
+            let path = tool_call.arguments.get("path").and_then(|v| v.as_str());
+            let dest = tool_call
+                .arguments
+                .get("destination")
+                .and_then(|v| v.as_str());
+            let overwrite = tool_call
+                .arguments
+                .get("overwrite")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            match (path, dest) {
+                (Some(p), Some(d)) => {
+                    let result = operations::extract_archive(store, p, d, overwrite);
+                    let bytes = result.as_ref().ok().map(|s| s.bytes_written);
+                    ctx.audit(
+                        tool_call,
+                        AuditEntry::from_result("extract_archive", p, &result, bytes)
+                            .with_target(d),
+                    );
+                    match result {
+                        Ok(summary) => format_extract_summary(d, &summary),
+                        Err(e) => tool_error(&e),
+                    }
+                }
+                _ => "Error: Missing 'path' or 'destination' argument".to_string(),
+            }
+        }
+        "create_archive" => {
+            let sources = string_list(&tool_call.arguments, "paths");
+            let dest = tool_call
+                .arguments
+                .get("destination")
+                .and_then(|v| v.as_str());
+            match dest {
+                Some(d) if !sources.is_empty() => {
+                    let result = operations::create_archive(store, &sources, d);
+                    let bytes = result.as_ref().ok().map(|s| s.bytes_added);
+                    ctx.audit(
+                        tool_call,
+                        AuditEntry::from_result("create_archive", d, &result, bytes),
+                    );
+                    match result {
+                        Ok(summary) => format!(
+                            "Created {} with {} files ({})",
+                            d,
+                            summary.files_added,
+                            format_size(summary.bytes_added)
+                        ),
+                        Err(e) => tool_error(&e),
+                    }
+                }
+                _ => "Error: Missing 'paths' or 'destination' argument".to_string(),
+            }
+        }
+        "search_files" => {
+            let pattern = tool_call.arguments.get("pattern").and_then(|v| v.as_str());
+            match pattern {
+                Some(p) => search_files(ctx, tool_call, p),
+                None => "Error: Missing 'pattern' argument".to_string(),
+            }
+        }
+        name if name.starts_with(mcp::MCP_TOOL_PREFIX) => call_mcp_tool(ctx, tool_call),
+        _ => format!("Error: Unknown tool '{}'", tool_call.name),
+    }
+}
+
+/// Tool result for a failed file operation, with a hint at what the model can do about it
+fn tool_error(error: &FileOpError) -> String {
+    let hint = match error {
+        FileOpError::AccessDenied(_) => {
+            "Only folders the user has granted can be accessed; ask the user to grant this folder if it is needed."
+        }
+        FileOpError::Blocked(_) => {
+            "This path is protected by the user's deny-list and can't be accessed from any folder; don't retry it or work around it."
+        }
+        FileOpError::NotFound(_) => "Check the path with list_files.",
+        FileOpError::AlreadyExists(_) => {
+            "Pick a different destination, or use write_file to replace an existing file."
+        }
+        FileOpError::NotUtf8(_) => {
+            "Use read_document for documents, or stat_file to inspect other binary files."
+        }
+        FileOpError::TooLarge(_)
+        | FileOpError::Io { .. }
+        | FileOpError::Invalid(_)
+        | FileOpError::Other(_) => {
+            return format!("Error: {}", error);
+        }
+    };
+    format!("Error: {}. {}", error, hint)
+}
+
+/// Read edits from the `edits` array, or a single top-level search/replace pair
+fn parse_edits(args: &Value) -> Result<Vec<FileEdit>, String> {
+    let items: Vec<&Value> = match args.get("edits").and_then(|v| v.as_array()) {
+        Some(items) => items.iter().collect(),
+        None if args.get("search").is_some() => vec![args],
+        None => return Err("Missing 'edits' argument".to_string()),
+    };
+
+    items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let text = |key: &str| {
+                item.get(key)
+                    .and_then(|v| v.as_str())
+                    .map(|s| s.to_string())
+            };
+            let line = |key: &str| item.get(key).and_then(|v| v.as_u64()).map(|n| n as usize);
+            if let Some(search) = text("search") {
+                Ok(FileEdit::Replace {
+                    search,
+                    replace: text("replace").unwrap_or_default(),
+                })
+            } else if let Some(start) = line("start_line") {
+                Ok(FileEdit::ReplaceLines {
+                    start,
+                    end: line("end_line").unwrap_or(start),
+                    content: text("content").unwrap_or_default(),
+                })
+            } else {
+                Err(format!(
+                    "Edit {} needs either 'search' or 'start_line'",
+                    i + 1
+                ))
+            }
+        })
+        .collect()
+}
+
+fn truncate_diff(diff: &str) -> String {
+    if diff.is_empty() {
+        return "(no changes)".to_string();
+    }
+    match diff.char_indices().nth(MAX_TOOL_DIFF_CHARS) {
+        Some((idx, _)) => format!("{}\n[Diff truncated]", &diff[..idx]),
+        None => diff.to_string(),
+    }
+}
+
+fn read_range_from_args(args: &Value) -> ReadRange {
+    let arg = |key: &str| args.get(key).and_then(|v| v.as_u64());
+    let lines = |key: &str| arg(key).map(|n| (n as usize).min(MAX_TOOL_READ_LINES));
+    if let Some(lines) = lines("head") {
+        ReadRange::Head { lines }
+    } else if let Some(lines) = lines("tail") {
+        ReadRange::Tail { lines }
+    } else if arg("byte_offset").is_some() || arg("byte_limit").is_some() {
+        ReadRange::Bytes {
+            offset: arg("byte_offset").unwrap_or(0),
+            limit: arg("byte_limit"),
+        }
+    } else {
+        ReadRange::Lines {
+            offset: arg("offset").unwrap_or(1) as usize,
+            limit: lines("limit"),
+        }
+    }
+}
+
+fn read_file(ctx: &ToolContext, tool_call: &ToolCall, path: &str) -> String {
+    let range = read_range_from_args(&tool_call.arguments);
+    let result = operations::read_file_range(ctx.store, path, &range, MAX_TOOL_READ_BYTES);
+    let bytes = match &result {
+        Ok(FileRead::Text(chunk)) => Some(chunk.content.len() as u64),
+        _ => None,
+    };
+    ctx.audit(
+        tool_call,
+        AuditEntry::from_result("read_file", path, &result, bytes),
+    );
+    match result {
+        Ok(FileRead::Text(chunk)) => format_file_chunk(path, &chunk),
+        Ok(FileRead::Binary(info)) => format_binary_file(path, &info),
+        Err(e) => tool_error(&e),
+    }
+}
+
+fn format_binary_file(path: &str, info: &BinaryFileInfo) -> String {
+    let hint = if DocumentFormat::from_path(std::path::Path::new(path)).is_some() {
+        " Use read_document to extract its text."
+    } else {
+        ""
+    };
+    format!(
+        "[Binary file: {} | {} | {} | sha256 {}]\nBinary content is not shown.{}",
+        path,
+        info.mime_type,
+        format_size(info.size),
+        info.sha256,
+        hint
+    )
+}
+
+fn read_document(ctx: &ToolContext, tool_call: &ToolCall, path: &str) -> String {
+    let section = tool_call
+        .arguments
+        .get("section")
+        .and_then(|v| v.as_u64())
+        .map(|s| s as usize);
+    let result = operations::read_document(ctx.store, path);
+    let bytes = result
+        .as_ref()
+        .ok()
+        .map(|doc| doc.sections.iter().map(|s| s.content.len() as u64).sum());
+    ctx.audit(
+        tool_call,
+        AuditEntry::from_result("read_document", path, &result, bytes),
+    );
+    match result {
+        Ok(doc) => format_document(path, doc, section),
+        Err(e) => tool_error(&e),
+    }
+}
+
+/// Render an extracted document with a header, optionally narrowed to one section
+fn format_document(path: &str, mut doc: ExtractedDocument, section: Option<usize>) -> String {
+    let noun = match doc.format {
+        DocumentFormat::Pdf => "pages",
+        DocumentFormat::Pptx => "slides",
+        DocumentFormat::Spreadsheet => "sheets",
+        _ => "sections",
+    };
+    let mut header = format!(
+        "[Document: {} | {} | {} {}",
+        path,
+        doc.format.label(),
+        doc.sections.len(),
+        noun
+    );
+
+    if let Some(n) = section {
+        if n == 0 || n > doc.sections.len() {
+            return format!(
+                "Error: section {} is out of range; the document has {} {}",
+                n,
+                doc.sections.len(),
+                noun
+            );
+        }
+        doc.sections = vec![doc.sections.swap_remove(n - 1)];
+        header.push_str(&format!(" | showing {} {}", &noun[..noun.len() - 1], n));
+    }
+    header.push(']');
+
+    let markdown = doc.to_markdown();
+    if markdown.trim().is_empty() {
+        return format!(
+            "{}\n(no text found; the document may be scanned images)",
+            header
+        );
+    }
+    match markdown.char_indices().nth(MAX_TOOL_DOCUMENT_CHARS) {
+        None => format!("{}\n{}", header, markdown),
+        Some((idx, _)) => format!(
+            "{}\n{}\n[Document truncated after {} of {} characters. Pass section to read one of the {} at a time.]",
+            header,
+            &markdown[..idx],
+            MAX_TOOL_DOCUMENT_CHARS,
+            markdown.chars().count(),
+            noun
+        ),
+    }
+}
+
+/// Prefix file content with its size and, for partial reads, how to get the rest
+fn format_file_chunk(path: &str, chunk: &FileChunk) -> String {
+    let span = match (
+        chunk.start_line,
+        chunk.end_line,
+        chunk.start_byte,
+        chunk.end_byte,
+    ) {
+        (Some(start), Some(end), _, _) if end >= start => format!("lines {}-{}", start, end),
+        (_, _, Some(start), Some(end)) => format!("bytes {}-{}", start, end),
+        _ => "no lines in range".to_string(),
+    };
+    let mut size = match chunk.total_lines {
+        Some(lines) => format!("{} lines, {}", lines, format_size(chunk.total_bytes)),
+        None => format_size(chunk.total_bytes),
+    };
+    // Only mention the encoding and line endings when they aren't plain UTF-8 with LF
+    let plain_endings = matches!(chunk.line_ending, LineEnding::Lf | LineEnding::None);
+    if chunk.encoding != TextEncoding::Utf8 || !plain_endings {
+        size.push_str(&format!(
+            " | {}, {}",
+            chunk.encoding.label(),
+            chunk.line_ending.label()
+        ));
+    }
+
+    if chunk.is_complete() {
+        return format!("[File: {} | {}]\n{}", path, size, chunk.content);
+    }
+
+    let mut out = format!(
+        "[File: {} | {} | showing {}]\n{}",
+        path, size, span, chunk.content
+    );
+    if !out.ends_with('\n') {
+        out.push('\n');
+    }
+    let total = match chunk.total_lines {
+        Some(lines) => format!("{} lines", lines),
+        None => format_size(chunk.total_bytes),
+    };
+    out.push_str(&format!(
+        "[File truncated: showing {} of {}. Use offset/limit, head, tail or byte_offset/byte_limit to read other ranges.]",
+        span, total
+    ));
+    out
+}
+
+/// Render query results as a markdown table, capped at `MAX_TOOL_QUERY_ROWS` rows
+fn format_query_result(path: &str, result: &QueryResult) -> String {
+    let mut out = format!(
+        "[Query: {} | {} of {} rows matched, {} returned]\n",
+        path,
+        result.matched_rows,
+        result.source_rows,
+        result.rows.len()
+    );
+    if result.rows.is_empty() {
+        out.push_str(&format!(
+            "(no rows; columns: {})",
+            result.columns.join(", ")
+        ));
+        return out;
+    }
+
+    let mut table = vec![result.columns.clone()];
+    table.extend(
+        result
+            .rows
+            .iter()
+            .take(MAX_TOOL_QUERY_ROWS)
+            .map(|row| row.iter().map(query::display).collect()),
+    );
+    out.push_str(&markdown_table(&table));
+    if result.rows.len() > MAX_TOOL_QUERY_ROWS {
+        out.push_str(&format!(
+            "[Showing the first {} of {} rows. Narrow the query with WHERE, GROUP BY or LIMIT.]",
+            MAX_TOOL_QUERY_ROWS,
+            result.rows.len()
+        ));
+    }
+    out
+}
+
+fn wants_recursive_listing(args: &Value) -> bool {
+    args.get("recursive")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+        || args.get("max_depth").is_some()
+}
+
+fn string_list(args: &Value, key: &str) -> Vec<String> {
+    args.get(key)
+        .and_then(|v| v.as_array())
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|i| i.as_str().map(|s| s.to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn list_files_recursive(ctx: &ToolContext, tool_call: &ToolCall, path: &str) -> String {
+    let args = &tool_call.arguments;
+    let options = ListOptions {
+        max_depth: Some(
+            args.get("max_depth")
+                .and_then(|v| v.as_u64())
+                .map(|d| d as usize)
+                .unwrap_or(DEFAULT_TREE_DEPTH),
+        ),
+        include: string_list(args, "include"),
+        exclude: string_list(args, "exclude"),
+        show_hidden: args
+            .get("hidden")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false),
+        respect_gitignore: args
+            .get("gitignore")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true),
+        max_entries: MAX_TREE_ENTRIES,
+    };
+
+    let result = operations::list_directory_recursive(ctx.store, path, &options);
+    ctx.audit(
+        tool_call,
+        AuditEntry::from_result("list_directory_recursive", path, &result, None),
+    );
+    match result {
+        Ok(tree) => format_tree(&tree),
+        Err(e) => tool_error(&e),
+    }
+}
+
+fn search_files(ctx: &ToolContext, tool_call: &ToolCall, pattern: &str) -> String {
+    let args = &tool_call.arguments;
+    let options = SearchOptions {
+        pattern: pattern.to_string(),
+        path: args
+            .get("path")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string()),
+        literal: args
+            .get("literal")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false),
+        case_sensitive: args
+            .get("case_sensitive")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false),
+        include: string_list(args, "include"),
+        exclude: string_list(args, "exclude"),
+        context_lines: args
+            .get("context_lines")
+            .and_then(|v| v.as_u64())
+            .map(|n| (n as usize).min(MAX_SEARCH_CONTEXT))
+            .unwrap_or(1),
+        max_results: args
+            .get("max_results")
+            .and_then(|v| v.as_u64())
+            .map(|n| (n as usize).min(MAX_SEARCH_RESULTS))
+            .unwrap_or(DEFAULT_SEARCH_RESULTS),
+        ..Default::default()
+    };
+
+    let result = operations::search_files(ctx.store, &options);
+    let audit_path = options.path.as_deref().unwrap_or("(all granted folders)");
+    ctx.audit(
+        tool_call,
+        AuditEntry::from_result("search_files", audit_path, &result, None),
+    );
+    match result {
+        Ok(results) => format_search_results(&results),
+        Err(e) => tool_error(&e),
+    }
+}
+
+/// Render search matches grouped by file, grep-style (`:` marks a match, `-` context)
+fn format_search_results(results: &SearchResults) -> String {
+    if results.matches.is_empty() {
+        return format!("No matches found in {} files", results.files_searched);
+    }
+
+    let mut lines = Vec::new();
+    let mut current_file: Option<&str> = None;
+    for m in &results.matches {
+        if current_file != Some(m.path.as_str()) {
+            lines.push(m.path.clone());
+            current_file = Some(m.path.as_str());
+        } else if !m.before.is_empty() || !m.after.is_empty() {
+            lines.push("  --".to_string());
+        }
+        let first = m.line_number - m.before.len();
+        for (i, line) in m.before.iter().enumerate() {
+            lines.push(format!("  {}- {}", first + i, line));
+        }
+        lines.push(format!("  {}: {}", m.line_number, m.line));
+        for (i, line) in m.after.iter().enumerate() {
+            lines.push(format!("  {}- {}", m.line_number + 1 + i, line));
+        }
+    }
+    if results.truncated {
+        lines.push(format!(
+            "[Stopped after {} matches. Narrow the search with path, include or a more specific pattern.]",
+            results.matches.len()
+        ));
+    }
+    lines.join("\n")
+}
+
+/// Render a directory tree compactly, one indented line per entry
+fn format_tree(tree: &DirectoryTree) -> String {
+    if tree.entries.is_empty() {
+        return "Directory is empty".to_string();
+    }
+
+    let mut lines = vec![format!("{}/", tree.root.trim_end_matches('/'))];
+    for entry in &tree.entries {
+        let indent = "  ".repeat(entry.depth);
+        if entry.info.is_directory {
+            lines.push(format!("{}{}/", indent, entry.info.name));
+        } else {
+            lines.push(format!(
+                "{}{} ({})",
+                indent,
+                entry.info.name,
+                format_size(entry.info.size)
+            ));
+        }
+    }
+    if tree.truncated {
+        lines.push(format!(
+            "[Listing truncated after {} entries. Narrow it with max_depth, include or exclude.]",
+            tree.entries.len()
+        ));
+    }
+    lines.join("\n")
+}
+
+fn batch_move(ctx: &ToolContext, tool_call: &ToolCall) -> String {
+    let request: BatchRequest = match serde_json::from_value(tool_call.arguments.clone()) {
+        Ok(request) => request,
+        Err(e) => return format!("Error: Invalid batch arguments: {}", e),
+    };
+    let apply = tool_call
+        .arguments
+        .get("apply")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    let path = request.primary_path().to_string();
+
+    if !apply {
+        let result = operations::plan_batch(ctx.store, &request);
+        ctx.audit(
+            tool_call,
+            AuditEntry::from_result("plan_batch", &path, &result, None),
+        );
+        return match result {
+            Ok(plan) => format_batch_plan(&plan),
+            Err(e) => tool_error(&e),
+        };
+    }
+
+    let result = operations::apply_batch(ctx.store, &request);
+    ctx.audit(
+        tool_call,
+        AuditEntry::from_result("batch_move", &path, &result, None),
+    );
+    match result {
+        Ok(applied) => {
+            let summary = format!(
+                "Moved {} items{}. The user can undo this batch from the app.",
+                applied.moves.len(),
+                match applied.created_directories.len() {
+                    0 => String::new(),
+                    n => format!(" and created {} folders", n),
+                }
+            );
+            ctx.batches.push(applied);
+            summary
+        }
+        Err(e) => tool_error(&e),
+    }
+}
+
+fn run_command(ctx: &ToolContext, tool_call: &ToolCall) -> String {
+    let args = &tool_call.arguments;
+    let (Some(command), Some(cwd)) = (
+        args.get("command").and_then(|v| v.as_str()),
+        args.get("cwd").and_then(|v| v.as_str()),
+    ) else {
+        return "Error: Missing 'command' or 'cwd' argument".to_string();
+    };
+    let timeout = terminal::timeout_from_secs(args.get("timeout_secs").and_then(|v| v.as_u64()));
+    let network = args
+        .get("network")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let result = approve_and_run(ctx, tool_call, command, cwd, timeout, network);
+    ctx.audit(
+        tool_call,
+        AuditEntry::from_result("run_command", cwd, &result, None).with_command(command),
+    );
+    match result {
+        Ok(output) => format_command_output(&output),
+        Err(e) => format!("Error: {}", e),
+    }
+}
+
+fn approve_and_run(
+    ctx: &ToolContext,
+    tool_call: &ToolCall,
+    command: &str,
+    cwd: &str,
+    timeout: std::time::Duration,
+    network: bool,
+) -> Result<CommandOutput, String> {
+    let (cwd, sandbox) = authorize_command(ctx, tool_call, command, cwd, network)?;
+    let spec = CommandSpec {
+        command: command.to_string(),
+        cwd,
+        timeout,
+        sandbox,
+    };
+    let emit = ctx.on_command_output;
+    let conversation_id = ctx.conversation_id;
+    terminal::run_command(&spec, &|stream, text| {
+        emit(CommandOutputEvent {
+            tool_call_id: tool_call.id.clone(),
+            conversation_id: conversation_id.map(|c| c.to_string()),
+            stream,
+            text: text.to_string(),
+        })
+    })
+}
+
+/// Check a command with the command policy, asking the user when it says to, and return
+/// where the command may run and how it is sandboxed
+fn authorize_command(
+    ctx: &ToolContext,
+    tool_call: &ToolCall,
+    command: &str,
+    cwd: &str,
+    network: bool,
+) -> Result<(std::path::PathBuf, SandboxSpec), String> {
+    let cwd = terminal::resolve_cwd(ctx.store, cwd)?;
+    let sandbox = SandboxSpec {
+        writable: ctx
+            .store
+            .list()
+            .into_iter()
+            .map(|f| f.path.into())
+            .collect(),
+        allow_network: network,
+    };
+    let mut decision = ctx.policy.evaluate(command, &cwd, ctx.store);
+    if network && decision.action == PolicyAction::Allow {
+        decision.action = PolicyAction::Ask;
+        decision.reason = "The command asks for network access".to_string();
+        decision.rule_id = None;
+    }
+    match decision.action {
+        PolicyAction::Allow => {}
+        PolicyAction::Deny => {
+            return Err(format!("Blocked by command policy: {}", decision.reason));
+        }
+        PolicyAction::Ask => {
+            let request = ApprovalRequest::new(
+                ApprovalSubject::Command {
+                    command: command.to_string(),
+                    cwd: cwd.to_string_lossy().to_string(),
+                    reason: decision.reason,
+                    network,
+                    sandbox: terminal::sandbox::expected(&sandbox),
+                },
+                Some(&tool_call.id),
+                ctx.conversation_id,
+            );
+            match (ctx.approve)(&request) {
+                ApprovalDecision::Approved => {}
+                ApprovalDecision::Denied => {
+                    return Err("The user declined to run this command".to_string())
+                }
+                ApprovalDecision::Expired => {
+                    return Err("The command was not approved in time and did not run".to_string())
+                }
+            }
+        }
+    }
+    Ok((cwd, sandbox))
+}
+
+fn fetch_url(ctx: &ToolContext, tool_call: &ToolCall) -> String {
+    let Some(url) = tool_call.arguments.get("url").and_then(|v| v.as_str()) else {
+        return "Error: Missing 'url' argument".to_string();
+    };
+    let offset = tool_call
+        .arguments
+        .get("offset")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0) as usize;
+
+    let result = web::fetch_page(url, &mut |url| authorize_domain(ctx, tool_call, url));
+    let bytes = result.as_ref().ok().map(|page| page.content.len() as u64);
+    let mut entry = AuditEntry::from_result("fetch_url", url, &result, bytes);
+    if let Ok(ref page) = result {
+        if page.url != url {
+            entry = entry.with_target(&page.url);
+        }
+    }
+    ctx.audit(tool_call, entry);
+    match result {
+        Ok(page) => format_fetched_page(&page, offset),
+        Err(e) => format!("Error: {}", e),
+    }
+}
+
+fn web_search(ctx: &ToolContext, tool_call: &ToolCall) -> String {
+    let Some(query) = tool_call.arguments.get("query").and_then(|v| v.as_str()) else {
+        return "Error: Missing 'query' argument".to_string();
+    };
+    let max_results = tool_call
+        .arguments
+        .get("max_results")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(8) as usize;
+
+    let result = ctx.search.search(query, max_results);
+    ctx.audit(
+        tool_call,
+        AuditEntry::from_result("web_search", query, &result, None),
+    );
+    match result {
+        Ok(response) => format_web_results(&response),
+        Err(e) => format!("Error: {}", e),
+    }
+}
+
+/// Check a URL's host with the domain policy, asking the user about domains not seen before
+fn authorize_domain(ctx: &ToolContext, tool_call: &ToolCall, url: &url::Url) -> Result<(), String> {
+    let host = url.host_str().unwrap_or_default();
+    match ctx.domains.check(host) {
+        DomainDecision::Allow => Ok(()),
+        DomainDecision::Deny(reason) => Err(format!("Blocked by domain policy: {}", reason)),
+        DomainDecision::Ask => {
+            let request = ApprovalRequest::new(
+                ApprovalSubject::Domain {
+                    domain: host.to_string(),
+                    url: url.to_string(),
+                },
+                Some(&tool_call.id),
+                ctx.conversation_id,
+            );
+            match (ctx.approve)(&request) {
+                ApprovalDecision::Approved => ctx.domains.allow(host),
+                ApprovalDecision::Denied => Err(format!("The user declined access to {}", host)),
+                ApprovalDecision::Expired => {
+                    Err(format!("Access to {} was not approved in time", host))
+                }
+            }
+        }
+    }
+}
+
+fn call_mcp_tool(ctx: &ToolContext, tool_call: &ToolCall) -> String {
+    let Some((server, tool)) = mcp::split_tool_name(&tool_call.name) else {
+        return format!("Error: Unknown tool '{}'", tool_call.name);
+    };
+    // Declined and expired approvals are audited along with calls that ran
+    let result = authorize_mcp_tool(ctx, tool_call, server, tool)
+        .and_then(|()| ctx.mcp.call(server, tool, &tool_call.arguments));
+    ctx.audit(
+        tool_call,
+        AuditEntry::from_result("mcp_tool", &tool_call.name, &result, None),
+    );
+    match result {
+        Ok(output) => {
+            let text = match output.text.char_indices().nth(MAX_TOOL_MCP_OUTPUT_CHARS) {
+                Some((idx, _)) => format!("{}\n[Output truncated]", &output.text[..idx]),
+                None => output.text,
+            };
+            if output.is_error {
+                format!("Error: {}", text)
+            } else {
+                text
+            }
+        }
+        Err(e) => format!("Error: {}", e),
+    }
+}
+
+/// Apply the server's approval rules to a tool call, asking the user when they say so
+fn authorize_mcp_tool(
+    ctx: &ToolContext,
+    tool_call: &ToolCall,
+    server: &str,
+    tool: &str,
+) -> Result<(), String> {
+    match ctx.mcp.action_for(server, tool) {
+        PolicyAction::Allow => Ok(()),
+        PolicyAction::Deny => Err(format!(
+            "Blocked by MCP server rules: '{}' on '{}' is not allowed",
+            tool, server
+        )),
+        PolicyAction::Ask => {
+            let request = ApprovalRequest::new(
+                ApprovalSubject::McpTool {
+                    server: server.to_string(),
+                    tool: tool.to_string(),
+                    arguments: tool_call.arguments.clone(),
+                },
+                Some(&tool_call.id),
+                ctx.conversation_id,
+            );
+            match (ctx.approve)(&request) {
+                ApprovalDecision::Approved => Ok(()),
+                ApprovalDecision::Denied => Err(format!(
+                    "The user declined to run '{}' from '{}'",
+                    tool, server
+                )),
+                ApprovalDecision::Expired => Err(format!(
+                    "Running '{}' from '{}' was not approved in time",
+                    tool, server
+                )),
+            }
+        }
+    }
+}
+
+/// Numbered results, one title, URL and snippet each
+fn format_web_results(response: &SearchResponse) -> String {
+    if response.results.is_empty() {
+        return format!("No results for \"{}\"", response.query);
+    }
+    let mut result = format!("Results for \"{}\":", response.query);
+    for (i, r) in response.results.iter().enumerate() {
+        result.push_str(&format!("\n\n{}. {}\n{}", i + 1, r.title.trim(), r.url));
+        let snippet = r.snippet.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !snippet.is_empty() {
+            result.push_str(&format!("\n{}", snippet));
+        }
+    }
+    result
+}
+
+/// Title, source and one part of a fetched page, saying how to read the next part
+fn format_fetched_page(page: &FetchedPage, offset: usize) -> String {
+    let content = &page.content;
+    let mut start = offset.min(content.len());
+    while !content.is_char_boundary(start) {
+        start += 1;
+    }
+    let mut end = (start + MAX_TOOL_FETCH_BYTES).min(content.len());
+    while !content.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    let mut result = match page.title {
+        Some(ref title) => format!("# {}\nSource: {}", title, page.url),
+        None => format!("Source: {} ({})", page.url, page.content_type),
+    };
+    result.push_str("\n\n");
+    if start > 0 {
+        result.push_str(&format!("[... first {} bytes omitted]\n", start));
+    }
+    result.push_str(content[start..end].trim());
+    if end < content.len() {
+        result.push_str(&format!(
+            "\n\n[{} more bytes; call fetch_url with offset={} to continue]",
+            content.len() - end,
+            end
+        ));
+    }
+    if page.truncated {
+        result.push_str(&format!(
+            "\n\n[The page is larger than {} MB; the rest was not downloaded]",
+            web::fetch::MAX_FETCH_BYTES / (1024 * 1024)
+        ));
+    }
+    result
+}
+
+fn start_process(ctx: &ToolContext, tool_call: &ToolCall) -> String {
+    let args = &tool_call.arguments;
+    let (Some(command), Some(cwd)) = (
+        args.get("command").and_then(|v| v.as_str()),
+        args.get("cwd").and_then(|v| v.as_str()),
+    ) else {
+        return "Error: Missing 'command' or 'cwd' argument".to_string();
+    };
+    let network = args
+        .get("network")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let result =
+        authorize_command(ctx, tool_call, command, cwd, network).and_then(|(cwd, sandbox)| {
+            ctx.processes
+                .start(command, &cwd, &sandbox, ctx.conversation_id)
+        });
+    ctx.audit(
+        tool_call,
+        AuditEntry::from_result("start_process", cwd, &result, None).with_command(command),
+    );
+    match result {
+        Ok(info) => {
+            let mut result = format!(
+                "Started process {} (pid {}). Use read_process_output to see its output",
+                info.id, info.pid
+            );
+            if info.sandbox.mode != SandboxMode::Full {
+                result.push_str(&format!(
+                    "\n\n[Sandbox: {}]",
+                    info.sandbox.limitations.join("; ")
+                ));
+            }
+            result
+        }
+        Err(e) => format!("Error: {}", e),
+    }
+}
+
+/// Audit entry for acting on a background process, filed under its folder and command line;
+/// an unknown id is recorded as the path so the failed attempt still shows up
+pub fn process_audit_entry<T, E: std::fmt::Display>(
+    operation: &str,
+    id: &str,
+    info: Option<&ProcessInfo>,
+    result: &Result<T, E>,
+    bytes: Option<u64>,
+) -> AuditEntry {
+    match info {
+        Some(info) => {
+            AuditEntry::from_result(operation, &info.cwd, result, bytes).with_command(&info.command)
+        }
+        None => AuditEntry::from_result(operation, id, result, bytes),
+    }
+}
+
+fn process_id_arg(tool_call: &ToolCall) -> Result<&str, String> {
+    tool_call
+        .arguments
+        .get("process_id")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| "Error: Missing 'process_id' argument".to_string())
+}
+
+fn read_process_output(ctx: &ToolContext, tool_call: &ToolCall) -> String {
+    let id = match process_id_arg(tool_call) {
+        Ok(id) => id,
+        Err(e) => return e,
+    };
+    let args = &tool_call.arguments;
+    let since = args.get("since").and_then(|v| v.as_u64());
+    let wait =
+        std::time::Duration::from_secs(args.get("wait_secs").and_then(|v| v.as_u64()).unwrap_or(0));
+    match ctx.processes.read(id, ctx.process_scope(), since, wait) {
+        Ok(output) => format_process_output(id, &output),
+        Err(e) => format!("Error: {}", e),
+    }
+}
+
+fn send_process_input(ctx: &ToolContext, tool_call: &ToolCall) -> String {
+    let id = match process_id_arg(tool_call) {
+        Ok(id) => id,
+        Err(e) => return e,
+    };
+    let Some(input) = tool_call.arguments.get("input").and_then(|v| v.as_str()) else {
+        return "Error: Missing 'input' argument".to_string();
+    };
+    let close = tool_call
+        .arguments
+        .get("close")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    let info = ctx.processes.info(id, ctx.process_scope()).ok();
+    let result = ctx
+        .processes
+        .send_input(id, ctx.process_scope(), input, close);
+    ctx.audit(
+        tool_call,
+        process_audit_entry(
+            "send_process_input",
+            id,
+            info.as_ref(),
+            &result,
+            Some(input.len() as u64),
+        ),
+    );
+    match result {
+        Ok(()) if close => format!("Sent {} bytes to {} and closed its input", input.len(), id),
+        Ok(()) => format!("Sent {} bytes to {}", input.len(), id),
+        Err(e) => format!("Error: {}", e),
+    }
+}
+
+fn stop_process(ctx: &ToolContext, tool_call: &ToolCall) -> String {
+    let id = match process_id_arg(tool_call) {
+        Ok(id) => id,
+        Err(e) => return e,
+    };
+    let info = ctx.processes.info(id, ctx.process_scope()).ok();
+    let result = ctx.processes.stop(id, ctx.process_scope());
+    ctx.audit(
+        tool_call,
+        process_audit_entry("stop_process", id, info.as_ref(), &result, None),
+    );
+    match result {
+        Ok(info) => match info.status {
+            ProcessStatus::Exited {
+                exit_code: Some(code),
+            } => format!("Process {} had already exited with code {}", id, code),
+            _ => format!("Stopped process {}", id),
+        },
+        Err(e) => format!("Error: {}", e),
+    }
+}
+
+/// Status of a background process and the output read from it, ending with where to read on from
+fn format_process_output(id: &str, output: &ProcessOutput) -> String {
+    let mut result = match output.status {
+        ProcessStatus::Running => format!("Process {} is running", id),
+        ProcessStatus::Exited {
+            exit_code: Some(code),
+        } => format!("Process {} exited with code {}", id, code),
+        ProcessStatus::Exited { exit_code: None } => {
+            format!("Process {} was killed by a signal", id)
+        }
+    };
+    let text = &output.text;
+    let mut start = text.len().saturating_sub(MAX_TOOL_COMMAND_OUTPUT_BYTES);
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    let omitted = output.skipped + start as u64;
+    if text.is_empty() {
+        result.push_str("\n\nNo new output");
+    } else {
+        result.push_str("\n\n");
+        if omitted > 0 {
+            result.push_str(&format!("[... {} earlier bytes omitted]\n", omitted));
+        }
+        result.push_str(text[start..].trim_end());
+    }
+    result.push_str(&format!(
+        "\n\n[Next offset: {}; pass it as 'since' to read only newer output]",
+        output.next_offset
+    ));
+    result
+}
+
+/// Exit status and output of a command, keeping the end of long output where errors usually are
+fn format_command_output(output: &CommandOutput) -> String {
+    let secs = output.duration_ms as f64 / 1000.0;
+    let mut result = match (output.timed_out, output.exit_code) {
+        (true, _) => format!("Command timed out after {:.1}s and was killed", secs),
+        (false, Some(code)) => format!("Command exited with code {} after {:.1}s", code, secs),
+        (false, None) => format!("Command was killed by a signal after {:.1}s", secs),
+    };
+    for (stream, text) in [
+        (OutputStream::Stdout, &output.stdout),
+        (OutputStream::Stderr, &output.stderr),
+    ] {
+        if text.is_empty() {
+            continue;
+        }
+        let label = match stream {
+            OutputStream::Stdout => "stdout",
+            OutputStream::Stderr => "stderr",
+        };
+        let mut start = text.len().saturating_sub(MAX_TOOL_COMMAND_OUTPUT_BYTES);
+        while !text.is_char_boundary(start) {
+            start += 1;
+        }
+        result.push_str(&format!("\n\n{}:\n", label));
+        if start > 0 {
+            result.push_str(&format!("[... first {} bytes omitted]\n", start));
+        }
+        result.push_str(text[start..].trim_end());
+    }
+    if output.truncated {
+        result.push_str(&format!(
+            "\n\n[Output past the first {} KB of a stream was not captured]",
+            terminal::command::MAX_OUTPUT_BYTES / 1024
+        ));
+    }
+    if output.sandbox.mode != SandboxMode::Full {
+        result.push_str(&format!(
+            "\n\n[Sandbox: {}]",
+            output.sandbox.limitations.join("; ")
+        ));
+    }
+    result
+}
+
+/// Most planned moves itemised in a dry run
+const MAX_LISTED_MOVES: usize = 100;
+
+fn format_batch_plan(plan: &BatchPlan) -> String {
+    if plan.moves.is_empty() && plan.conflicts.is_empty() {
+        return "Nothing to move".to_string();
+    }
+
+    let mut lines = vec![format!(
+        "Plan: {} moves, {} new folders, {} conflicts",
+        plan.moves.len(),
+        plan.create_directories.len(),
+        plan.conflicts.len()
+    )];
+    for dir in &plan.create_directories {
+        lines.push(format!("  mkdir {}", dir));
+    }
+    for m in plan.moves.iter().take(MAX_LISTED_MOVES) {
+        lines.push(format!("  {} -> {}", m.from, m.to));
+    }
+    if plan.moves.len() > MAX_LISTED_MOVES {
+        lines.push(format!(
+            "  ... and {} more",
+            plan.moves.len() - MAX_LISTED_MOVES
+        ));
+    }
+    if plan.conflicts.is_empty() {
+        lines.push("Nothing has been changed yet. Call batch_move again with the same arguments and apply=true to carry out this plan.".to_string());
+    } else {
+        lines.push("Conflicts (nothing will be applied until these are resolved):".to_string());
+        for c in &plan.conflicts {
+            lines.push(format!("  {} -> {}: {}", c.from, c.to, c.reason));
+        }
+    }
+    lines.join("\n")
+}
+
+fn format_file_stat(stat: &FileStat) -> String {
+    let kind = if stat.is_symlink {
+        match stat.symlink_target {
+            Some(ref target) => format!("symlink -> {}", target),
+            None => "symlink".to_string(),
+        }
+    } else if stat.info.is_directory {
+        "directory".to_string()
+    } else {
+        "file".to_string()
+    };
+
+    let mut lines = vec![
+        format!("Path: {}", stat.info.path),
+        format!("Type: {}", kind),
+        format!("Size: {}", format_size(stat.info.size)),
+    ];
+    if let Some(ref mime_type) = stat.mime_type {
+        lines.push(format!("MIME type: {}", mime_type));
+    }
+    lines.push(format!(
+        "Modified: {}",
+        format_timestamp(stat.info.modified)
+    ));
+    if let Some(created) = stat.created {
+        lines.push(format!("Created: {}", format_timestamp(created)));
+    }
+    if let Some(accessed) = stat.accessed {
+        lines.push(format!("Accessed: {}", format_timestamp(accessed)));
+    }
+    match stat.mode {
+        Some(mode) if stat.readonly => lines.push(format!("Mode: {:04o} (read-only)", mode)),
+        Some(mode) => lines.push(format!("Mode: {:04o}", mode)),
+        None if stat.readonly => lines.push("Read-only: yes".to_string()),
+        None => {}
+    }
+    if let Some(ref sha256) = stat.sha256 {
+        lines.push(format!("SHA-256: {}", sha256));
+    }
+    lines.join("\n")
+}
+
+/// Most duplicate groups listed in a single tool result
+const MAX_LISTED_DUPLICATE_GROUPS: usize = 50;
+
+fn format_duplicates(report: &DuplicateReport) -> String {
+    if report.groups.is_empty() {
+        return format!(
+            "No duplicate files found among {} files",
+            report.files_scanned
+        );
+    }
+
+    let mut lines = vec![format!(
+        "{} groups of identical files among {} scanned; {} could be reclaimed",
+        report.groups.len(),
+        report.files_scanned,
+        format_size(report.wasted_bytes)
+    )];
+    for group in report.groups.iter().take(MAX_LISTED_DUPLICATE_GROUPS) {
+        lines.push(format!(
+            "\n{} copies of {} (sha256 {}):",
+            group.paths.len(),
+            format_size(group.size),
+            &group.sha256[..12.min(group.sha256.len())]
+        ));
+        lines.extend(group.paths.iter().map(|p| format!("  {}", p)));
+    }
+    if report.groups.len() > MAX_LISTED_DUPLICATE_GROUPS {
+        lines.push(format!(
+            "\n[Showing the {} largest of {} groups]",
+            MAX_LISTED_DUPLICATE_GROUPS,
+            report.groups.len()
+        ));
+    }
+    if report.truncated {
+        lines.push(format!(
+            "[Scan stopped after {} files; scan a narrower folder for complete results]",
+            report.files_scanned
+        ));
+    }
+    lines.join("\n")
+}
+
+/// Render a Unix timestamp as a UTC date and time
+fn format_timestamp(secs: u64) -> String {
+    let (year, month, day) = civil_date(secs);
+    let rem = secs % 86_400;
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        rem / 3_600,
+        rem % 3_600 / 60,
+        rem % 60
+    )
+}
+
+fn format_archive_listing(path: &str, listing: &ArchiveListing) -> String {
+    let mut out = format!(
+        "[Archive: {} | {} entries, {} uncompressed]\n",
+        path,
+        listing.entries.len(),
+        format_size(listing.total_size)
+    );
+    for entry in listing.entries.iter().take(MAX_TREE_ENTRIES) {
+        if entry.is_directory {
+            out.push_str(&format!("{}/\n", entry.path.trim_end_matches('/')));
+        } else {
+            out.push_str(&format!("{} ({})\n", entry.path, format_size(entry.size)));
+        }
+    }
+    if listing.entries.len() > MAX_TREE_ENTRIES {
+        out.push_str(&format!(
+            "[Showing the first {} of {} entries]\n",
+            MAX_TREE_ENTRIES,
+            listing.entries.len()
+        ));
+    }
+    out
+}
+
+/// Most skipped entries itemised after an extraction
+const MAX_LISTED_SKIPS: usize = 20;
+
+fn format_extract_summary(dest: &str, summary: &ExtractSummary) -> String {
+    let mut out = format!(
+        "Extracted {} files ({}) to {}",
+        summary.files_extracted,
+        format_size(summary.bytes_written),
+        dest
+    );
+    if !summary.skipped.is_empty() {
+        out.push_str(&format!("\nSkipped {} entries:", summary.skipped.len()));
+        for skipped in summary.skipped.iter().take(MAX_LISTED_SKIPS) {
+            out.push_str(&format!("\n- {}: {}", skipped.path, skipped.reason));
+        }
+        if summary.skipped.len() > MAX_LISTED_SKIPS {
+            out.push_str(&format!(
+                "\n- ... and {} more",
+                summary.skipped.len() - MAX_LISTED_SKIPS
+            ));
+        }
+    }
+    out
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Most changed paths itemised in a "files changed" note
+const MAX_NOTED_CHANGES: usize = 30;
+
+/// Tell the agent what changed in granted folders since its previous turn, if anything did
+pub fn format_changes_note(since: &ChangesSince) -> Option<String> {
+    if since.changes.is_empty() && since.missed == 0 {
+        return None;
+    }
+
+    let mut lines = vec!["[Files changed in granted folders since your last turn]".to_string()];
+    for change in since.changes.iter().take(MAX_NOTED_CHANGES) {
+        match change.from {
+            Some(ref from) => lines.push(format!("- renamed {} -> {}", from, change.path)),
+            None => lines.push(format!("- {} {}", change.kind.label(), change.path)),
+        }
+    }
+    let more = since.changes.len().saturating_sub(MAX_NOTED_CHANGES) as u64 + since.missed;
+    if more > 0 {
+        lines.push(format!("- ... and {} more changes", more));
+    }
+    Some(lines.join("\n"))
+}
+
+/// Parse tool calls from LLM output; ids are unique across turns so audit entries stay distinct
+pub fn parse_tool_calls(text: &str) -> Vec<ToolCall> {
+    let mut tool_calls = Vec::new();
+
+    // Find all <tool_call>...</tool_call> blocks
+    let mut remaining = text;
+    while let Some(start) = remaining.find("<tool_call>") {
+        let after_start = &remaining[start + 11..];
+        if let Some(end) = after_start.find("</tool_call>") {
+            let json_str = &after_start[..end].trim();
+
+            // Parse the JSON inside the tags
+            if let Ok(parsed) = serde_json::from_str::<Value>(json_str) {
+                if let (Some(name), Some(arguments)) = (
+                    parsed.get("name").and_then(|v| v.as_str()),
+                    parsed.get("arguments"),
+                ) {
+                    tool_calls.push(ToolCall {
+                        id: format!("call_{}", uuid::Uuid::new_v4().simple()),
+                        name: name.to_string(),
+                        arguments: arguments.clone(),
+                        result: None,
+                    });
+                }
+            }
+            remaining = &after_start[end + 12..];
+        } else {
+            break;
+        }
+    }
+
+    tool_calls
+}
+
+/// Extract the text content from LLM output, excluding tool calls
+pub fn extract_text_content(text: &str) -> String {
+    let mut result = text.to_string();
+
+    // Remove all <tool_call>...</tool_call> blocks
+    while let Some(start) = result.find("<tool_call>") {
+        if let Some(end) = result[start..].find("</tool_call>") {
+            result = format!("{}{}", &result[..start], &result[start + end + 12..]);
+        } else {
+            break;
+        }
+    }
+
+    result.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::files::audit::AuditFilter;
+    use crate::files::DocumentSection;
+    use crate::terminal::SandboxReport;
+    use crate::web::SearchResult;
+    use std::cell::Cell;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_single_tool_call() {
+        let text = r#"Let me list the files for you.
+<tool_call>{"name": "list_files", "arguments": {"path": "/tmp/test"}}</tool_call>"#;
+
+        let calls = parse_tool_calls(text);
         assert_eq!(calls.len(), 1);
         assert_eq!(calls[0].name, "list_files");
         assert_eq!(calls[0].arguments.get("path").unwrap(), "/tmp/test");
@@ -312,6 +2473,8 @@ mod tests {
         assert_eq!(calls.len(), 2);
         assert_eq!(calls[0].arguments.get("path").unwrap(), "/tmp/a.txt");
         assert_eq!(calls[1].arguments.get("path").unwrap(), "/tmp/b.txt");
+        assert_ne!(calls[0].id, calls[1].id);
+        assert_ne!(calls[0].id, parse_tool_calls(text)[0].id);
     }
 
     #[test]
@@ -331,10 +2494,680 @@ Here are the results."#;
         assert_eq!(content, "Let me list those files.\n\nHere are the results.");
     }
 
+    #[test]
+    fn test_format_tree() {
+        use crate::files::{FileInfo, TreeEntry};
+
+        let entry = |name: &str, is_directory: bool, size: u64, depth: usize| TreeEntry {
+            info: FileInfo {
+                name: name.to_string(),
+                path: format!("/tmp/project/{}", name),
+                is_directory,
+                size,
+                modified: 0,
+            },
+            depth,
+        };
+        let tree = DirectoryTree {
+            root: "/tmp/project".to_string(),
+            entries: vec![
+                entry("src", true, 0, 1),
+                entry("main.rs", false, 12, 2),
+                entry("data.csv", false, 2048, 1),
+            ],
+            truncated: true,
+        };
+
+        let rendered = format_tree(&tree);
+        assert_eq!(
+            rendered,
+            "/tmp/project/\n  src/\n    main.rs (12 B)\n  data.csv (2.0 KB)\n\
+             [Listing truncated after 3 entries. Narrow it with max_depth, include or exclude.]"
+        );
+    }
+
+    #[test]
+    fn test_format_search_results() {
+        use crate::files::SearchMatch;
+
+        let results = SearchResults {
+            matches: vec![
+                SearchMatch {
+                    path: "/tmp/a.txt".to_string(),
+                    line_number: 2,
+                    line: "needle".to_string(),
+                    before: vec!["first".to_string()],
+                    after: vec![],
+                },
+                SearchMatch {
+                    path: "/tmp/b.txt".to_string(),
+                    line_number: 1,
+                    line: "needle again".to_string(),
+                    before: vec![],
+                    after: vec![],
+                },
+            ],
+            files_searched: 2,
+            truncated: false,
+        };
+
+        assert_eq!(
+            format_search_results(&results),
+            "/tmp/a.txt\n  1- first\n  2: needle\n/tmp/b.txt\n  1: needle again"
+        );
+    }
+
+    #[test]
+    fn test_read_range_from_args() {
+        assert!(matches!(
+            read_range_from_args(&json!({"path": "/a"})),
+            ReadRange::Lines {
+                offset: 1,
+                limit: None
+            }
+        ));
+        assert!(matches!(
+            read_range_from_args(&json!({"offset": 10, "limit": 5})),
+            ReadRange::Lines {
+                offset: 10,
+                limit: Some(5)
+            }
+        ));
+        assert!(matches!(
+            read_range_from_args(&json!({"tail": 20})),
+            ReadRange::Tail { lines: 20 }
+        ));
+        assert!(matches!(
+            read_range_from_args(&json!({"tail": 1_000_000_000_000u64})),
+            ReadRange::Tail {
+                lines: MAX_TOOL_READ_LINES
+            }
+        ));
+        assert!(matches!(
+            read_range_from_args(&json!({"byte_offset": 100})),
+            ReadRange::Bytes {
+                offset: 100,
+                limit: None
+            }
+        ));
+    }
+
+    #[test]
+    fn test_format_file_chunk() {
+        let mut chunk = FileChunk {
+            content: "a\nb\n".to_string(),
+            total_bytes: 4,
+            total_lines: Some(2),
+            start_line: Some(1),
+            end_line: Some(2),
+            start_byte: None,
+            end_byte: None,
+            truncated: false,
+            encoding: TextEncoding::Utf8,
+            line_ending: LineEnding::Lf,
+        };
+        assert_eq!(
+            format_file_chunk("/tmp/x.txt", &chunk),
+            "[File: /tmp/x.txt | 2 lines, 4 B]\na\nb\n"
+        );
+
+        chunk.encoding = TextEncoding::Utf16Le;
+        chunk.line_ending = LineEnding::Crlf;
+        assert!(format_file_chunk("/tmp/x.txt", &chunk)
+            .starts_with("[File: /tmp/x.txt | 2 lines, 4 B | utf-16le, crlf]\n"));
+        chunk.encoding = TextEncoding::Utf8;
+        chunk.line_ending = LineEnding::Lf;
+
+        chunk.content = "a\n".to_string();
+        chunk.end_line = Some(1);
+        let rendered = format_file_chunk("/tmp/x.txt", &chunk);
+        assert!(rendered.starts_with("[File: /tmp/x.txt | 2 lines, 4 B | showing lines 1-1]\na\n"));
+        assert!(rendered.contains("[File truncated: showing lines 1-1 of 2 lines."));
+
+        chunk.total_lines = None;
+        chunk.start_line = None;
+        chunk.end_line = None;
+        chunk.start_byte = Some(0);
+        chunk.end_byte = Some(2);
+        let rendered = format_file_chunk("/tmp/x.txt", &chunk);
+        assert!(rendered.starts_with("[File: /tmp/x.txt | 4 B | showing bytes 0-2]\na\n"));
+        assert!(rendered.contains("[File truncated: showing bytes 0-2 of 4 B."));
+    }
+
+    #[test]
+    fn test_format_document() {
+        let doc = ExtractedDocument {
+            format: DocumentFormat::Pdf,
+            sections: vec![
+                DocumentSection {
+                    title: Some("Page 1".to_string()),
+                    content: "Intro".to_string(),
+                },
+                DocumentSection {
+                    title: Some("Page 2".to_string()),
+                    content: "Details".to_string(),
+                },
+            ],
+        };
+
+        assert_eq!(
+            format_document("/tmp/a.pdf", doc.clone(), None),
+            "[Document: /tmp/a.pdf | PDF | 2 pages]\n## Page 1\n\nIntro\n\n## Page 2\n\nDetails\n"
+        );
+        assert_eq!(
+            format_document("/tmp/a.pdf", doc.clone(), Some(2)),
+            "[Document: /tmp/a.pdf | PDF | 2 pages | showing page 2]\n## Page 2\n\nDetails\n"
+        );
+        assert!(format_document("/tmp/a.pdf", doc, Some(3)).starts_with("Error: section 3"));
+    }
+
+    #[test]
+    fn test_format_query_result() {
+        let result = QueryResult {
+            columns: vec!["region".to_string(), "total".to_string()],
+            rows: vec![
+                vec![json!("EU"), json!(12.5)],
+                vec![json!("US"), Value::Null],
+            ],
+            source_rows: 10,
+            matched_rows: 2,
+        };
+        assert_eq!(
+            format_query_result("/tmp/d.csv", &result),
+            "[Query: /tmp/d.csv | 2 of 10 rows matched, 2 returned]\n| region | total |\n| --- | --- |\n| EU | 12.5 |\n| US |  |\n"
+        );
+
+        let empty = QueryResult {
+            rows: Vec::new(),
+            matched_rows: 0,
+            ..result
+        };
+        assert!(format_query_result("/tmp/d.csv", &empty)
+            .ends_with("(no rows; columns: region, total)"));
+    }
+
+    #[test]
+    fn test_format_changes_note() {
+        use crate::files::{ChangeKind, FsChange};
+
+        assert_eq!(format_changes_note(&ChangesSince::default()), None);
+
+        let since = ChangesSince {
+            changes: vec![
+                FsChange {
+                    path: "/p/a.txt".to_string(),
+                    kind: ChangeKind::Modified,
+                    from: None,
+                },
+                FsChange {
+                    path: "/p/new.txt".to_string(),
+                    kind: ChangeKind::Renamed,
+                    from: Some("/p/old.txt".to_string()),
+                },
+            ],
+            missed: 3,
+        };
+        assert_eq!(
+            format_changes_note(&since).unwrap(),
+            "[Files changed in granted folders since your last turn]\n\
+             - modified /p/a.txt\n\
+             - renamed /p/old.txt -> /p/new.txt\n\
+             - ... and 3 more changes"
+        );
+    }
+
+    #[test]
+    fn test_format_batch_plan() {
+        use crate::files::batch::{BatchConflict, BatchMove};
+
+        let mut plan = BatchPlan {
+            moves: vec![BatchMove {
+                from: "/p/a.jpg".to_string(),
+                to: "/p/Images/a.jpg".to_string(),
+            }],
+            create_directories: vec!["/p/Images".to_string()],
+            conflicts: Vec::new(),
+        };
+        let text = format_batch_plan(&plan);
+        assert!(text.starts_with(
+            "Plan: 1 moves, 1 new folders, 0 conflicts\n  mkdir /p/Images\n  /p/a.jpg -> /p/Images/a.jpg\n"
+        ));
+        assert!(text.contains("apply=true"));
+
+        plan.conflicts.push(BatchConflict {
+            from: "/p/b.jpg".to_string(),
+            to: "/p/Images/b.jpg".to_string(),
+            reason: "destination already exists".to_string(),
+        });
+        let text = format_batch_plan(&plan);
+        assert!(!text.contains("apply=true"));
+        assert!(text.ends_with("/p/b.jpg -> /p/Images/b.jpg: destination already exists"));
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00:00 UTC");
+        assert_eq!(format_timestamp(951_782_400), "2000-02-29 00:00:00 UTC");
+        assert_eq!(format_timestamp(1_700_000_000), "2023-11-14 22:13:20 UTC");
+    }
+
+    #[test]
+    fn test_format_duplicates() {
+        use crate::files::DuplicateGroup;
+
+        let report = DuplicateReport {
+            root: "/data".to_string(),
+            groups: vec![DuplicateGroup {
+                sha256: "2cf24dba5fb0a30e26e83b2ac5b9e29e".to_string(),
+                size: 2048,
+                paths: vec!["/data/a.bin".to_string(), "/data/b.bin".to_string()],
+            }],
+            files_scanned: 10,
+            wasted_bytes: 2048,
+            truncated: false,
+        };
+        assert_eq!(
+            format_duplicates(&report),
+            "1 groups of identical files among 10 scanned; 2.0 KB could be reclaimed\n\n\
+             2 copies of 2.0 KB (sha256 2cf24dba5fb0):\n  /data/a.bin\n  /data/b.bin"
+        );
+    }
+
+    #[test]
+    fn test_format_extract_summary() {
+        use crate::files::archive::SkippedEntry;
+
+        let mut summary = ExtractSummary {
+            files_extracted: 3,
+            bytes_written: 2048,
+            skipped: Vec::new(),
+        };
+        assert_eq!(
+            format_extract_summary("/tmp/out", &summary),
+            "Extracted 3 files (2.0 KB) to /tmp/out"
+        );
+
+        summary.skipped.push(SkippedEntry {
+            path: "../evil".to_string(),
+            reason: "path escapes the destination folder".to_string(),
+        });
+        assert!(format_extract_summary("/tmp/out", &summary)
+            .ends_with("\nSkipped 1 entries:\n- ../evil: path escapes the destination folder"));
+    }
+
+    #[test]
+    fn test_tool_error_hints() {
+        let denied = tool_error(&FileOpError::AccessDenied(
+            "Access denied: file not in granted folder".to_string(),
+        ));
+        assert!(denied.starts_with("Error: Access denied: file not in granted folder. "));
+        assert!(denied.contains("grant this folder"));
+
+        let blocked = tool_error(&FileOpError::Blocked(
+            "Blocked by policy: /home/me/.ssh/id_rsa matches a protected file pattern".to_string(),
+        ));
+        assert!(blocked.contains("protected by the user's deny-list"));
+        assert!(!blocked.contains("grant this folder"));
+
+        let io = tool_error(&FileOpError::Io {
+            kind: std::io::ErrorKind::Other,
+            message: "Failed to write file: disk full".to_string(),
+        });
+        assert_eq!(io, "Error: Failed to write file: disk full");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_command_follows_policy() {
+        let dir = tempdir().unwrap();
+        let folder = dir.path().to_str().unwrap();
+        let mut store = PermissionStore::new();
+        store.add(folder.to_string());
+        let audit = AuditLog::new(dir.path().join("audit.jsonl"));
+        let batches = BatchHistory::default();
+        let policy = CommandPolicy::load(dir.path().join("command-policy.json"));
+        let processes = ProcessManager::default();
+        let domains = DomainPolicy::load(dir.path().join("web-domains.json"));
+        let search = WebSearch::load(dir.path().join("web-search.json"));
+        let mcp = McpManager::load(dir.path().join("mcp-servers.json"));
+        let asked = Cell::new(0);
+        let approve = |_: &ApprovalRequest| {
+            asked.set(asked.get() + 1);
+            ApprovalDecision::Denied
+        };
+        let ctx = ToolContext {
+            store: &store,
+            audit: &audit,
+            batches: &batches,
+            conversation_id: Some("conv"),
+            policy: &policy,
+            approve: &approve,
+            on_command_output: &|_| {},
+            processes: &processes,
+            domains: &domains,
+            search: &search,
+            mcp: &mcp,
+        };
+        let call = |command: &str| ToolCall {
+            id: "call_1".to_string(),
+            name: "run_command".to_string(),
+            arguments: json!({ "command": command, "cwd": folder }),
+            result: None,
+        };
+
+        let denied = execute_tool(&ctx, &call("sudo rm notes.txt"));
+        assert!(denied.starts_with("Error: Blocked by command policy"));
+        assert_eq!(asked.get(), 0);
+
+        let declined = execute_tool(&ctx, &call("touch notes.txt"));
+        assert_eq!(declined, "Error: The user declined to run this command");
+        assert_eq!(asked.get(), 1);
+
+        let allowed = execute_tool(&ctx, &call("pwd"));
+        assert!(allowed.starts_with("Command exited with code 0"));
+        assert_eq!(asked.get(), 1);
+
+        let stop = ToolCall {
+            id: "call_2".to_string(),
+            name: "stop_process".to_string(),
+            arguments: json!({ "process_id": "missing" }),
+            result: None,
+        };
+        assert!(execute_tool(&ctx, &stop).starts_with("Error: No background process"));
+        let entries = audit.query(&AuditFilter::default()).unwrap();
+        assert!(entries
+            .iter()
+            .any(|e| e.operation == "stop_process" && e.path == "missing" && e.error.is_some()));
+    }
+
+    #[test]
+    fn test_fetch_url_asks_for_new_domains() {
+        let dir = tempdir().unwrap();
+        let store = PermissionStore::new();
+        let audit = AuditLog::new(dir.path().join("audit.jsonl"));
+        let batches = BatchHistory::default();
+        let policy = CommandPolicy::load(dir.path().join("command-policy.json"));
+        let processes = ProcessManager::default();
+        let domains = DomainPolicy::load(dir.path().join("web-domains.json"));
+        let search = WebSearch::load(dir.path().join("web-search.json"));
+        let mcp = McpManager::load(dir.path().join("mcp-servers.json"));
+        let asked = Cell::new(0);
+        let approve = |request: &ApprovalRequest| {
+            asked.set(asked.get() + 1);
+            match request.subject {
+                ApprovalSubject::Domain { ref domain, .. } if domain == "docs.example.org" => {
+                    ApprovalDecision::Approved
+                }
+                _ => ApprovalDecision::Denied,
+            }
+        };
+        let ctx = ToolContext {
+            store: &store,
+            audit: &audit,
+            batches: &batches,
+            conversation_id: Some("conv"),
+            policy: &policy,
+            approve: &approve,
+            on_command_output: &|_| {},
+            processes: &processes,
+            domains: &domains,
+            search: &search,
+            mcp: &mcp,
+        };
+        let call = |url: &str| ToolCall {
+            id: "call_1".to_string(),
+            name: "fetch_url".to_string(),
+            arguments: json!({ "url": url }),
+            result: None,
+        };
+
+        let declined = execute_tool(&ctx, &call("https://example.com/page"));
+        assert_eq!(declined, "Error: The user declined access to example.com");
+        assert_eq!(asked.get(), 1);
+
+        let local = execute_tool(&ctx, &call("http://localhost:3000/"));
+        assert!(local.starts_with("Error: Blocked by domain policy"));
+        assert_eq!(asked.get(), 1);
+
+        let docs = url::Url::parse("https://docs.example.org/guide").unwrap();
+        assert!(authorize_domain(&ctx, &call(docs.as_str()), &docs).is_ok());
+        assert!(authorize_domain(&ctx, &call(docs.as_str()), &docs).is_ok());
+        assert_eq!(asked.get(), 2);
+        assert_eq!(domains.rules().allow, vec!["docs.example.org".to_string()]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_mcp_tool_follows_server_rules() {
+        let dir = tempdir().unwrap();
+        let store = PermissionStore::new();
+        let audit = AuditLog::new(dir.path().join("audit.jsonl"));
+        let batches = BatchHistory::default();
+        let policy = CommandPolicy::load(dir.path().join("command-policy.json"));
+        let processes = ProcessManager::default();
+        let domains = DomainPolicy::load(dir.path().join("web-domains.json"));
+        let search = WebSearch::load(dir.path().join("web-search.json"));
+        let mcp = McpManager::load(dir.path().join("mcp-servers.json"));
+        let server = |approval: PolicyAction| mcp::McpServerConfig {
+            name: "stub".to_string(),
+            enabled: true,
+            transport: mcp::stub::stdio(),
+            approval,
+            tool_rules: Default::default(),
+        };
+        mcp.set_servers(vec![server(PolicyAction::Ask)]).unwrap();
+        mcp.connect("stub").unwrap();
+        let asked = Cell::new(0);
+        let approve = |request: &ApprovalRequest| {
+            asked.set(asked.get() + 1);
+            match request.subject {
+                ApprovalSubject::McpTool { ref arguments, .. } if arguments["text"] == "yes" => {
+                    ApprovalDecision::Approved
+                }
+                _ => ApprovalDecision::Denied,
+            }
+        };
+        let ctx = ToolContext {
+            store: &store,
+            audit: &audit,
+            batches: &batches,
+            conversation_id: Some("conv"),
+            policy: &policy,
+            approve: &approve,
+            on_command_output: &|_| {},
+            processes: &processes,
+            domains: &domains,
+            search: &search,
+            mcp: &mcp,
+        };
+        let call = |text: &str| ToolCall {
+            id: "call_1".to_string(),
+            name: "mcp__stub__echo".to_string(),
+            arguments: json!({ "text": text }),
+            result: None,
+        };
+
+        assert_eq!(execute_tool(&ctx, &call("yes")), "echo: yes");
+        assert_eq!(
+            execute_tool(&ctx, &call("no")),
+            "Error: The user declined to run 'echo' from 'stub'"
+        );
+        assert_eq!(asked.get(), 2);
+        let entries = audit.query(&AuditFilter::default()).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries.iter().all(|e| e.operation == "mcp_tool"));
+        assert_eq!(entries.iter().filter(|e| e.error.is_some()).count(), 1);
+
+        mcp.set_servers(vec![server(PolicyAction::Allow)]).unwrap();
+        mcp.connect("stub").unwrap();
+        assert_eq!(execute_tool(&ctx, &call("no")), "echo: no");
+        assert_eq!(asked.get(), 2);
+
+        mcp.set_servers(vec![server(PolicyAction::Deny)]).unwrap();
+        assert!(execute_tool(&ctx, &call("yes")).starts_with("Error: Blocked by MCP server rules"));
+        assert_eq!(audit.query(&AuditFilter::default()).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_format_web_results() {
+        let response = SearchResponse {
+            query: "tauri".to_string(),
+            results: vec![
+                SearchResult {
+                    title: "Tauri ".to_string(),
+                    url: "https://tauri.app/".to_string(),
+                    snippet: "Build smaller,\n  faster apps".to_string(),
+                },
+                SearchResult {
+                    title: "Repo".to_string(),
+                    url: "https://github.com/tauri-apps/tauri".to_string(),
+                    snippet: String::new(),
+                },
+            ],
+            cached: false,
+        };
+        assert_eq!(
+            format_web_results(&response),
+            "Results for \"tauri\":\n\n1. Tauri\nhttps://tauri.app/\nBuild smaller, faster apps\n\n2. Repo\nhttps://github.com/tauri-apps/tauri"
+        );
+        let empty = SearchResponse {
+            results: Vec::new(),
+            ..response
+        };
+        assert_eq!(format_web_results(&empty), "No results for \"tauri\"");
+    }
+
+    #[test]
+    fn test_format_fetched_page() {
+        let page = FetchedPage {
+            url: "https://example.com/".to_string(),
+            status: 200,
+            content_type: "text/html".to_string(),
+            title: Some("Example".to_string()),
+            content: "é".repeat(MAX_TOOL_FETCH_BYTES),
+            truncated: false,
+        };
+        let first = format_fetched_page(&page, 0);
+        assert!(first.starts_with("# Example\nSource: https://example.com/\n\néé"));
+        assert!(first.ends_with(&format!(
+            "[{} more bytes; call fetch_url with offset={} to continue]",
+            MAX_TOOL_FETCH_BYTES, MAX_TOOL_FETCH_BYTES
+        )));
+
+        let rest = format_fetched_page(&page, MAX_TOOL_FETCH_BYTES + 1);
+        assert!(rest.contains(&format!(
+            "[... first {} bytes omitted]\né",
+            MAX_TOOL_FETCH_BYTES + 2
+        )));
+        assert!(!rest.contains("more bytes"));
+    }
+
+    #[test]
+    fn test_format_process_output() {
+        let output = ProcessOutput {
+            text: "listening on :3000\n".to_string(),
+            next_offset: 120,
+            skipped: 100,
+            status: ProcessStatus::Running,
+        };
+        let formatted = format_process_output("proc-1", &output);
+        assert!(formatted.starts_with("Process proc-1 is running"));
+        assert!(formatted.contains("[... 100 earlier bytes omitted]\nlistening on :3000"));
+        assert!(
+            formatted.ends_with("[Next offset: 120; pass it as 'since' to read only newer output]")
+        );
+
+        let exited = ProcessOutput {
+            text: String::new(),
+            skipped: 0,
+            status: ProcessStatus::Exited { exit_code: Some(1) },
+            ..output
+        };
+        let formatted = format_process_output("proc-1", &exited);
+        assert!(formatted.starts_with("Process proc-1 exited with code 1\n\nNo new output"));
+    }
+
+    #[test]
+    fn test_format_command_output() {
+        let output = CommandOutput {
+            exit_code: Some(1),
+            stdout: String::new(),
+            stderr: "x".repeat(MAX_TOOL_COMMAND_OUTPUT_BYTES + 10),
+            truncated: false,
+            timed_out: false,
+            duration_ms: 1500,
+            sandbox: SandboxReport {
+                mode: SandboxMode::Full,
+                writes_confined: true,
+                network_blocked: true,
+                limitations: Vec::new(),
+            },
+        };
+        let formatted = format_command_output(&output);
+        assert!(formatted.starts_with("Command exited with code 1 after 1.5s"));
+        assert!(!formatted.contains("stdout:"));
+        assert!(formatted.contains("stderr:\n[... first 10 bytes omitted]\n"));
+        assert!(!formatted.contains("[Sandbox"));
+
+        let timed_out = CommandOutput {
+            exit_code: None,
+            stdout: "partial\n".to_string(),
+            timed_out: true,
+            sandbox: SandboxReport {
+                mode: SandboxMode::Unsandboxed,
+                writes_confined: false,
+                network_blocked: false,
+                limitations: vec!["Sandboxing is only available on Linux".to_string()],
+            },
+            ..output
+        };
+        let formatted = format_command_output(&timed_out);
+        assert!(formatted.starts_with("Command timed out after 1.5s"));
+        assert!(formatted.contains("stdout:\npartial"));
+        assert!(formatted.ends_with("[Sandbox: Sandboxing is only available on Linux]"));
+    }
+
+    #[test]
+    fn test_parse_edits() {
+        let edits = parse_edits(&json!({
+            "path": "/a",
+            "edits": [
+                {"search": "old", "replace": "new"},
+                {"start_line": 3, "end_line": 4, "content": "x"}
+            ]
+        }))
+        .unwrap();
+        assert_eq!(edits.len(), 2);
+        assert!(
+            matches!(&edits[0], FileEdit::Replace { search, replace } if search == "old" && replace == "new")
+        );
+        assert!(matches!(
+            &edits[1],
+            FileEdit::ReplaceLines {
+                start: 3,
+                end: 4,
+                ..
+            }
+        ));
+
+        let single = parse_edits(&json!({"path": "/a", "search": "a", "replace": "b"})).unwrap();
+        assert_eq!(single.len(), 1);
+
+        assert!(parse_edits(&json!({"path": "/a", "edits": [{"replace": "b"}]})).is_err());
+        assert!(parse_edits(&json!({"path": "/a"})).is_err());
+    }
+
     #[test]
     fn test_format_tools_for_prompt() {
-        let prompt = format_tools_for_prompt();
+        let prompt = format_tools_for_prompt(&[McpTool {
+            server: "git".to_string(),
+            name: "git_status".to_string(),
+            description: "Show the working tree status".to_string(),
+            input_schema: json!({"type": "object"}),
+        }]);
         assert!(prompt.contains("list_files"));
+        assert!(prompt.contains(r#""name": "mcp__git__git_status""#));
+        assert!(prompt.contains("Show the working tree status (from the 'git' MCP server)"));
         assert!(prompt.contains("read_file"));
         assert!(prompt.contains("write_file"));
         assert!(prompt.contains("<tool_call>"));
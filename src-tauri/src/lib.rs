@@ -1,16 +1,46 @@
+mod approval;
 mod files;
 mod inference;
+mod mcp;
 mod models;
+mod terminal;
+mod watcher;
+mod web;
 
-use std::path::Path;
-use std::sync::Mutex;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 #[cfg(debug_assertions)]
 use log::info;
-use files::{FileInfo, FolderPermission, PermissionStore};
-use inference::{execute_tool, extract_text_content, format_tools_for_prompt, parse_tool_calls, ToolCall};
+use approval::{ApprovalBroker, ApprovalRequest, APPROVAL_TIMEOUT};
+use files::{
+    AppliedBatch, ArchiveListing, AuditEntry, AuditFilter, AuditLog, BatchHistory, BatchPlan,
+    BatchRequest, ChangeJournal, CreateSummary, DirectoryTree, DuplicateReport, EditResult,
+    ExtractSummary, ExtractedDocument, FileEdit, FileInfo, FileOpError, FileRead, FileStat,
+    FolderPermission, FsChange, ListOptions, PathAccess, PermissionStore, QueryResult, ReadRange,
+    SearchOptions, SearchResults,
+};
+use inference::{
+    execute_tool, extract_text_content, format_changes_note, format_tools_for_prompt,
+    parse_tool_calls, process_audit_entry, ToolCall, ToolContext,
+};
+use mcp::{
+    McpManager, McpServer, McpServerConfig, McpServerSettings, McpServerStatus, ToolHost,
+};
 use models::{download, ModelInfo};
-use tauri::{AppHandle, Emitter, State};
+use terminal::{
+    CommandOutputEvent, CommandPolicy, CommandRule, PolicyDecision, ProcessInfo, ProcessManager,
+    ProcessOutput, ProcessScope, SandboxReport, SandboxSpec,
+};
+use tauri::{AppHandle, Emitter, Manager, State};
+use web::{DomainPolicy, DomainRules, SearchSettings, WebSearch};
+
+/// Must match `identifier` in tauri.conf.json, which names the app data folder
+const APP_IDENTIFIER: &str = "com.localworkhero.desktop";
+
+/// How often time-limited folder grants are checked for expiry
+const GRANT_EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(5);
 
 #[derive(serde::Serialize)]
 struct AppInfo {
@@ -21,6 +51,48 @@ struct AppInfo {
 struct AppState {
     inference: Mutex<Option<inference::LlamaInference>>,
     permissions: Mutex<PermissionStore>,
+    /// Where the user's deny-list is saved
+    deny_patterns_path: PathBuf,
+    audit: AuditLog,
+    batches: BatchHistory,
+    /// Recent changes in granted folders, for "files changed" notes to the agent
+    changes: ChangeJournal,
+    /// `None` when the platform watcher couldn't be started
+    watcher: Option<watcher::FsWatcher>,
+    /// Tool calls waiting on the user, such as commands to run
+    approvals: ApprovalBroker,
+    command_policy: CommandPolicy,
+    /// Background processes, stopped with their session or when the app exits
+    processes: ProcessManager,
+    /// Domains fetch_url may reach
+    domains: DomainPolicy,
+    /// The web_search endpoint, with its cache and rate limit
+    search: WebSearch,
+    /// External MCP servers and their tools
+    mcp: McpManager,
+    /// The opt-in endpoint serving our own tools to other MCP clients
+    mcp_server: McpServer,
+}
+
+impl AppState {
+    /// Record a file operation requested directly by the UI
+    fn record_audit(&self, entry: AuditEntry) {
+        if let Err(e) = self.audit.record(&entry) {
+            log::warn!("{}", e);
+        }
+    }
+
+    /// Point the file watcher at the folders currently granted
+    fn sync_watched_folders(&self) {
+        let Some(ref watcher) = self.watcher else {
+            return;
+        };
+        let folders: Vec<String> = match self.permissions.lock() {
+            Ok(store) => store.list().into_iter().map(|f| f.path).collect(),
+            Err(_) => return,
+        };
+        watcher.sync(&folders);
+    }
 }
 
 #[tauri::command]
@@ -130,14 +202,100 @@ fn send_message(
 
 /// Send a message with tool support - implements the agentic loop
 #[tauri::command]
-fn send_message_with_tools(
-    state: State<AppState>,
+async fn send_message_with_tools(
+    app: AppHandle,
     messages: Vec<inference::Message>,
+    conversation_id: Option<String>,
+    include_file_changes: Option<bool>,
+) -> Result<AgentResponse, String> {
+    // Off the main thread, since a tool call may wait minutes for the user's approval
+    tokio::task::spawn_blocking(move || {
+        run_agent_loop(&app, messages, conversation_id, include_file_changes)
+    })
+    .await
+    .map_err(|e| format!("Task error: {}", e))?
+}
+
+/// Execute a tool call with the app's grants, rules and audit log, asking the user through the UI
+fn run_tool(
+    app: &AppHandle,
+    tool_call: &ToolCall,
+    conversation_id: Option<&str>,
+) -> Result<String, String> {
+    let state = app.state::<AppState>();
+    let approve = |request: &ApprovalRequest| {
+        state.approvals.request(
+            request,
+            |r| {
+                let _ = app.emit("approval-requested", r);
+            },
+            APPROVAL_TIMEOUT,
+        )
+    };
+    let on_command_output = |event: CommandOutputEvent| {
+        let _ = app.emit("command-output", event);
+    };
+    // A copy, so a call waiting on the user doesn't hold the permissions lock
+    let store = state.permissions.lock().map_err(|e| e.to_string())?.clone();
+    let ctx = ToolContext {
+        store: &store,
+        audit: &state.audit,
+        batches: &state.batches,
+        conversation_id,
+        policy: &state.command_policy,
+        approve: &approve,
+        on_command_output: &on_command_output,
+        processes: &state.processes,
+        domains: &state.domains,
+        search: &state.search,
+        mcp: &state.mcp,
+    };
+    Ok(execute_tool(&ctx, tool_call))
+}
+
+/// MCP clients' tool calls run like the agent's, each client session as its own conversation
+impl ToolHost for AppHandle {
+    fn call_tool(&self, tool_call: &ToolCall, session: &str) -> String {
+        let conversation_id = format!("mcp-{}", session);
+        run_tool(self, tool_call, Some(&conversation_id))
+            .unwrap_or_else(|e| format!("Error: {}", e))
+    }
+
+    fn end_session(&self, session: &str) {
+        let conversation_id = format!("mcp-{}", session);
+        let state = self.state::<AppState>();
+        state.changes.forget(&conversation_id);
+        state.approvals.cancel_conversation(&conversation_id);
+        state.processes.stop_conversation(&conversation_id);
+    }
+}
+
+fn run_agent_loop(
+    app: &AppHandle,
+    messages: Vec<inference::Message>,
+    conversation_id: Option<String>,
+    include_file_changes: Option<bool>,
 ) -> Result<AgentResponse, String> {
     const MAX_ITERATIONS: usize = 5;
 
-    let tool_definitions = format_tools_for_prompt();
-    let mut conversation = messages.clone();
+    let state = app.state::<AppState>();
+    let tool_definitions = format_tools_for_prompt(&state.mcp.tools());
+    let mut conversation = messages;
+    if include_file_changes.unwrap_or(false) {
+        let since = state
+            .changes
+            .take_since(conversation_id.as_deref().unwrap_or_default());
+        if let Some(note) = format_changes_note(&since) {
+            // Ahead of the newest message so the user's request still comes last
+            conversation.insert(
+                conversation.len().saturating_sub(1),
+                inference::Message {
+                    role: "system".to_string(),
+                    content: note,
+                },
+            );
+        }
+    }
     let mut all_tool_calls: Vec<ToolCall> = Vec::new();
     let mut final_content = String::new();
 
@@ -170,12 +328,9 @@ fn send_message_with_tools(
         }
 
         // Execute each tool call
-        {
-            let permissions_guard = state.permissions.lock().map_err(|e| e.to_string())?;
-            for tool_call in &mut tool_calls {
-                let result = execute_tool(&permissions_guard, tool_call);
-                tool_call.result = Some(result);
-            }
+        for tool_call in &mut tool_calls {
+            let result = run_tool(app, tool_call, conversation_id.as_deref())?;
+            tool_call.result = Some(result);
         }
 
         // Add all tool calls to our collection
@@ -221,21 +376,286 @@ fn grant_folder(
     app: AppHandle,
     state: State<AppState>,
     path: String,
+    expires_in_secs: Option<u64>,
+    session_id: Option<String>,
 ) -> Result<FolderPermission, String> {
     files::permissions::grant_folder_to_scope(&app, &path)?;
-    let mut store = state.permissions.lock().map_err(|e| e.to_string())?;
-    Ok(store.add(path))
+    let permission = {
+        let mut store = state.permissions.lock().map_err(|e| e.to_string())?;
+        let expires_at = expires_in_secs.map(|secs| files::permissions::now_secs() + secs);
+        store.add_scoped(path, expires_at, session_id)
+    };
+    state.sync_watched_folders();
+    Ok(permission)
+}
+
+/// End a conversation, revoking every folder grant scoped to it
+#[tauri::command]
+fn end_session(app: AppHandle, state: State<AppState>, session_id: String) -> Result<(), String> {
+    let removed = {
+        let mut store = state.permissions.lock().map_err(|e| e.to_string())?;
+        store.remove_session(&session_id)
+    };
+    state.changes.forget(&session_id);
+    state.approvals.cancel_conversation(&session_id);
+    state.processes.stop_conversation(&session_id);
+    state.sync_watched_folders();
+    for perm in removed {
+        let _ = app.emit("folder-grant-expired", perm);
+    }
+    Ok(())
+}
+
+/// Periodically drop expired folder grants and notify the UI
+fn spawn_grant_expiry_watcher(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(GRANT_EXPIRY_CHECK_INTERVAL);
+        let state = app.state::<AppState>();
+        let expired = match state.permissions.lock() {
+            Ok(mut store) => store.remove_expired(),
+            Err(_) => continue,
+        };
+        if !expired.is_empty() {
+            state.sync_watched_folders();
+        }
+        for perm in expired {
+            let _ = app.emit("folder-grant-expired", perm);
+        }
+    });
 }
 
 #[tauri::command]
 fn revoke_folder(state: State<AppState>, id: String) -> Result<(), String> {
-    let mut store = state.permissions.lock().map_err(|e| e.to_string())?;
-    store
-        .remove(&id)
-        .ok_or_else(|| "Folder not found".to_string())?;
+    {
+        let mut store = state.permissions.lock().map_err(|e| e.to_string())?;
+        store
+            .remove(&id)
+            .ok_or_else(|| "Folder not found".to_string())?;
+    }
+    state.sync_watched_folders();
     Ok(())
 }
 
+/// Share changes in granted folders with the UI and the agent, leaving out protected paths
+fn report_fs_changes(app: &AppHandle, changes: Vec<FsChange>) {
+    let state = app.state::<AppState>();
+    let changes: Vec<FsChange> = match state.permissions.lock() {
+        Ok(store) => changes
+            .into_iter()
+            .filter(|c| store.check_access(&c.path) == PathAccess::Allowed)
+            .collect(),
+        Err(_) => return,
+    };
+    if changes.is_empty() {
+        return;
+    }
+    state.changes.record(&changes);
+    let _ = app.emit("fs-changed", changes);
+}
+
+/// Answer an `approval-requested` event
+#[tauri::command]
+fn respond_approval(state: State<AppState>, id: String, approved: bool) -> Result<(), String> {
+    state.approvals.respond(&id, approved)
+}
+
+#[derive(serde::Serialize)]
+struct CommandRules {
+    defaults: Vec<CommandRule>,
+    user: Vec<CommandRule>,
+}
+
+#[tauri::command]
+fn list_command_rules(state: State<AppState>) -> CommandRules {
+    CommandRules {
+        defaults: terminal::policy::default_rules(),
+        user: state.command_policy.user_rules(),
+    }
+}
+
+#[tauri::command]
+fn add_command_rule(state: State<AppState>, rule: CommandRule) -> Result<CommandRule, String> {
+    state.command_policy.add_rule(rule)
+}
+
+#[tauri::command]
+fn remove_command_rule(state: State<AppState>, id: String) -> Result<(), String> {
+    state.command_policy.remove_rule(&id)
+}
+
+#[tauri::command]
+fn get_domain_rules(state: State<AppState>) -> DomainRules {
+    state.domains.rules()
+}
+
+/// Replace the allowed and blocked domain lists, returning them as saved
+#[tauri::command]
+fn set_domain_rules(state: State<AppState>, rules: DomainRules) -> Result<DomainRules, String> {
+    state.domains.set_rules(rules)
+}
+
+#[tauri::command]
+fn get_search_settings(state: State<AppState>) -> SearchSettings {
+    state.search.settings()
+}
+
+/// Set the search endpoint; an empty endpoint turns web_search off
+#[tauri::command]
+fn set_search_settings(
+    state: State<AppState>,
+    settings: SearchSettings,
+) -> Result<SearchSettings, String> {
+    state.search.set_settings(settings)
+}
+
+#[tauri::command]
+fn get_mcp_servers(state: State<AppState>) -> Vec<McpServerConfig> {
+    state.mcp.servers()
+}
+
+/// Replace the MCP server list and connect the servers that aren't connected yet
+#[tauri::command]
+async fn set_mcp_servers(
+    app: AppHandle,
+    servers: Vec<McpServerConfig>,
+) -> Result<Vec<McpServerStatus>, String> {
+    tokio::task::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        state.mcp.set_servers(servers)?;
+        state.mcp.connect_all();
+        Ok(state.mcp.status())
+    })
+    .await
+    .map_err(|e| format!("Task error: {}", e))?
+}
+
+#[tauri::command]
+fn get_mcp_status(state: State<AppState>) -> Vec<McpServerStatus> {
+    state.mcp.status()
+}
+
+/// Reconnect a server and rediscover its tools, e.g. after it crashed
+#[tauri::command]
+async fn connect_mcp_server(app: AppHandle, name: String) -> Result<McpServerStatus, String> {
+    tokio::task::spawn_blocking(move || app.state::<AppState>().mcp.connect(&name))
+        .await
+        .map_err(|e| format!("Task error: {}", e))?
+}
+
+#[tauri::command]
+fn get_mcp_server_settings(state: State<AppState>) -> McpServerSettings {
+    state.mcp_server.settings()
+}
+
+/// Turn the MCP server on or off, or move it to another port
+#[tauri::command]
+fn set_mcp_server_settings(
+    app: AppHandle,
+    state: State<AppState>,
+    enabled: bool,
+    port: u16,
+) -> Result<McpServerSettings, String> {
+    state.mcp_server.set_settings(enabled, port, Arc::new(app))
+}
+
+/// Preview what the command policy would do with a command, without running it
+#[tauri::command]
+fn check_command(
+    state: State<AppState>,
+    command: String,
+    cwd: String,
+) -> Result<PolicyDecision, String> {
+    let store = state.permissions.lock().map_err(|e| e.to_string())?;
+    Ok(state.command_policy.evaluate(&command, Path::new(&cwd), &store))
+}
+
+/// Start a background process from the UI; the user asked for it, so no approval is needed
+#[tauri::command]
+fn start_process(
+    state: State<AppState>,
+    command: String,
+    cwd: String,
+    network: Option<bool>,
+    conversation_id: Option<String>,
+) -> Result<ProcessInfo, String> {
+    let store = state.permissions.lock().map_err(|e| e.to_string())?.clone();
+    let result = terminal::resolve_cwd(&store, &cwd).and_then(|dir| {
+        let sandbox = SandboxSpec {
+            writable: store.list().into_iter().map(|f| f.path.into()).collect(),
+            allow_network: network.unwrap_or(false),
+        };
+        state
+            .processes
+            .start(&command, &dir, &sandbox, conversation_id.as_deref())
+    });
+    state.record_audit(
+        AuditEntry::from_result("start_process", &cwd, &result, None).with_command(&command),
+    );
+    result
+}
+
+#[tauri::command]
+fn list_processes(state: State<AppState>, conversation_id: Option<String>) -> Vec<ProcessInfo> {
+    let scope = match conversation_id.as_deref() {
+        Some(id) => ProcessScope::Conversation(Some(id)),
+        None => ProcessScope::User,
+    };
+    state.processes.list(scope)
+}
+
+#[tauri::command]
+fn read_process_output(
+    state: State<AppState>,
+    id: String,
+    since: Option<u64>,
+) -> Result<ProcessOutput, String> {
+    state
+        .processes
+        .read(&id, ProcessScope::User, since, std::time::Duration::ZERO)
+}
+
+#[tauri::command]
+fn send_process_input(
+    state: State<AppState>,
+    id: String,
+    input: String,
+    close: Option<bool>,
+) -> Result<(), String> {
+    let info = state.processes.info(&id, ProcessScope::User).ok();
+    let result =
+        state
+            .processes
+            .send_input(&id, ProcessScope::User, &input, close.unwrap_or(false));
+    state.record_audit(process_audit_entry(
+        "send_process_input",
+        &id,
+        info.as_ref(),
+        &result,
+        Some(input.len() as u64),
+    ));
+    result
+}
+
+#[tauri::command]
+fn stop_process(state: State<AppState>, id: String) -> Result<ProcessInfo, String> {
+    let info = state.processes.info(&id, ProcessScope::User).ok();
+    let result = state.processes.stop(&id, ProcessScope::User);
+    state.record_audit(process_audit_entry(
+        "stop_process",
+        &id,
+        info.as_ref(),
+        &result,
+        None,
+    ));
+    result
+}
+
+/// Isolation commands get on this machine when they don't ask for network access
+#[tauri::command]
+fn get_sandbox_status() -> SandboxReport {
+    terminal::sandbox::expected(&SandboxSpec::default())
+}
+
 #[tauri::command]
 fn list_folders(state: State<AppState>) -> Result<Vec<FolderPermission>, String> {
     let store = state.permissions.lock().map_err(|e| e.to_string())?;
@@ -243,39 +663,399 @@ fn list_folders(state: State<AppState>) -> Result<Vec<FolderPermission>, String>
 }
 
 #[tauri::command]
-fn list_files(state: State<AppState>, path: String) -> Result<Vec<FileInfo>, String> {
+fn get_deny_patterns(state: State<AppState>) -> Result<Vec<String>, String> {
     let store = state.permissions.lock().map_err(|e| e.to_string())?;
-    files::operations::list_directory(&store, &path)
+    Ok(store.deny_patterns())
 }
 
 #[tauri::command]
-fn read_text_file(state: State<AppState>, path: String) -> Result<String, String> {
+fn set_deny_patterns(state: State<AppState>, patterns: Vec<String>) -> Result<(), String> {
+    let mut store = state.permissions.lock().map_err(|e| e.to_string())?;
+    store.save_deny_patterns(&state.deny_patterns_path, patterns)
+}
+
+#[tauri::command]
+fn list_files(state: State<AppState>, path: String) -> Result<Vec<FileInfo>, FileOpError> {
+    let store = state.permissions.lock().map_err(|e| e.to_string())?;
+    let result = files::operations::list_directory(&store, &path);
+    state.record_audit(AuditEntry::from_result(
+        "list_directory",
+        &path,
+        &result,
+        None,
+    ));
+    result
+}
+
+#[tauri::command]
+fn list_files_recursive(
+    state: State<AppState>,
+    path: String,
+    options: Option<ListOptions>,
+) -> Result<DirectoryTree, FileOpError> {
+    let store = state.permissions.lock().map_err(|e| e.to_string())?;
+    let options = options.unwrap_or_default();
+    let result = files::operations::list_directory_recursive(&store, &path, &options);
+    state.record_audit(AuditEntry::from_result(
+        "list_directory_recursive",
+        &path,
+        &result,
+        None,
+    ));
+    result
+}
+
+#[tauri::command]
+fn search_files(
+    state: State<AppState>,
+    options: SearchOptions,
+) -> Result<SearchResults, FileOpError> {
+    let store = state.permissions.lock().map_err(|e| e.to_string())?;
+    let result = files::operations::search_files(&store, &options);
+    let audit_path = options.path.as_deref().unwrap_or("(all granted folders)");
+    state.record_audit(AuditEntry::from_result(
+        "search_files",
+        audit_path,
+        &result,
+        None,
+    ));
+    result
+}
+
+#[tauri::command]
+fn read_text_file(state: State<AppState>, path: String) -> Result<String, FileOpError> {
+    let store = state.permissions.lock().map_err(|e| e.to_string())?;
+    let result = files::operations::read_file(&store, &path);
+    let bytes = result.as_ref().ok().map(|c| c.len() as u64);
+    state.record_audit(AuditEntry::from_result("read_file", &path, &result, bytes));
+    result
+}
+
+#[tauri::command]
+fn read_document(state: State<AppState>, path: String) -> Result<ExtractedDocument, FileOpError> {
+    let store = state.permissions.lock().map_err(|e| e.to_string())?;
+    let result = files::operations::read_document(&store, &path);
+    let bytes = result
+        .as_ref()
+        .ok()
+        .map(|doc| doc.sections.iter().map(|s| s.content.len() as u64).sum());
+    state.record_audit(AuditEntry::from_result(
+        "read_document",
+        &path,
+        &result,
+        bytes,
+    ));
+    result
+}
+
+#[tauri::command]
+fn query_data(
+    state: State<AppState>,
+    path: String,
+    query: String,
+) -> Result<QueryResult, FileOpError> {
+    let store = state.permissions.lock().map_err(|e| e.to_string())?;
+    let result = files::operations::query_data(&store, &path, &query);
+    state.record_audit(AuditEntry::from_result("query_data", &path, &result, None));
+    result
+}
+
+#[tauri::command]
+fn read_file_range(
+    state: State<AppState>,
+    path: String,
+    range: ReadRange,
+    max_bytes: Option<u64>,
+) -> Result<FileRead, FileOpError> {
+    let store = state.permissions.lock().map_err(|e| e.to_string())?;
+    let max_bytes = max_bytes
+        .unwrap_or(files::operations::MAX_READ_BYTES)
+        .min(files::operations::MAX_READ_BYTES);
+    let result = files::operations::read_file_range(&store, &path, &range, max_bytes);
+    let bytes = match &result {
+        Ok(FileRead::Text(chunk)) => Some(chunk.content.len() as u64),
+        _ => None,
+    };
+    state.record_audit(AuditEntry::from_result("read_file", &path, &result, bytes));
+    result
+}
+
+#[tauri::command]
+fn write_text_file(
+    state: State<AppState>,
+    path: String,
+    content: String,
+) -> Result<(), FileOpError> {
+    let store = state.permissions.lock().map_err(|e| e.to_string())?;
+    let result = files::operations::write_file(&store, &path, &content);
+    state.record_audit(AuditEntry::from_result(
+        "write_file",
+        &path,
+        &result,
+        Some(content.len() as u64),
+    ));
+    result
+}
+
+#[tauri::command]
+fn edit_text_file(
+    state: State<AppState>,
+    path: String,
+    edits: Vec<FileEdit>,
+) -> Result<EditResult, FileOpError> {
+    let store = state.permissions.lock().map_err(|e| e.to_string())?;
+    let result = files::operations::edit_file(&store, &path, &edits);
+    state.record_audit(AuditEntry::from_result("edit_file", &path, &result, None));
+    result
+}
+
+#[tauri::command]
+fn create_text_file(
+    state: State<AppState>,
+    path: String,
+    content: String,
+) -> Result<(), FileOpError> {
+    let store = state.permissions.lock().map_err(|e| e.to_string())?;
+    let result = files::operations::create_file(&store, &path, &content);
+    state.record_audit(AuditEntry::from_result(
+        "create_file",
+        &path,
+        &result,
+        Some(content.len() as u64),
+    ));
+    result
+}
+
+#[tauri::command]
+fn delete_fs_file(state: State<AppState>, path: String) -> Result<(), FileOpError> {
+    let store = state.permissions.lock().map_err(|e| e.to_string())?;
+    let result = files::operations::delete_file(&store, &path);
+    state.record_audit(AuditEntry::from_result("delete_file", &path, &result, None));
+    result
+}
+
+#[tauri::command]
+fn move_fs_file(state: State<AppState>, src: String, dest: String) -> Result<(), FileOpError> {
+    let store = state.permissions.lock().map_err(|e| e.to_string())?;
+    let result = files::operations::move_file(&store, &src, &dest);
+    state
+        .record_audit(AuditEntry::from_result("move_file", &src, &result, None).with_target(&dest));
+    result
+}
+
+#[tauri::command]
+fn create_fs_directory(state: State<AppState>, path: String) -> Result<(), FileOpError> {
     let store = state.permissions.lock().map_err(|e| e.to_string())?;
-    files::operations::read_file(&store, &path)
+    let result = files::operations::create_directory(&store, &path);
+    state.record_audit(AuditEntry::from_result(
+        "create_directory",
+        &path,
+        &result,
+        None,
+    ));
+    result
 }
 
 #[tauri::command]
-fn write_text_file(state: State<AppState>, path: String, content: String) -> Result<(), String> {
+fn copy_fs_file(state: State<AppState>, src: String, dest: String) -> Result<u64, FileOpError> {
     let store = state.permissions.lock().map_err(|e| e.to_string())?;
-    files::operations::write_file(&store, &path, &content)
+    let result = files::operations::copy_file(&store, &src, &dest);
+    let bytes = result.as_ref().ok().copied();
+    state.record_audit(
+        AuditEntry::from_result("copy_file", &src, &result, bytes).with_target(&dest),
+    );
+    result
 }
 
 #[tauri::command]
-fn create_text_file(state: State<AppState>, path: String, content: String) -> Result<(), String> {
+fn copy_fs_directory(
+    state: State<AppState>,
+    src: String,
+    dest: String,
+) -> Result<usize, FileOpError> {
     let store = state.permissions.lock().map_err(|e| e.to_string())?;
-    files::operations::create_file(&store, &path, &content)
+    let result = files::operations::copy_directory(&store, &src, &dest);
+    state.record_audit(
+        AuditEntry::from_result("copy_directory", &src, &result, None).with_target(&dest),
+    );
+    result
 }
 
 #[tauri::command]
-fn delete_fs_file(state: State<AppState>, path: String) -> Result<(), String> {
+fn remove_empty_fs_directory(state: State<AppState>, path: String) -> Result<(), FileOpError> {
     let store = state.permissions.lock().map_err(|e| e.to_string())?;
-    files::operations::delete_file(&store, &path)
+    let result = files::operations::remove_empty_directory(&store, &path);
+    state.record_audit(AuditEntry::from_result(
+        "remove_empty_directory",
+        &path,
+        &result,
+        None,
+    ));
+    result
 }
 
 #[tauri::command]
-fn move_fs_file(state: State<AppState>, src: String, dest: String) -> Result<(), String> {
+fn plan_batch(state: State<AppState>, request: BatchRequest) -> Result<BatchPlan, FileOpError> {
     let store = state.permissions.lock().map_err(|e| e.to_string())?;
-    files::operations::move_file(&store, &src, &dest)
+    let result = files::operations::plan_batch(&store, &request);
+    state.record_audit(AuditEntry::from_result(
+        "plan_batch",
+        request.primary_path(),
+        &result,
+        None,
+    ));
+    result
+}
+
+#[tauri::command]
+fn apply_batch(state: State<AppState>, request: BatchRequest) -> Result<AppliedBatch, FileOpError> {
+    let store = state.permissions.lock().map_err(|e| e.to_string())?;
+    let result = files::operations::apply_batch(&store, &request);
+    state.record_audit(AuditEntry::from_result(
+        "batch_move",
+        request.primary_path(),
+        &result,
+        None,
+    ));
+    if let Ok(ref applied) = result {
+        state.batches.push(applied.clone());
+    }
+    result
+}
+
+/// Reverse the most recently applied batch, whether the user or the agent ran it
+#[tauri::command]
+fn undo_last_batch(state: State<AppState>) -> Result<AppliedBatch, FileOpError> {
+    let store = state.permissions.lock().map_err(|e| e.to_string())?;
+    let batch = state
+        .batches
+        .last()
+        .ok_or_else(|| FileOpError::NotFound("No batch to undo".to_string()))?;
+    let path = batch.moves.first().map(|m| m.to.as_str()).unwrap_or("");
+    let result = files::operations::undo_batch(&store, &batch);
+    state.record_audit(AuditEntry::from_result("undo_batch", path, &result, None));
+    result?;
+    state.batches.pop();
+    Ok(batch)
+}
+
+#[tauri::command]
+fn list_batch_history(state: State<AppState>) -> Vec<AppliedBatch> {
+    state.batches.list()
+}
+
+#[tauri::command]
+fn stat_file(
+    state: State<AppState>,
+    path: String,
+    hash: Option<bool>,
+) -> Result<FileStat, FileOpError> {
+    let store = state.permissions.lock().map_err(|e| e.to_string())?;
+    let result = files::operations::stat_file(&store, &path, hash.unwrap_or(false));
+    state.record_audit(AuditEntry::from_result("stat_file", &path, &result, None));
+    result
+}
+
+#[tauri::command]
+fn find_duplicates(
+    state: State<AppState>,
+    path: String,
+    min_size: Option<u64>,
+) -> Result<DuplicateReport, FileOpError> {
+    let store = state.permissions.lock().map_err(|e| e.to_string())?;
+    let result = files::operations::find_duplicates(&store, &path, min_size.unwrap_or(0));
+    state.record_audit(AuditEntry::from_result(
+        "find_duplicates",
+        &path,
+        &result,
+        None,
+    ));
+    result
+}
+
+#[tauri::command]
+fn list_archive(state: State<AppState>, path: String) -> Result<ArchiveListing, FileOpError> {
+    let store = state.permissions.lock().map_err(|e| e.to_string())?;
+    let result = files::operations::list_archive(&store, &path);
+    state.record_audit(AuditEntry::from_result(
+        "list_archive",
+        &path,
+        &result,
+        None,
+    ));
+    result
+}
+
+#[tauri::command]
+fn extract_archive(
+    state: State<AppState>,
+    path: String,
+    dest: String,
+    overwrite: Option<bool>,
+) -> Result<ExtractSummary, FileOpError> {
+    let store = state.permissions.lock().map_err(|e| e.to_string())?;
+    let result =
+        files::operations::extract_archive(&store, &path, &dest, overwrite.unwrap_or(false));
+    let bytes = result.as_ref().ok().map(|s| s.bytes_written);
+    state.record_audit(
+        AuditEntry::from_result("extract_archive", &path, &result, bytes).with_target(&dest),
+    );
+    result
+}
+
+#[tauri::command]
+fn create_archive(
+    state: State<AppState>,
+    paths: Vec<String>,
+    dest: String,
+) -> Result<CreateSummary, FileOpError> {
+    let store = state.permissions.lock().map_err(|e| e.to_string())?;
+    let result = files::operations::create_archive(&store, &paths, &dest);
+    let bytes = result.as_ref().ok().map(|s| s.bytes_added);
+    state.record_audit(AuditEntry::from_result(
+        "create_archive",
+        &dest,
+        &result,
+        bytes,
+    ));
+    result
+}
+
+#[tauri::command]
+fn append_text_file(
+    state: State<AppState>,
+    path: String,
+    content: String,
+) -> Result<(), FileOpError> {
+    let store = state.permissions.lock().map_err(|e| e.to_string())?;
+    let result = files::operations::append_to_file(&store, &path, &content);
+    state.record_audit(AuditEntry::from_result(
+        "append_to_file",
+        &path,
+        &result,
+        Some(content.len() as u64),
+    ));
+    result
+}
+
+#[tauri::command]
+fn query_audit_log(
+    state: State<AppState>,
+    filter: Option<AuditFilter>,
+) -> Result<Vec<AuditEntry>, String> {
+    state.audit.query(&filter.unwrap_or_default())
+}
+
+/// Export matching audit entries as JSONL to a user-chosen location
+#[tauri::command]
+fn export_audit_log(
+    state: State<AppState>,
+    dest: String,
+    filter: Option<AuditFilter>,
+) -> Result<usize, String> {
+    state
+        .audit
+        .export(Path::new(&dest), &filter.unwrap_or_default())
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -285,9 +1065,41 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_persisted_scope::init())
         .plugin(tauri_plugin_opener::init())
-        .manage(AppState {
-            inference: Mutex::new(None),
-            permissions: Mutex::new(PermissionStore::new()),
+        .setup(|app| {
+            let data_dir = app.path().app_data_dir()?;
+            let app_handle = app.handle().clone();
+            let fs_watcher =
+                watcher::FsWatcher::new(move |changes| report_fs_changes(&app_handle, changes))
+                    .map_err(|e| log::warn!("{}", e))
+                    .ok();
+            let deny_patterns_path = data_dir.join("deny-patterns.json");
+            let mut permissions = PermissionStore::new();
+            permissions.load_deny_patterns(&deny_patterns_path);
+            app.manage(AppState {
+                inference: Mutex::new(None),
+                permissions: Mutex::new(permissions),
+                deny_patterns_path,
+                audit: AuditLog::new(data_dir.join("audit.jsonl")),
+                batches: BatchHistory::default(),
+                changes: ChangeJournal::default(),
+                watcher: fs_watcher,
+                approvals: ApprovalBroker::default(),
+                command_policy: CommandPolicy::load(data_dir.join("command-policy.json")),
+                processes: ProcessManager::default(),
+                domains: DomainPolicy::load(data_dir.join("web-domains.json")),
+                search: WebSearch::load(data_dir.join("web-search.json")),
+                mcp: McpManager::load(data_dir.join("mcp-servers.json")),
+                mcp_server: McpServer::load(data_dir.join(mcp::server::SETTINGS_FILE)),
+            });
+            spawn_grant_expiry_watcher(app.handle().clone());
+            // Servers can take a while to start, so connect them off the setup path
+            let handle = app.handle().clone();
+            std::thread::spawn(move || handle.state::<AppState>().mcp.connect_all());
+            let state = app.state::<AppState>();
+            if let Err(e) = state.mcp_server.start(Arc::new(app.handle().clone())) {
+                log::warn!("MCP server not started: {}", e);
+            }
+            Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             get_app_info,
@@ -298,13 +1110,59 @@ pub fn run() {
             send_message_with_tools,
             grant_folder,
             revoke_folder,
+            end_session,
+            respond_approval,
+            list_command_rules,
+            add_command_rule,
+            remove_command_rule,
+            get_domain_rules,
+            set_domain_rules,
+            get_search_settings,
+            set_search_settings,
+            get_mcp_servers,
+            set_mcp_servers,
+            get_mcp_status,
+            connect_mcp_server,
+            get_mcp_server_settings,
+            set_mcp_server_settings,
+            check_command,
+            get_sandbox_status,
+            start_process,
+            list_processes,
+            read_process_output,
+            send_process_input,
+            stop_process,
             list_folders,
+            get_deny_patterns,
+            set_deny_patterns,
             list_files,
+            list_files_recursive,
+            search_files,
             read_text_file,
+            read_file_range,
+            read_document,
+            query_data,
             write_text_file,
+            edit_text_file,
             create_text_file,
             delete_fs_file,
-            move_fs_file
+            move_fs_file,
+            create_fs_directory,
+            copy_fs_file,
+            copy_fs_directory,
+            remove_empty_fs_directory,
+            append_text_file,
+            plan_batch,
+            apply_batch,
+            undo_last_batch,
+            list_batch_history,
+            stat_file,
+            find_duplicates,
+            list_archive,
+            extract_archive,
+            create_archive,
+            query_audit_log,
+            export_audit_log
         ]);
 
     // Enable MCP plugin for AI-assisted debugging in development builds
@@ -315,6 +1173,31 @@ pub fn run() {
     }
 
     builder
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // Don't leave background processes running after the window closes
+            if let tauri::RunEvent::Exit = event {
+                if let Some(state) = app.try_state::<AppState>() {
+                    state.processes.stop_all();
+                    state.mcp.disconnect_all();
+                    state.mcp_server.stop();
+                }
+            }
+        });
+}
+
+/// Entry point for `localwork-hero mcp`: relay an MCP client on stdio to the running app
+pub fn run_mcp_stdio() -> i32 {
+    let Some(data_dir) = dirs::data_dir().map(|dir| dir.join(APP_IDENTIFIER)) else {
+        eprintln!("Could not find the app data folder");
+        return 1;
+    };
+    match mcp::bridge::run_stdio(&data_dir, std::io::stdin().lock(), std::io::stdout().lock()) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
 }
@@ -2,5 +2,9 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+    // `localwork-hero mcp` serves the running app's tools to an MCP client over stdio
+    if std::env::args().nth(1).as_deref() == Some("mcp") {
+        std::process::exit(localwork_hero_lib::run_mcp_stdio());
+    }
     localwork_hero_lib::run()
 }
@@ -0,0 +1,138 @@
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use super::server::{McpServerSettings, SETTINGS_FILE};
+use super::transport::HttpTransport;
+use crate::approval::APPROVAL_TIMEOUT;
+
+/// Serve MCP over stdin and stdout by relaying each message to the running app's HTTP endpoint
+///
+/// Grants, approvals and the audit log live in the app, so the bridge only forwards.
+pub fn run_stdio(
+    data_dir: &Path,
+    input: impl BufRead,
+    mut output: impl Write,
+) -> Result<(), String> {
+    let settings = McpServerSettings::load(&data_dir.join(SETTINGS_FILE));
+    let headers = BTreeMap::from([(
+        "Authorization".to_string(),
+        format!("Bearer {}", settings.token),
+    )]);
+    // Leave room for a call that waits on the user
+    let timeout = APPROVAL_TIMEOUT + Duration::from_secs(30);
+    let mut transport = HttpTransport::new(&settings.url(), &headers, timeout);
+
+    for line in input.lines() {
+        let line = line.map_err(|e| format!("Failed to read stdin: {}", e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let replies = match serde_json::from_str::<Value>(&line) {
+            Ok(message) if !settings.enabled => error_reply(
+                &message,
+                "The MCP server is turned off; enable it in LocalWork Hero's settings",
+            ),
+            Ok(message) => match transport.post(&message, message.get("id")) {
+                Ok(replies) => replies,
+                Err(e) => error_reply(
+                    &message,
+                    &format!(
+                        "LocalWork Hero is not reachable ({}); is the app running?",
+                        e
+                    ),
+                ),
+            },
+            Err(e) => vec![json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "error": { "code": -32700, "message": format!("Parse error: {}", e) }
+            })],
+        };
+        for reply in replies {
+            writeln!(output, "{}", reply)
+                .and_then(|_| output.flush())
+                .map_err(|e| format!("Failed to write stdout: {}", e))?;
+        }
+    }
+    Ok(())
+}
+
+/// An error response for a request; notifications get none
+fn error_reply(message: &Value, error: &str) -> Vec<Value> {
+    match message.get("id") {
+        Some(id) if message.get("method").is_some() => vec![json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": -32000, "message": error }
+        })],
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inference::tools::ToolCall;
+    use crate::mcp::server::{McpServer, ToolHost};
+    use std::net::{Ipv4Addr, TcpListener};
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    struct EchoHost;
+
+    impl ToolHost for EchoHost {
+        fn call_tool(&self, tool_call: &ToolCall, _session: &str) -> String {
+            format!("ran {}", tool_call.name)
+        }
+
+        fn end_session(&self, _session: &str) {}
+    }
+
+    fn bridge(data_dir: &Path, input: &str) -> Vec<Value> {
+        let mut output = Vec::new();
+        run_stdio(data_dir, input.as_bytes(), &mut output).unwrap();
+        String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_bridge_relays_to_app() {
+        let dir = tempdir().unwrap();
+        let input = concat!(
+            r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#,
+            "\n",
+            r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#,
+            "\n",
+            r#"{"jsonrpc":"2.0","id":2,"method":"tools/call","params":{"name":"list_files","arguments":{"path":"/"}}}"#,
+            "\n",
+            "not json\n"
+        );
+
+        let off = bridge(dir.path(), input);
+        assert_eq!(off.len(), 3);
+        assert!(off[0]["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("turned off"));
+
+        let server = McpServer::load(dir.path().join(SETTINGS_FILE));
+        let port = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        server.set_settings(true, port, Arc::new(EchoHost)).unwrap();
+        let on = bridge(dir.path(), input);
+        assert_eq!(on.len(), 3);
+        assert_eq!(on[0]["result"]["serverInfo"]["name"], "localwork-hero");
+        assert_eq!(on[1]["result"]["content"][0]["text"], "ran list_files");
+        assert_eq!(on[2]["error"]["code"], -32700);
+        server.stop();
+    }
+}
@@ -0,0 +1,283 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use super::config::McpTransportConfig;
+use super::transport::Transport;
+
+/// The MCP revision this client speaks
+pub const PROTOCOL_VERSION: &str = "2025-03-26";
+
+/// Prefix of the names external tools are offered to the model under
+pub const MCP_TOOL_PREFIX: &str = "mcp__";
+
+/// Pages of `tools/list` followed before giving up on a server
+const MAX_TOOL_PAGES: usize = 20;
+
+/// A tool published by an MCP server
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct McpTool {
+    pub server: String,
+    pub name: String,
+    pub description: String,
+    pub input_schema: Value,
+}
+
+impl McpTool {
+    /// The name the model calls the tool by, `mcp__{server}__{tool}`
+    pub fn qualified_name(&self) -> String {
+        format!("{}{}__{}", MCP_TOOL_PREFIX, self.server, self.name)
+    }
+}
+
+/// Server and tool name from a qualified tool name
+pub fn split_tool_name(name: &str) -> Option<(&str, &str)> {
+    name.strip_prefix(MCP_TOOL_PREFIX)?
+        .split_once("__")
+        .filter(|(server, tool)| !server.is_empty() && !tool.is_empty())
+}
+
+/// A `tools/call` result flattened to text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct McpCallResult {
+    pub text: String,
+    /// The tool ran but reported a failure
+    pub is_error: bool,
+}
+
+/// An initialized session with one MCP server
+pub struct McpClient {
+    server: String,
+    transport: Transport,
+    next_id: u64,
+}
+
+impl McpClient {
+    /// Start or reach the server and complete the initialize handshake
+    pub fn connect(server: &str, config: &McpTransportConfig) -> Result<Self, String> {
+        let mut client = Self {
+            server: server.to_string(),
+            transport: Transport::connect(server, config)?,
+            next_id: 1,
+        };
+        let result = client.request(
+            "initialize",
+            json!({
+                "protocolVersion": PROTOCOL_VERSION,
+                "capabilities": {},
+                "clientInfo": {
+                    "name": "localwork-hero",
+                    "version": env!("CARGO_PKG_VERSION")
+                }
+            }),
+        )?;
+        if let Some(version) = result.get("protocolVersion").and_then(|v| v.as_str()) {
+            if version != PROTOCOL_VERSION {
+                log::info!(
+                    "MCP server '{}' uses protocol {}, we asked for {}",
+                    server,
+                    version,
+                    PROTOCOL_VERSION
+                );
+            }
+        }
+        client.transport.notify(&json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/initialized"
+        }))?;
+        Ok(client)
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> Result<Value, String> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.transport.request(&json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params
+        }))
+    }
+
+    /// Every tool the server publishes, following pagination
+    pub fn list_tools(&mut self) -> Result<Vec<McpTool>, String> {
+        let mut tools = Vec::new();
+        let mut cursor: Option<String> = None;
+        for _ in 0..MAX_TOOL_PAGES {
+            let params = match cursor {
+                Some(ref cursor) => json!({ "cursor": cursor }),
+                None => json!({}),
+            };
+            let result = self.request("tools/list", params)?;
+            let page = result
+                .get("tools")
+                .and_then(|t| t.as_array())
+                .ok_or("MCP server sent a tool list without tools")?;
+            for tool in page {
+                let Some(name) = tool.get("name").and_then(|n| n.as_str()) else {
+                    continue;
+                };
+                tools.push(McpTool {
+                    server: self.server.clone(),
+                    name: name.to_string(),
+                    description: tool
+                        .get("description")
+                        .and_then(|d| d.as_str())
+                        .unwrap_or_default()
+                        .to_string(),
+                    input_schema: tool
+                        .get("inputSchema")
+                        .cloned()
+                        .unwrap_or_else(|| json!({ "type": "object" })),
+                });
+            }
+            cursor = result
+                .get("nextCursor")
+                .and_then(|c| c.as_str())
+                .map(|c| c.to_string());
+            if cursor.is_none() {
+                break;
+            }
+        }
+        Ok(tools)
+    }
+
+    pub fn call_tool(&mut self, name: &str, arguments: &Value) -> Result<McpCallResult, String> {
+        let arguments = match arguments {
+            Value::Null => json!({}),
+            arguments => arguments.clone(),
+        };
+        let result = self.request(
+            "tools/call",
+            json!({ "name": name, "arguments": arguments }),
+        )?;
+        Ok(McpCallResult {
+            text: content_text(&result),
+            is_error: result
+                .get("isError")
+                .and_then(|e| e.as_bool())
+                .unwrap_or(false),
+        })
+    }
+}
+
+/// Text of a tool result's content blocks, with placeholders for media
+fn content_text(result: &Value) -> String {
+    let blocks = result
+        .get("content")
+        .and_then(|c| c.as_array())
+        .cloned()
+        .unwrap_or_default();
+    let str_of = |value: &Value, key: &str| {
+        value
+            .get(key)
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string()
+    };
+    let parts: Vec<String> = blocks
+        .iter()
+        .map(|block| match block.get("type").and_then(|t| t.as_str()) {
+            Some("text") => str_of(block, "text"),
+            Some("image") => format!("[Image: {}]", str_of(block, "mimeType")),
+            Some("audio") => format!("[Audio: {}]", str_of(block, "mimeType")),
+            Some("resource") => {
+                let resource = block.get("resource").cloned().unwrap_or_default();
+                match resource.get("text").and_then(|t| t.as_str()) {
+                    Some(text) => text.to_string(),
+                    None => format!("[Resource: {}]", str_of(&resource, "uri")),
+                }
+            }
+            Some("resource_link") => format!("[Resource: {}]", str_of(block, "uri")),
+            _ => block.to_string(),
+        })
+        .collect();
+    if parts.is_empty() {
+        if let Some(structured) = result.get("structuredContent") {
+            return structured.to_string();
+        }
+    }
+    parts.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::stub;
+
+    #[test]
+    fn test_split_tool_name() {
+        assert_eq!(
+            split_tool_name("mcp__git__git_status"),
+            Some(("git", "git_status"))
+        );
+        assert_eq!(split_tool_name("mcp__git__"), None);
+        assert_eq!(split_tool_name("read_file"), None);
+    }
+
+    #[test]
+    fn test_content_text() {
+        let result = json!({"content": [
+            {"type": "text", "text": "Two files changed"},
+            {"type": "image", "data": "iVBOR", "mimeType": "image/png"},
+            {"type": "resource", "resource": {"uri": "file:///a.txt", "text": "hello"}},
+            {"type": "resource_link", "uri": "file:///b.txt", "name": "b"}
+        ]});
+        assert_eq!(
+            content_text(&result),
+            "Two files changed\n[Image: image/png]\nhello\n[Resource: file:///b.txt]"
+        );
+        assert_eq!(
+            content_text(&json!({"content": [], "structuredContent": {"n": 1}})),
+            r#"{"n":1}"#
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_stdio_client() {
+        let mut client = McpClient::connect("stub", &stub::stdio()).unwrap();
+        let tools = client.list_tools().unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].qualified_name(), "mcp__stub__echo");
+        assert_eq!(
+            tools[0].input_schema["properties"]["text"]["type"],
+            "string"
+        );
+
+        let result = client.call_tool("echo", &json!({"text": "hi"})).unwrap();
+        assert_eq!(
+            result,
+            McpCallResult {
+                text: "echo: hi".to_string(),
+                is_error: false
+            }
+        );
+        let failed = client.call_tool("fail", &Value::Null).unwrap();
+        assert!(failed.is_error);
+    }
+
+    #[test]
+    fn test_http_client() {
+        let server = stub::http();
+        let mut client = McpClient::connect("stub", &server.config).unwrap();
+        let tools = client.list_tools().unwrap();
+        assert_eq!(tools[0].name, "echo");
+        let result = client.call_tool("echo", &json!({"text": "hi"})).unwrap();
+        assert_eq!(result.text, "echo: hi");
+
+        let methods = server.methods.lock().unwrap().clone();
+        assert_eq!(
+            methods,
+            vec![
+                ("initialize".to_string(), None),
+                (
+                    "notifications/initialized".to_string(),
+                    Some("session-1".to_string())
+                ),
+                ("tools/list".to_string(), Some("session-1".to_string())),
+                ("reply".to_string(), Some("session-1".to_string())),
+                ("tools/call".to_string(), Some("session-1".to_string())),
+            ]
+        );
+    }
+}
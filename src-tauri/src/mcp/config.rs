@@ -0,0 +1,132 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::terminal::PolicyAction;
+
+/// An external MCP server whose tools the agent can call
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct McpServerConfig {
+    /// Letters, digits and `-`; the server's tools are offered as `mcp__{name}__{tool}`
+    pub name: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    pub transport: McpTransportConfig,
+    /// What happens when the agent calls one of the server's tools
+    #[serde(default = "default_approval")]
+    pub approval: PolicyAction,
+    /// Per-tool overrides of `approval`, keyed by the server's tool name
+    #[serde(default)]
+    pub tool_rules: BTreeMap<String, PolicyAction>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum McpTransportConfig {
+    /// A local program speaking JSON-RPC over its stdin and stdout
+    Stdio {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+        /// Added to the app's environment
+        #[serde(default)]
+        env: BTreeMap<String, String>,
+        #[serde(default)]
+        cwd: Option<String>,
+    },
+    /// A streamable HTTP endpoint
+    Http {
+        url: String,
+        /// Sent with every request, e.g. `Authorization`
+        #[serde(default)]
+        headers: BTreeMap<String, String>,
+    },
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_approval() -> PolicyAction {
+    PolicyAction::Ask
+}
+
+impl McpServerConfig {
+    /// The approval rule for one of the server's tools
+    pub fn action_for(&self, tool: &str) -> PolicyAction {
+        self.tool_rules.get(tool).copied().unwrap_or(self.approval)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let valid_name = !self.name.is_empty()
+            && self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-');
+        if !valid_name {
+            return Err(format!(
+                "Invalid MCP server name '{}': use letters, digits and '-'",
+                self.name
+            ));
+        }
+        match self.transport {
+            McpTransportConfig::Stdio { ref command, .. } if command.trim().is_empty() => {
+                Err(format!("MCP server '{}' needs a command to run", self.name))
+            }
+            McpTransportConfig::Http { ref url, .. } => match url::Url::parse(url) {
+                Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => Ok(()),
+                _ => Err(format!(
+                    "MCP server '{}' needs an http or https URL: {}",
+                    self.name, url
+                )),
+            },
+            McpTransportConfig::Stdio { .. } => Ok(()),
+        }
+    }
+}
+
+/// Check every server and that no two share a name
+pub fn validate_servers(servers: &[McpServerConfig]) -> Result<(), String> {
+    for (i, server) in servers.iter().enumerate() {
+        server.validate()?;
+        if servers[..i].iter().any(|s| s.name == server.name) {
+            return Err(format!("Duplicate MCP server name '{}'", server.name));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_config() {
+        let server: McpServerConfig = serde_json::from_str(
+            r#"{"name": "git", "transport": {"type": "stdio", "command": "mcp-git"},
+                "tool_rules": {"git_status": "allow", "git_push": "deny"}}"#,
+        )
+        .unwrap();
+        assert!(server.enabled);
+        assert_eq!(server.action_for("git_status"), PolicyAction::Allow);
+        assert_eq!(server.action_for("git_push"), PolicyAction::Deny);
+        assert_eq!(server.action_for("git_commit"), PolicyAction::Ask);
+
+        let http = McpServerConfig {
+            name: "docs".to_string(),
+            transport: McpTransportConfig::Http {
+                url: "file:///tmp/socket".to_string(),
+                headers: BTreeMap::new(),
+            },
+            ..server.clone()
+        };
+        assert!(http.validate().unwrap_err().contains("http or https"));
+        let spaced = McpServerConfig {
+            name: "my server".to_string(),
+            ..server.clone()
+        };
+        assert!(spaced.validate().is_err());
+        assert!(validate_servers(&[server.clone(), server])
+            .unwrap_err()
+            .starts_with("Duplicate"));
+    }
+}
@@ -0,0 +1,312 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use super::client::{McpCallResult, McpClient, McpTool};
+use super::config::{validate_servers, McpServerConfig};
+use crate::terminal::PolicyAction;
+
+/// How a configured server is doing
+#[derive(Debug, Clone, Serialize)]
+pub struct McpServerStatus {
+    pub name: String,
+    pub enabled: bool,
+    pub connected: bool,
+    /// Why the last connection attempt failed
+    pub error: Option<String>,
+    pub tools: Vec<String>,
+}
+
+struct Connection {
+    client: Option<Arc<Mutex<McpClient>>>,
+    tools: Vec<McpTool>,
+    error: Option<String>,
+}
+
+/// The user's MCP servers, saved as JSON, and the live connections to them
+pub struct McpManager {
+    path: PathBuf,
+    servers: Mutex<Vec<McpServerConfig>>,
+    connections: Mutex<HashMap<String, Connection>>,
+}
+
+impl McpManager {
+    pub fn load(path: PathBuf) -> Self {
+        let servers = match fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str::<Vec<McpServerConfig>>(&text).unwrap_or_else(|e| {
+                log::warn!("Failed to parse MCP servers: {}", e);
+                Vec::new()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => {
+                log::warn!("Failed to read MCP servers: {}", e);
+                Vec::new()
+            }
+        };
+        Self {
+            path,
+            servers: Mutex::new(servers),
+            connections: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn servers(&self) -> Vec<McpServerConfig> {
+        self.servers.lock().map(|s| s.clone()).unwrap_or_default()
+    }
+
+    /// Replace the server list; servers that were removed or changed are disconnected
+    pub fn set_servers(&self, servers: Vec<McpServerConfig>) -> Result<(), String> {
+        validate_servers(&servers)?;
+        let mut current = self.servers.lock().map_err(|e| e.to_string())?;
+        let json = serde_json::to_string_pretty(&servers)
+            .map_err(|e| format!("Failed to serialize MCP servers: {}", e))?;
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create MCP config directory: {}", e))?;
+        }
+        fs::write(&self.path, json).map_err(|e| format!("Failed to save MCP servers: {}", e))?;
+        let changed: Vec<String> = current
+            .iter()
+            .filter(|old| !servers.contains(old))
+            .map(|old| old.name.clone())
+            .collect();
+        *current = servers;
+        drop(current);
+        if let Ok(mut connections) = self.connections.lock() {
+            for name in changed {
+                connections.remove(&name);
+            }
+        }
+        Ok(())
+    }
+
+    /// Connect every enabled server that isn't connected yet
+    pub fn connect_all(&self) {
+        let pending: Vec<String> = self
+            .servers()
+            .into_iter()
+            .filter(|s| s.enabled)
+            .map(|s| s.name)
+            .filter(|name| {
+                self.connections
+                    .lock()
+                    .is_ok_and(|c| c.get(name).is_none_or(|c| c.client.is_none()))
+            })
+            .collect();
+        for name in pending {
+            if let Err(e) = self.connect(&name) {
+                log::warn!("{}", e);
+            }
+        }
+    }
+
+    /// (Re)connect a server and discover its tools
+    pub fn connect(&self, name: &str) -> Result<McpServerStatus, String> {
+        let server = self
+            .servers()
+            .into_iter()
+            .find(|s| s.name == name)
+            .ok_or_else(|| format!("No MCP server named '{}'", name))?;
+        if let Ok(mut connections) = self.connections.lock() {
+            connections.remove(name);
+        }
+        if !server.enabled {
+            return Err(format!("MCP server '{}' is disabled", name));
+        }
+
+        let connected = McpClient::connect(&server.name, &server.transport)
+            .and_then(|mut client| client.list_tools().map(|tools| (client, tools)));
+        let connection = match connected {
+            Ok((client, tools)) => {
+                log::info!("Connected MCP server '{}' with {} tools", name, tools.len());
+                Connection {
+                    client: Some(Arc::new(Mutex::new(client))),
+                    tools,
+                    error: None,
+                }
+            }
+            Err(e) => Connection {
+                client: None,
+                tools: Vec::new(),
+                error: Some(e),
+            },
+        };
+        let error = connection.error.clone();
+        self.connections
+            .lock()
+            .map_err(|e| e.to_string())?
+            .insert(name.to_string(), connection);
+        match error {
+            Some(e) => Err(format!("Failed to connect MCP server '{}': {}", name, e)),
+            None => self
+                .status()
+                .into_iter()
+                .find(|s| s.name == name)
+                .ok_or_else(|| format!("No MCP server named '{}'", name)),
+        }
+    }
+
+    /// Close every connection, stopping stdio servers
+    pub fn disconnect_all(&self) {
+        if let Ok(mut connections) = self.connections.lock() {
+            connections.clear();
+        }
+    }
+
+    pub fn status(&self) -> Vec<McpServerStatus> {
+        let connections = self.connections.lock();
+        self.servers()
+            .into_iter()
+            .map(|server| {
+                let connection = connections.as_ref().ok().and_then(|c| c.get(&server.name));
+                McpServerStatus {
+                    enabled: server.enabled,
+                    connected: connection.is_some_and(|c| c.client.is_some()),
+                    error: connection.and_then(|c| c.error.clone()),
+                    tools: connection
+                        .map(|c| c.tools.iter().map(|t| t.name.clone()).collect())
+                        .unwrap_or_default(),
+                    name: server.name,
+                }
+            })
+            .collect()
+    }
+
+    /// Tools of connected servers, leaving out those the server's rules deny
+    pub fn tools(&self) -> Vec<McpTool> {
+        let servers = self.servers();
+        let Ok(connections) = self.connections.lock() else {
+            return Vec::new();
+        };
+        servers
+            .iter()
+            .filter_map(|server| Some((server, connections.get(&server.name)?)))
+            .flat_map(|(server, connection)| {
+                connection
+                    .tools
+                    .iter()
+                    .filter(|tool| server.action_for(&tool.name) != PolicyAction::Deny)
+                    .cloned()
+            })
+            .collect()
+    }
+
+    /// The approval rule for a server's tool; unknown servers are denied
+    pub fn action_for(&self, server: &str, tool: &str) -> PolicyAction {
+        self.servers()
+            .iter()
+            .find(|s| s.name == server)
+            .map(|s| s.action_for(tool))
+            .unwrap_or(PolicyAction::Deny)
+    }
+
+    pub fn call(
+        &self,
+        server: &str,
+        tool: &str,
+        arguments: &Value,
+    ) -> Result<McpCallResult, String> {
+        let client = {
+            let connections = self.connections.lock().map_err(|e| e.to_string())?;
+            let connection = connections
+                .get(server)
+                .filter(|c| c.tools.iter().any(|t| t.name == tool))
+                .ok_or_else(|| format!("MCP server '{}' has no tool '{}'", server, tool))?;
+            connection
+                .client
+                .clone()
+                .ok_or_else(|| format!("MCP server '{}' is not connected", server))?
+        };
+        // Only this server's calls wait on each other
+        let mut client = client.lock().map_err(|e| e.to_string())?;
+        client.call_tool(tool, arguments)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::stub;
+    use std::collections::BTreeMap;
+    use tempfile::tempdir;
+
+    fn server(name: &str) -> McpServerConfig {
+        McpServerConfig {
+            name: name.to_string(),
+            enabled: true,
+            transport: stub::stdio(),
+            approval: PolicyAction::Ask,
+            tool_rules: BTreeMap::new(),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_manager_connects_and_calls() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("mcp-servers.json");
+        let manager = McpManager::load(path.clone());
+        let broken = McpServerConfig {
+            transport: crate::mcp::McpTransportConfig::Stdio {
+                command: "/nonexistent/mcp-server".to_string(),
+                args: Vec::new(),
+                env: BTreeMap::new(),
+                cwd: None,
+            },
+            ..server("broken")
+        };
+        manager
+            .set_servers(vec![
+                server("stub"),
+                broken,
+                McpServerConfig {
+                    enabled: false,
+                    ..server("off")
+                },
+            ])
+            .unwrap();
+        manager.connect_all();
+
+        let status = manager.status();
+        assert!(status[0].connected);
+        assert_eq!(status[0].tools, vec!["echo".to_string()]);
+        assert!(!status[1].connected);
+        assert!(status[1]
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("Failed to start"));
+        assert!(!status[2].connected && status[2].error.is_none());
+
+        let result = manager.call("stub", "echo", &serde_json::json!({"text": "hi"}));
+        assert_eq!(result.unwrap().text, "echo: hi");
+        assert!(manager.call("stub", "missing", &Value::Null).is_err());
+        assert_eq!(McpManager::load(path).servers().len(), 3);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_rules_filter_tools() {
+        let dir = tempdir().unwrap();
+        let manager = McpManager::load(dir.path().join("mcp-servers.json"));
+        manager.set_servers(vec![server("stub")]).unwrap();
+        manager.connect("stub").unwrap();
+        assert_eq!(manager.tools().len(), 1);
+        assert_eq!(manager.action_for("stub", "echo"), PolicyAction::Ask);
+        assert_eq!(manager.action_for("other", "echo"), PolicyAction::Deny);
+
+        let mut denied = server("stub");
+        denied
+            .tool_rules
+            .insert("echo".to_string(), PolicyAction::Deny);
+        manager.set_servers(vec![denied]).unwrap();
+        // Changing a server drops its connection until it is reconnected
+        assert!(!manager.status()[0].connected);
+        manager.connect("stub").unwrap();
+        assert!(manager.tools().is_empty());
+        assert_eq!(manager.action_for("stub", "echo"), PolicyAction::Deny);
+    }
+}
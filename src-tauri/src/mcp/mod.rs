@@ -0,0 +1,13 @@
+pub mod bridge;
+pub mod client;
+pub mod config;
+pub mod manager;
+pub mod server;
+#[cfg(test)]
+pub(crate) mod stub;
+pub mod transport;
+
+pub use client::{split_tool_name, McpCallResult, McpTool, MCP_TOOL_PREFIX};
+pub use config::{McpServerConfig, McpTransportConfig};
+pub use manager::{McpManager, McpServerStatus};
+pub use server::{McpServer, McpServerSettings, ToolHost};
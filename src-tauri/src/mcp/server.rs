@@ -0,0 +1,715 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use uuid::Uuid;
+
+use super::client::PROTOCOL_VERSION;
+use crate::inference::tools::{get_file_tools, ToolCall, ToolDefinition};
+
+/// Port the HTTP endpoint listens on unless the user picks another
+pub const DEFAULT_PORT: u16 = 47321;
+
+/// Settings file in the app data folder, also read by the stdio bridge
+pub const SETTINGS_FILE: &str = "mcp-server.json";
+
+/// Largest request body accepted
+const MAX_REQUEST_BYTES: usize = 4 * 1024 * 1024;
+
+/// How long a client may take to send its request
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+const SESSION_HEADER: &str = "Mcp-Session-Id";
+
+/// Tools offered to MCP clients: file operations only, so commands, processes and web access
+/// stay with the app's own agent
+const PUBLISHED_TOOLS: &[&str] = &[
+    "list_files",
+    "read_file",
+    "read_document",
+    "query_data",
+    "write_file",
+    "edit_file",
+    "create_file",
+    "delete_file",
+    "move_file",
+    "create_directory",
+    "copy_file",
+    "copy_directory",
+    "remove_empty_directory",
+    "append_to_file",
+    "batch_move",
+    "stat_file",
+    "find_duplicates",
+    "list_archive",
+    "extract_archive",
+    "create_archive",
+    "search_files",
+];
+
+/// Whether the app serves its tools over MCP, and how clients reach it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct McpServerSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    /// Clients send it as `Authorization: Bearer {token}`
+    #[serde(default)]
+    pub token: String,
+}
+
+fn default_port() -> u16 {
+    DEFAULT_PORT
+}
+
+impl McpServerSettings {
+    /// Saved settings, with a fresh token when none was saved yet
+    pub fn load(path: &Path) -> Self {
+        let mut settings = match fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str::<Self>(&text).unwrap_or_else(|e| {
+                log::warn!("Failed to parse MCP server settings: {}", e);
+                Self::default()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => {
+                log::warn!("Failed to read MCP server settings: {}", e);
+                Self::default()
+            }
+        };
+        if settings.token.is_empty() {
+            settings.token = Uuid::new_v4().simple().to_string();
+        }
+        settings
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}:{}/mcp", Ipv4Addr::LOCALHOST, self.port)
+    }
+}
+
+impl Default for McpServerSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: DEFAULT_PORT,
+            token: String::new(),
+        }
+    }
+}
+
+/// Runs tools for MCP clients; the app runs them like the agent's own calls
+pub trait ToolHost: Send + Sync + 'static {
+    /// Execute a tool call on behalf of a session, returning its result text
+    fn call_tool(&self, tool_call: &ToolCall, session: &str) -> String;
+    /// Forget what the session started, such as processes and pending approvals
+    fn end_session(&self, session: &str);
+}
+
+/// Answer one JSON-RPC message from a session; `None` for notifications and responses
+pub fn handle_message(host: &dyn ToolHost, session: &str, message: &Value) -> Option<Value> {
+    let id = message.get("id")?.clone();
+    let method = message.get("method")?.as_str()?;
+    let params = message.get("params").cloned().unwrap_or(Value::Null);
+    let result = match method {
+        "initialize" => Ok(json!({
+            "protocolVersion": PROTOCOL_VERSION,
+            "capabilities": { "tools": { "listChanged": false } },
+            "serverInfo": { "name": "localwork-hero", "version": env!("CARGO_PKG_VERSION") },
+            "instructions": "File tools limited to the folders granted in LocalWork Hero. Some calls wait for the user to approve them in the app."
+        })),
+        "ping" => Ok(json!({})),
+        "tools/list" => Ok(json!({ "tools": list_tools() })),
+        "tools/call" => call_tool(host, session, &params),
+        _ => Err((-32601, format!("Method not found: {}", method))),
+    };
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, message)) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": code, "message": message }
+        }),
+    })
+}
+
+fn published_tools() -> Vec<ToolDefinition> {
+    get_file_tools()
+        .into_iter()
+        .filter(|tool| PUBLISHED_TOOLS.contains(&tool.name))
+        .collect()
+}
+
+fn list_tools() -> Vec<Value> {
+    published_tools()
+        .into_iter()
+        .map(|tool| {
+            json!({
+                "name": tool.name,
+                "description": tool.description,
+                "inputSchema": tool.parameters
+            })
+        })
+        .collect()
+}
+
+fn call_tool(host: &dyn ToolHost, session: &str, params: &Value) -> Result<Value, (i64, String)> {
+    let name = params
+        .get("name")
+        .and_then(|n| n.as_str())
+        .ok_or((-32602, "Missing tool name".to_string()))?;
+    if !PUBLISHED_TOOLS.contains(&name) {
+        return Err((-32602, format!("Unknown tool: {}", name)));
+    }
+    let tool_call = ToolCall {
+        id: format!("mcp_{}", Uuid::new_v4().simple()),
+        name: name.to_string(),
+        arguments: params.get("arguments").cloned().unwrap_or(json!({})),
+        result: None,
+    };
+    let text = host.call_tool(&tool_call, session);
+    Ok(json!({
+        "content": [{ "type": "text", "text": text }],
+        "isError": text.starts_with("Error:")
+    }))
+}
+
+struct Endpoint {
+    token: String,
+    host: Arc<dyn ToolHost>,
+    sessions: Mutex<HashSet<String>>,
+}
+
+struct Running {
+    port: u16,
+    stop: Arc<AtomicBool>,
+    endpoint: Arc<Endpoint>,
+    /// Owns the listener, so joining it frees the port
+    accept: JoinHandle<()>,
+}
+
+/// The opt-in localhost HTTP endpoint publishing the app's tools over MCP
+pub struct McpServer {
+    path: PathBuf,
+    settings: Mutex<McpServerSettings>,
+    running: Mutex<Option<Running>>,
+}
+
+impl McpServer {
+    pub fn load(path: PathBuf) -> Self {
+        let settings = McpServerSettings::load(&path);
+        Self {
+            path,
+            settings: Mutex::new(settings),
+            running: Mutex::new(None),
+        }
+    }
+
+    pub fn settings(&self) -> McpServerSettings {
+        self.settings.lock().map(|s| s.clone()).unwrap_or_default()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.lock().is_ok_and(|r| r.is_some())
+    }
+
+    /// Start or stop the endpoint to match the new settings, saving them only once that worked;
+    /// on failure the previous settings stay in effect
+    pub fn set_settings(
+        &self,
+        enabled: bool,
+        port: u16,
+        host: Arc<dyn ToolHost>,
+    ) -> Result<McpServerSettings, String> {
+        if port == 0 {
+            return Err("Choose a port for the MCP server".to_string());
+        }
+        let previous = self.settings();
+        let updated = McpServerSettings {
+            enabled,
+            port,
+            token: previous.token.clone(),
+        };
+        self.stop();
+        let applied = self
+            .listen(&updated, host.clone())
+            .and_then(|()| self.save(&updated));
+        if let Err(e) = applied {
+            self.stop();
+            if let Err(restart) = self.listen(&previous, host) {
+                log::warn!("{}", restart);
+            }
+            return Err(e);
+        }
+        *self.settings.lock().map_err(|e| e.to_string())? = updated.clone();
+        Ok(updated)
+    }
+
+    /// Save through a temp file only the user can read, since the settings hold the token,
+    /// and rename it into place so a crash never leaves half a file
+    fn save(&self, settings: &McpServerSettings) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(settings)
+            .map_err(|e| format!("Failed to serialize MCP server settings: {}", e))?;
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create MCP config directory: {}", e))?;
+        }
+        let tmp = self
+            .path
+            .with_extension(format!("{}.tmp", Uuid::new_v4().simple()));
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let result = options.open(&tmp).and_then(|mut file| {
+            file.write_all(json.as_bytes())?;
+            file.sync_all()?;
+            fs::rename(&tmp, &self.path)
+        });
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp);
+        }
+        result.map_err(|e| format!("Failed to save MCP server settings: {}", e))
+    }
+
+    /// Listen on localhost if the endpoint is enabled
+    pub fn start(&self, host: Arc<dyn ToolHost>) -> Result<(), String> {
+        self.listen(&self.settings(), host)
+    }
+
+    fn listen(&self, settings: &McpServerSettings, host: Arc<dyn ToolHost>) -> Result<(), String> {
+        let mut running = self.running.lock().map_err(|e| e.to_string())?;
+        if !settings.enabled || running.is_some() {
+            return Ok(());
+        }
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, settings.port))
+            .map_err(|e| format!("Failed to listen on port {}: {}", settings.port, e))?;
+        let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+        let endpoint = Arc::new(Endpoint {
+            token: settings.token.clone(),
+            host,
+            sessions: Mutex::new(HashSet::new()),
+        });
+        let stop = Arc::new(AtomicBool::new(false));
+        let (accepting, stopped) = (endpoint.clone(), stop.clone());
+        let accept = std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                if stopped.load(Ordering::SeqCst) {
+                    break;
+                }
+                let Ok(stream) = stream else { continue };
+                let endpoint = accepting.clone();
+                // A call can wait minutes for approval, so each connection gets its own thread
+                std::thread::spawn(move || serve_connection(&endpoint, stream));
+            }
+        });
+        log::info!("MCP server listening on {}", settings.port);
+        *running = Some(Running {
+            port,
+            stop,
+            endpoint,
+            accept,
+        });
+        Ok(())
+    }
+
+    /// Stop listening and end every session
+    pub fn stop(&self) {
+        let Some(running) = self.running.lock().ok().and_then(|mut r| r.take()) else {
+            return;
+        };
+        running.stop.store(true, Ordering::SeqCst);
+        // Wake the accept loop so it sees the flag, then wait for it to drop the listener
+        if TcpStream::connect((Ipv4Addr::LOCALHOST, running.port)).is_ok() {
+            let _ = running.accept.join();
+        }
+        let sessions: Vec<String> = running
+            .endpoint
+            .sessions
+            .lock()
+            .map(|mut s| s.drain().collect())
+            .unwrap_or_default();
+        for session in sessions {
+            running.endpoint.host.end_session(&session);
+        }
+    }
+}
+
+struct HttpRequest {
+    method: String,
+    path: String,
+    /// Header names lowercased
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+struct HttpResponse {
+    status: &'static str,
+    headers: Vec<(&'static str, String)>,
+    body: Option<Value>,
+}
+
+impl HttpResponse {
+    fn status(status: &'static str) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: None,
+        }
+    }
+
+    fn json(body: Value) -> Self {
+        Self {
+            status: "200 OK",
+            headers: Vec::new(),
+            body: Some(body),
+        }
+    }
+}
+
+fn serve_connection(endpoint: &Endpoint, mut stream: TcpStream) {
+    let _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+    let response = match read_request(&stream) {
+        Ok(request) => respond(endpoint, request),
+        Err(status) => HttpResponse::status(status),
+    };
+    let body = response.body.map(|b| b.to_string()).unwrap_or_default();
+    let mut head = format!("HTTP/1.1 {}\r\n", response.status);
+    if !body.is_empty() {
+        head.push_str("Content-Type: application/json\r\n");
+    }
+    for (name, value) in response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str(&format!(
+        "Content-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    ));
+    let _ = stream
+        .write_all(head.as_bytes())
+        .and_then(|_| stream.write_all(body.as_bytes()));
+}
+
+fn read_request(stream: &TcpStream) -> Result<HttpRequest, &'static str> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).map_err(|_| "400 Bad Request")?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err("400 Bad Request");
+    };
+    let method = method.to_string();
+    let path = target.split('?').next().unwrap_or(target).to_string();
+
+    let mut headers = HashMap::new();
+    loop {
+        line.clear();
+        reader.read_line(&mut line).map_err(|_| "400 Bad Request")?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+    let length: usize = match headers.get("content-length") {
+        Some(length) => length.parse().map_err(|_| "400 Bad Request")?,
+        None => 0,
+    };
+    if length > MAX_REQUEST_BYTES {
+        return Err("413 Payload Too Large");
+    }
+    let mut body = vec![0; length];
+    reader
+        .read_exact(&mut body)
+        .map_err(|_| "400 Bad Request")?;
+    Ok(HttpRequest {
+        method,
+        path,
+        headers,
+        body,
+    })
+}
+
+/// Compare every byte, so response timing doesn't reveal how much of a token was right
+fn tokens_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn respond(endpoint: &Endpoint, request: HttpRequest) -> HttpResponse {
+    if request.path != "/mcp" {
+        return HttpResponse::status("404 Not Found");
+    }
+    // Browsers send an Origin; refusing them keeps web pages from reaching the tools
+    if request.headers.contains_key("origin") {
+        return HttpResponse::status("403 Forbidden");
+    }
+    let authorized = request
+        .headers
+        .get("authorization")
+        .and_then(|a| a.strip_prefix("Bearer "))
+        .is_some_and(|token| tokens_match(token, &endpoint.token));
+    if !authorized {
+        return HttpResponse::status("401 Unauthorized");
+    }
+    let session = request.headers.get(&SESSION_HEADER.to_ascii_lowercase());
+    let known = |session: &String| {
+        endpoint
+            .sessions
+            .lock()
+            .is_ok_and(|s| s.contains(session.as_str()))
+    };
+
+    match request.method.as_str() {
+        "POST" => {}
+        "DELETE" => {
+            return match session {
+                Some(session) if known(session) => {
+                    if let Ok(mut sessions) = endpoint.sessions.lock() {
+                        sessions.remove(session.as_str());
+                    }
+                    endpoint.host.end_session(session);
+                    HttpResponse::status("200 OK")
+                }
+                _ => HttpResponse::status("404 Not Found"),
+            };
+        }
+        _ => {
+            let mut response = HttpResponse::status("405 Method Not Allowed");
+            response.headers.push(("Allow", "POST, DELETE".to_string()));
+            return response;
+        }
+    }
+
+    let Ok(message) = serde_json::from_slice::<Value>(&request.body) else {
+        let mut response = HttpResponse::json(json!({
+            "jsonrpc": "2.0",
+            "id": null,
+            "error": { "code": -32700, "message": "Parse error" }
+        }));
+        response.status = "400 Bad Request";
+        return response;
+    };
+    let initializing = message.get("method").and_then(|m| m.as_str()) == Some("initialize");
+    let session = match session {
+        _ if initializing => {
+            let session = Uuid::new_v4().to_string();
+            if let Ok(mut sessions) = endpoint.sessions.lock() {
+                sessions.insert(session.clone());
+            }
+            session
+        }
+        Some(session) if known(session) => session.clone(),
+        Some(_) => return HttpResponse::status("404 Not Found"),
+        None => return HttpResponse::status("400 Bad Request"),
+    };
+
+    let replies: Vec<Value> = match message {
+        Value::Array(ref batch) => batch
+            .iter()
+            .filter_map(|m| handle_message(endpoint.host.as_ref(), &session, m))
+            .collect(),
+        ref message => handle_message(endpoint.host.as_ref(), &session, message)
+            .into_iter()
+            .collect(),
+    };
+    let mut response = match (message.is_array(), replies.len()) {
+        (_, 0) => HttpResponse::status("202 Accepted"),
+        (false, _) => HttpResponse::json(replies.into_iter().next().unwrap_or_default()),
+        (true, _) => HttpResponse::json(Value::Array(replies)),
+    };
+    if initializing {
+        response.headers.push((SESSION_HEADER, session));
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::client::McpClient;
+    use crate::mcp::McpTransportConfig;
+    use std::collections::BTreeMap;
+    use tempfile::tempdir;
+
+    #[derive(Default)]
+    struct RecordingHost {
+        calls: Mutex<Vec<(String, Value, String)>>,
+        ended: Mutex<Vec<String>>,
+    }
+
+    impl ToolHost for RecordingHost {
+        fn call_tool(&self, tool_call: &ToolCall, session: &str) -> String {
+            self.calls.lock().unwrap().push((
+                tool_call.name.clone(),
+                tool_call.arguments.clone(),
+                session.to_string(),
+            ));
+            match tool_call.arguments.get("path").and_then(|p| p.as_str()) {
+                Some("/granted/notes.txt") => "hello".to_string(),
+                _ => "Error: Access denied".to_string(),
+            }
+        }
+
+        fn end_session(&self, session: &str) {
+            self.ended.lock().unwrap().push(session.to_string());
+        }
+    }
+
+    fn client_config(settings: &McpServerSettings, token: &str) -> McpTransportConfig {
+        McpTransportConfig::Http {
+            url: settings.url(),
+            headers: BTreeMap::from([("Authorization".to_string(), format!("Bearer {}", token))]),
+        }
+    }
+
+    #[test]
+    fn test_handle_message() {
+        let host = RecordingHost::default();
+        let list = handle_message(&host, "s", &json!({"id": 1, "method": "tools/list"})).unwrap();
+        let tools = list["result"]["tools"].as_array().unwrap();
+        assert_eq!(tools.len(), PUBLISHED_TOOLS.len());
+        assert!(tools.iter().any(|t| t["name"] == "read_file"));
+        assert!(!tools.iter().any(|t| t["name"] == "run_command"));
+        assert_eq!(tools[0]["inputSchema"]["type"], "object");
+
+        let unknown = handle_message(
+            &host,
+            "s",
+            &json!({"id": 2, "method": "tools/call", "params": {"name": "mcp__x__y"}}),
+        )
+        .unwrap();
+        assert_eq!(unknown["error"]["code"], -32602);
+        let command = handle_message(
+            &host,
+            "s",
+            &json!({"id": 3, "method": "tools/call", "params": {"name": "run_command"}}),
+        )
+        .unwrap();
+        assert_eq!(command["error"]["code"], -32602);
+        assert!(host.calls.lock().unwrap().is_empty());
+        assert_eq!(
+            handle_message(&host, "s", &json!({"method": "notifications/initialized"})),
+            None
+        );
+    }
+
+    #[test]
+    fn test_serves_tools_over_http() {
+        let dir = tempdir().unwrap();
+        let server = McpServer::load(dir.path().join(SETTINGS_FILE));
+        let host = Arc::new(RecordingHost::default());
+        let port = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let settings = server.set_settings(true, port, host.clone()).unwrap();
+        assert!(server.is_running());
+        assert_eq!(
+            McpServerSettings::load(&dir.path().join(SETTINGS_FILE)).token,
+            settings.token
+        );
+
+        let mut client =
+            McpClient::connect("app", &client_config(&settings, &settings.token)).unwrap();
+        assert!(client
+            .list_tools()
+            .unwrap()
+            .iter()
+            .any(|t| t.name == "write_file"));
+        let read = client
+            .call_tool("read_file", &json!({"path": "/granted/notes.txt"}))
+            .unwrap();
+        assert_eq!(read.text, "hello");
+        assert!(!read.is_error);
+        let denied = client
+            .call_tool("read_file", &json!({"path": "/etc/passwd"}))
+            .unwrap();
+        assert!(denied.is_error);
+
+        let calls = host.calls.lock().unwrap().clone();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].0, "read_file");
+        drop(client);
+        assert_eq!(*host.ended.lock().unwrap(), vec![calls[0].2.clone()]);
+
+        assert!(
+            McpClient::connect("app", &client_config(&settings, "wrong"))
+                .err()
+                .unwrap()
+                .contains("HTTP 401")
+        );
+        let from_browser = ureq::post(&settings.url())
+            .set("Authorization", &format!("Bearer {}", settings.token))
+            .set("Origin", "https://example.com")
+            .send_string("{}");
+        assert!(matches!(from_browser, Err(ureq::Error::Status(403, _))));
+
+        // Saving again rebinds the same port
+        server.set_settings(true, port, host.clone()).unwrap();
+        assert!(McpClient::connect("app", &client_config(&settings, &settings.token)).is_ok());
+
+        server.set_settings(false, port, host).unwrap();
+        assert!(!server.is_running());
+        assert!(McpClient::connect("app", &client_config(&settings, &settings.token)).is_err());
+    }
+
+    #[test]
+    fn test_failed_start_keeps_settings() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(SETTINGS_FILE);
+        let server = McpServer::load(path.clone());
+        let taken = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let port = taken.local_addr().unwrap().port();
+
+        let err = server
+            .set_settings(true, port, Arc::new(RecordingHost::default()))
+            .unwrap_err();
+        assert!(err.contains("Failed to listen"));
+        assert!(!server.is_running());
+        assert!(!server.settings().enabled);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_settings_are_saved_privately() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(SETTINGS_FILE);
+        let server = McpServer::load(path.clone());
+        let token = server.settings().token;
+
+        server
+            .set_settings(false, 4100, Arc::new(RecordingHost::default()))
+            .unwrap();
+        let saved = McpServerSettings::load(&path);
+        assert_eq!((saved.port, saved.token), (4100, token));
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn test_tokens_match() {
+        assert!(tokens_match("abc123", "abc123"));
+        assert!(!tokens_match("abc124", "abc123"));
+        assert!(!tokens_match("abc", "abc123"));
+    }
+}
@@ -0,0 +1,143 @@
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+
+use super::config::McpTransportConfig;
+
+/// A shell script speaking just enough MCP over stdio: an `echo` tool, and `fail` which errors
+const STDIO_SERVER: &str = r#"
+while IFS= read -r line; do
+  id=$(printf '%s' "$line" | sed -n 's/.*"id":\([0-9]*\).*/\1/p')
+  case "$line" in
+    *'"method":"initialize"'*)
+      result='{"protocolVersion":"2025-03-26","capabilities":{"tools":{}},"serverInfo":{"name":"stub","version":"1"}}' ;;
+    *'"method":"tools/list"'*)
+      echo 'starting up'
+      printf '{"jsonrpc":"2.0","method":"notifications/message","params":{"level":"info","data":"listing"}}\n'
+      result='{"tools":[{"name":"echo","description":"Echo the text back","inputSchema":{"type":"object","properties":{"text":{"type":"string"}}}}]}' ;;
+    *'"name":"echo"'*)
+      text=$(printf '%s' "$line" | sed -n 's/.*"text":"\([^"]*\)".*/\1/p')
+      result="{\"content\":[{\"type\":\"text\",\"text\":\"echo: $text\"}]}" ;;
+    *'"method":"tools/call"'*)
+      result='{"content":[{"type":"text","text":"no such tool"}],"isError":true}' ;;
+    *) continue ;;
+  esac
+  printf '{"jsonrpc":"2.0","id":%s,"result":%s}\n' "$id" "$result"
+done
+"#;
+
+pub fn stdio() -> McpTransportConfig {
+    McpTransportConfig::Stdio {
+        command: "sh".to_string(),
+        args: vec!["-c".to_string(), STDIO_SERVER.to_string()],
+        env: BTreeMap::new(),
+        cwd: None,
+    }
+}
+
+/// Method of each message posted, or `reply` for responses, with the session header sent
+type Posted = Arc<Mutex<Vec<(String, Option<String>)>>>;
+
+/// A streamable HTTP MCP server with the same `echo` tool
+pub struct HttpStub {
+    pub config: McpTransportConfig,
+    pub methods: Posted,
+}
+
+/// Answers `tools/list` with an event stream that pings the client before responding
+pub fn http() -> HttpStub {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+    let methods = Arc::new(Mutex::new(Vec::new()));
+    let seen = methods.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { break };
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut session = None;
+            let mut length = 0;
+            let mut line = String::new();
+            while reader.read_line(&mut line).is_ok_and(|n| n > 2) {
+                if let Some((name, value)) = line.trim().split_once(':') {
+                    match name.to_ascii_lowercase().as_str() {
+                        "mcp-session-id" => session = Some(value.trim().to_string()),
+                        "content-length" => length = value.trim().parse().unwrap(),
+                        _ => {}
+                    }
+                }
+                line.clear();
+            }
+            if !request_line.starts_with("POST") {
+                let _ = stream.write_all(respond("200 OK", "", "", "").as_bytes());
+                continue;
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            let message: Value = serde_json::from_slice(&body).unwrap();
+            let method = message["method"].as_str().unwrap_or("reply").to_string();
+            seen.lock().unwrap().push((method.clone(), session));
+
+            let id = message["id"].clone();
+            let result = |result: Value| json!({"jsonrpc": "2.0", "id": id, "result": result});
+            let response = match method.as_str() {
+                "initialize" => {
+                    let body = result(json!({
+                        "protocolVersion": "2025-03-26",
+                        "capabilities": {"tools": {}},
+                        "serverInfo": {"name": "stub", "version": "1"}
+                    }));
+                    respond(
+                        "200 OK",
+                        "application/json",
+                        "Mcp-Session-Id: session-1\r\n",
+                        &body.to_string(),
+                    )
+                }
+                "tools/list" => {
+                    let ping = json!({"jsonrpc": "2.0", "id": "srv-1", "method": "ping"});
+                    let list = result(json!({"tools": [{
+                        "name": "echo",
+                        "inputSchema": {"type": "object"}
+                    }]}));
+                    let events = format!("data: {}\n\ndata: {}\n\n", ping, list);
+                    respond("200 OK", "text/event-stream", "", &events)
+                }
+                "tools/call" => {
+                    let text = format!("echo: {}", message["params"]["arguments"]["text"]);
+                    let body = result(json!({
+                        "content": [{"type": "text", "text": text.replace('"', "")}]
+                    }));
+                    respond("200 OK", "application/json", "", &body.to_string())
+                }
+                _ => respond("202 Accepted", "", "", ""),
+            };
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+    HttpStub {
+        config: McpTransportConfig::Http {
+            url,
+            headers: BTreeMap::new(),
+        },
+        methods,
+    }
+}
+
+fn respond(status: &str, content_type: &str, headers: &str, body: &str) -> String {
+    let content_type = match content_type {
+        "" => String::new(),
+        t => format!("Content-Type: {}\r\n", t),
+    };
+    format!(
+        "HTTP/1.1 {}\r\n{}{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        headers,
+        body.len(),
+        body
+    )
+}
@@ -0,0 +1,350 @@
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use super::config::McpTransportConfig;
+
+/// How long a request waits for the server's response
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Largest response body read from an HTTP server
+const MAX_RESPONSE_BYTES: u64 = 16 * 1024 * 1024;
+
+const SESSION_HEADER: &str = "Mcp-Session-Id";
+
+/// A JSON-RPC connection to one MCP server
+pub enum Transport {
+    Stdio(StdioTransport),
+    Http(HttpTransport),
+}
+
+impl Transport {
+    pub fn connect(server: &str, config: &McpTransportConfig) -> Result<Self, String> {
+        match config {
+            McpTransportConfig::Stdio {
+                command,
+                args,
+                env,
+                cwd,
+            } => {
+                let mut cmd = Command::new(command);
+                cmd.args(args).envs(env);
+                if let Some(cwd) = cwd {
+                    cmd.current_dir(cwd);
+                }
+                StdioTransport::spawn(server, cmd).map(Transport::Stdio)
+            }
+            McpTransportConfig::Http { url, headers } => Ok(Transport::Http(HttpTransport::new(
+                url,
+                headers,
+                REQUEST_TIMEOUT,
+            ))),
+        }
+    }
+
+    /// Send a request and wait for the response with its id, answering server requests meanwhile
+    pub fn request(&mut self, message: &Value) -> Result<Value, String> {
+        let id = message.get("id").cloned().unwrap_or(Value::Null);
+        match self {
+            Transport::Stdio(stdio) => stdio.request(message, &id),
+            Transport::Http(http) => http.request(message, &id),
+        }
+    }
+
+    pub fn notify(&mut self, message: &Value) -> Result<(), String> {
+        match self {
+            Transport::Stdio(stdio) => stdio.send(message),
+            Transport::Http(http) => http.post(message, None).map(|_| ()),
+        }
+    }
+}
+
+/// The result of a response, or its JSON-RPC error
+fn into_result(response: Value) -> Result<Value, String> {
+    if let Some(error) = response.get("error") {
+        let message = error
+            .get("message")
+            .and_then(|m| m.as_str())
+            .unwrap_or("unknown error");
+        return Err(match error.get("code") {
+            Some(code) => format!("MCP error {}: {}", code, message),
+            None => format!("MCP error: {}", message),
+        });
+    }
+    Ok(response.get("result").cloned().unwrap_or(Value::Null))
+}
+
+fn is_response_to(message: &Value, id: &Value) -> bool {
+    message.get("id") == Some(id)
+        && message.get("method").is_none()
+        && (message.get("result").is_some() || message.get("error").is_some())
+}
+
+/// The reply to a request the server sent us; only `ping` is supported
+fn reply_to(message: &Value) -> Option<Value> {
+    let id = message.get("id")?;
+    let method = message.get("method")?.as_str()?;
+    Some(match method {
+        "ping" => json!({ "jsonrpc": "2.0", "id": id, "result": {} }),
+        _ => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": -32601, "message": format!("Method not found: {}", method) }
+        }),
+    })
+}
+
+/// A child process exchanging newline-delimited JSON-RPC messages
+pub struct StdioTransport {
+    child: Child,
+    stdin: ChildStdin,
+    messages: Receiver<Value>,
+}
+
+impl StdioTransport {
+    fn spawn(server: &str, mut cmd: Command) -> Result<Self, String> {
+        let mut child = cmd
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to start MCP server '{}': {}", server, e))?;
+        let stdin = child.stdin.take().ok_or("MCP server has no stdin")?;
+        let stdout = child.stdout.take().ok_or("MCP server has no stdout")?;
+        let stderr = child.stderr.take().ok_or("MCP server has no stderr")?;
+
+        let (sender, messages) = mpsc::channel();
+        let name = server.to_string();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str::<Value>(&line) {
+                    Ok(message) => {
+                        if sender.send(message).is_err() {
+                            break;
+                        }
+                    }
+                    Err(_) => log::debug!("[mcp {}] ignoring non-JSON output: {}", name, line),
+                }
+            }
+        });
+        let name = server.to_string();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                log::debug!("[mcp {}] {}", name, line);
+            }
+        });
+        Ok(Self {
+            child,
+            stdin,
+            messages,
+        })
+    }
+
+    fn send(&mut self, message: &Value) -> Result<(), String> {
+        writeln!(self.stdin, "{}", message)
+            .and_then(|_| self.stdin.flush())
+            .map_err(|e| format!("Failed to write to MCP server: {}", e))
+    }
+
+    fn request(&mut self, message: &Value, id: &Value) -> Result<Value, String> {
+        self.send(message)?;
+        let deadline = Instant::now() + REQUEST_TIMEOUT;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let received = match self.messages.recv_timeout(remaining) {
+                Ok(received) => received,
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err("MCP server did not respond in time".to_string())
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    let status = self.child.try_wait().ok().flatten();
+                    return Err(match status {
+                        Some(status) => format!("MCP server exited ({})", status),
+                        None => "MCP server closed its output".to_string(),
+                    });
+                }
+            };
+            if is_response_to(&received, id) {
+                return into_result(received);
+            }
+            if let Some(reply) = reply_to(&received) {
+                self.send(&reply)?;
+            }
+        }
+    }
+}
+
+impl Drop for StdioTransport {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// A streamable HTTP endpoint; each message is a POST answered with JSON or an event stream
+pub struct HttpTransport {
+    url: String,
+    headers: Vec<(String, String)>,
+    agent: ureq::Agent,
+    /// Assigned by the server when the session is initialized
+    session: Option<String>,
+}
+
+impl HttpTransport {
+    pub fn new(url: &str, headers: &BTreeMap<String, String>, timeout: Duration) -> Self {
+        Self {
+            url: url.to_string(),
+            headers: headers.clone().into_iter().collect(),
+            agent: ureq::AgentBuilder::new().timeout(timeout).build(),
+            session: None,
+        }
+    }
+
+    fn request(&mut self, message: &Value, id: &Value) -> Result<Value, String> {
+        for received in self.post(message, Some(id))? {
+            if is_response_to(&received, id) {
+                return into_result(received);
+            }
+            if let Some(reply) = reply_to(&received) {
+                self.post(&reply, None)?;
+            }
+        }
+        Err("MCP server sent no response".to_string())
+    }
+
+    /// POST a message and collect what the server sends back, stopping at the response to `id`
+    pub fn post(&mut self, message: &Value, id: Option<&Value>) -> Result<Vec<Value>, String> {
+        let mut request = self
+            .agent
+            .post(&self.url)
+            .set("Content-Type", "application/json")
+            .set("Accept", "application/json, text/event-stream");
+        for (name, value) in &self.headers {
+            request = request.set(name, value);
+        }
+        if let Some(ref session) = self.session {
+            request = request.set(SESSION_HEADER, session);
+        }
+        let response = match request.send_string(&message.to_string()) {
+            Ok(response) => response,
+            Err(ureq::Error::Status(code, response)) => {
+                return Err(format!(
+                    "MCP server returned HTTP {} {}",
+                    code,
+                    response.status_text()
+                ))
+            }
+            Err(e) => return Err(format!("Failed to reach MCP server: {}", e)),
+        };
+        if let Some(session) = response.header(SESSION_HEADER) {
+            self.session = Some(session.to_string());
+        }
+        if response.status() == 202 {
+            return Ok(Vec::new());
+        }
+
+        let event_stream = response.content_type() == "text/event-stream";
+        let reader = response.into_reader().take(MAX_RESPONSE_BYTES);
+        if event_stream {
+            return read_events(BufReader::new(reader), id);
+        }
+        let mut body = String::new();
+        BufReader::new(reader)
+            .read_to_string(&mut body)
+            .map_err(|e| format!("Failed to read MCP response: {}", e))?;
+        if body.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        let value: Value = serde_json::from_str(&body)
+            .map_err(|e| format!("Invalid JSON from MCP server: {}", e))?;
+        Ok(match value {
+            Value::Array(batch) => batch,
+            value => vec![value],
+        })
+    }
+}
+
+impl Drop for HttpTransport {
+    fn drop(&mut self) {
+        // Let the server end the session; if this fails the server keeps it until it restarts
+        if let Some(ref session) = self.session {
+            let mut request = self.agent.delete(&self.url).set(SESSION_HEADER, session);
+            for (name, value) in &self.headers {
+                request = request.set(name, value);
+            }
+            let _ = request.call();
+        }
+    }
+}
+
+/// Messages from a server-sent event stream, read until the response to `id` arrives
+fn read_events(reader: impl BufRead, id: Option<&Value>) -> Result<Vec<Value>, String> {
+    let mut messages = Vec::new();
+    let mut data = String::new();
+    for line in reader.lines() {
+        let line = line.map_err(|e| format!("Failed to read MCP event stream: {}", e))?;
+        if let Some(value) = line.strip_prefix("data:") {
+            data.push_str(value.strip_prefix(' ').unwrap_or(value));
+            data.push('\n');
+            continue;
+        }
+        if !line.is_empty() || data.is_empty() {
+            continue;
+        }
+        let message: Value =
+            serde_json::from_str(&data).map_err(|e| format!("Invalid JSON in MCP event: {}", e))?;
+        data.clear();
+        let done = id.is_some_and(|id| is_response_to(&message, id));
+        messages.push(message);
+        if done {
+            break;
+        }
+    }
+    Ok(messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_events() {
+        let stream = "event: message\n\
+            data: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/progress\"}\n\
+            \n\
+            : keep-alive\n\
+            data: {\"jsonrpc\":\"2.0\",\n\
+            data: \"id\":3,\"result\":{}}\n\
+            \n\
+            data: {\"never\":\"read\"}\n\n";
+        let messages = read_events(stream.as_bytes(), Some(&json!(3))).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert!(is_response_to(&messages[1], &json!(3)));
+        assert!(!is_response_to(&messages[0], &json!(3)));
+    }
+
+    #[test]
+    fn test_into_result() {
+        assert_eq!(
+            into_result(json!({"id": 1, "result": {"ok": true}})).unwrap(),
+            json!({"ok": true})
+        );
+        assert_eq!(
+            into_result(json!({"id": 1, "error": {"code": -32602, "message": "bad params"}}))
+                .unwrap_err(),
+            "MCP error -32602: bad params"
+        );
+        assert_eq!(
+            reply_to(&json!({"id": 7, "method": "ping"})),
+            Some(json!({"jsonrpc": "2.0", "id": 7, "result": {}}))
+        );
+        assert_eq!(reply_to(&json!({"method": "notifications/progress"})), None);
+    }
+}
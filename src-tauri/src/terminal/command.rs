@@ -0,0 +1,469 @@
+use serde::Serialize;
+use std::io::{ErrorKind, Read};
+use std::path::{Component, Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::time::{Duration, Instant};
+
+use super::sandbox::{self, Sandbox, SandboxReport, SandboxSpec};
+use crate::files::{PathAccess, PermissionStore};
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+pub const MAX_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Most output kept from each of stdout and stderr; the rest is drained and dropped
+pub const MAX_OUTPUT_BYTES: usize = 64 * 1024;
+
+/// How often a running command is checked for exit or timeout
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+const READ_CHUNK_BYTES: usize = 8192;
+
+/// Environment variables a command inherits; everything else, such as API tokens, is dropped
+const INHERITED_ENV: &[&str] = &[
+    "PATH",
+    "HOME",
+    "USER",
+    "LOGNAME",
+    "SHELL",
+    "LANG",
+    "LC_ALL",
+    "LC_CTYPE",
+    "TZ",
+    "TMPDIR",
+    "SYSTEMROOT",
+    "SYSTEMDRIVE",
+    "WINDIR",
+    "COMSPEC",
+    "PATHEXT",
+    "TEMP",
+    "TMP",
+    "USERPROFILE",
+    "APPDATA",
+    "LOCALAPPDATA",
+    "PROGRAMDATA",
+    "PROGRAMFILES",
+];
+
+/// Set for every command so tools don't page, colorize or wait for a password
+const FORCED_ENV: &[(&str, &str)] = &[
+    ("TERM", "dumb"),
+    ("NO_COLOR", "1"),
+    ("PAGER", "cat"),
+    ("GIT_PAGER", "cat"),
+    ("GIT_TERMINAL_PROMPT", "0"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// Live command output, as sent with `command-output` events
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandOutputEvent {
+    pub tool_call_id: String,
+    pub conversation_id: Option<String>,
+    pub stream: OutputStream,
+    pub text: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct CommandSpec {
+    pub command: String,
+    pub cwd: PathBuf,
+    pub timeout: Duration,
+    pub sandbox: SandboxSpec,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandOutput {
+    /// `None` when the command was killed by a signal, including on timeout
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    /// Whether either stream went over `MAX_OUTPUT_BYTES`
+    pub truncated: bool,
+    pub timed_out: bool,
+    pub duration_ms: u64,
+    pub sandbox: SandboxReport,
+}
+
+impl CommandOutput {
+    pub fn success(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+}
+
+/// Check that a command may run in `cwd`: an existing directory inside a granted folder
+pub fn resolve_cwd(store: &PermissionStore, cwd: &str) -> Result<PathBuf, String> {
+    let path = Path::new(cwd);
+    if !path.is_absolute() {
+        return Err(format!(
+            "Working directory must be an absolute path: {}",
+            cwd
+        ));
+    }
+    if path.components().any(|c| c == Component::ParentDir) {
+        return Err(format!("Working directory must not contain '..': {}", cwd));
+    }
+    match store.check_access(cwd) {
+        PathAccess::Allowed => {}
+        PathAccess::Blocked => {
+            return Err(format!(
+                "Blocked by policy: {} matches a protected file pattern",
+                cwd
+            ))
+        }
+        PathAccess::NotGranted => {
+            return Err("Access denied: working directory not in granted folders".to_string())
+        }
+    }
+    if !path.is_dir() {
+        return Err(format!("Working directory does not exist: {}", cwd));
+    }
+    Ok(path.to_path_buf())
+}
+
+/// Timeout for a requested number of seconds, clamped to `MAX_TIMEOUT`
+pub fn timeout_from_secs(secs: Option<u64>) -> Duration {
+    secs.map_or(DEFAULT_TIMEOUT, |s| {
+        Duration::from_secs(s.max(1)).min(MAX_TIMEOUT)
+    })
+}
+
+/// The inherited variables that survive scrubbing, plus the forced ones
+pub fn scrubbed_env(vars: impl Iterator<Item = (String, String)>) -> Vec<(String, String)> {
+    let mut env: Vec<(String, String)> = vars
+        .filter(|(name, _)| INHERITED_ENV.iter().any(|k| k.eq_ignore_ascii_case(name)))
+        .collect();
+    env.extend(
+        FORCED_ENV
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string())),
+    );
+    env
+}
+
+#[cfg(unix)]
+fn shell_command(command: &str) -> Command {
+    use std::os::unix::process::CommandExt;
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(command);
+    // Own process group, so a timeout takes down everything the command started
+    cmd.process_group(0);
+    cmd
+}
+
+#[cfg(windows)]
+fn shell_command(command: &str) -> Command {
+    use std::os::windows::process::CommandExt;
+    let mut cmd = Command::new("cmd");
+    cmd.arg("/C").raw_arg(command);
+    cmd
+}
+
+#[cfg(unix)]
+pub(super) fn kill_tree(child: &mut Child) {
+    // The group outlives the shell while anything it started is still running
+    unsafe {
+        libc::kill(-(child.id() as i32), libc::SIGKILL);
+    }
+    let _ = child.kill();
+}
+
+#[cfg(windows)]
+pub(super) fn kill_tree(child: &mut Child) {
+    let _ = child.kill();
+}
+
+/// Run a shell command to completion, passing output to `on_output` as it arrives
+pub fn run_command(
+    spec: &CommandSpec,
+    on_output: &(dyn Fn(OutputStream, &str) + Sync),
+) -> Result<CommandOutput, String> {
+    let temp = create_temp_dir()?;
+    let result = run_in(spec, &temp, on_output);
+    let _ = std::fs::remove_dir_all(&temp);
+    result
+}
+
+/// A private temp dir for one command, since the sandbox leaves the shared one read-only
+pub(super) fn create_temp_dir() -> Result<PathBuf, String> {
+    let temp = std::env::temp_dir().join(format!("localwork-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir(&temp).map_err(|e| format!("Failed to create temp directory: {}", e))?;
+    Ok(temp)
+}
+
+/// A sandboxed shell command with a scrubbed environment and piped output, ready to spawn
+pub(super) fn prepare(
+    command_line: &str,
+    cwd: &Path,
+    sandbox: &SandboxSpec,
+    temp: &Path,
+) -> (Command, Sandbox) {
+    let mut command = shell_command(command_line);
+    command
+        .current_dir(cwd)
+        .env_clear()
+        .envs(scrubbed_env(std::env::vars()))
+        .envs(["TMPDIR", "TMP", "TEMP"].map(|name| (name, temp)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    let mut sandbox_spec = sandbox.clone();
+    sandbox_spec.writable.push(temp.to_path_buf());
+    let sandbox = sandbox::apply(&mut command, &sandbox_spec);
+    (command, sandbox)
+}
+
+fn run_in(
+    spec: &CommandSpec,
+    temp: &Path,
+    on_output: &(dyn Fn(OutputStream, &str) + Sync),
+) -> Result<CommandOutput, String> {
+    let (mut command, sandbox) = prepare(&spec.command, &spec.cwd, &spec.sandbox, temp);
+    let started = Instant::now();
+    let mut child = command
+        .spawn()
+        .map_err(|e| format!("Failed to start command: {}", e))?;
+    let sandbox = sandbox.report;
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let stderr = child.stderr.take().expect("stderr is piped");
+
+    std::thread::scope(|scope| {
+        let out = scope.spawn(|| capture(stdout, OutputStream::Stdout, on_output));
+        let err = scope.spawn(|| capture(stderr, OutputStream::Stderr, on_output));
+
+        let waited = wait_until(&mut child, started + spec.timeout);
+        // Background jobs left behind would otherwise keep the pipes open
+        kill_tree(&mut child);
+        let (stdout, stdout_truncated) = out.join().unwrap_or_default();
+        let (stderr, stderr_truncated) = err.join().unwrap_or_default();
+        let (status, timed_out) = waited?;
+
+        Ok(CommandOutput {
+            exit_code: status.and_then(|s| s.code()),
+            stdout,
+            stderr,
+            truncated: stdout_truncated || stderr_truncated,
+            timed_out,
+            duration_ms: started.elapsed().as_millis() as u64,
+            sandbox,
+        })
+    })
+}
+
+/// Wait for the child to exit, killing it at the deadline
+fn wait_until(child: &mut Child, deadline: Instant) -> Result<(Option<ExitStatus>, bool), String> {
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return Ok((Some(status), false)),
+            Ok(None) if Instant::now() >= deadline => {
+                kill_tree(child);
+                return Ok((child.wait().ok(), true));
+            }
+            Ok(None) => std::thread::sleep(POLL_INTERVAL),
+            Err(e) => {
+                kill_tree(child);
+                return Err(format!("Failed to wait for command: {}", e));
+            }
+        }
+    }
+}
+
+/// Read a pipe to the end, keeping and forwarding the first `MAX_OUTPUT_BYTES`
+fn capture(
+    mut pipe: impl Read,
+    stream: OutputStream,
+    on_output: &(dyn Fn(OutputStream, &str) + Sync),
+) -> (String, bool) {
+    let mut text = String::new();
+    let mut truncated = false;
+    let mut pending: Vec<u8> = Vec::new();
+    let mut buf = [0u8; READ_CHUNK_BYTES];
+
+    let keep = |chunk: &str, text: &mut String| {
+        let room = MAX_OUTPUT_BYTES - text.len();
+        let mut cut = chunk.len().min(room);
+        while !chunk.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        if cut > 0 {
+            text.push_str(&chunk[..cut]);
+            on_output(stream, &chunk[..cut]);
+        }
+        cut < chunk.len()
+    };
+
+    loop {
+        let n = match pipe.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(_) => break,
+        };
+        // Keep draining after the cap so the command never blocks on a full pipe
+        if truncated {
+            continue;
+        }
+        pending.extend_from_slice(&buf[..n]);
+        let complete = complete_utf8_len(&pending);
+        let chunk = String::from_utf8_lossy(&pending[..complete]).into_owned();
+        pending.drain(..complete);
+        truncated = keep(&chunk, &mut text);
+    }
+    if !truncated && !pending.is_empty() {
+        truncated = keep(&String::from_utf8_lossy(&pending), &mut text);
+    }
+    (text, truncated)
+}
+
+/// Length of `bytes` without a trailing partial UTF-8 sequence, which waits for the next read
+pub(super) fn complete_utf8_len(bytes: &[u8]) -> usize {
+    let len = bytes.len();
+    for back in 1..=len.min(3) {
+        let byte = bytes[len - back];
+        if byte & 0xC0 == 0x80 {
+            continue;
+        }
+        let needed = match byte {
+            0xF0.. => 4,
+            0xE0.. => 3,
+            0xC0.. => 2,
+            _ => 1,
+        };
+        return if needed > back { len - back } else { len };
+    }
+    len
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use tempfile::tempdir;
+
+    fn spec(command: &str, cwd: &Path) -> CommandSpec {
+        CommandSpec {
+            command: command.to_string(),
+            cwd: cwd.to_path_buf(),
+            timeout: Duration::from_secs(10),
+            sandbox: SandboxSpec {
+                writable: vec![cwd.to_path_buf()],
+                allow_network: false,
+            },
+        }
+    }
+
+    #[test]
+    fn test_resolve_cwd() {
+        let dir = tempdir().unwrap();
+        let mut store = PermissionStore::new();
+        store.add(dir.path().to_str().unwrap().to_string());
+        let granted = dir.path().to_str().unwrap();
+
+        assert!(resolve_cwd(&store, granted).is_ok());
+        assert!(resolve_cwd(&store, "/etc")
+            .unwrap_err()
+            .starts_with("Access denied"));
+        assert!(resolve_cwd(&store, "relative").is_err());
+        assert!(resolve_cwd(&store, &format!("{}/../..", granted)).is_err());
+        assert!(resolve_cwd(&store, &format!("{}/missing", granted))
+            .unwrap_err()
+            .contains("does not exist"));
+    }
+
+    #[test]
+    fn test_scrubbed_env() {
+        let vars = [
+            ("PATH", "/usr/bin"),
+            ("HOME", "/home/me"),
+            ("OPENAI_API_KEY", "secret"),
+            ("AWS_SECRET_ACCESS_KEY", "secret"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()));
+        let env = scrubbed_env(vars);
+
+        assert!(env.contains(&("PATH".to_string(), "/usr/bin".to_string())));
+        assert!(env.contains(&("TERM".to_string(), "dumb".to_string())));
+        assert!(!env.iter().any(|(_, v)| v == "secret"));
+    }
+
+    #[test]
+    fn test_complete_utf8_len() {
+        let e_acute = "é".as_bytes();
+        assert_eq!(complete_utf8_len(b"abc"), 3);
+        assert_eq!(complete_utf8_len(&[b'a', e_acute[0]]), 1);
+        assert_eq!(complete_utf8_len(&[b'a', e_acute[0], e_acute[1]]), 3);
+        assert_eq!(complete_utf8_len(&[0xE2, 0x82]), 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_command_streams_output() {
+        let dir = tempdir().unwrap();
+        let streamed = Mutex::new(String::new());
+        let output = run_command(
+            &spec("pwd; echo oops >&2; exit 3", dir.path()),
+            &|stream, text| {
+                if stream == OutputStream::Stdout {
+                    streamed.lock().unwrap().push_str(text);
+                }
+            },
+        )
+        .unwrap();
+
+        assert_eq!(output.exit_code, Some(3));
+        assert!(!output.success());
+        assert!(output
+            .stdout
+            .trim_end()
+            .ends_with(dir.path().file_name().unwrap().to_str().unwrap()));
+        assert_eq!(output.stderr, "oops\n");
+        assert_eq!(*streamed.lock().unwrap(), output.stdout);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_command_does_not_leak_env() {
+        std::env::set_var("LOCALWORK_TEST_SECRET", "hunter2");
+        let dir = tempdir().unwrap();
+        let output = run_command(&spec("env", dir.path()), &|_, _| {}).unwrap();
+        assert!(output.success());
+        assert!(!output.stdout.contains("hunter2"));
+        assert!(output.stdout.contains("NO_COLOR=1"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_command_times_out() {
+        let dir = tempdir().unwrap();
+        let mut slow = spec("echo started; sleep 30", dir.path());
+        slow.timeout = Duration::from_millis(200);
+        let output = run_command(&slow, &|_, _| {}).unwrap();
+
+        assert!(output.timed_out);
+        assert_eq!(output.exit_code, None);
+        assert_eq!(output.stdout, "started\n");
+        assert!(output.duration_ms < 10_000);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_command_caps_output() {
+        let dir = tempdir().unwrap();
+        let output = run_command(
+            &spec("head -c 200000 /dev/zero | tr '\\0' x", dir.path()),
+            &|_, _| {},
+        )
+        .unwrap();
+
+        assert!(output.success());
+        assert!(output.truncated);
+        assert_eq!(output.stdout.len(), MAX_OUTPUT_BYTES);
+    }
+}
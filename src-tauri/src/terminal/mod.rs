@@ -0,0 +1,12 @@
+pub mod command;
+pub mod policy;
+pub mod process;
+pub mod sandbox;
+
+pub use command::{
+    resolve_cwd, run_command, timeout_from_secs, CommandOutput, CommandOutputEvent, CommandSpec,
+    OutputStream,
+};
+pub use policy::{CommandPolicy, CommandRule, PolicyAction, PolicyDecision};
+pub use process::{ProcessInfo, ProcessManager, ProcessOutput, ProcessScope, ProcessStatus};
+pub use sandbox::{SandboxMode, SandboxReport, SandboxSpec};
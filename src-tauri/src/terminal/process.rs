@@ -0,0 +1,589 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::{ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Stdio};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use super::command::{complete_utf8_len, create_temp_dir, kill_tree, prepare};
+use super::sandbox::{SandboxReport, SandboxSpec};
+use crate::files::permissions::now_secs;
+
+/// Most processes running at once
+pub const MAX_PROCESSES: usize = 8;
+
+/// Output kept per process; older output is dropped as new output arrives
+pub const OUTPUT_BUFFER_BYTES: usize = 64 * 1024;
+
+/// Longest a read waits for new output
+pub const MAX_READ_WAIT: Duration = Duration::from_secs(30);
+
+/// How often a waiting read checks whether the process has exited
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long an exited process is kept if nobody reads its final output
+const EXITED_TTL: Duration = Duration::from_secs(10 * 60);
+
+const READ_CHUNK_BYTES: usize = 8192;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum ProcessStatus {
+    Running,
+    /// `exit_code` is `None` when the process was killed by a signal
+    Exited {
+        exit_code: Option<i32>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessInfo {
+    pub id: String,
+    pub command: String,
+    pub cwd: String,
+    pub conversation_id: Option<String>,
+    pub pid: u32,
+    pub started_at: u64,
+    pub status: ProcessStatus,
+    pub sandbox: SandboxReport,
+}
+
+/// Whose processes a caller can see and control
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessScope<'a> {
+    /// The user in the UI, who sees every process
+    User,
+    /// An agent, which only sees the processes started in its own conversation
+    Conversation(Option<&'a str>),
+}
+
+/// Output read from a process's buffer
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessOutput {
+    /// Interleaved stdout and stderr
+    pub text: String,
+    /// Pass as `since` to read only what comes after this
+    pub next_offset: u64,
+    /// Bytes after `since` that had already dropped out of the buffer
+    pub skipped: u64,
+    pub status: ProcessStatus,
+}
+
+/// The last `OUTPUT_BUFFER_BYTES` of output, addressed by offset from the first byte ever written
+#[derive(Default)]
+struct OutputBuffer {
+    text: String,
+    /// Offset of the first byte still in `text`
+    start: u64,
+}
+
+impl OutputBuffer {
+    fn end(&self) -> u64 {
+        self.start + self.text.len() as u64
+    }
+
+    fn push(&mut self, chunk: &str) {
+        self.text.push_str(chunk);
+        if self.text.len() > OUTPUT_BUFFER_BYTES {
+            let mut cut = self.text.len() - OUTPUT_BUFFER_BYTES;
+            while !self.text.is_char_boundary(cut) {
+                cut += 1;
+            }
+            self.text.drain(..cut);
+            self.start += cut as u64;
+        }
+    }
+
+    /// Output from `since` on, and how much of it was already dropped
+    fn read_from(&self, since: u64) -> (&str, u64) {
+        let skipped = self.start.saturating_sub(since);
+        let mut at = (since.clamp(self.start, self.end()) - self.start) as usize;
+        while !self.text.is_char_boundary(at) {
+            at += 1;
+        }
+        (&self.text[at..], skipped)
+    }
+}
+
+/// Output shared with the threads draining the pipes
+#[derive(Default)]
+struct SharedOutput {
+    buffer: Mutex<OutputBuffer>,
+    changed: Condvar,
+    /// Pipes that have reached end of file
+    closed_pipes: AtomicUsize,
+}
+
+impl SharedOutput {
+    /// Whether both stdout and stderr are closed, so no more output can arrive
+    fn finished(&self) -> bool {
+        self.closed_pipes.load(Ordering::Acquire) == 2
+    }
+
+    fn push(&self, chunk: &str) {
+        if let Ok(mut buffer) = self.buffer.lock() {
+            buffer.push(chunk);
+        }
+        self.changed.notify_all();
+    }
+}
+
+struct ManagedProcess {
+    /// Start order, for listing
+    seq: u64,
+    info: ProcessInfo,
+    child: Mutex<Child>,
+    /// `None` once input has been closed
+    stdin: Mutex<Option<ChildStdin>>,
+    output: Arc<SharedOutput>,
+    temp: PathBuf,
+    /// When the process was first seen to have exited
+    exited_at: Mutex<Option<Instant>>,
+}
+
+impl ManagedProcess {
+    fn status(&self) -> ProcessStatus {
+        let Ok(mut child) = self.child.lock() else {
+            return ProcessStatus::Exited { exit_code: None };
+        };
+        let status = match child.try_wait() {
+            Ok(None) => return ProcessStatus::Running,
+            Ok(Some(status)) => ProcessStatus::Exited {
+                exit_code: status.code(),
+            },
+            Err(_) => ProcessStatus::Exited { exit_code: None },
+        };
+        if let Ok(mut exited_at) = self.exited_at.lock() {
+            exited_at.get_or_insert_with(Instant::now);
+        }
+        status
+    }
+
+    /// Whether the process exited longer than `EXITED_TTL` ago
+    fn expired(&self) -> bool {
+        self.status() != ProcessStatus::Running
+            && self
+                .exited_at
+                .lock()
+                .is_ok_and(|t| t.is_some_and(|t| t.elapsed() >= EXITED_TTL))
+    }
+
+    fn visible_to(&self, scope: ProcessScope) -> bool {
+        match scope {
+            ProcessScope::User => true,
+            ProcessScope::Conversation(id) => self.info.conversation_id.as_deref() == id,
+        }
+    }
+
+    fn info(&self) -> ProcessInfo {
+        ProcessInfo {
+            status: self.status(),
+            ..self.info.clone()
+        }
+    }
+
+    /// Output from `since` (or the oldest buffered), waiting up to `wait` for some to arrive,
+    /// and whether it is the last output the process will produce
+    fn read(&self, since: Option<u64>, wait: Duration) -> Result<(ProcessOutput, bool), String> {
+        let deadline = Instant::now() + wait.min(MAX_READ_WAIT);
+        let mut buffer = self.output.buffer.lock().map_err(|e| e.to_string())?;
+        let since = since.unwrap_or(buffer.start);
+        let mut status = self.status();
+        while buffer.end() <= since && status == ProcessStatus::Running {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            let timeout = (deadline - now).min(EXIT_POLL_INTERVAL);
+            buffer = self
+                .output
+                .changed
+                .wait_timeout(buffer, timeout)
+                .map_err(|e| e.to_string())?
+                .0;
+            status = self.status();
+        }
+        // Checked under the buffer lock, after which the pipes can't add anything more
+        let last = status != ProcessStatus::Running && self.output.finished();
+        let (text, skipped) = buffer.read_from(since);
+        let output = ProcessOutput {
+            text: text.to_string(),
+            next_offset: buffer.end(),
+            skipped,
+            status,
+        };
+        Ok((output, last))
+    }
+
+    fn kill(&self) {
+        if let Ok(mut child) = self.child.lock() {
+            kill_tree(&mut child);
+            let _ = child.wait();
+        }
+    }
+}
+
+impl Drop for ManagedProcess {
+    fn drop(&mut self) {
+        self.kill();
+        let _ = std::fs::remove_dir_all(&self.temp);
+    }
+}
+
+/// Child processes the agent or the user started, by handle
+#[derive(Default)]
+pub struct ProcessManager {
+    processes: Mutex<HashMap<String, Arc<ManagedProcess>>>,
+    started: AtomicU64,
+}
+
+impl ProcessManager {
+    /// Start a sandboxed shell command in the background with piped input
+    pub fn start(
+        &self,
+        command: &str,
+        cwd: &Path,
+        sandbox: &SandboxSpec,
+        conversation_id: Option<&str>,
+    ) -> Result<ProcessInfo, String> {
+        let mut processes = self.processes.lock().map_err(|e| e.to_string())?;
+        processes.retain(|_, p| !p.expired());
+        let running = processes
+            .values()
+            .filter(|p| p.status() == ProcessStatus::Running)
+            .count();
+        if running >= MAX_PROCESSES {
+            return Err(format!(
+                "Too many background processes ({} running); stop one first",
+                running
+            ));
+        }
+
+        let temp = create_temp_dir()?;
+        let (mut cmd, sandbox) = prepare(command, cwd, sandbox, &temp);
+        cmd.stdin(Stdio::piped());
+        let mut child = match cmd.spawn() {
+            Ok(child) => child,
+            Err(e) => {
+                let _ = std::fs::remove_dir_all(&temp);
+                return Err(format!("Failed to start process: {}", e));
+            }
+        };
+
+        let output = Arc::new(SharedOutput::default());
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let stderr = child.stderr.take().expect("stderr is piped");
+        let shared = output.clone();
+        std::thread::spawn(move || pump(stdout, &shared));
+        let shared = output.clone();
+        std::thread::spawn(move || pump(stderr, &shared));
+
+        let seq = self.started.fetch_add(1, Ordering::Relaxed) + 1;
+        let id = format!("proc-{}", seq);
+        let info = ProcessInfo {
+            id: id.clone(),
+            command: command.to_string(),
+            cwd: cwd.to_string_lossy().to_string(),
+            conversation_id: conversation_id.map(|c| c.to_string()),
+            pid: child.id(),
+            started_at: now_secs(),
+            status: ProcessStatus::Running,
+            sandbox: sandbox.report,
+        };
+        let process = ManagedProcess {
+            seq,
+            info: info.clone(),
+            stdin: Mutex::new(child.stdin.take()),
+            child: Mutex::new(child),
+            output,
+            temp,
+            exited_at: Mutex::new(None),
+        };
+        processes.insert(id, Arc::new(process));
+        Ok(info)
+    }
+
+    /// Processes visible in `scope`, oldest first
+    pub fn list(&self, scope: ProcessScope) -> Vec<ProcessInfo> {
+        let Ok(mut processes) = self.processes.lock() else {
+            return Vec::new();
+        };
+        processes.retain(|_, p| !p.expired());
+        let mut list: Vec<&Arc<ManagedProcess>> =
+            processes.values().filter(|p| p.visible_to(scope)).collect();
+        list.sort_by_key(|p| p.seq);
+        list.into_iter().map(|p| p.info()).collect()
+    }
+
+    fn get(&self, id: &str, scope: ProcessScope) -> Result<Arc<ManagedProcess>, String> {
+        let processes = self.processes.lock().map_err(|e| e.to_string())?;
+        processes
+            .get(id)
+            .filter(|p| p.visible_to(scope))
+            .cloned()
+            .ok_or_else(|| format!("No background process with id {}", id))
+    }
+
+    pub fn info(&self, id: &str, scope: ProcessScope) -> Result<ProcessInfo, String> {
+        self.get(id, scope).map(|p| p.info())
+    }
+
+    /// Read a process's output; once an exited process's last output has been read it is
+    /// forgotten
+    pub fn read(
+        &self,
+        id: &str,
+        scope: ProcessScope,
+        since: Option<u64>,
+        wait: Duration,
+    ) -> Result<ProcessOutput, String> {
+        // Not holding the map lock while waiting
+        let process = self.get(id, scope)?;
+        let (output, last) = process.read(since, wait)?;
+        if last {
+            if let Ok(mut processes) = self.processes.lock() {
+                processes.remove(id);
+            }
+        }
+        Ok(output)
+    }
+
+    /// Write to the process's standard input, optionally closing it afterwards
+    pub fn send_input(
+        &self,
+        id: &str,
+        scope: ProcessScope,
+        input: &str,
+        close: bool,
+    ) -> Result<(), String> {
+        let process = self.get(id, scope)?;
+        let mut stdin = process.stdin.lock().map_err(|e| e.to_string())?;
+        let pipe = stdin
+            .as_mut()
+            .ok_or_else(|| format!("Input to {} has already been closed", id))?;
+        if !input.is_empty() {
+            pipe.write_all(input.as_bytes())
+                .and_then(|_| pipe.flush())
+                .map_err(|e| format!("Failed to write to {}: {}", id, e))?;
+        }
+        if close {
+            *stdin = None;
+        }
+        Ok(())
+    }
+
+    /// Kill a process and forget it, returning its last state
+    pub fn stop(&self, id: &str, scope: ProcessScope) -> Result<ProcessInfo, String> {
+        let process = self.get(id, scope)?;
+        self.processes.lock().map_err(|e| e.to_string())?.remove(id);
+        process.kill();
+        Ok(process.info())
+    }
+
+    /// Stop everything a conversation started, e.g. when its session ends
+    pub fn stop_conversation(&self, conversation_id: &str) {
+        let stopped: Vec<Arc<ManagedProcess>> = match self.processes.lock() {
+            Ok(mut processes) => {
+                let ids: Vec<String> = processes
+                    .iter()
+                    .filter(|(_, p)| p.info.conversation_id.as_deref() == Some(conversation_id))
+                    .map(|(id, _)| id.clone())
+                    .collect();
+                ids.iter().filter_map(|id| processes.remove(id)).collect()
+            }
+            Err(_) => return,
+        };
+        for process in stopped {
+            process.kill();
+        }
+    }
+
+    pub fn stop_all(&self) {
+        let stopped: Vec<Arc<ManagedProcess>> = match self.processes.lock() {
+            Ok(mut processes) => processes.drain().map(|(_, p)| p).collect(),
+            Err(_) => return,
+        };
+        for process in stopped {
+            process.kill();
+        }
+    }
+}
+
+/// Drain a pipe into the shared buffer until the process closes it
+fn pump(mut pipe: impl Read, output: &SharedOutput) {
+    let mut pending: Vec<u8> = Vec::new();
+    let mut buf = [0u8; READ_CHUNK_BYTES];
+    loop {
+        let n = match pipe.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(_) => break,
+        };
+        pending.extend_from_slice(&buf[..n]);
+        let complete = complete_utf8_len(&pending);
+        output.push(&String::from_utf8_lossy(&pending[..complete]));
+        pending.drain(..complete);
+    }
+    if !pending.is_empty() {
+        output.push(&String::from_utf8_lossy(&pending));
+    }
+    output.closed_pipes.fetch_add(1, Ordering::Release);
+    output.changed.notify_all();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn sandbox(dir: &Path) -> SandboxSpec {
+        SandboxSpec {
+            writable: vec![dir.to_path_buf()],
+            allow_network: false,
+        }
+    }
+
+    #[test]
+    fn test_output_buffer_drops_oldest() {
+        let mut buffer = OutputBuffer::default();
+        buffer.push("hello ");
+        assert_eq!(buffer.read_from(0), ("hello ", 0));
+        buffer.push(&"x".repeat(OUTPUT_BUFFER_BYTES));
+
+        assert_eq!(buffer.end(), 6 + OUTPUT_BUFFER_BYTES as u64);
+        assert_eq!(buffer.text.len(), OUTPUT_BUFFER_BYTES);
+        let (text, skipped) = buffer.read_from(2);
+        assert_eq!(skipped, 4);
+        assert_eq!(text.len(), OUTPUT_BUFFER_BYTES);
+        assert_eq!(buffer.read_from(buffer.end()), ("", 0));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_process_input_and_output() {
+        let dir = tempdir().unwrap();
+        let manager = ProcessManager::default();
+        let info = manager
+            .start("cat", dir.path(), &sandbox(dir.path()), Some("conv"))
+            .unwrap();
+        assert_eq!(info.status, ProcessStatus::Running);
+
+        manager
+            .send_input(
+                &info.id,
+                ProcessScope::Conversation(Some("conv")),
+                "ping\n",
+                false,
+            )
+            .unwrap();
+        let first = manager
+            .read(
+                &info.id,
+                ProcessScope::Conversation(Some("conv")),
+                Some(0),
+                Duration::from_secs(5),
+            )
+            .unwrap();
+        assert_eq!(first.text, "ping\n");
+        assert_eq!(first.status, ProcessStatus::Running);
+
+        manager
+            .send_input(
+                &info.id,
+                ProcessScope::Conversation(Some("conv")),
+                "pong\n",
+                true,
+            )
+            .unwrap();
+        let mut rest = manager
+            .read(
+                &info.id,
+                ProcessScope::Conversation(Some("conv")),
+                Some(first.next_offset),
+                Duration::from_secs(5),
+            )
+            .unwrap();
+        assert_eq!(rest.text, "pong\n");
+        // cat exits once its input is closed
+        while rest.status == ProcessStatus::Running {
+            rest = manager
+                .read(
+                    &info.id,
+                    ProcessScope::User,
+                    Some(rest.next_offset),
+                    Duration::from_secs(5),
+                )
+                .unwrap();
+        }
+        assert_eq!(rest.status, ProcessStatus::Exited { exit_code: Some(0) });
+        assert!(manager
+            .send_input(
+                &info.id,
+                ProcessScope::Conversation(Some("conv")),
+                "more",
+                false
+            )
+            .is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_processes_are_scoped_and_stopped() {
+        let dir = tempdir().unwrap();
+        let manager = ProcessManager::default();
+        let mine = manager
+            .start("sleep 30", dir.path(), &sandbox(dir.path()), Some("conv-1"))
+            .unwrap();
+        let other = manager
+            .start("sleep 30", dir.path(), &sandbox(dir.path()), Some("conv-2"))
+            .unwrap();
+
+        assert!(manager
+            .read(
+                &other.id,
+                ProcessScope::Conversation(Some("conv-1")),
+                None,
+                Duration::ZERO
+            )
+            .is_err());
+        assert_eq!(
+            manager
+                .list(ProcessScope::Conversation(Some("conv-1")))
+                .len(),
+            1
+        );
+        assert_eq!(manager.list(ProcessScope::User).len(), 2);
+        // An agent outside any conversation doesn't see the others' processes either
+        assert!(manager.list(ProcessScope::Conversation(None)).is_empty());
+
+        let stopped = manager
+            .stop(&mine.id, ProcessScope::Conversation(Some("conv-1")))
+            .unwrap();
+        assert_eq!(stopped.status, ProcessStatus::Exited { exit_code: None });
+        manager.stop_conversation("conv-2");
+        assert!(manager.list(ProcessScope::User).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exited_processes_are_forgotten_once_read() {
+        let dir = tempdir().unwrap();
+        let manager = ProcessManager::default();
+        let info = manager
+            .start("echo done", dir.path(), &sandbox(dir.path()), Some("conv"))
+            .unwrap();
+        let scope = ProcessScope::Conversation(Some("conv"));
+
+        let mut text = String::new();
+        let mut since = Some(0);
+        while let Ok(output) = manager.read(&info.id, scope, since, Duration::from_secs(5)) {
+            text.push_str(&output.text);
+            since = Some(output.next_offset);
+        }
+        assert_eq!(text, "done\n");
+        assert!(manager.list(ProcessScope::User).is_empty());
+    }
+}
@@ -0,0 +1,483 @@
+use serde::Serialize;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// How much isolation a command runs with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SandboxMode {
+    /// Writes confined to the writable folders and network blocked unless allowed
+    Full,
+    /// Some isolation was unavailable; the limitations say which
+    Partial,
+    Unsandboxed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SandboxReport {
+    pub mode: SandboxMode,
+    pub writes_confined: bool,
+    pub network_blocked: bool,
+    /// What could not be isolated and why
+    pub limitations: Vec<String>,
+}
+
+impl SandboxReport {
+    fn new(writes_confined: bool, network_blocked: bool, limitations: Vec<String>) -> Self {
+        let mode = if limitations.is_empty() {
+            SandboxMode::Full
+        } else if writes_confined || network_blocked {
+            SandboxMode::Partial
+        } else {
+            SandboxMode::Unsandboxed
+        };
+        Self {
+            mode,
+            writes_confined,
+            network_blocked,
+            limitations,
+        }
+    }
+}
+
+/// What a sandboxed command may do
+#[derive(Debug, Clone, Default)]
+pub struct SandboxSpec {
+    /// Folders the command may write to; the rest of the filesystem is read-only
+    pub writable: Vec<PathBuf>,
+    pub allow_network: bool,
+}
+
+/// Sandbox set up on a `Command`; keep it alive until the command has been spawned
+pub struct Sandbox {
+    pub report: SandboxReport,
+    _resources: imp::Resources,
+}
+
+/// The isolation `spec` would get on this machine, without setting anything up
+pub fn expected(spec: &SandboxSpec) -> SandboxReport {
+    imp::expected(spec)
+}
+
+/// Arrange for `command` to run sandboxed; whatever can't be applied is reported, not an error
+pub fn apply(command: &mut Command, spec: &SandboxSpec) -> Sandbox {
+    let (report, resources) = imp::apply(command, spec);
+    Sandbox {
+        report,
+        _resources: resources,
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use super::{SandboxReport, SandboxSpec};
+    use std::ffi::{CStr, CString};
+    use std::io;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::process::CommandExt;
+    use std::path::Path;
+    use std::process::{Command, Stdio};
+    use std::sync::OnceLock;
+
+    // Landlock constants from linux/landlock.h
+    const CREATE_RULESET_VERSION: u32 = 1 << 0;
+    const RULE_PATH_BENEATH: u32 = 1;
+
+    const ACCESS_FS_EXECUTE: u64 = 1 << 0;
+    const ACCESS_FS_WRITE_FILE: u64 = 1 << 1;
+    const ACCESS_FS_READ_FILE: u64 = 1 << 2;
+    const ACCESS_FS_READ_DIR: u64 = 1 << 3;
+    /// Every filesystem access right known to ABI 1
+    const ACCESS_FS_ABI_1: u64 = (1 << 13) - 1;
+    const ACCESS_FS_REFER: u64 = 1 << 13;
+    const ACCESS_FS_TRUNCATE: u64 = 1 << 14;
+    const ACCESS_FS_IOCTL_DEV: u64 = 1 << 15;
+    const ACCESS_NET_BIND_TCP: u64 = 1 << 0;
+    const ACCESS_NET_CONNECT_TCP: u64 = 1 << 1;
+
+    const ACCESS_FS_READ: u64 = ACCESS_FS_EXECUTE | ACCESS_FS_READ_FILE | ACCESS_FS_READ_DIR;
+
+    #[repr(C)]
+    struct RulesetAttr {
+        handled_access_fs: u64,
+        handled_access_net: u64,
+    }
+
+    #[repr(C, packed)]
+    struct PathBeneathAttr {
+        allowed_access: u64,
+        parent_fd: i32,
+    }
+
+    /// Ruleset descriptor the child inherits at fork
+    pub type Resources = Option<OwnedFd>;
+
+    struct Capabilities {
+        /// 0 when Landlock is missing or disabled
+        landlock_abi: i64,
+        /// Whether this user may create a user and network namespace
+        net_namespace: bool,
+    }
+
+    fn capabilities() -> &'static Capabilities {
+        static CAPABILITIES: OnceLock<Capabilities> = OnceLock::new();
+        CAPABILITIES.get_or_init(|| Capabilities {
+            landlock_abi: landlock_abi(),
+            net_namespace: probe_net_namespace(),
+        })
+    }
+
+    fn landlock_abi() -> i64 {
+        let abi = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                std::ptr::null::<RulesetAttr>(),
+                0usize,
+                CREATE_RULESET_VERSION,
+            )
+        };
+        abi.max(0)
+    }
+
+    /// Distributions can forbid unprivileged user namespaces, so try one in a throwaway child
+    fn probe_net_namespace() -> bool {
+        let mut probe = Command::new("/bin/sh");
+        probe
+            .args(["-c", "exit 0"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        let namespace = NetNamespace::new();
+        unsafe {
+            probe.pre_exec(move || namespace.enter());
+        }
+        probe.status().is_ok_and(|s| s.success())
+    }
+
+    /// A new user and network namespace, with the current user mapped to itself inside
+    struct NetNamespace {
+        uid_map: CString,
+        gid_map: CString,
+    }
+
+    impl NetNamespace {
+        fn new() -> Self {
+            let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+            Self {
+                uid_map: CString::new(format!("{} {} 1", uid, uid)).unwrap(),
+                gid_map: CString::new(format!("{} {} 1", gid, gid)).unwrap(),
+            }
+        }
+
+        /// Runs between fork and exec, so it only makes plain system calls
+        fn enter(&self) -> io::Result<()> {
+            if unsafe { libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNET) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            // Without a mapping files would show as owned by nobody; setgroups must be
+            // denied before an unprivileged process may write gid_map
+            write_proc(c"/proc/self/setgroups", c"deny")?;
+            write_proc(c"/proc/self/gid_map", &self.gid_map)?;
+            write_proc(c"/proc/self/uid_map", &self.uid_map)
+        }
+    }
+
+    fn write_proc(path: &CStr, contents: &CStr) -> io::Result<()> {
+        let fd = unsafe { libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let bytes = contents.to_bytes();
+        let written = unsafe { libc::write(fd, bytes.as_ptr().cast(), bytes.len()) };
+        let result = if written < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        };
+        unsafe { libc::close(fd) };
+        result
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum NetworkBlock {
+        Allowed,
+        Namespace,
+        /// No namespaces, but Landlock can still refuse TCP binds and connects
+        LandlockTcp,
+        Unavailable,
+    }
+
+    struct Plan {
+        confine_writes: bool,
+        network: NetworkBlock,
+        limitations: Vec<String>,
+    }
+
+    impl Plan {
+        fn new(spec: &SandboxSpec) -> Self {
+            let caps = capabilities();
+            let mut limitations = Vec::new();
+            let confine_writes = caps.landlock_abi >= 1;
+            if !confine_writes {
+                limitations.push(
+                    "Landlock is not available, so writes are not limited to granted folders"
+                        .to_string(),
+                );
+            }
+            let network = if spec.allow_network {
+                NetworkBlock::Allowed
+            } else if caps.net_namespace {
+                NetworkBlock::Namespace
+            } else if caps.landlock_abi >= 4 {
+                limitations.push(
+                    "Network namespaces are unavailable; only TCP connections are blocked"
+                        .to_string(),
+                );
+                NetworkBlock::LandlockTcp
+            } else {
+                limitations
+                    .push("Network namespaces are unavailable; network is not blocked".to_string());
+                NetworkBlock::Unavailable
+            };
+            Self {
+                confine_writes,
+                network,
+                limitations,
+            }
+        }
+
+        fn report(self) -> SandboxReport {
+            let network_blocked = matches!(
+                self.network,
+                NetworkBlock::Namespace | NetworkBlock::LandlockTcp
+            );
+            SandboxReport::new(self.confine_writes, network_blocked, self.limitations)
+        }
+    }
+
+    pub fn expected(spec: &SandboxSpec) -> SandboxReport {
+        Plan::new(spec).report()
+    }
+
+    pub fn apply(command: &mut Command, spec: &SandboxSpec) -> (SandboxReport, Resources) {
+        let mut plan = Plan::new(spec);
+        let block_tcp = plan.network == NetworkBlock::LandlockTcp;
+        let mut ruleset = None;
+        if plan.confine_writes || block_tcp {
+            match build_ruleset(spec, capabilities().landlock_abi, block_tcp) {
+                Ok(fd) => ruleset = Some(fd),
+                Err(e) => {
+                    plan.limitations
+                        .push(format!("Failed to set up Landlock: {}", e));
+                    plan.confine_writes = false;
+                    if block_tcp {
+                        plan.network = NetworkBlock::Unavailable;
+                    }
+                }
+            }
+        }
+
+        let namespace = (plan.network == NetworkBlock::Namespace).then(NetNamespace::new);
+        let ruleset_fd: Option<RawFd> = ruleset.as_ref().map(|fd| fd.as_raw_fd());
+        if namespace.is_some() || ruleset_fd.is_some() {
+            unsafe {
+                command.pre_exec(move || {
+                    if let Some(ref namespace) = namespace {
+                        namespace.enter()?;
+                    }
+                    if let Some(fd) = ruleset_fd {
+                        restrict_self(fd)?;
+                    }
+                    Ok(())
+                });
+            }
+        }
+        (plan.report(), ruleset)
+    }
+
+    fn restrict_self(ruleset: RawFd) -> io::Result<()> {
+        if unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if unsafe { libc::syscall(libc::SYS_landlock_restrict_self, ruleset, 0u32) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn handled_fs(abi: i64) -> u64 {
+        let mut access = ACCESS_FS_ABI_1;
+        if abi >= 2 {
+            access |= ACCESS_FS_REFER;
+        }
+        if abi >= 3 {
+            access |= ACCESS_FS_TRUNCATE;
+        }
+        if abi >= 5 {
+            access |= ACCESS_FS_IOCTL_DEV;
+        }
+        access
+    }
+
+    fn build_ruleset(spec: &SandboxSpec, abi: i64, block_tcp: bool) -> io::Result<OwnedFd> {
+        let handled_fs = handled_fs(abi);
+        let attr = RulesetAttr {
+            handled_access_fs: handled_fs,
+            handled_access_net: if block_tcp {
+                ACCESS_NET_BIND_TCP | ACCESS_NET_CONNECT_TCP
+            } else {
+                0
+            },
+        };
+        // Kernels before ABI 4 reject the larger struct
+        let size = if abi >= 4 {
+            std::mem::size_of::<RulesetAttr>()
+        } else {
+            std::mem::size_of::<u64>()
+        };
+        let fd = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                &attr as *const RulesetAttr,
+                size,
+                0u32,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let ruleset = unsafe { OwnedFd::from_raw_fd(fd as RawFd) };
+
+        allow_beneath(&ruleset, Path::new("/"), ACCESS_FS_READ)?;
+        // Terminals and /dev/null
+        let mut dev_access = ACCESS_FS_READ | ACCESS_FS_WRITE_FILE;
+        if abi >= 5 {
+            dev_access |= ACCESS_FS_IOCTL_DEV;
+        }
+        allow_beneath(&ruleset, Path::new("/dev"), dev_access)?;
+        for folder in &spec.writable {
+            allow_beneath(&ruleset, folder, handled_fs)?;
+        }
+        Ok(ruleset)
+    }
+
+    fn allow_beneath(ruleset: &OwnedFd, path: &Path, access: u64) -> io::Result<()> {
+        let c_path = CString::new(path.as_os_str().as_bytes())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "path contains a NUL byte"))?;
+        let fd = unsafe { libc::open(c_path.as_ptr(), libc::O_PATH | libc::O_CLOEXEC) };
+        if fd < 0 {
+            let err = io::Error::last_os_error();
+            // A granted folder that has since been deleted just gets no rule
+            if err.kind() == io::ErrorKind::NotFound {
+                return Ok(());
+            }
+            return Err(io::Error::new(
+                err.kind(),
+                format!("{}: {}", path.display(), err),
+            ));
+        }
+        let parent = unsafe { OwnedFd::from_raw_fd(fd) };
+        let attr = PathBeneathAttr {
+            allowed_access: access,
+            parent_fd: parent.as_raw_fd(),
+        };
+        let result = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_add_rule,
+                ruleset.as_raw_fd(),
+                RULE_PATH_BENEATH,
+                &attr as *const PathBeneathAttr,
+                0u32,
+            )
+        };
+        if result != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod imp {
+    use super::{SandboxReport, SandboxSpec};
+    use std::process::Command;
+
+    pub type Resources = ();
+
+    pub fn expected(_spec: &SandboxSpec) -> SandboxReport {
+        SandboxReport::new(
+            false,
+            false,
+            vec!["Sandboxing is only available on Linux".to_string()],
+        )
+    }
+
+    pub fn apply(_command: &mut Command, spec: &SandboxSpec) -> (SandboxReport, Resources) {
+        (expected(spec), ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_mode() {
+        assert_eq!(
+            SandboxReport::new(true, true, vec![]).mode,
+            SandboxMode::Full
+        );
+        let partial = SandboxReport::new(true, false, vec!["no network".to_string()]);
+        assert_eq!(partial.mode, SandboxMode::Partial);
+        let none = SandboxReport::new(false, false, vec!["no sandbox".to_string()]);
+        assert_eq!(none.mode, SandboxMode::Unsandboxed);
+    }
+
+    #[cfg(target_os = "linux")]
+    fn run(script: &str, spec: &SandboxSpec) -> (SandboxReport, std::process::Output) {
+        let mut command = Command::new("sh");
+        command.args(["-c", script]);
+        let sandbox = apply(&mut command, spec);
+        let output = command.output().unwrap();
+        (sandbox.report, output)
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_writes_confined_to_writable_folders() {
+        let granted = tempfile::tempdir().unwrap();
+        let other = tempfile::tempdir().unwrap();
+        let spec = SandboxSpec {
+            writable: vec![granted.path().to_path_buf()],
+            allow_network: false,
+        };
+        let script = format!(
+            "echo a > {}/inside; echo b > {}/outside",
+            granted.path().display(),
+            other.path().display()
+        );
+        let (report, _) = run(&script, &spec);
+
+        assert!(granted.path().join("inside").exists());
+        assert_eq!(
+            other.path().join("outside").exists(),
+            !report.writes_confined
+        );
+        assert_eq!(report, expected(&spec));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_network_namespace_has_only_loopback() {
+        let spec = SandboxSpec::default();
+        let (report, output) = run("tail -n +3 /proc/net/dev", &spec);
+        // A namespace is the only way the network gets blocked without a limitation
+        if report.network_blocked && report.limitations.is_empty() {
+            let interfaces = String::from_utf8_lossy(&output.stdout);
+            assert!(interfaces
+                .lines()
+                .all(|line| line.trim_start().starts_with("lo:")));
+        }
+    }
+}
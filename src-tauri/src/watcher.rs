@@ -0,0 +1,109 @@
+use notify_debouncer_full::notify::event::{ModifyKind, RenameMode};
+use notify_debouncer_full::notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use notify_debouncer_full::{
+    new_debouncer, DebounceEventResult, DebouncedEvent, Debouncer, FileIdMap,
+};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::files::{ChangeKind, FsChange};
+
+/// How long a path must stay quiet before its changes are reported
+const DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Watches every granted folder recursively and reports debounced batches of changes
+pub struct FsWatcher {
+    debouncer: Mutex<Debouncer<RecommendedWatcher, FileIdMap>>,
+    watched: Mutex<HashSet<PathBuf>>,
+}
+
+impl FsWatcher {
+    pub fn new(on_changes: impl Fn(Vec<FsChange>) + Send + 'static) -> Result<Self, String> {
+        let debouncer = new_debouncer(
+            DEBOUNCE_TIMEOUT,
+            None,
+            move |result: DebounceEventResult| match result {
+                Ok(events) => {
+                    let changes: Vec<FsChange> = events.iter().flat_map(to_changes).collect();
+                    if !changes.is_empty() {
+                        on_changes(changes);
+                    }
+                }
+                Err(errors) => {
+                    for e in errors {
+                        log::warn!("File watcher error: {}", e);
+                    }
+                }
+            },
+        )
+        .map_err(|e| format!("Failed to start file watcher: {}", e))?;
+
+        Ok(Self {
+            debouncer: Mutex::new(debouncer),
+            watched: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// Watch exactly `folders`, adding and dropping watches as grants come and go
+    pub fn sync(&self, folders: &[String]) {
+        let (Ok(mut debouncer), Ok(mut watched)) = (self.debouncer.lock(), self.watched.lock())
+        else {
+            return;
+        };
+        let wanted: HashSet<PathBuf> = folders.iter().map(PathBuf::from).collect();
+
+        let stale: Vec<PathBuf> = watched.difference(&wanted).cloned().collect();
+        for path in stale {
+            if let Err(e) = debouncer.watcher().unwatch(&path) {
+                log::warn!("Failed to stop watching {}: {}", path.display(), e);
+            }
+            debouncer.cache().remove_root(&path);
+            watched.remove(&path);
+        }
+
+        let added: Vec<PathBuf> = wanted.difference(&watched).cloned().collect();
+        for path in added {
+            match debouncer.watcher().watch(&path, RecursiveMode::Recursive) {
+                Ok(()) => {
+                    debouncer.cache().add_root(&path, RecursiveMode::Recursive);
+                    watched.insert(path);
+                }
+                Err(e) => log::warn!("Failed to watch {}: {}", path.display(), e),
+            }
+        }
+    }
+}
+
+fn to_changes(event: &DebouncedEvent) -> Vec<FsChange> {
+    let paths: Vec<String> = event
+        .paths
+        .iter()
+        .map(|p| p.to_string_lossy().to_string())
+        .collect();
+    let kind = match event.kind {
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if paths.len() == 2 => {
+            return vec![FsChange {
+                path: paths[1].clone(),
+                kind: ChangeKind::Renamed,
+                from: Some(paths[0].clone()),
+            }];
+        }
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) => ChangeKind::Removed,
+        EventKind::Modify(ModifyKind::Name(RenameMode::To)) => ChangeKind::Created,
+        EventKind::Modify(ModifyKind::Name(_)) => ChangeKind::Renamed,
+        EventKind::Create(_) => ChangeKind::Created,
+        EventKind::Modify(_) => ChangeKind::Modified,
+        EventKind::Remove(_) => ChangeKind::Removed,
+        EventKind::Access(_) | EventKind::Any | EventKind::Other => return Vec::new(),
+    };
+    paths
+        .into_iter()
+        .map(|path| FsChange {
+            path,
+            kind,
+            from: None,
+        })
+        .collect()
+}
@@ -0,0 +1,240 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Domains the agent may or may not fetch from; a domain also covers its subdomains
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DomainRules {
+    #[serde(default)]
+    pub allow: Vec<String>,
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DomainDecision {
+    Allow,
+    /// Refused, with the reason
+    Deny(String),
+    /// Not seen before; the user decides on first access
+    Ask,
+}
+
+/// The user's domain lists, saved as JSON; approving a new domain adds it to the allow list
+pub struct DomainPolicy {
+    path: PathBuf,
+    rules: Mutex<DomainRules>,
+}
+
+impl DomainPolicy {
+    pub fn load(path: PathBuf) -> Self {
+        let rules = match fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str::<DomainRules>(&text).unwrap_or_else(|e| {
+                log::warn!("Failed to parse domain rules: {}", e);
+                DomainRules::default()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => DomainRules::default(),
+            Err(e) => {
+                log::warn!("Failed to read domain rules: {}", e);
+                DomainRules::default()
+            }
+        };
+        Self {
+            path,
+            rules: Mutex::new(rules),
+        }
+    }
+
+    pub fn rules(&self) -> DomainRules {
+        self.rules.lock().map(|r| r.clone()).unwrap_or_default()
+    }
+
+    /// Replace both lists, normalizing entries like `*.Example.com` to `example.com`
+    pub fn set_rules(&self, rules: DomainRules) -> Result<DomainRules, String> {
+        let rules = DomainRules {
+            allow: normalize_all(&rules.allow)?,
+            deny: normalize_all(&rules.deny)?,
+        };
+        let mut current = self.rules.lock().map_err(|e| e.to_string())?;
+        self.save(&rules)?;
+        *current = rules.clone();
+        Ok(rules)
+    }
+
+    /// Remember that the user allowed a domain
+    pub fn allow(&self, domain: &str) -> Result<(), String> {
+        let domain = normalize(domain)?;
+        let mut rules = self.rules.lock().map_err(|e| e.to_string())?;
+        if rules.allow.contains(&domain) {
+            return Ok(());
+        }
+        let mut updated = rules.clone();
+        updated.allow.push(domain);
+        self.save(&updated)?;
+        *rules = updated;
+        Ok(())
+    }
+
+    /// Deny rules win over allow rules; local addresses are refused unless explicitly allowed
+    pub fn check(&self, host: &str) -> DomainDecision {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        let Ok(rules) = self.rules.lock() else {
+            return DomainDecision::Deny("Domain rules are unavailable".to_string());
+        };
+        if let Some(pattern) = rules.deny.iter().find(|p| covers(p, &host)) {
+            return DomainDecision::Deny(format!("{} is on the blocked domain list", pattern));
+        }
+        if rules.allow.iter().any(|p| covers(p, &host)) {
+            return DomainDecision::Allow;
+        }
+        if is_local(&host) {
+            return DomainDecision::Deny(format!(
+                "{} is a local or private address; add it to the allowed domains to fetch from it",
+                host
+            ));
+        }
+        DomainDecision::Ask
+    }
+
+    fn save(&self, rules: &DomainRules) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(rules)
+            .map_err(|e| format!("Failed to serialize domain rules: {}", e))?;
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create domain rules directory: {}", e))?;
+        }
+        fs::write(&self.path, json).map_err(|e| format!("Failed to save domain rules: {}", e))
+    }
+}
+
+fn normalize(domain: &str) -> Result<String, String> {
+    let domain = domain
+        .trim()
+        .trim_start_matches("*.")
+        .trim_end_matches('.')
+        .to_ascii_lowercase();
+    let valid = !domain.is_empty()
+        && domain
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | ':' | '[' | ']'));
+    if !valid {
+        return Err(format!("Invalid domain: {}", domain));
+    }
+    Ok(domain)
+}
+
+fn normalize_all(domains: &[String]) -> Result<Vec<String>, String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for domain in domains {
+        let domain = normalize(domain)?;
+        if !normalized.contains(&domain) {
+            normalized.push(domain);
+        }
+    }
+    Ok(normalized)
+}
+
+/// Whether `pattern` is `host` or one of its parent domains
+fn covers(pattern: &str, host: &str) -> bool {
+    host == pattern
+        || host
+            .strip_suffix(pattern)
+            .is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+/// Loopback, private and link-local hosts, which would let a page reach local services
+pub(super) fn is_local(host: &str) -> bool {
+    let host = host.trim_end_matches('.');
+    if host == "localhost" || host.ends_with(".localhost") || host.ends_with(".local") {
+        return true;
+    }
+    let ip = host.trim_start_matches('[').trim_end_matches(']');
+    ip.parse::<IpAddr>().is_ok_and(is_local_ip)
+}
+
+/// Loopback, private, shared, link-local (cloud metadata included) and unspecified addresses
+pub(super) fn is_local_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                // "This network" (0.0.0.0/8) and carrier-grade NAT (100.64.0.0/10)
+                || a == 0
+                || (a == 100 && b & 0xc0 == 64)
+        }
+        IpAddr::V6(ip) => {
+            let segments = ip.segments();
+            ip.is_loopback()
+                || ip.is_unspecified()
+                // Unique local (fc00::/7) and link-local (fe80::/10)
+                || segments[0] & 0xfe00 == 0xfc00
+                || segments[0] & 0xffc0 == 0xfe80
+                || ip.to_ipv4_mapped().is_some_and(|v4| is_local_ip(IpAddr::V4(v4)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_check_domains() {
+        let dir = tempdir().unwrap();
+        let policy = DomainPolicy::load(dir.path().join("web-domains.json"));
+        policy
+            .set_rules(DomainRules {
+                allow: vec!["*.Example.com".to_string(), "127.0.0.1".to_string()],
+                deny: vec!["ads.example.com".to_string()],
+            })
+            .unwrap();
+
+        assert_eq!(policy.check("example.com"), DomainDecision::Allow);
+        assert_eq!(policy.check("docs.example.com."), DomainDecision::Allow);
+        assert!(matches!(
+            policy.check("x.ads.example.com"),
+            DomainDecision::Deny(_)
+        ));
+        assert_eq!(policy.check("notexample.com"), DomainDecision::Ask);
+        assert_eq!(policy.check("127.0.0.1"), DomainDecision::Allow);
+        for local in [
+            "localhost",
+            "192.168.1.10",
+            "[::1]",
+            "169.254.169.254",
+            "0.0.0.0",
+            "100.64.0.1",
+            "[::ffff:127.0.0.1]",
+            "[::ffff:a9fe:a9fe]",
+            "[fd00::1]",
+        ] {
+            assert!(matches!(policy.check(local), DomainDecision::Deny(_)));
+        }
+    }
+
+    #[test]
+    fn test_allowed_domains_persist() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("web-domains.json");
+        let policy = DomainPolicy::load(path.clone());
+        policy.allow("rust-lang.org").unwrap();
+        policy.allow("rust-lang.org").unwrap();
+        assert!(policy
+            .set_rules(DomainRules {
+                allow: vec!["bad domain".to_string()],
+                deny: Vec::new(),
+            })
+            .is_err());
+
+        let reloaded = DomainPolicy::load(path);
+        assert_eq!(reloaded.rules().allow, vec!["rust-lang.org".to_string()]);
+        assert_eq!(reloaded.check("doc.rust-lang.org"), DomainDecision::Allow);
+    }
+}
@@ -0,0 +1,289 @@
+use regex::Regex;
+use scraper::{Html, Selector};
+use serde::Serialize;
+use std::io::{self, Read};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use url::Url;
+
+use super::domains::{is_local, is_local_ip};
+use crate::files::extract::html_to_markdown;
+
+/// Most of a response body downloaded; the rest is never read
+pub const MAX_FETCH_BYTES: u64 = 2 * 1024 * 1024;
+
+pub const FETCH_TIMEOUT: Duration = Duration::from_secs(20);
+
+const MAX_REDIRECTS: usize = 5;
+
+const USER_AGENT: &str = concat!("LocalWorkHero/", env!("CARGO_PKG_VERSION"));
+
+/// Elements holding a page's main content, in order of preference
+const MAIN_CONTENT_SELECTORS: &[&str] = &["main", "article", "[role=main]", "body"];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FetchedPage {
+    /// Where the page was fetched from, after redirects
+    pub url: String,
+    pub status: u16,
+    pub content_type: String,
+    pub title: Option<String>,
+    /// The page as markdown, or the body as-is for plain text and data
+    pub content: String,
+    /// Whether the body went over `MAX_FETCH_BYTES`
+    pub truncated: bool,
+}
+
+/// Fetch a page as readable text, calling `check_url` before every request, redirects included.
+/// Each request connects only to the addresses its host resolved to, once those are checked.
+pub fn fetch_page(
+    url: &str,
+    check_url: &mut dyn FnMut(&Url) -> Result<(), String>,
+) -> Result<FetchedPage, String> {
+    let mut url = parse_url(url)?;
+    // Requests are made one at a time, each to the addresses resolved and checked for it
+    let pinned: Arc<Mutex<Vec<SocketAddr>>> = Arc::default();
+    let resolved = pinned.clone();
+    let agent = ureq::AgentBuilder::new()
+        .timeout(FETCH_TIMEOUT)
+        .redirects(0)
+        .user_agent(USER_AGENT)
+        .resolver(move |_: &str| {
+            resolved
+                .lock()
+                .map(|addrs| addrs.clone())
+                .map_err(|_| io::Error::other("resolved addresses are unavailable"))
+        })
+        .build();
+
+    for _ in 0..=MAX_REDIRECTS {
+        check_url(&url)?;
+        let addrs = url
+            .socket_addrs(|| None)
+            .map_err(|e| format!("Failed to resolve {}: {}", url, e))?;
+        check_addresses(url.host_str().unwrap_or_default(), &addrs)?;
+        *pinned.lock().map_err(|e| e.to_string())? = addrs;
+        let response = match agent.request_url("GET", &url).call() {
+            Ok(response) => response,
+            Err(ureq::Error::Status(code, response)) => {
+                return Err(format!(
+                    "HTTP {} {} from {}",
+                    code,
+                    response.status_text(),
+                    url
+                ))
+            }
+            Err(e) => return Err(format!("Failed to fetch {}: {}", url, e)),
+        };
+        if (300..400).contains(&response.status()) {
+            let location = response
+                .header("location")
+                .ok_or_else(|| format!("Redirect from {} has no location", url))?;
+            url = url
+                .join(location)
+                .map_err(|e| format!("Invalid redirect to {}: {}", location, e))?;
+            continue;
+        }
+        return read_page(url, response);
+    }
+    Err(format!("Too many redirects fetching {}", url))
+}
+
+/// Refuse a host name that resolves to a local or private address, so DNS can't point an
+/// approved domain at local services; a host that is a local address itself was already
+/// allowed explicitly by `check_url`
+fn check_addresses(host: &str, addrs: &[SocketAddr]) -> Result<(), String> {
+    if is_local(host) {
+        return Ok(());
+    }
+    match addrs.iter().find(|addr| is_local_ip(addr.ip())) {
+        Some(addr) => Err(format!(
+            "Blocked by domain policy: {} resolves to the local or private address {}",
+            host,
+            addr.ip()
+        )),
+        None => Ok(()),
+    }
+}
+
+fn parse_url(url: &str) -> Result<Url, String> {
+    let url = Url::parse(url.trim()).map_err(|e| format!("Invalid URL {}: {}", url, e))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("Only http and https URLs can be fetched: {}", url));
+    }
+    if url.host_str().is_none() {
+        return Err(format!("URL has no host: {}", url));
+    }
+    Ok(url)
+}
+
+fn read_page(url: Url, response: ureq::Response) -> Result<FetchedPage, String> {
+    let status = response.status();
+    let content_type = response.content_type().to_ascii_lowercase();
+    let charset = response.charset().to_string();
+
+    let mut body = Vec::new();
+    response
+        .into_reader()
+        .take(MAX_FETCH_BYTES + 1)
+        .read_to_end(&mut body)
+        .map_err(|e| format!("Failed to read {}: {}", url, e))?;
+    let truncated = body.len() as u64 > MAX_FETCH_BYTES;
+    body.truncate(MAX_FETCH_BYTES as usize);
+    let text = decode(&body, &charset);
+
+    let (title, content) = match content_type.as_str() {
+        "text/html" | "application/xhtml+xml" => readable(&text, &url),
+        t if t.starts_with("text/") || is_text_data(t) => (None, text),
+        t => return Err(format!("Unsupported content type {} at {}", t, url)),
+    };
+    Ok(FetchedPage {
+        url: url.to_string(),
+        status,
+        content_type,
+        title,
+        content,
+        truncated,
+    })
+}
+
+fn is_text_data(content_type: &str) -> bool {
+    matches!(
+        content_type,
+        "application/json" | "application/xml" | "application/javascript"
+    ) || content_type.ends_with("+json")
+        || content_type.ends_with("+xml")
+}
+
+fn decode(body: &[u8], charset: &str) -> String {
+    match encoding_rs::Encoding::for_label(charset.as_bytes()) {
+        Some(encoding) => encoding.decode(body).0.into_owned(),
+        None => String::from_utf8_lossy(body).into_owned(),
+    }
+}
+
+/// Title and main content of an HTML page, with links made absolute
+fn readable(html: &str, base: &Url) -> (Option<String>, String) {
+    let document = Html::parse_document(html);
+    let text_of = |selector: &str| {
+        let selector = Selector::parse(selector).ok()?;
+        let text: String = document.select(&selector).next()?.text().collect();
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        (!text.is_empty()).then_some(text)
+    };
+    let title = text_of("title").or_else(|| text_of("h1"));
+
+    let main = MAIN_CONTENT_SELECTORS.iter().find_map(|selector| {
+        let selector = Selector::parse(selector).ok()?;
+        document.select(&selector).next()
+    });
+    let markdown = match main {
+        Some(element) => html_to_markdown(&element.html()),
+        None => html_to_markdown(html),
+    };
+    (title, absolute_links(&markdown, base))
+}
+
+/// Rewrite relative markdown link targets against the page's URL
+fn absolute_links(markdown: &str, base: &Url) -> String {
+    static LINK: OnceLock<Regex> = OnceLock::new();
+    let link = LINK.get_or_init(|| Regex::new(r"\]\(([^()\s]+)\)").unwrap());
+    link.replace_all(markdown, |caps: &regex::Captures| {
+        match base.join(&caps[1]) {
+            Ok(url) => format!("]({})", url),
+            Err(_) => caps[0].to_string(),
+        }
+    })
+    .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web::stub::{ok, serve};
+
+    fn redirect(location: &str) -> String {
+        format!(
+            "HTTP/1.1 302 Found\r\nLocation: {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            location
+        )
+    }
+
+    const PAGE: &str = r#"<html><head><title> Release notes </title><script>track()</script></head>
+        <body><nav><a href="/">Home</a></nav>
+        <main><h1>Version 2</h1><p>See the <a href="docs/guide.html">guide</a>.</p></main>
+        </body></html>"#;
+
+    #[test]
+    fn test_fetch_page_follows_redirects() {
+        let base = serve(vec![
+            ("/old", redirect("/news/")),
+            ("/news/", ok("text/html; charset=utf-8", PAGE)),
+        ])
+        .base;
+        let mut checked = Vec::new();
+        let page = fetch_page(&format!("{}/old", base), &mut |url| {
+            checked.push(url.path().to_string());
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(checked, vec!["/old", "/news/"]);
+        assert_eq!(page.url, format!("{}/news/", base));
+        assert_eq!(page.title.as_deref(), Some("Release notes"));
+        assert!(page.content.starts_with("# Version 2"));
+        assert!(page
+            .content
+            .contains(&format!("[guide]({}/news/docs/guide.html)", base)));
+        assert!(!page.content.contains("Home"));
+        assert!(!page.content.contains("track()"));
+    }
+
+    #[test]
+    fn test_fetch_page_errors() {
+        let base = serve(vec![
+            ("/image", ok("image/png", "png")),
+            ("/data", ok("application/json", r#"{"ok":true}"#)),
+        ])
+        .base;
+        let allow = &mut |_: &Url| Ok(());
+
+        assert!(fetch_page("file:///etc/passwd", allow)
+            .unwrap_err()
+            .starts_with("Only http and https"));
+        assert!(fetch_page(&format!("{}/missing", base), allow)
+            .unwrap_err()
+            .starts_with("HTTP 404"));
+        assert!(fetch_page(&format!("{}/image", base), allow)
+            .unwrap_err()
+            .starts_with("Unsupported content type image/png"));
+        let data = fetch_page(&format!("{}/data", base), allow).unwrap();
+        assert_eq!(data.content, r#"{"ok":true}"#);
+
+        let refused = fetch_page(&format!("{}/data", base), &mut |_| {
+            Err("Blocked by domain policy".to_string())
+        });
+        assert_eq!(refused.unwrap_err(), "Blocked by domain policy");
+    }
+
+    #[test]
+    fn test_local_addresses_are_checked_after_resolving() {
+        let loopback: SocketAddr = "127.0.0.1:80".parse().unwrap();
+        let metadata: SocketAddr = "169.254.169.254:80".parse().unwrap();
+        let public: SocketAddr = "93.184.215.14:443".parse().unwrap();
+
+        assert!(check_addresses("example.com", &[public]).is_ok());
+        assert!(check_addresses("example.com", &[public, loopback])
+            .unwrap_err()
+            .contains("resolves to the local or private address 127.0.0.1"));
+        assert!(check_addresses("rebind.example", &[metadata]).is_err());
+        // Only reached once the user allowed the local host itself
+        assert!(check_addresses("127.0.0.1", &[loopback]).is_ok());
+
+        // Decimal and octal forms are normalized before any check sees them
+        for url in ["http://2130706433/", "http://0177.0.0.1/", "http://0x7f.1/"] {
+            assert_eq!(parse_url(url).unwrap().host_str(), Some("127.0.0.1"));
+        }
+    }
+}
@@ -0,0 +1,9 @@
+pub mod domains;
+pub mod fetch;
+pub mod search;
+#[cfg(test)]
+mod stub;
+
+pub use domains::{DomainDecision, DomainPolicy, DomainRules};
+pub use fetch::{fetch_page, FetchedPage};
+pub use search::{SearchResponse, SearchResult, SearchSettings, WebSearch};
@@ -0,0 +1,313 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use url::Url;
+
+/// Most results returned for one query
+pub const MAX_SEARCH_RESULTS: usize = 20;
+
+/// How long results for a query are reused
+pub const CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+
+const MAX_CACHED_QUERIES: usize = 100;
+
+/// Requests allowed to the endpoint per `RATE_WINDOW`; cached results don't count
+const RATE_LIMIT: usize = 10;
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+const SEARCH_TIMEOUT: Duration = Duration::from_secs(15);
+
+const MAX_RESPONSE_BYTES: u64 = 1024 * 1024;
+
+/// Where web searches go
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchSettings {
+    /// Base URL of a SearXNG instance with the JSON format enabled, e.g. `http://localhost:8888`
+    #[serde(default)]
+    pub endpoint: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub title: String,
+    pub url: String,
+    #[serde(default, rename(deserialize = "content"))]
+    pub snippet: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchResponse {
+    pub query: String,
+    pub results: Vec<SearchResult>,
+    /// Whether the results came from the cache instead of the endpoint
+    pub cached: bool,
+}
+
+/// Endpoint and lowercased query
+type CacheKey = (String, String);
+
+/// The part of a SearXNG JSON response we use
+#[derive(Deserialize)]
+struct SearxResponse {
+    #[serde(default)]
+    results: Vec<SearchResult>,
+}
+
+/// Searches a user-configured SearXNG-compatible endpoint, with caching and rate limiting
+pub struct WebSearch {
+    path: PathBuf,
+    settings: Mutex<SearchSettings>,
+    cache: Mutex<HashMap<CacheKey, (Instant, Vec<SearchResult>)>>,
+    /// When recent requests to the endpoint were sent
+    requests: Mutex<VecDeque<Instant>>,
+}
+
+impl WebSearch {
+    pub fn load(path: PathBuf) -> Self {
+        let settings = match fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str::<SearchSettings>(&text).unwrap_or_else(|e| {
+                log::warn!("Failed to parse search settings: {}", e);
+                SearchSettings::default()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => SearchSettings::default(),
+            Err(e) => {
+                log::warn!("Failed to read search settings: {}", e);
+                SearchSettings::default()
+            }
+        };
+        Self {
+            path,
+            settings: Mutex::new(settings),
+            cache: Mutex::new(HashMap::new()),
+            requests: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn settings(&self) -> SearchSettings {
+        self.settings.lock().map(|s| s.clone()).unwrap_or_default()
+    }
+
+    pub fn set_settings(&self, settings: SearchSettings) -> Result<SearchSettings, String> {
+        let endpoint = settings
+            .endpoint
+            .as_deref()
+            .map(str::trim)
+            .filter(|e| !e.is_empty());
+        if let Some(endpoint) = endpoint {
+            search_url(endpoint, "")?;
+        }
+        let settings = SearchSettings {
+            endpoint: endpoint.map(|e| e.to_string()),
+        };
+        let mut current = self.settings.lock().map_err(|e| e.to_string())?;
+        let json = serde_json::to_string_pretty(&settings)
+            .map_err(|e| format!("Failed to serialize search settings: {}", e))?;
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create search settings directory: {}", e))?;
+        }
+        fs::write(&self.path, json)
+            .map_err(|e| format!("Failed to save search settings: {}", e))?;
+        *current = settings.clone();
+        Ok(settings)
+    }
+
+    pub fn search(&self, query: &str, max_results: usize) -> Result<SearchResponse, String> {
+        let query = query.trim();
+        if query.is_empty() {
+            return Err("Search query is empty".to_string());
+        }
+        let endpoint = self.settings().endpoint.ok_or_else(|| {
+            "Web search is not configured; a SearXNG endpoint must be set in settings".to_string()
+        })?;
+        let max_results = max_results.clamp(1, MAX_SEARCH_RESULTS);
+        let key = (endpoint.clone(), query.to_lowercase());
+
+        let cached = self.cache.lock().ok().and_then(|cache| {
+            cache
+                .get(&key)
+                .filter(|(at, _)| at.elapsed() < CACHE_TTL)
+                .map(|(_, results)| results.clone())
+        });
+        let (results, cached) = match cached {
+            Some(results) => (results, true),
+            None => {
+                self.take_request_slot()?;
+                let results = fetch_results(&endpoint, query)?;
+                self.remember(key, results.clone());
+                (results, false)
+            }
+        };
+        Ok(SearchResponse {
+            query: query.to_string(),
+            results: results.into_iter().take(max_results).collect(),
+            cached,
+        })
+    }
+
+    fn take_request_slot(&self) -> Result<(), String> {
+        let mut requests = self.requests.lock().map_err(|e| e.to_string())?;
+        let now = Instant::now();
+        while requests
+            .front()
+            .is_some_and(|at| now.duration_since(*at) >= RATE_WINDOW)
+        {
+            requests.pop_front();
+        }
+        if let (true, Some(oldest)) = (requests.len() >= RATE_LIMIT, requests.front()) {
+            let wait = RATE_WINDOW.saturating_sub(now.duration_since(*oldest));
+            return Err(format!(
+                "Search rate limit reached ({} searches per minute); try again in {}s",
+                RATE_LIMIT,
+                wait.as_secs().max(1)
+            ));
+        }
+        requests.push_back(now);
+        Ok(())
+    }
+
+    fn remember(&self, key: CacheKey, results: Vec<SearchResult>) {
+        let Ok(mut cache) = self.cache.lock() else {
+            return;
+        };
+        cache.retain(|_, (at, _)| at.elapsed() < CACHE_TTL);
+        if cache.len() >= MAX_CACHED_QUERIES {
+            let oldest = cache
+                .iter()
+                .min_by_key(|(_, (at, _))| *at)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                cache.remove(&oldest);
+            }
+        }
+        cache.insert(key, (Instant::now(), results));
+    }
+}
+
+/// `{endpoint}/search?q=...&format=json`, keeping any path the endpoint has
+fn search_url(endpoint: &str, query: &str) -> Result<Url, String> {
+    let mut base = Url::parse(endpoint).map_err(|e| format!("Invalid search endpoint: {}", e))?;
+    if !matches!(base.scheme(), "http" | "https") {
+        return Err(format!(
+            "Search endpoint must be an http or https URL: {}",
+            endpoint
+        ));
+    }
+    if !base.path().ends_with('/') {
+        base.set_path(&format!("{}/", base.path()));
+    }
+    let mut url = base
+        .join("search")
+        .map_err(|e| format!("Invalid search endpoint: {}", e))?;
+    url.query_pairs_mut()
+        .append_pair("q", query)
+        .append_pair("format", "json");
+    Ok(url)
+}
+
+fn fetch_results(endpoint: &str, query: &str) -> Result<Vec<SearchResult>, String> {
+    let url = search_url(endpoint, query)?;
+    let agent = ureq::AgentBuilder::new().timeout(SEARCH_TIMEOUT).build();
+    let response =
+        match agent.request_url("GET", &url).call() {
+            Ok(response) => response,
+            Err(ureq::Error::Status(403, _)) => return Err(
+                "The search endpoint refused the request; enable the json format in its settings"
+                    .to_string(),
+            ),
+            Err(ureq::Error::Status(code, response)) => {
+                return Err(format!(
+                    "Search endpoint returned HTTP {} {}",
+                    code,
+                    response.status_text()
+                ))
+            }
+            Err(e) => return Err(format!("Failed to reach search endpoint: {}", e)),
+        };
+    let parsed: SearxResponse =
+        serde_json::from_reader(response.into_reader().take(MAX_RESPONSE_BYTES))
+            .map_err(|e| format!("Unexpected response from search endpoint: {}", e))?;
+    Ok(parsed
+        .results
+        .into_iter()
+        .filter(|r| r.url.starts_with("http://") || r.url.starts_with("https://"))
+        .take(MAX_SEARCH_RESULTS)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web::stub::{ok, serve};
+    use tempfile::tempdir;
+
+    const RESULTS: &str = r#"{"query": "rust", "results": [
+        {"title": "Rust", "url": "https://www.rust-lang.org/", "content": "A language", "engine": "ddg"},
+        {"title": "Docs", "url": "https://doc.rust-lang.org/", "content": "Documentation"},
+        {"title": "Magnet", "url": "magnet:?xt=urn:btih:abc"}
+    ]}"#;
+
+    #[test]
+    fn test_search_url() {
+        assert_eq!(
+            search_url("http://localhost:8888", "a b").unwrap().as_str(),
+            "http://localhost:8888/search?q=a+b&format=json"
+        );
+        assert_eq!(
+            search_url("https://example.com/searx", "x")
+                .unwrap()
+                .as_str(),
+            "https://example.com/searx/search?q=x&format=json"
+        );
+        assert!(search_url("ftp://example.com", "x").is_err());
+    }
+
+    #[test]
+    fn test_search_caches_results() {
+        let server = serve(vec![("/search", ok("application/json", RESULTS))]);
+        let dir = tempdir().unwrap();
+        let search = WebSearch::load(dir.path().join("web-search.json"));
+        assert!(search
+            .search("rust", 5)
+            .unwrap_err()
+            .contains("not configured"));
+        search
+            .set_settings(SearchSettings {
+                endpoint: Some(server.base.clone()),
+            })
+            .unwrap();
+
+        let first = search.search("rust", 5).unwrap();
+        assert!(!first.cached);
+        assert_eq!(first.results.len(), 2);
+        assert_eq!(first.results[0].snippet, "A language");
+
+        let second = search.search(" Rust ", 1).unwrap();
+        assert!(second.cached);
+        assert_eq!(second.results.len(), 1);
+        assert_eq!(
+            *server.requests.lock().unwrap(),
+            vec!["/search?q=rust&format=json".to_string()]
+        );
+
+        let reloaded = WebSearch::load(dir.path().join("web-search.json"));
+        assert_eq!(reloaded.settings().endpoint, Some(server.base));
+    }
+
+    #[test]
+    fn test_search_rate_limit() {
+        let dir = tempdir().unwrap();
+        let search = WebSearch::load(dir.path().join("web-search.json"));
+        for _ in 0..RATE_LIMIT {
+            search.take_request_slot().unwrap();
+        }
+        assert!(search
+            .take_request_slot()
+            .unwrap_err()
+            .starts_with("Search rate limit reached"));
+    }
+}
@@ -0,0 +1,52 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+
+/// A local HTTP server answering with canned responses, for tests
+pub struct StubServer {
+    pub base: String,
+    /// Request targets in the order they arrived, query strings included
+    pub requests: Arc<Mutex<Vec<String>>>,
+}
+
+/// Serve `routes`, matched by path without the query string, until the test exits
+pub fn serve(routes: Vec<(&'static str, String)>) -> StubServer {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let seen = requests.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { break };
+            let mut request_line = String::new();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            reader.read_line(&mut request_line).unwrap();
+            // Drain the headers
+            let mut line = String::new();
+            while reader.read_line(&mut line).is_ok_and(|n| n > 2) {
+                line.clear();
+            }
+            let target = request_line.split_whitespace().nth(1).unwrap_or("/");
+            seen.lock().unwrap().push(target.to_string());
+            let path = target.split('?').next().unwrap_or(target);
+            let response = routes
+                .iter()
+                .find(|(route, _)| *route == path)
+                .map(|(_, response)| response.clone())
+                .unwrap_or_else(|| {
+                    "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_string()
+                });
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+    StubServer { base, requests }
+}
+
+pub fn ok(content_type: &str, body: &str) -> String {
+    format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        content_type,
+        body.len(),
+        body
+    )
+}
@@ -13,6 +13,9 @@ import {
   listFolders,
   grantFolder,
   revokeFolder,
+  respondApproval,
+  type ApprovalRequest,
+  type CommandOutputEvent,
   type Message,
   type ModelInfo,
   type FolderPermission,
@@ -35,6 +38,9 @@ function App() {
   const [downloadProgress, setDownloadProgress] = useState<number | null>(null);
   const [isLoadingModel, setIsLoadingModel] = useState(false);
   const [grantedFolders, setGrantedFolders] = useState<FolderPermission[]>([]);
+  const [conversationId] = useState(() => crypto.randomUUID());
+  const [approvals, setApprovals] = useState<ApprovalRequest[]>([]);
+  const [commandOutput, setCommandOutput] = useState<Record<string, string>>({});
 
   // Load app info, models, and folders on mount
   useEffect(() => {
@@ -44,6 +50,23 @@ function App() {
 
     refreshModels();
     listFolders().then(setGrantedFolders).catch(console.error);
+
+    // Drop folders from the list when a time-limited or session grant lapses
+    const unlisten = listen<FolderPermission>("folder-grant-expired", (event) => {
+      setGrantedFolders((prev) => prev.filter((f) => f.id !== event.payload.id));
+    });
+    const unlistenApprovals = listen<ApprovalRequest>("approval-requested", (event) => {
+      setApprovals((prev) => [...prev, event.payload]);
+    });
+    const unlistenOutput = listen<CommandOutputEvent>("command-output", (event) => {
+      const { tool_call_id, text } = event.payload;
+      setCommandOutput((prev) => ({ ...prev, [tool_call_id]: (prev[tool_call_id] ?? "") + text }));
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+      unlistenApprovals.then((fn) => fn());
+      unlistenOutput.then((fn) => fn());
+    };
   }, []);
 
   const refreshModels = async () => {
@@ -124,7 +147,7 @@ function App() {
 
     try {
       // Send full conversation history to the backend with tool support
-      const response = await sendMessageWithTools(updatedMessages);
+      const response = await sendMessageWithTools(updatedMessages, conversationId);
       const assistantMessage: Message = {
         role: "assistant",
         content: response.content,
@@ -141,6 +164,19 @@ function App() {
       setMessages((prev) => [...prev, errorMessage]);
     } finally {
       setIsLoading(false);
+      // Unanswered requests expired with the turn, and command results are in the tool calls;
+      // requests from MCP clients stay until answered
+      setApprovals((prev) => prev.filter((a) => a.conversation_id?.startsWith("mcp-")));
+      setCommandOutput({});
+    }
+  };
+
+  const handleRespondApproval = async (id: string, approved: boolean) => {
+    setApprovals((prev) => prev.filter((a) => a.id !== id));
+    try {
+      await respondApproval(id, approved);
+    } catch (err) {
+      console.error("Failed to answer approval:", err);
     }
   };
 
@@ -173,6 +209,9 @@ function App() {
         messages={messages}
         isLoading={isLoading}
         onSend={handleSendMessage}
+        approvals={approvals}
+        commandOutput={commandOutput}
+        onRespondApproval={handleRespondApproval}
       />
       <SettingsPanel
         isOpen={isSettingsOpen}
@@ -0,0 +1,92 @@
+import { Globe, Plug, ShieldAlert, Terminal } from "lucide-react";
+import { Button } from "@/components/ui/button";
+import { type ApprovalRequest, type ApprovalSubject } from "@/lib/tauri";
+
+interface ApprovalPromptProps {
+  approvals: ApprovalRequest[];
+  commandOutput: Record<string, string>;
+  onRespond: (id: string, approved: boolean) => void;
+}
+
+export function ApprovalPrompt({ approvals, commandOutput, onRespond }: ApprovalPromptProps) {
+  const running = Object.entries(commandOutput);
+  if (approvals.length === 0 && running.length === 0) {
+    return null;
+  }
+
+  return (
+    <div className="border-t p-4 space-y-2 max-w-3xl w-full mx-auto">
+      {approvals.map((approval) => (
+        <div key={approval.id} className="border rounded-md p-3 bg-muted/50 space-y-2">
+          {approval.kind === "command" ? (
+            <CommandApproval approval={approval} />
+          ) : approval.kind === "mcp_tool" ? (
+            <>
+              <div className="flex items-center gap-2 text-sm font-medium">
+                <Plug className="h-4 w-4 shrink-0" />
+                The assistant wants to use {approval.tool} from the {approval.server} MCP server
+              </div>
+              <pre className="text-xs bg-background rounded p-2 overflow-x-auto whitespace-pre-wrap">
+                {JSON.stringify(approval.arguments, null, 2)}
+              </pre>
+            </>
+          ) : (
+            <>
+              <div className="flex items-center gap-2 text-sm font-medium">
+                <Globe className="h-4 w-4 shrink-0" />
+                The assistant wants to access {approval.domain}
+              </div>
+              <p className="text-xs text-muted-foreground break-all">
+                {approval.url} · allowing it lets the assistant fetch from this domain from now on
+              </p>
+            </>
+          )}
+          {approval.conversation_id?.startsWith("mcp-") && (
+            <p className="text-xs text-muted-foreground">Requested by an external MCP client</p>
+          )}
+          <div className="flex gap-2 justify-end">
+            <Button variant="outline" size="sm" onClick={() => onRespond(approval.id, false)}>
+              Deny
+            </Button>
+            <Button size="sm" onClick={() => onRespond(approval.id, true)}>
+              {approval.kind === "command" ? "Run" : "Allow"}
+            </Button>
+          </div>
+        </div>
+      ))}
+      {running.map(([toolCallId, output]) => (
+        <pre
+          key={toolCallId}
+          className="text-xs bg-background border rounded p-2 max-h-48 overflow-auto whitespace-pre-wrap"
+        >
+          {output}
+        </pre>
+      ))}
+    </div>
+  );
+}
+
+function CommandApproval({ approval }: { approval: Extract<ApprovalSubject, { kind: "command" }> }) {
+  return (
+    <>
+      <div className="flex items-center gap-2 text-sm font-medium">
+        <Terminal className="h-4 w-4 shrink-0" />
+        The assistant wants to run a command
+      </div>
+      <pre className="text-xs bg-background rounded p-2 overflow-x-auto whitespace-pre-wrap">
+        {approval.command}
+      </pre>
+      <p className="text-xs text-muted-foreground">
+        in {approval.cwd} · {approval.reason}
+        {approval.network && " · with network access"}
+      </p>
+      {approval.sandbox.mode !== "full" && (
+        <p className="flex items-start gap-1 text-xs text-destructive">
+          <ShieldAlert className="h-3 w-3 mt-0.5 shrink-0" />
+          {approval.sandbox.mode === "unsandboxed" ? "Runs unsandboxed: " : "Partly sandboxed: "}
+          {approval.sandbox.limitations.join("; ")}
+        </p>
+      )}
+    </>
+  );
+}
@@ -1,17 +1,33 @@
+import { ApprovalPrompt } from "./ApprovalPrompt";
 import { ChatInput } from "./ChatInput";
 import { MessageList } from "./MessageList";
-import { type Message } from "@/lib/tauri";
+import { type ApprovalRequest, type Message } from "@/lib/tauri";
 
 interface ChatAreaProps {
   messages: Message[];
   isLoading: boolean;
   onSend: (message: string) => void;
+  approvals: ApprovalRequest[];
+  commandOutput: Record<string, string>;
+  onRespondApproval: (id: string, approved: boolean) => void;
 }
 
-export function ChatArea({ messages, isLoading, onSend }: ChatAreaProps) {
+export function ChatArea({
+  messages,
+  isLoading,
+  onSend,
+  approvals,
+  commandOutput,
+  onRespondApproval,
+}: ChatAreaProps) {
   return (
     <div className="flex-1 flex flex-col">
       <MessageList messages={messages} isLoading={isLoading} />
+      <ApprovalPrompt
+        approvals={approvals}
+        commandOutput={commandOutput}
+        onRespond={onRespondApproval}
+      />
       <ChatInput onSend={onSend} disabled={isLoading} />
     </div>
   );
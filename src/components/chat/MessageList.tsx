@@ -7,10 +7,14 @@ import {
   ChevronRight,
   FileText,
   FolderOpen,
+  Globe,
   FilePlus,
   FileEdit,
   Trash2,
   Move,
+  Plug,
+  Search,
+  Terminal,
   Wrench,
 } from "lucide-react";
 
@@ -33,8 +37,18 @@ function getToolIcon(toolName: string) {
       return Trash2;
     case "move_file":
       return Move;
+    case "run_command":
+    case "start_process":
+    case "read_process_output":
+    case "send_process_input":
+    case "stop_process":
+      return Terminal;
+    case "fetch_url":
+      return Globe;
+    case "web_search":
+      return Search;
     default:
-      return Wrench;
+      return toolName.startsWith("mcp__") ? Plug : Wrench;
   }
 }
 
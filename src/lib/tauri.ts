@@ -55,8 +55,251 @@ export async function sendMessage(messages: Message[]): Promise<string> {
   return invoke<string>("send_message", { messages });
 }
 
-export async function sendMessageWithTools(messages: Message[]): Promise<AgentResponse> {
-  return invoke<AgentResponse>("send_message_with_tools", { messages });
+export async function sendMessageWithTools(
+  messages: Message[],
+  conversationId?: string,
+  includeFileChanges = false
+): Promise<AgentResponse> {
+  return invoke<AgentResponse>("send_message_with_tools", {
+    messages,
+    conversationId: conversationId ?? null,
+    includeFileChanges,
+  });
+}
+
+export type ChangeKind = "created" | "modified" | "removed" | "renamed";
+
+// Payload of the "fs-changed" event is an array of these
+export interface FsChange {
+  path: string;
+  kind: ChangeKind;
+  from: string | null;
+}
+
+// Approvals: the agent waits on respondApproval after an "approval-requested" event
+export type SandboxMode = "full" | "partial" | "unsandboxed";
+
+export interface SandboxReport {
+  mode: SandboxMode;
+  writes_confined: boolean;
+  network_blocked: boolean;
+  limitations: string[];
+}
+
+export type ApprovalSubject =
+  | {
+      kind: "command";
+      command: string;
+      cwd: string;
+      reason: string;
+      network: boolean;
+      sandbox: SandboxReport;
+    }
+  | { kind: "domain"; domain: string; url: string }
+  | { kind: "mcp_tool"; server: string; tool: string; arguments: unknown };
+
+export type ApprovalRequest = {
+  id: string;
+  tool_call_id: string | null;
+  conversation_id: string | null;
+} & ApprovalSubject;
+
+export async function respondApproval(id: string, approved: boolean): Promise<void> {
+  return invoke<void>("respond_approval", { id, approved });
+}
+
+export type OutputStream = "stdout" | "stderr";
+
+// Payload of the "command-output" event, streamed while run_command executes
+export interface CommandOutputEvent {
+  tool_call_id: string;
+  conversation_id: string | null;
+  stream: OutputStream;
+  text: string;
+}
+
+// Command policy
+export type PolicyAction = "allow" | "ask" | "deny";
+
+export interface CommandRule {
+  id: string;
+  program: string;
+  args: string | null;
+  cwd: string | null;
+  piped_from: string | null;
+  action: PolicyAction;
+  description: string | null;
+}
+
+export interface CommandRules {
+  defaults: CommandRule[];
+  user: CommandRule[];
+}
+
+export interface PolicyDecision {
+  action: PolicyAction;
+  reason: string;
+  rule_id: string | null;
+}
+
+export async function listCommandRules(): Promise<CommandRules> {
+  return invoke<CommandRules>("list_command_rules");
+}
+
+export async function addCommandRule(rule: Omit<CommandRule, "id">): Promise<CommandRule> {
+  return invoke<CommandRule>("add_command_rule", { rule });
+}
+
+export async function removeCommandRule(id: string): Promise<void> {
+  return invoke<void>("remove_command_rule", { id });
+}
+
+export async function checkCommand(command: string, cwd: string): Promise<PolicyDecision> {
+  return invoke<PolicyDecision>("check_command", { command, cwd });
+}
+
+export async function getSandboxStatus(): Promise<SandboxReport> {
+  return invoke<SandboxReport>("get_sandbox_status");
+}
+
+// Background processes
+export type ProcessStatus = { state: "running" } | { state: "exited"; exit_code: number | null };
+
+export interface ProcessInfo {
+  id: string;
+  command: string;
+  cwd: string;
+  conversation_id: string | null;
+  pid: number;
+  started_at: number;
+  status: ProcessStatus;
+  sandbox: SandboxReport;
+}
+
+export interface ProcessOutput {
+  text: string;
+  next_offset: number;
+  skipped: number;
+  status: ProcessStatus;
+}
+
+export async function startProcess(
+  command: string,
+  cwd: string,
+  options: { network?: boolean; conversationId?: string } = {}
+): Promise<ProcessInfo> {
+  return invoke<ProcessInfo>("start_process", {
+    command,
+    cwd,
+    network: options.network ?? null,
+    conversationId: options.conversationId ?? null,
+  });
+}
+
+export async function listProcesses(conversationId?: string): Promise<ProcessInfo[]> {
+  return invoke<ProcessInfo[]>("list_processes", { conversationId: conversationId ?? null });
+}
+
+export async function readProcessOutput(id: string, since?: number): Promise<ProcessOutput> {
+  return invoke<ProcessOutput>("read_process_output", { id, since: since ?? null });
+}
+
+export async function sendProcessInput(id: string, input: string, close = false): Promise<void> {
+  return invoke<void>("send_process_input", { id, input, close });
+}
+
+export async function stopProcess(id: string): Promise<ProcessInfo> {
+  return invoke<ProcessInfo>("stop_process", { id });
+}
+
+// Web domains
+export interface DomainRules {
+  allow: string[];
+  deny: string[];
+}
+
+export async function getDomainRules(): Promise<DomainRules> {
+  return invoke<DomainRules>("get_domain_rules");
+}
+
+export async function setDomainRules(rules: DomainRules): Promise<DomainRules> {
+  return invoke<DomainRules>("set_domain_rules", { rules });
+}
+
+// Web search
+export interface SearchSettings {
+  endpoint: string | null;
+}
+
+export async function getSearchSettings(): Promise<SearchSettings> {
+  return invoke<SearchSettings>("get_search_settings");
+}
+
+export async function setSearchSettings(
+  settings: SearchSettings
+): Promise<SearchSettings> {
+  return invoke<SearchSettings>("set_search_settings", { settings });
+}
+
+// MCP servers
+export type McpTransportConfig =
+  | {
+      type: "stdio";
+      command: string;
+      args?: string[];
+      env?: Record<string, string>;
+      cwd?: string | null;
+    }
+  | { type: "http"; url: string; headers?: Record<string, string> };
+
+export interface McpServerConfig {
+  name: string;
+  enabled: boolean;
+  transport: McpTransportConfig;
+  approval: PolicyAction;
+  tool_rules: Record<string, PolicyAction>;
+}
+
+export interface McpServerStatus {
+  name: string;
+  enabled: boolean;
+  connected: boolean;
+  error: string | null;
+  tools: string[];
+}
+
+export async function getMcpServers(): Promise<McpServerConfig[]> {
+  return invoke<McpServerConfig[]>("get_mcp_servers");
+}
+
+export async function setMcpServers(servers: McpServerConfig[]): Promise<McpServerStatus[]> {
+  return invoke<McpServerStatus[]>("set_mcp_servers", { servers });
+}
+
+export async function getMcpStatus(): Promise<McpServerStatus[]> {
+  return invoke<McpServerStatus[]>("get_mcp_status");
+}
+
+export async function connectMcpServer(name: string): Promise<McpServerStatus> {
+  return invoke<McpServerStatus>("connect_mcp_server", { name });
+}
+
+// Serving our tools to other MCP clients at http://127.0.0.1:{port}/mcp
+export interface McpServerSettings {
+  enabled: boolean;
+  port: number;
+  token: string;
+}
+
+export async function getMcpServerSettings(): Promise<McpServerSettings> {
+  return invoke<McpServerSettings>("get_mcp_server_settings");
+}
+
+export async function setMcpServerSettings(
+  enabled: boolean,
+  port: number
+): Promise<McpServerSettings> {
+  return invoke<McpServerSettings>("set_mcp_server_settings", { enabled, port });
 }
 
 // Folder permissions
@@ -64,6 +307,30 @@ export interface FolderPermission {
   id: string;
   path: string;
   granted_at: number;
+  expires_at: number | null;
+  session_id: string | null;
+}
+
+export interface GrantFolderOptions {
+  expiresInSecs?: number;
+  sessionId?: string;
+}
+
+// File operations reject with this shape
+export type FileOpErrorCode =
+  | "access_denied"
+  | "blocked"
+  | "not_found"
+  | "already_exists"
+  | "not_utf8"
+  | "too_large"
+  | "io"
+  | "invalid"
+  | "other";
+
+export interface FileOpError {
+  code: FileOpErrorCode;
+  message: string;
 }
 
 export interface FileInfo {
@@ -74,30 +341,184 @@ export interface FileInfo {
   modified: number;
 }
 
-export async function grantFolder(path: string): Promise<FolderPermission> {
-  return invoke<FolderPermission>("grant_folder", { path });
+export interface ListOptions {
+  max_depth?: number | null;
+  include?: string[];
+  exclude?: string[];
+  show_hidden?: boolean;
+  respect_gitignore?: boolean;
+  max_entries?: number;
+}
+
+export interface TreeEntry extends FileInfo {
+  depth: number;
+}
+
+export interface DirectoryTree {
+  root: string;
+  entries: TreeEntry[];
+  truncated: boolean;
+}
+
+export interface SearchOptions {
+  pattern: string;
+  path?: string | null;
+  literal?: boolean;
+  case_sensitive?: boolean;
+  include?: string[];
+  exclude?: string[];
+  context_lines?: number;
+  max_results?: number;
+  max_file_size?: number;
+}
+
+export interface SearchMatch {
+  path: string;
+  line_number: number;
+  line: string;
+  before: string[];
+  after: string[];
+}
+
+export interface SearchResults {
+  matches: SearchMatch[];
+  files_searched: number;
+  truncated: boolean;
+}
+
+export type ReadRange =
+  | { mode: "lines"; offset: number; limit: number | null }
+  | { mode: "bytes"; offset: number; limit: number | null }
+  | { mode: "head"; lines: number }
+  | { mode: "tail"; lines: number };
+
+export type TextEncoding = "utf-8" | "utf-8-bom" | "utf-16le" | "utf-16be" | "windows-1252";
+
+export type LineEnding = "lf" | "crlf" | "mixed" | "none";
+
+export interface FileChunk {
+  content: string;
+  total_bytes: number;
+  total_lines: number | null;
+  start_line: number | null;
+  end_line: number | null;
+  start_byte: number | null;
+  end_byte: number | null;
+  truncated: boolean;
+  encoding: TextEncoding;
+  line_ending: LineEnding;
+}
+
+export interface BinaryFileInfo {
+  mime_type: string;
+  size: number;
+  sha256: string;
+}
+
+export type FileRead = ({ kind: "text" } & FileChunk) | ({ kind: "binary" } & BinaryFileInfo);
+
+export type DocumentFormat = "pdf" | "docx" | "odt" | "pptx" | "spreadsheet" | "csv" | "tsv" | "html";
+
+export interface DocumentSection {
+  title: string | null;
+  content: string;
+}
+
+export interface ExtractedDocument {
+  format: DocumentFormat;
+  sections: DocumentSection[];
+}
+
+export interface QueryResult {
+  columns: string[];
+  rows: unknown[][];
+  source_rows: number;
+  matched_rows: number;
+}
+
+export type FileEdit =
+  | { type: "replace"; search: string; replace: string }
+  | { type: "replace_lines"; start: number; end: number; content: string };
+
+export interface EditResult {
+  edits_applied: number;
+  diff: string;
+}
+
+export async function grantFolder(
+  path: string,
+  options: GrantFolderOptions = {}
+): Promise<FolderPermission> {
+  return invoke<FolderPermission>("grant_folder", {
+    path,
+    expiresInSecs: options.expiresInSecs ?? null,
+    sessionId: options.sessionId ?? null,
+  });
 }
 
 export async function revokeFolder(id: string): Promise<void> {
   return invoke<void>("revoke_folder", { id });
 }
 
+export async function endSession(sessionId: string): Promise<void> {
+  return invoke<void>("end_session", { sessionId });
+}
+
 export async function listFolders(): Promise<FolderPermission[]> {
   return invoke<FolderPermission[]>("list_folders");
 }
 
+export async function getDenyPatterns(): Promise<string[]> {
+  return invoke<string[]>("get_deny_patterns");
+}
+
+export async function setDenyPatterns(patterns: string[]): Promise<void> {
+  return invoke<void>("set_deny_patterns", { patterns });
+}
+
 export async function listFiles(path: string): Promise<FileInfo[]> {
   return invoke<FileInfo[]>("list_files", { path });
 }
 
+export async function listFilesRecursive(
+  path: string,
+  options?: ListOptions
+): Promise<DirectoryTree> {
+  return invoke<DirectoryTree>("list_files_recursive", { path, options: options ?? null });
+}
+
+export async function searchFiles(options: SearchOptions): Promise<SearchResults> {
+  return invoke<SearchResults>("search_files", { options });
+}
+
 export async function readTextFile(path: string): Promise<string> {
   return invoke<string>("read_text_file", { path });
 }
 
+export async function readFileRange(
+  path: string,
+  range: ReadRange,
+  maxBytes?: number
+): Promise<FileRead> {
+  return invoke<FileRead>("read_file_range", { path, range, maxBytes: maxBytes ?? null });
+}
+
+export async function readDocument(path: string): Promise<ExtractedDocument> {
+  return invoke<ExtractedDocument>("read_document", { path });
+}
+
+export async function queryData(path: string, query: string): Promise<QueryResult> {
+  return invoke<QueryResult>("query_data", { path, query });
+}
+
 export async function writeTextFile(path: string, content: string): Promise<void> {
   return invoke<void>("write_text_file", { path, content });
 }
 
+export async function editTextFile(path: string, edits: FileEdit[]): Promise<EditResult> {
+  return invoke<EditResult>("edit_text_file", { path, edits });
+}
+
 export async function createTextFile(path: string, content: string): Promise<void> {
   return invoke<void>("create_text_file", { path, content });
 }
@@ -109,3 +530,180 @@ export async function deleteFsFile(path: string): Promise<void> {
 export async function moveFsFile(src: string, dest: string): Promise<void> {
   return invoke<void>("move_fs_file", { src, dest });
 }
+
+export async function createFsDirectory(path: string): Promise<void> {
+  return invoke<void>("create_fs_directory", { path });
+}
+
+export async function copyFsFile(src: string, dest: string): Promise<number> {
+  return invoke<number>("copy_fs_file", { src, dest });
+}
+
+export async function copyFsDirectory(src: string, dest: string): Promise<number> {
+  return invoke<number>("copy_fs_directory", { src, dest });
+}
+
+export async function removeEmptyFsDirectory(path: string): Promise<void> {
+  return invoke<void>("remove_empty_fs_directory", { path });
+}
+
+export async function appendTextFile(path: string, content: string): Promise<void> {
+  return invoke<void>("append_text_file", { path, content });
+}
+
+export interface BatchMove {
+  from: string;
+  to: string;
+}
+
+export type BatchRequest =
+  | { kind: "moves"; moves: BatchMove[] }
+  | { kind: "rename"; folder: string; template: string; include?: string }
+  | { kind: "organize_by_type"; folder: string };
+
+export interface BatchConflict {
+  from: string;
+  to: string;
+  reason: string;
+}
+
+export interface BatchPlan {
+  moves: BatchMove[];
+  create_directories: string[];
+  conflicts: BatchConflict[];
+}
+
+export interface AppliedBatch {
+  id: string;
+  applied_at: number;
+  moves: BatchMove[];
+  created_directories: string[];
+}
+
+export async function planBatch(request: BatchRequest): Promise<BatchPlan> {
+  return invoke<BatchPlan>("plan_batch", { request });
+}
+
+export async function applyBatch(request: BatchRequest): Promise<AppliedBatch> {
+  return invoke<AppliedBatch>("apply_batch", { request });
+}
+
+export async function undoLastBatch(): Promise<AppliedBatch> {
+  return invoke<AppliedBatch>("undo_last_batch");
+}
+
+export async function listBatchHistory(): Promise<AppliedBatch[]> {
+  return invoke<AppliedBatch[]>("list_batch_history");
+}
+
+export interface FileStat extends FileInfo {
+  created: number | null;
+  accessed: number | null;
+  mode: number | null;
+  readonly: boolean;
+  is_symlink: boolean;
+  symlink_target: string | null;
+  mime_type: string | null;
+  sha256: string | null;
+}
+
+export interface DuplicateGroup {
+  sha256: string;
+  size: number;
+  paths: string[];
+}
+
+export interface DuplicateReport {
+  root: string;
+  groups: DuplicateGroup[];
+  files_scanned: number;
+  wasted_bytes: number;
+  truncated: boolean;
+}
+
+export async function statFile(path: string, hash = false): Promise<FileStat> {
+  return invoke<FileStat>("stat_file", { path, hash });
+}
+
+export async function findDuplicates(path: string, minSize?: number): Promise<DuplicateReport> {
+  return invoke<DuplicateReport>("find_duplicates", { path, minSize });
+}
+
+export type ArchiveFormat = "zip" | "tar" | "tar_gz";
+
+export interface ArchiveEntry {
+  path: string;
+  is_directory: boolean;
+  size: number;
+}
+
+export interface ArchiveListing {
+  format: ArchiveFormat;
+  entries: ArchiveEntry[];
+  total_size: number;
+}
+
+export interface SkippedEntry {
+  path: string;
+  reason: string;
+}
+
+export interface ExtractSummary {
+  files_extracted: number;
+  bytes_written: number;
+  skipped: SkippedEntry[];
+}
+
+export interface CreateSummary {
+  files_added: number;
+  bytes_added: number;
+}
+
+export async function listArchive(path: string): Promise<ArchiveListing> {
+  return invoke<ArchiveListing>("list_archive", { path });
+}
+
+export async function extractArchive(
+  path: string,
+  dest: string,
+  overwrite = false
+): Promise<ExtractSummary> {
+  return invoke<ExtractSummary>("extract_archive", { path, dest, overwrite });
+}
+
+export async function createArchive(paths: string[], dest: string): Promise<CreateSummary> {
+  return invoke<CreateSummary>("create_archive", { paths, dest });
+}
+
+// Audit log
+export interface AuditEntry {
+  timestamp: number;
+  tool_call_id: string | null;
+  conversation_id: string | null;
+  operation: string;
+  path: string;
+  target?: string;
+  command?: string;
+  outcome: "success" | "error";
+  error?: string;
+  bytes?: number;
+}
+
+export interface AuditFilter {
+  conversation_id?: string;
+  tool_call_id?: string;
+  operation?: string;
+  path_contains?: string;
+  outcome?: "success" | "error";
+  since?: number;
+  until?: number;
+  limit?: number;
+}
+
+export async function queryAuditLog(filter?: AuditFilter): Promise<AuditEntry[]> {
+  return invoke<AuditEntry[]>("query_audit_log", { filter: filter ?? null });
+}
+
+export async function exportAuditLog(dest: string, filter?: AuditFilter): Promise<number> {
+  return invoke<number>("export_audit_log", { dest, filter: filter ?? null });
+}